@@ -1,12 +1,22 @@
 use crate::context::{AnswerResult, AnswerStream};
 use crate::logic::RootSearchFail;
 use crate::slg::SlgContextOps;
+use crate::solve::TableBudget;
 use crate::table::AnswerIndex;
-use crate::tables::Tables;
+use crate::tables::{SharedTables, Tables};
 use crate::{TableIndex, TimeStamp};
 
 use chalk_ir::interner::Interner;
-use chalk_ir::{Goal, InEnvironment, Substitution, UCanonical};
+use chalk_ir::{Canonical, DomainGoal, Goal, GoalData, InEnvironment, Substitution, UCanonical};
+use chalk_solve::dependencies::{Dependencies, ItemId};
+use chalk_solve::env_trim::EnvTrimmer;
+use chalk_solve::solve::{
+    AmbiguityCause, Guidance, Overflow, ProofCandidate, ProofOutcome, ProofTree, Solution,
+    SolverStats,
+};
+use chalk_solve::RustIrDatabase;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::Arc;
 use tracing::debug;
 
 pub(crate) struct Forest<I: Interner> {
@@ -17,6 +27,24 @@ pub(crate) struct Forest<I: Interner> {
     /// This effectively gives us way to track what depth
     /// and loop a table or strand was last followed.
     pub(crate) clock: TimeStamp,
+
+    /// If true, tables record the candidates their strands came from,
+    /// so that we can build a proof tree afterwards.
+    pub(crate) explain: bool,
+
+    /// The completed tables of other forests, which we consult before
+    /// creating a table of our own (see `ParallelSLGSolver`).
+    pub(crate) shared: Option<Arc<SharedTables<I>>>,
+
+    /// What we have done so far, for `Solver::stats`.
+    pub(crate) stats: SolverStats<I>,
+
+    /// The limits that `evict` keeps our tables within.
+    pub(crate) budget: TableBudget,
+
+    /// Drops the environment clauses that cannot affect a subgoal before
+    /// we look up its table.
+    pub(crate) env_trimmer: EnvTrimmer<I>,
 }
 
 impl<I: Interner> Forest<I> {
@@ -24,7 +52,310 @@ impl<I: Interner> Forest<I> {
         Forest {
             tables: Tables::new(),
             clock: TimeStamp::default(),
+            explain: false,
+            shared: None,
+            stats: SolverStats::new(),
+            budget: TableBudget::default(),
+            env_trimmer: EnvTrimmer::new(),
+        }
+    }
+
+    /// Creates a forest that reuses the completed tables in `shared`, and
+    /// can publish its own to it (see `publish_completed_tables`).
+    pub(crate) fn sharing(shared: Arc<SharedTables<I>>) -> Self {
+        Forest {
+            shared: Some(shared),
+            ..Forest::new()
+        }
+    }
+
+    /// Adds our completed tables to the shared tables, along with the items
+    /// their answers depend upon (transitively, through the tables of their
+    /// subgoals). Must only be called between solves, when no strand is
+    /// active.
+    pub(crate) fn publish_completed_tables(&self) {
+        let shared = match &self.shared {
+            Some(shared) => shared,
+            None => return,
+        };
+        for index in self.tables.live_indices() {
+            let table = &self.tables[index];
+            if !table.is_completed() || shared.contains(&table.table_goal) {
+                continue;
+            }
+            let completed = table.to_completed(
+                self.transitive_dependencies(index),
+                self.transitive_overflow(index),
+                self.transitive_lookup_failure(index),
+            );
+            shared.insert(table.table_goal.clone(), completed);
+        }
+    }
+
+    /// `table` and the tables of the subgoals selected (transitively) by
+    /// its strands, in the order they are reached.
+    fn reachable_tables(&self, table: TableIndex) -> Vec<TableIndex> {
+        let mut reachable = vec![];
+        let mut visited = FxHashSet::default();
+        let mut stack = vec![table];
+        while let Some(table) = stack.pop() {
+            if visited.insert(table) {
+                reachable.push(table);
+                stack.extend(self.tables[table].subgoal_tables.iter().copied());
+            }
+        }
+        reachable
+    }
+
+    /// The items consulted by `table`, or by the tables of the subgoals
+    /// selected (transitively) by its strands.
+    fn transitive_dependencies(&self, table: TableIndex) -> Dependencies<I> {
+        let mut dependencies = Dependencies::new();
+        for table in self.reachable_tables(table) {
+            dependencies.extend(&self.tables[table].dependencies);
+        }
+        dependencies
+    }
+
+    /// The first overflow recorded for `table`, or for the tables of the
+    /// subgoals selected (transitively) by its strands.
+    fn transitive_overflow(&self, table: TableIndex) -> Option<Overflow<I>> {
+        self.reachable_tables(table)
+            .into_iter()
+            .find_map(|table| self.tables[table].overflow().cloned())
+    }
+
+    /// The first failed lookup recorded for `table`, or for the tables of
+    /// the subgoals selected (transitively) by its strands.
+    fn transitive_lookup_failure(&self, table: TableIndex) -> Option<ItemId<I>> {
+        self.reachable_tables(table)
+            .into_iter()
+            .find_map(|table| self.tables[table].lookup_failure().cloned())
+    }
+
+    /// Completes an ambiguous `solution` for the root `goal` with what the
+    /// tables of the goal and its subgoals tell us about the ambiguity. A
+    /// solution without any guidance becomes an overflow if the goal or
+    /// one of its subgoals overflowed, as the ambiguity is then likely due
+    /// to the overflow.
+    pub(crate) fn report_ambiguity(
+        &self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+        solution: Option<Solution<I>>,
+    ) -> Option<Solution<I>> {
+        let table = match (&solution, self.tables.index_of(goal)) {
+            (Some(Solution::Ambig(_)), Some(table)) => table,
+            _ => return solution,
+        };
+        let guidance = match solution {
+            Some(Solution::Ambig(Guidance::Unknown(_))) => {
+                if let Some(overflow) = self.transitive_overflow(table) {
+                    return Some(Solution::Overflow(overflow));
+                }
+                Guidance::Unknown(self.ambiguity_cause(program, table))
+            }
+            Some(Solution::Ambig(Guidance::Definite(subst, AmbiguityCause::Other))) => {
+                Guidance::Definite(subst, self.ambiguity_cause(program, table))
+            }
+            Some(Solution::Ambig(Guidance::Suggested(subst, AmbiguityCause::Other))) => {
+                Guidance::Suggested(subst, self.ambiguity_cause(program, table))
+            }
+            solution => return solution,
+        };
+        Some(Solution::Ambig(guidance))
+    }
+
+    /// Why the goal of `table` is ambiguous: because it or one of the
+    /// tables of its subgoals overflowed, failed to look up an item or
+    /// floundered, or because of what
+    /// the goals of the tables with several or ambiguous answers tell us
+    /// (see `AmbiguityCause::of_goal`).
+    fn ambiguity_cause(
+        &self,
+        program: &dyn RustIrDatabase<I>,
+        table: TableIndex,
+    ) -> AmbiguityCause<I> {
+        let reachable = self.reachable_tables(table);
+        if reachable
+            .iter()
+            .any(|&table| self.tables[table].overflow().is_some())
+        {
+            return AmbiguityCause::Overflow;
+        }
+        if let Some(item) = self.transitive_lookup_failure(table) {
+            return AmbiguityCause::LookupFailed(item);
         }
+
+        let interner = program.interner();
+        let mut cause = AmbiguityCause::Other;
+        for &index in &reachable {
+            let table = &self.tables[index];
+            let floundered = table.is_floundered() || table.has_floundered_answers();
+            // Tables with several answers, or ambiguous ones, are where the
+            // ambiguity comes from.
+            let ambiguous =
+                table.answers().nth(1).is_some() || table.answers().any(|answer| answer.ambiguous);
+            if !floundered && !ambiguous && index != reachable[0] {
+                continue;
+            }
+            let goal_cause = match &table.table_goal.canonical.value.goal.data(interner) {
+                GoalData::DomainGoal(domain_goal) => AmbiguityCause::of_goal(
+                    program,
+                    &domain_goal_of(&table.table_goal, domain_goal),
+                ),
+                _ => None,
+            };
+            cause = cause.merge(match goal_cause {
+                // We usually flounder because the self type is unknown.
+                Some(AmbiguityCause::UnresolvedSelfType) => AmbiguityCause::UnresolvedSelfType,
+                _ if floundered => AmbiguityCause::Floundered,
+                Some(goal_cause) => goal_cause,
+                None => AmbiguityCause::Other,
+            });
+        }
+        cause
+    }
+
+    /// Creates a forest that records the information needed to build
+    /// proof trees (see `proof_tree`).
+    pub fn explaining() -> Self {
+        Forest {
+            explain: true,
+            ..Forest::new()
+        }
+    }
+
+    /// Retires the tables whose answers may depend on any of the `changed`
+    /// items: those that consulted one of them directly, and, transitively,
+    /// those whose strands selected a subgoal from a retired table.
+    pub(crate) fn invalidate(&mut self, changed: &Dependencies<I>) {
+        let tables = &self.tables;
+        let invalid: Vec<TableIndex> = tables
+            .live_indices()
+            .filter(|&table| tables[table].dependencies.affected_by(changed))
+            .collect();
+        let retired = self.retire_with_dependents(invalid);
+        self.env_trimmer.invalidate(changed);
+        debug!("invalidate: retired {} tables", retired);
+    }
+
+    /// Evicts the least recently used completed tables until we are
+    /// within our budget again. Must only be called between solves, when
+    /// no strand is active.
+    pub(crate) fn evict(&mut self) {
+        let mut count = self.tables.len();
+        // Summing up the sizes of our tables takes a while, so only do it
+        // if we are going to need it.
+        let mut bytes = match self.budget.max_bytes {
+            Some(_) => self.approximate_size(),
+            None => 0,
+        };
+        if self.budget.allows(count, bytes) {
+            return;
+        }
+        let recency = self.recency();
+        let dependents = self.dependents();
+        let tables = &self.tables;
+        let mut candidates: Vec<TableIndex> = tables
+            .live_indices()
+            .filter(|&table| tables[table].is_completed())
+            .collect();
+        candidates.sort_by_key(|table| recency[table]);
+        let mut retired = FxHashSet::default();
+        for table in candidates {
+            if self.budget.allows(count, bytes) {
+                break;
+            }
+            // Tables evicted already, as dependents of another table, are
+            // skipped here.
+            let evicted = with_dependents(vec![table], &dependents, &mut retired);
+            for &table in &evicted {
+                bytes = bytes.saturating_sub(self.tables[table].approximate_size());
+                self.tables.retire(table);
+            }
+            count -= evicted.len();
+            debug!(?table, "evict: evicted {} tables", evicted.len());
+            self.stats.evictions += evicted.len();
+        }
+    }
+
+    /// Retires the tables that were on the stack when a panic unwound
+    /// through a solve, along with their dependents. Strands of theirs
+    /// may have been lost on the way out, so their answers cannot be
+    /// trusted to be complete.
+    pub(crate) fn abandon(&mut self, tables: Vec<TableIndex>) {
+        let retired = self.retire_with_dependents(tables);
+        debug!("abandon: retired {} tables", retired);
+    }
+
+    /// Drops all of our tables. Must only be called between solves.
+    pub(crate) fn clear(&mut self) {
+        self.stats.evictions += self.tables.len();
+        self.tables = Tables::new();
+        self.env_trimmer.clear();
+    }
+
+    /// When each table was last used, either directly or by one of the
+    /// tables that depend on it: evicting a table evicts those as well
+    /// (see `retire_with_dependents`), so it is only as stale as they are.
+    fn recency(&self) -> FxHashMap<TableIndex, u64> {
+        let tables = &self.tables;
+        let mut by_last_use: Vec<TableIndex> = tables.live_indices().collect();
+        by_last_use.sort_by_key(|&table| std::cmp::Reverse(tables[table].last_used));
+
+        // Each table gets the time of the most recently used table that
+        // reaches it, which is the first one to do so in this order.
+        let mut recency = FxHashMap::default();
+        for root in by_last_use {
+            let last_used = tables[root].last_used;
+            let mut stack = vec![root];
+            while let Some(table) = stack.pop() {
+                if recency.contains_key(&table) {
+                    continue;
+                }
+                recency.insert(table, last_used);
+                stack.extend(tables[table].subgoal_tables.iter().copied());
+            }
+        }
+        recency
+    }
+
+    /// The number of tables we hold.
+    pub(crate) fn table_count(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// A rough estimate of the memory our tables hold, in bytes (see
+    /// `Table::approximate_size`).
+    pub(crate) fn approximate_size(&self) -> usize {
+        self.tables
+            .live_indices()
+            .map(|table| self.tables[table].approximate_size())
+            .sum()
+    }
+
+    /// Retires `tables` along with the tables that depend on them,
+    /// transitively: their strands may refer to the retired tables, and
+    /// their answers may have been derived from the answers of those.
+    /// Returns the number of tables retired.
+    fn retire_with_dependents(&mut self, tables: Vec<TableIndex>) -> usize {
+        let retired = with_dependents(tables, &self.dependents(), &mut FxHashSet::default());
+        for &table in &retired {
+            self.tables.retire(table);
+        }
+        retired.len()
+    }
+
+    /// For each table, the tables whose strands selected a subgoal from it.
+    fn dependents(&self) -> FxHashMap<TableIndex, Vec<TableIndex>> {
+        let mut dependents: FxHashMap<TableIndex, Vec<TableIndex>> = FxHashMap::default();
+        for table in self.tables.live_indices() {
+            for &subgoal_table in &self.tables[table].subgoal_tables {
+                dependents.entry(subgoal_table).or_default().push(table);
+            }
+        }
+        dependents
     }
 
     // Gets the next clock TimeStamp. This will never decrease.
@@ -33,6 +364,101 @@ impl<I: Interner> Forest<I> {
         self.clock
     }
 
+    /// Builds a proof tree for the given table from the candidates recorded
+    /// in it and (recursively) in the tables of its subgoals. The forest
+    /// must have been created with `explaining`.
+    pub(crate) fn proof_tree(&self, interner: &I, table: TableIndex) -> ProofTree<I> {
+        assert!(self.explain);
+        self.proof_tree_with_path(interner, table, &mut vec![])
+    }
+
+    fn proof_tree_with_path(
+        &self,
+        interner: &I,
+        table: TableIndex,
+        path: &mut Vec<TableIndex>,
+    ) -> ProofTree<I> {
+        let goal = self.tables[table].table_goal.clone();
+        if path.contains(&table) {
+            return ProofTree::leaf(goal, ProofOutcome::Cycle);
+        }
+        path.push(table);
+
+        let table_ref = &self.tables[table];
+        let candidates = table_ref
+            .candidates()
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| {
+                let subgoals: Vec<_> = candidate
+                    .subgoals
+                    .iter()
+                    .map(|&(subgoal, positive)| {
+                        (self.proof_tree_with_path(interner, subgoal, path), positive)
+                    })
+                    .collect();
+                // A strand cannot succeed once one of its positive subgoals
+                // has failed, even if the strand itself was pruned.
+                let subgoal_failed = subgoals
+                    .iter()
+                    .any(|(tree, positive)| *positive && tree.outcome == ProofOutcome::Failed);
+                let outcome = if candidate.proven {
+                    ProofOutcome::Proven
+                } else if candidate.ambiguous {
+                    ProofOutcome::Ambiguous
+                } else if candidate.floundered {
+                    ProofOutcome::Floundered
+                } else if !subgoal_failed
+                    && (table_ref.has_strands_for(index) || table_ref.is_pruned())
+                {
+                    ProofOutcome::Incomplete
+                } else {
+                    ProofOutcome::Failed
+                };
+                ProofCandidate {
+                    clause: candidate.clause.clone(),
+                    outcome,
+                    subgoals: subgoals.into_iter().map(|(tree, _)| tree).collect(),
+                }
+            })
+            .collect();
+
+        let outcome = if table_ref.is_floundered() {
+            ProofOutcome::Floundered
+        } else if self.has_unique_answer(interner, table) {
+            ProofOutcome::Proven
+        } else if table_ref.answers().next().is_some() {
+            ProofOutcome::Ambiguous
+        } else if table_ref.strands().next().is_some() {
+            ProofOutcome::Incomplete
+        } else {
+            ProofOutcome::Failed
+        };
+
+        path.pop();
+        ProofTree {
+            goal,
+            outcome,
+            candidates,
+        }
+    }
+
+    /// True if the table has an unambiguous answer that is either its only
+    /// answer or holds for all values of the table's variables.
+    fn has_unique_answer(&self, interner: &I, table: TableIndex) -> bool {
+        let table = &self.tables[table];
+        let mut answers = table.answers().filter(|answer| !answer.ambiguous);
+        match answers.next() {
+            Some(_) if table.answers().count() == 1 => true,
+            Some(first) => std::iter::once(first).chain(answers).any(|answer| {
+                table
+                    .table_goal
+                    .is_trivial_substitution(interner, &answer.subst)
+            }),
+            None => false,
+        }
+    }
+
     /// Returns a "solver" for a given goal in the form of an
     /// iterator. Each time you invoke `next`, it will do the work to
     /// extract one more answer. These answers are cached in between
@@ -53,11 +479,11 @@ impl<I: Interner> Forest<I> {
     }
 }
 
-struct ForestSolver<'me, I: Interner> {
-    forest: &'me mut Forest<I>,
-    context: &'me SlgContextOps<'me, I>,
-    table: TableIndex,
-    answer: AnswerIndex,
+pub(crate) struct ForestSolver<'me, I: Interner> {
+    pub(crate) forest: &'me mut Forest<I>,
+    pub(crate) context: &'me SlgContextOps<'me, I>,
+    pub(crate) table: TableIndex,
+    pub(crate) answer: AnswerIndex,
 }
 
 impl<'me, I: Interner> AnswerStream<I> for ForestSolver<'me, I> {
@@ -114,3 +540,35 @@ impl<'me, I: Interner> AnswerStream<I> for ForestSolver<'me, I> {
         self.forest.any_future_answer(self.table, self.answer, test)
     }
 }
+
+/// The table goal `goal`, whose goal is `domain_goal`, as a domain goal.
+fn domain_goal_of<I: Interner>(
+    goal: &UCanonical<InEnvironment<Goal<I>>>,
+    domain_goal: &DomainGoal<I>,
+) -> UCanonical<InEnvironment<DomainGoal<I>>> {
+    UCanonical {
+        universes: goal.universes,
+        canonical: Canonical {
+            binders: goal.canonical.binders.clone(),
+            value: InEnvironment::new(&goal.canonical.value.environment, domain_goal.clone()),
+        },
+    }
+}
+
+/// Adds `tables` and, transitively, their `dependents` to `retired`,
+/// returning the tables that were not in it yet.
+fn with_dependents(
+    tables: Vec<TableIndex>,
+    dependents: &FxHashMap<TableIndex, Vec<TableIndex>>,
+    retired: &mut FxHashSet<TableIndex>,
+) -> Vec<TableIndex> {
+    let mut added = vec![];
+    let mut stack = tables;
+    while let Some(table) = stack.pop() {
+        if retired.insert(table) {
+            added.push(table);
+            stack.extend(dependents.get(&table).into_iter().flatten().copied());
+        }
+    }
+    added
+}
@@ -12,11 +12,15 @@ use crate::{
 
 use chalk_ir::interner::Interner;
 use chalk_ir::{
-    AnswerSubst, Canonical, CanonicalVarKinds, ConstrainedSubst, FallibleOrFloundered, Floundered,
-    Goal, GoalData, InEnvironment, NoSolution, Substitution, UCanonical, UniverseMap,
+    AnswerSubst, Canonical, CanonicalVarKinds, ConstrainedSubst, FallibleOrFloundered, Goal,
+    GoalData, InEnvironment, NoSolution, Substitution, UCanonical, UniverseMap,
 };
-use chalk_solve::clauses::program_clauses_for_goal;
+use chalk_solve::clauses::{program_clauses_for_goal, ClauseError};
 use chalk_solve::coinductive_goal::IsCoinductive;
+use chalk_solve::dependencies::DependencyRecorder;
+use chalk_solve::env_trim::keep_clauses;
+use chalk_solve::solve::{Overflow, OverflowLimit};
+use std::thread;
 use tracing::{debug, debug_span, info, instrument};
 
 type RootSearchResult<T> = Result<T, RootSearchFail>;
@@ -145,6 +149,7 @@ impl<I: Interner> Forest<I> {
             ex_clause,
             selected_subgoal,
             last_pursued_time,
+            candidate,
         } = strand;
         Forest::canonicalize_strand_from(
             context,
@@ -152,6 +157,7 @@ impl<I: Interner> Forest<I> {
             &ex_clause,
             selected_subgoal,
             last_pursued_time,
+            candidate,
         )
     }
 
@@ -161,6 +167,7 @@ impl<I: Interner> Forest<I> {
         ex_clause: &ExClause<I>,
         selected_subgoal: Option<SelectedSubgoal>,
         last_pursued_time: TimeStamp,
+        candidate: Option<usize>,
     ) -> CanonicalStrand<I> {
         let canonical_ex_clause =
             infer.canonicalize_ex_clause(context.program().interner(), &ex_clause);
@@ -168,6 +175,7 @@ impl<I: Interner> Forest<I> {
             canonical_ex_clause,
             selected_subgoal,
             last_pursued_time,
+            candidate,
         }
     }
 
@@ -179,7 +187,13 @@ impl<I: Interner> Forest<I> {
     /// Returns `None` if the literal cannot be converted into a table
     /// -- for example, this can occur when we have selected a
     /// negative literal with free existential variables, in which
-    /// case the execution is said to "flounder".
+    /// case the execution is said to "flounder". If that is because the
+    /// subgoal is too big, the overflow is recorded on `selecting_table`.
+    ///
+    /// The table goal of a positive literal only keeps the environment
+    /// clauses that can affect it (see `EnvTrimmer`), so that it shares its
+    /// table with the subgoals that only differ from it in the others; we
+    /// also return which clauses were kept, if any were dropped.
     ///
     /// In terms of the NFTD paper, creating a new table corresponds
     /// to the *New Subgoal* step as well as the *Program Clause
@@ -189,23 +203,45 @@ impl<I: Interner> Forest<I> {
         &mut self,
         context: &SlgContextOps<I>,
         infer: &mut TruncatingInferenceTable<I>,
+        selecting_table: TableIndex,
         subgoal: &Literal<I>,
-    ) -> Option<(TableIndex, UniverseMap)> {
+    ) -> Option<(TableIndex, UniverseMap, Option<Vec<bool>>)> {
         // Subgoal abstraction:
-        let (ucanonical_subgoal, universe_map) = match subgoal {
+        let (mut ucanonical_subgoal, universe_map) = match subgoal {
             Literal::Positive(subgoal) => {
-                Forest::abstract_positive_literal(context, infer, subgoal)?
+                self.abstract_positive_literal(context, infer, selecting_table, subgoal)?
             }
             Literal::Negative(subgoal) => {
-                Forest::abstract_negative_literal(context, infer, subgoal)?
+                self.abstract_negative_literal(context, infer, selecting_table, subgoal)?
             }
         };
 
+        let mut kept_clauses = None;
+        if let Literal::Positive(_) = subgoal {
+            let program = context.program();
+            let recorder = program.dependency_recorder();
+            let _unwind_guard = recorder.map(DependencyRecorder::unwind_guard);
+            if let Some(recorder) = recorder {
+                recorder.start();
+            }
+            let value = &mut ucanonical_subgoal.canonical.value;
+            if let Some(kept) = self.env_trimmer.relevant_clauses(program, value) {
+                *value = keep_clauses(program.interner(), value, &kept);
+                kept_clauses = Some(kept);
+            }
+            if let Some(recorder) = recorder {
+                let dependencies = recorder.finish();
+                self.tables[selecting_table]
+                    .dependencies
+                    .extend(&dependencies);
+            }
+        }
+
         debug!(?ucanonical_subgoal, ?universe_map);
 
         let table = self.get_or_create_table_for_ucanonical_goal(context, ucanonical_subgoal);
 
-        Some((table, universe_map))
+        Some((table, universe_map, kept_clauses))
     }
 
     /// Given a u-canonical goal, searches for an existing table. If
@@ -223,15 +259,25 @@ impl<I: Interner> Forest<I> {
     ) -> TableIndex {
         if let Some(table) = self.tables.index_of(&goal) {
             debug!(?table, "found existing table");
+            self.stats.cache_hits += 1;
+            self.tables.touch(table);
             return table;
         }
 
+        if let Some(completed) = self.shared.as_ref().and_then(|shared| shared.get(&goal)) {
+            debug!("found completed table in shared tables");
+            self.stats.cache_hits += 1;
+            return self.tables.insert(Table::from_completed(goal, &completed));
+        }
+
         info!(
             table = ?self.tables.next_index(),
             "creating new table with goal = {:#?}",
             goal,
         );
-        let table = Self::build_table(context, self.tables.next_index(), goal);
+        self.stats.tables += 1;
+        self.stats.cache_misses += 1;
+        let table = Self::build_table(context, self.tables.next_index(), goal, self.explain);
         self.tables.insert(table)
     }
 
@@ -250,16 +296,22 @@ impl<I: Interner> Forest<I> {
         context: &SlgContextOps<I>,
         table_idx: TableIndex,
         goal: UCanonical<InEnvironment<Goal<I>>>,
+        explain: bool,
     ) -> Table<I> {
+        let recorder = context.program().dependency_recorder();
+        let _unwind_guard = recorder.map(DependencyRecorder::unwind_guard);
+        if let Some(recorder) = recorder {
+            recorder.start();
+        }
         let coinductive = goal.is_coinductive(context.program());
-        let mut table = Table::new(goal.clone(), coinductive);
+        let mut table = Table::new(goal.clone(), coinductive, explain);
         let (infer, subst, InEnvironment { environment, goal }) =
             chalk_solve::infer::InferenceTable::from_canonical(
                 context.program().interner(),
                 goal.universes,
                 &goal.canonical,
             );
-        let mut infer = TruncatingInferenceTable::new(context.max_size(), infer);
+        let mut infer = context.truncating_table(infer);
         let goal_data = goal.data(context.program().interner());
 
         match goal_data {
@@ -276,6 +328,7 @@ impl<I: Interner> Forest<I> {
                     Ok(clauses) => {
                         for clause in clauses {
                             info!("program clause = {:#?}", clause);
+                            let candidate = table.push_candidate(Some(clause.clone()));
                             let mut infer = infer.clone();
                             if let Ok(resolvent) = infer.resolvent_clause(
                                 context.unification_database(),
@@ -291,13 +344,14 @@ impl<I: Interner> Forest<I> {
                                     ex_clause: resolvent,
                                     selected_subgoal: None,
                                     last_pursued_time: TimeStamp::default(),
+                                    candidate,
                                 };
                                 let canonical_strand = Self::canonicalize_strand(context, strand);
                                 table.enqueue_strand(canonical_strand);
                             }
                         }
                     }
-                    Err(Floundered) => {
+                    Err(ClauseError::Floundered) => {
                         debug!(
                             table = ?table_idx,
                             "Marking table {:?} as floundered! (failed to create program clauses)",
@@ -305,6 +359,15 @@ impl<I: Interner> Forest<I> {
                         );
                         table.mark_floundered();
                     }
+                    Err(ClauseError::LookupFailed(error)) => {
+                        debug!(
+                            table = ?table_idx,
+                            item = ?error.item,
+                            "Marking table {:?} as floundered! (failed to look up an item)",
+                            table_idx
+                        );
+                        table.mark_lookup_failed(error.item);
+                    }
                 }
             }
 
@@ -317,6 +380,7 @@ impl<I: Interner> Forest<I> {
                 // simplified subgoals. You can think of this as
                 // applying built-in "meta program clauses" that
                 // reduce goals into Domain goals.
+                let candidate = table.push_candidate(None);
                 match Self::simplify_goal(context, &mut infer, subst, environment, goal) {
                     FallibleOrFloundered::Ok(ex_clause) => {
                         info!(
@@ -328,6 +392,7 @@ impl<I: Interner> Forest<I> {
                             ex_clause,
                             selected_subgoal: None,
                             last_pursued_time: TimeStamp::default(),
+                            candidate,
                         };
                         let canonical_strand = Self::canonicalize_strand(context, strand);
                         table.enqueue_strand(canonical_strand);
@@ -338,6 +403,9 @@ impl<I: Interner> Forest<I> {
             }
         }
 
+        if let Some(recorder) = recorder {
+            table.dependencies = recorder.finish();
+        }
         table
     }
 
@@ -345,14 +413,18 @@ impl<I: Interner> Forest<I> {
     /// abstraction function to yield the canonical form that will be
     /// used to pick a table. Typically, this abstraction has no
     /// effect, and hence we are simply returning the canonical form
-    /// of `subgoal`; but if the subgoal is getting too big, we return
-    /// `None`, which causes the subgoal to flounder.
+    /// of `subgoal`; but if the subgoal is getting too big, we record
+    /// the overflow on `selecting_table` and return `None`, which causes
+    /// the subgoal to flounder.
     fn abstract_positive_literal(
+        &mut self,
         context: &SlgContextOps<I>,
         infer: &mut TruncatingInferenceTable<I>,
+        selecting_table: TableIndex,
         subgoal: &InEnvironment<Goal<I>>,
     ) -> Option<(UCanonical<InEnvironment<Goal<I>>>, UniverseMap)> {
         if infer.goal_needs_truncation(context.program().interner(), subgoal) {
+            self.record_subgoal_overflow(context, infer, selecting_table, subgoal);
             None
         } else {
             Some(infer.fully_canonicalize_goal(context.program().interner(), subgoal))
@@ -367,8 +439,10 @@ impl<I: Interner> Forest<I> {
     /// variables appear in `subgoal` (in which case the execution is
     /// said to "flounder").
     fn abstract_negative_literal(
+        &mut self,
         context: &SlgContextOps<I>,
         infer: &mut TruncatingInferenceTable<I>,
+        selecting_table: TableIndex,
         subgoal: &InEnvironment<Goal<I>>,
     ) -> Option<(UCanonical<InEnvironment<Goal<I>>>, UniverseMap)> {
         // First, we have to check that the selected negative literal
@@ -411,11 +485,29 @@ impl<I: Interner> Forest<I> {
         let inverted_subgoal = infer.invert_goal(context.program().interner(), subgoal)?;
 
         if infer.goal_needs_truncation(context.program().interner(), &inverted_subgoal) {
+            self.record_subgoal_overflow(context, infer, selecting_table, &inverted_subgoal);
             None
         } else {
             Some(infer.fully_canonicalize_goal(context.program().interner(), &inverted_subgoal))
         }
     }
+
+    /// Records that `subgoal`, selected by a strand of `selecting_table`,
+    /// is too big to get a table of its own.
+    fn record_subgoal_overflow(
+        &mut self,
+        context: &SlgContextOps<I>,
+        infer: &mut TruncatingInferenceTable<I>,
+        selecting_table: TableIndex,
+        subgoal: &InEnvironment<Goal<I>>,
+    ) {
+        self.stats.overflows += 1;
+        let (goal, _) = infer.fully_canonicalize_goal(context.program().interner(), subgoal);
+        self.tables[selecting_table].record_overflow(Overflow {
+            goal: goal.canonical,
+            limit: OverflowLimit::Size(context.max_size()),
+        });
+    }
 }
 
 pub(crate) struct SolveState<'forest, I: Interner> {
@@ -426,6 +518,15 @@ pub(crate) struct SolveState<'forest, I: Interner> {
 
 impl<'forest, I: Interner> Drop for SolveState<'forest, I> {
     fn drop(&mut self) {
+        if thread::panicking() {
+            // We may be unwinding from a `RustIrDatabase` callback, which
+            // must not be called again here, and the strand we were
+            // working on may be lost. Give up on the tables we were in the
+            // middle of instead, so that later solves start them afresh.
+            let tables = self.stack.tables().collect();
+            self.forest.abandon(tables);
+            return;
+        }
         if !self.stack.is_empty() {
             if let Some(active_strand) = self.stack.top().active_strand.take() {
                 let table = self.stack.top().table;
@@ -509,6 +610,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                             canonical_ex_clause,
                             selected_subgoal,
                             last_pursued_time,
+                            candidate,
                         } = canonical_strand;
 
                         let (infer, _, ex_clause) =
@@ -517,12 +619,13 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                                 num_universes,
                                 &canonical_ex_clause,
                             );
-                        let infer = TruncatingInferenceTable::new(context.max_size(), infer);
+                        let infer = context.truncating_table(infer);
                         Strand {
                             infer,
                             ex_clause,
                             selected_subgoal,
                             last_pursued_time,
+                            candidate,
                         }
                     })
             });
@@ -624,6 +727,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                     ex_clause: strand.ex_clause.clone(),
                     selected_subgoal: Some(next_subgoal),
                     last_pursued_time: strand.last_pursued_time,
+                    candidate: strand.candidate,
                 };
                 let table = self.stack.top().table;
                 let canonical_next_strand = Forest::canonicalize_strand(self.context, next_strand);
@@ -644,6 +748,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                     subgoal_table,
                     answer_index,
                     ref universe_map,
+                    ref kept_clauses,
                 } = selected_subgoal;
                 use chalk_solve::infer::ucanonicalize::UniverseMapExt;
                 let table_goal = universe_map.map_from_canonical(
@@ -654,20 +759,35 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                     self.context.program().interner(),
                     &self.forest.answer(subgoal_table, answer_index).subst,
                 );
-                match strand.infer.apply_answer_subst(
+                // Unifying with the answer consults the variances of the
+                // types involved, which our answers then depend upon.
+                let recorder = self.context.program().dependency_recorder();
+                let _unwind_guard = recorder.map(DependencyRecorder::unwind_guard);
+                if let Some(recorder) = recorder {
+                    recorder.start();
+                }
+                let result = strand.infer.apply_answer_subst(
                     self.context.program().interner(),
                     self.context.unification_database(),
                     &mut strand.ex_clause,
                     &subgoal,
+                    kept_clauses.as_deref(),
                     &table_goal,
                     &answer_subst,
-                ) {
+                );
+                if let Some(recorder) = recorder {
+                    let table = self.stack.top().table;
+                    let dependencies = recorder.finish();
+                    self.forest.tables[table].dependencies.extend(&dependencies);
+                }
+                match result {
                     Ok(()) => {
                         let Strand {
                             infer: _,
                             ex_clause,
                             selected_subgoal: _,
                             last_pursued_time: _,
+                            candidate: _,
                         } = strand;
 
                         // If the answer had was ambiguous, we have to
@@ -707,6 +827,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                     subgoal_table,
                     answer_index,
                     universe_map: _,
+                    kept_clauses: _,
                 } = selected_subgoal;
                 // We got back an answer. This is bad, because we want
                 // to disprove the subgoal, but it may be
@@ -903,6 +1024,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
             subgoal_table,
             answer_index,
             universe_map: _,
+            kept_clauses: _,
         } = *strand.selected_subgoal.as_ref().unwrap();
 
         debug!(
@@ -991,6 +1113,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
         let floundered = !strand.ex_clause.floundered_subgoals.is_empty();
         if floundered {
             debug!("all remaining subgoals floundered for the table");
+            self.forest.tables[self.stack.top().table].record_floundered_answer();
         } else {
             debug!("no remaining subgoals for the table");
         };
@@ -1080,7 +1203,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
             num_universes,
             &answer.subst,
         );
-        let table = TruncatingInferenceTable::new(self.context.max_size(), infer);
+        let table = self.context.truncating_table(infer);
 
         let delayed_subgoals = delayed_subgoals
             .into_iter()
@@ -1102,6 +1225,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
             },
             selected_subgoal: None,
             last_pursued_time: TimeStamp::default(),
+            candidate: None,
         };
 
         Some(Forest::canonicalize_strand(self.context, strand))
@@ -1267,6 +1391,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                 canonical_ex_clause,
                 selected_subgoal,
                 last_pursued_time: _,
+                candidate: _,
             } = strand;
             let selected_subgoal = selected_subgoal.unwrap_or_else(|| {
                 panic!(
@@ -1310,14 +1435,31 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                 match self.forest.get_or_create_table_for_subgoal(
                     self.context,
                     &mut strand.infer,
+                    self.stack.top().table,
                     &strand.ex_clause.subgoals[subgoal_index],
                 ) {
-                    Some((subgoal_table, universe_map)) => {
+                    Some((subgoal_table, universe_map, kept_clauses)) => {
+                        let table = self.stack.top().table;
+                        self.forest.tables[table]
+                            .subgoal_tables
+                            .insert(subgoal_table);
+                        if let Some(candidate) =
+                            self.forest.tables[table].candidate_mut(strand.candidate)
+                        {
+                            let positive = match strand.ex_clause.subgoals[subgoal_index] {
+                                Literal::Positive(_) => true,
+                                Literal::Negative(_) => false,
+                            };
+                            if !candidate.subgoals.contains(&(subgoal_table, positive)) {
+                                candidate.subgoals.push((subgoal_table, positive));
+                            }
+                        }
                         strand.selected_subgoal = Some(SelectedSubgoal {
                             subgoal_index,
                             subgoal_table,
                             universe_map,
                             answer_index: AnswerIndex::ZERO,
+                            kept_clauses,
                         });
                     }
 
@@ -1325,6 +1467,12 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                         // If we failed to create a table for the subgoal,
                         // that is because we have a floundered negative
                         // literal.
+                        let table = self.stack.top().table;
+                        if let Some(candidate) =
+                            self.forest.tables[table].candidate_mut(strand.candidate)
+                        {
+                            candidate.floundered = true;
+                        }
                         self.flounder_subgoal(&mut strand.ex_clause, subgoal_index);
                     }
                 }
@@ -1372,6 +1520,7 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
                 },
             selected_subgoal: _,
             last_pursued_time: _,
+            candidate,
         } = strand;
         // If there are subgoals left, they should be followed
         assert!(subgoals.is_empty());
@@ -1404,7 +1553,13 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
         // down to "it works as we expect for the current tests". And, we likely don't
         // even *need* the added complexity just for potentially more answers.
         if infer.answer_needs_truncation(self.context.program().interner(), &subst) {
-            self.forest.tables[table].mark_floundered();
+            self.forest.stats.overflows += 1;
+            let table = &mut self.forest.tables[table];
+            table.record_overflow(Overflow {
+                goal: table.table_goal.canonical.clone(),
+                limit: OverflowLimit::Size(self.context.max_size()),
+            });
+            table.mark_floundered();
             return None;
         }
 
@@ -1430,6 +1585,15 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
 
         let answer = Answer { subst, ambiguous };
 
+        if let Some(candidate) = self.forest.tables[table].candidate_mut(candidate) {
+            if ambiguous {
+                candidate.ambiguous = true;
+            } else {
+                candidate.proven = true;
+            }
+            candidate.floundered |= floundered;
+        }
+
         // A "trivial" answer is one that is 'just true for all cases'
         // -- in other words, it gives no information back to the
         // caller. For example, `Vec<u32>: Sized` is "just true".
@@ -1500,10 +1664,11 @@ impl<'forest, I: Interner> SolveState<'forest, I> {
         };
 
         if let Some(answer_index) = self.forest.tables[table].push_answer(answer) {
+            self.forest.stats.answers += 1;
             // See above, if we have a *complete* and trivial answer, we don't
             // want to follow any more strands
             if !ambiguous && is_trivial_answer {
-                self.forest.tables[table].take_strands();
+                self.forest.tables[table].prune_strands();
             }
 
             Some(answer_index)
@@ -7,7 +7,7 @@ use chalk_ir::interner::Interner;
 use chalk_ir::*;
 use chalk_solve::infer::ucanonicalize::UCanonicalized;
 use chalk_solve::infer::unify::RelationResult;
-use chalk_solve::infer::InferenceTable;
+use chalk_solve::infer::{InferenceTable, NormalizationMode};
 use chalk_solve::solve::truncate;
 use chalk_solve::RustIrDatabase;
 
@@ -39,6 +39,7 @@ pub(crate) struct SlgContextOps<'me, I: Interner> {
     program: &'me dyn RustIrDatabase<I>,
     max_size: usize,
     expected_answers: Option<usize>,
+    normalization: NormalizationMode,
 }
 
 impl<I: Interner> SlgContextOps<'_, I> {
@@ -46,11 +47,13 @@ impl<I: Interner> SlgContextOps<'_, I> {
         program: &dyn RustIrDatabase<I>,
         max_size: usize,
         expected_answers: Option<usize>,
+        normalization: NormalizationMode,
     ) -> SlgContextOps<'_, I> {
         SlgContextOps {
             program,
             max_size,
             expected_answers,
+            normalization,
         }
     }
 
@@ -82,6 +85,16 @@ impl<I: Interner> SlgContextOps<'_, I> {
         self.max_size
     }
 
+    /// Wraps an inference table created for one of our goals, setting it
+    /// up to unify the way the solver was asked to.
+    pub(crate) fn truncating_table(
+        &self,
+        mut infer: InferenceTable<I>,
+    ) -> TruncatingInferenceTable<I> {
+        infer.set_normalization_mode(self.normalization);
+        TruncatingInferenceTable::new(self.max_size, infer)
+    }
+
     pub(crate) fn unification_database(&self) -> &dyn UnificationDatabase<I> {
         self.program.unification_database()
     }
@@ -123,12 +136,17 @@ pub trait ResolventOps<I: Interner> {
         clause: &ProgramClause<I>,
     ) -> Fallible<ExClause<I>>;
 
+    /// Applies an answer of the table with goal `answer_table_goal` to
+    /// `selected_goal`, of which the table goal only keeps the environment
+    /// clauses in `kept_clauses` (if given).
+    #[allow(clippy::too_many_arguments)]
     fn apply_answer_subst(
         &mut self,
         interner: &I,
         unification_database: &dyn UnificationDatabase<I>,
         ex_clause: &mut ExClause<I>,
         selected_goal: &InEnvironment<Goal<I>>,
+        kept_clauses: Option<&[bool]>,
         answer_table_goal: &Canonical<InEnvironment<Goal<I>>>,
         canonical_answer_subst: &Canonical<AnswerSubst<I>>,
     ) -> Fallible<()>;
@@ -554,8 +572,20 @@ impl<I: Interner> MayInvalidate<'_, I> {
                 !c1.const_eq(new_ty, c2, interner)
             }
 
-            // Only variants left are placeholder = concrete, which always fails
-            (ConstValue::Placeholder(_), _) | (ConstValue::Concrete(_), _) => true,
+            (ConstValue::Unevaluated(u1), ConstValue::Unevaluated(u2)) => {
+                u1.interned != u2.interned
+                    || u1
+                        .substitution
+                        .iter(interner)
+                        .zip(u2.substitution.iter(interner))
+                        .any(|(new, current)| self.aggregate_generic_args(new, current))
+            }
+
+            // Only variants left are mismatches (e.g. placeholder = concrete),
+            // which always fail
+            (ConstValue::Placeholder(_), _)
+            | (ConstValue::Concrete(_), _)
+            | (ConstValue::Unevaluated(_), _) => true,
         }
     }
 
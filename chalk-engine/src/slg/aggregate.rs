@@ -7,7 +7,7 @@ use chalk_ir::interner::Interner;
 use chalk_ir::*;
 use chalk_solve::ext::*;
 use chalk_solve::infer::InferenceTable;
-use chalk_solve::solve::{Guidance, Solution};
+use chalk_solve::solve::{AmbiguityCause, Guidance, Solution};
 
 use std::fmt::Debug;
 
@@ -31,18 +31,25 @@ impl<I: Interner> AggregateOps<I> for SlgContextOps<'_, I> {
         should_continue: impl std::ops::Fn() -> bool,
     ) -> Option<Solution<I>> {
         let interner = self.program.interner();
+
+        // Why the solution is ambiguous, as far as we can tell from the
+        // answers; the forest may know more (see `Forest::report_ambiguity`).
+        let mut cause = AmbiguityCause::Other;
         let CompleteAnswer { subst, ambiguous } = match answers.next_answer(|| should_continue()) {
             AnswerResult::NoMoreSolutions => {
                 // No answers at all
                 return None;
             }
             AnswerResult::Answer(answer) => answer,
-            AnswerResult::Floundered => CompleteAnswer {
-                subst: self.identity_constrained_subst(root_goal),
-                ambiguous: true,
-            },
+            AnswerResult::Floundered => {
+                cause = AmbiguityCause::Floundered;
+                CompleteAnswer {
+                    subst: self.identity_constrained_subst(root_goal),
+                    ambiguous: true,
+                }
+            }
             AnswerResult::QuantumExceeded => {
-                return Some(Solution::Ambig(Guidance::Unknown));
+                return Some(Solution::Ambig(Guidance::Unknown(cause)));
             }
         };
 
@@ -50,10 +57,11 @@ impl<I: Interner> AggregateOps<I> for SlgContextOps<'_, I> {
         let next_answer = answers.peek_answer(|| should_continue());
         if next_answer.is_quantum_exceeded() {
             if subst.value.subst.is_identity_subst(interner) {
-                return Some(Solution::Ambig(Guidance::Unknown));
+                return Some(Solution::Ambig(Guidance::Unknown(cause)));
             } else {
                 return Some(Solution::Ambig(Guidance::Suggested(
                     subst.map(interner, |cs| cs.subst),
+                    cause,
                 )));
             }
         }
@@ -80,13 +88,13 @@ impl<I: Interner> AggregateOps<I> for SlgContextOps<'_, I> {
         let mut num_answers = 1;
         let guidance = loop {
             if subst.value.is_empty(interner) || is_trivial(interner, &subst) {
-                break Guidance::Unknown;
+                break Guidance::Unknown(cause);
             }
 
             if !answers
                 .any_future_answer(|ref mut new_subst| new_subst.may_invalidate(interner, &subst))
             {
-                break Guidance::Definite(subst);
+                break Guidance::Definite(subst, cause);
             }
 
             if let Some(expected_answers) = self.expected_answers {
@@ -99,13 +107,14 @@ impl<I: Interner> AggregateOps<I> for SlgContextOps<'_, I> {
                 AnswerResult::Answer(answer1) => answer1.subst,
                 AnswerResult::Floundered => {
                     // FIXME: this doesn't trigger for any current tests
+                    cause = AmbiguityCause::Floundered;
                     self.identity_constrained_subst(root_goal)
                 }
                 AnswerResult::NoMoreSolutions => {
-                    break Guidance::Definite(subst);
+                    break Guidance::Definite(subst, cause);
                 }
                 AnswerResult::QuantumExceeded => {
-                    break Guidance::Suggested(subst);
+                    break Guidance::Suggested(subst, cause);
                 }
             };
             subst = merge_into_guidance(interner, &root_goal.canonical, subst, &new_subst);
@@ -547,10 +556,30 @@ impl<I: Interner> AntiUnifier<'_, '_, I> {
                     self.new_const_variable(ty)
                 }
             }
-
-            (ConstValue::Placeholder(_), _) | (_, ConstValue::Placeholder(_)) => {
-                self.new_const_variable(ty)
+            (ConstValue::Unevaluated(u1), ConstValue::Unevaluated(u2))
+                if u1.interned == u2.interned =>
+            {
+                let substitution = Substitution::from_iter(
+                    interner,
+                    u1.substitution
+                        .iter(interner)
+                        .zip(u2.substitution.iter(interner))
+                        .map(|(p1, p2)| self.aggregate_generic_args(p1, p2)),
+                );
+                ConstData {
+                    ty,
+                    value: ConstValue::Unevaluated(UnevaluatedConst {
+                        interned: u1.interned.clone(),
+                        substitution,
+                    }),
+                }
+                .intern(interner)
             }
+
+            (ConstValue::Placeholder(_), _)
+            | (_, ConstValue::Placeholder(_))
+            | (ConstValue::Unevaluated(_), _)
+            | (_, ConstValue::Unevaluated(_)) => self.new_const_variable(ty),
         }
     }
 
@@ -6,6 +6,7 @@ use chalk_ir::fold::Fold;
 use chalk_ir::interner::{HasInterner, Interner};
 use chalk_ir::zip::{Zip, Zipper};
 use chalk_ir::*;
+use chalk_solve::env_trim::{keep_clauses, restore_clauses};
 use chalk_solve::infer::InferenceTable;
 use tracing::{debug, instrument};
 
@@ -110,9 +111,12 @@ impl<I: Interner> ResolventOps<I> for TruncatingInferenceTable<I> {
         // Add the subgoals/region-constraints that unification gave us.
         slg::into_ex_clause(interner, unification_result, &mut ex_clause);
 
-        ex_clause
-            .constraints
-            .extend(constraints.as_slice(interner).to_owned());
+        // The constraints of the clause hold in the environment of the goal.
+        ex_clause.constraints.extend(
+            constraints
+                .iter(interner)
+                .map(|constraint| InEnvironment::new(environment, constraint.goal.clone())),
+        );
 
         // Add the `conditions` from the program clause into the result too.
         ex_clause
@@ -206,12 +210,14 @@ impl<I: Interner> ResolventOps<I> for TruncatingInferenceTable<I> {
     // failure will get propagated back up.
 
     #[instrument(level = "debug", skip(self, interner))]
+    #[allow(clippy::too_many_arguments)]
     fn apply_answer_subst(
         &mut self,
         interner: &I,
         unification_database: &dyn UnificationDatabase<I>,
         ex_clause: &mut ExClause<I>,
         selected_goal: &InEnvironment<Goal<I>>,
+        kept_clauses: Option<&[bool]>,
         answer_table_goal: &Canonical<InEnvironment<Goal<I>>>,
         canonical_answer_subst: &Canonical<AnswerSubst<I>>,
     ) -> Fallible<()> {
@@ -233,6 +239,11 @@ impl<I: Interner> ResolventOps<I> for TruncatingInferenceTable<I> {
             .infer
             .instantiate_canonical(interner, &canonical_answer_subst);
 
+        // The table goal may only keep some of the environment clauses of
+        // the selected goal, so we match it against the selected goal with
+        // the same clauses, and give the constraints and delayed subgoals of
+        // the answer back the clauses it dropped.
+        let trimmed_goal = kept_clauses.map(|kept| keep_clauses(interner, selected_goal, kept));
         AnswerSubstitutor::substitute(
             interner,
             unification_database,
@@ -241,14 +252,30 @@ impl<I: Interner> ResolventOps<I> for TruncatingInferenceTable<I> {
             &answer_subst,
             ex_clause,
             &answer_table_goal.value,
-            selected_goal,
+            trimmed_goal.as_ref().unwrap_or(selected_goal),
         )?;
+        let trimmed_len = answer_table_goal.value.environment.clauses.len(interner);
+        let restore = |environment: &Environment<I>| match trimmed_goal {
+            Some(_) => restore_clauses(
+                interner,
+                environment,
+                trimmed_len,
+                &selected_goal.environment,
+            ),
+            None => environment.clone(),
+        };
         ex_clause
             .constraints
-            .extend(answer_constraints.as_slice(interner).to_vec());
+            .extend(answer_constraints.iter(interner).map(|constraint| {
+                InEnvironment::new(&restore(&constraint.environment), constraint.goal.clone())
+            }));
         // at that point we should only have goals that stemmed
         // from non trivial self cycles
-        ex_clause.delayed_subgoals.extend(delayed_subgoals);
+        ex_clause.delayed_subgoals.extend(
+            delayed_subgoals
+                .into_iter()
+                .map(|subgoal| InEnvironment::new(&restore(&subgoal.environment), subgoal.goal)),
+        );
         Ok(())
     }
 }
@@ -684,6 +711,16 @@ impl<'i, I: Interner> Zipper<'i, I> for AnswerSubstitutor<'i, I> {
                 Ok(())
             }
 
+            (ConstValue::Unevaluated(u1), ConstValue::Unevaluated(u2)) => {
+                assert_eq!(u1.interned, u2.interned);
+                self.zip_substs(
+                    variance,
+                    None,
+                    u1.substitution.as_slice(interner),
+                    u2.substitution.as_slice(interner),
+                )
+            }
+
             (ConstValue::InferenceVar(_), _) | (_, ConstValue::InferenceVar(_)) => panic!(
                 "unexpected inference var in answer `{:?}` or pending goal `{:?}`",
                 answer, pending,
@@ -691,7 +728,8 @@ impl<'i, I: Interner> Zipper<'i, I> for AnswerSubstitutor<'i, I> {
 
             (ConstValue::BoundVar(_), _)
             | (ConstValue::Placeholder(_), _)
-            | (ConstValue::Concrete(_), _) => panic!(
+            | (ConstValue::Concrete(_), _)
+            | (ConstValue::Unevaluated(_), _) => panic!(
                 "structural mismatch between answer `{:?}` and pending goal `{:?}`",
                 answer, pending,
             ),
@@ -1,27 +1,119 @@
 use crate::context::{AnswerResult, AnswerStream};
-use crate::forest::Forest;
+use crate::forest::{Forest, ForestSolver};
 use crate::slg::aggregate::AggregateOps;
 use crate::slg::SlgContextOps;
+use crate::table::AnswerIndex;
+use crate::tables::SharedTables;
+use crate::TableIndex;
 use chalk_ir::interner::Interner;
 use chalk_ir::{Canonical, ConstrainedSubst, Goal, InEnvironment, UCanonical};
-use chalk_solve::{RustIrDatabase, Solution, Solver, SubstitutionResult};
+use chalk_solve::dependencies::Dependencies;
+use chalk_solve::infer::NormalizationMode;
+use chalk_solve::solve::stats::RootGoalStart;
+use chalk_solve::solve::{Answers, ProofOutcome, SolverStats};
+use chalk_solve::{ProofTree, RustIrDatabase, Solution, Solver, SubstitutionResult};
 
 use std::fmt;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 pub struct SLGSolver<I: Interner> {
     pub(crate) forest: Forest<I>,
     pub(crate) max_size: usize,
     pub(crate) expected_answers: Option<usize>,
+    pub(crate) normalization: NormalizationMode,
+}
+
+/// Limits on the tables an `SLGSolver` or a `ParallelSLGSolver` keeps
+/// between goals. Once a root goal is solved, the least recently used
+/// completed tables are evicted (along with the tables whose answers were
+/// derived from theirs, for an `SLGSolver`) until the solver is within its
+/// limits again. By default, there are none.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableBudget {
+    /// The maximum number of tables.
+    pub max_tables: Option<usize>,
+
+    /// The maximum memory the tables may hold, in bytes. This is compared
+    /// with a rough estimate; see `SLGSolver::approximate_size`.
+    pub max_bytes: Option<usize>,
+}
+
+impl TableBudget {
+    /// Whether `tables` tables, holding about `bytes` bytes, are within
+    /// the budget.
+    pub(crate) fn allows(&self, tables: usize, bytes: usize) -> bool {
+        self.max_tables
+            .into_iter()
+            .all(|max_tables| tables <= max_tables)
+            && self
+                .max_bytes
+                .into_iter()
+                .all(|max_bytes| bytes <= max_bytes)
+    }
 }
 
 impl<I: Interner> SLGSolver<I> {
     pub fn new(max_size: usize, expected_answers: Option<usize>) -> Self {
+        Self::with_budget(max_size, expected_answers, TableBudget::default())
+    }
+
+    /// Creates a solver that keeps its tables within `budget`.
+    pub fn with_budget(
+        max_size: usize,
+        expected_answers: Option<usize>,
+        budget: TableBudget,
+    ) -> Self {
+        let mut forest = Forest::new();
+        forest.budget = budget;
         Self {
-            forest: Forest::new(),
+            forest,
             max_size,
             expected_answers,
+            normalization: NormalizationMode::default(),
         }
     }
+
+    /// Uses `mode` for the inference tables of the goals this solver
+    /// solves (see `NormalizationMode`). Goals are solved with eager
+    /// normalization by default.
+    pub fn with_normalization_mode(mut self, mode: NormalizationMode) -> Self {
+        self.normalization = mode;
+        self
+    }
+
+    /// Keeps the statistics of the `max` root goals that took the longest
+    /// to solve (see `SolverStats::expensive_goals`), rather than of
+    /// `DEFAULT_MAX_EXPENSIVE_GOALS`.
+    pub fn with_max_expensive_goals(mut self, max: usize) -> Self {
+        self.forest.stats.max_expensive_goals = max;
+        self
+    }
+
+    /// Evicts tables until the solver is within its budget. This happens
+    /// after each root goal anyway, but is useful after lowering the
+    /// budget with `set_budget`.
+    pub fn gc(&mut self) {
+        self.forest.evict();
+    }
+
+    pub fn set_budget(&mut self, budget: TableBudget) {
+        self.forest.budget = budget;
+    }
+
+    /// The number of tables the solver holds.
+    pub fn table_count(&self) -> usize {
+        self.forest.table_count()
+    }
+
+    /// A rough estimate of the memory the solver's tables hold, in bytes.
+    /// It counts the tables along with their answers and strands, but not
+    /// the interned types and goals those point to.
+    pub fn approximate_size(&self) -> usize {
+        self.forest.approximate_size()
+    }
 }
 
 impl<I: Interner> fmt::Debug for SLGSolver<I> {
@@ -36,8 +128,7 @@ impl<I: Interner> Solver<I> for SLGSolver<I> {
         program: &dyn RustIrDatabase<I>,
         goal: &UCanonical<InEnvironment<Goal<I>>>,
     ) -> Option<Solution<I>> {
-        let ops = SlgContextOps::new(program, self.max_size, self.expected_answers);
-        ops.make_solution(goal, self.forest.iter_answers(&ops, goal), || true)
+        self.solve_limited(program, goal, &|| true)
     }
 
     fn solve_limited(
@@ -46,44 +137,416 @@ impl<I: Interner> Solver<I> for SLGSolver<I> {
         goal: &UCanonical<InEnvironment<Goal<I>>>,
         should_continue: &dyn std::ops::Fn() -> bool,
     ) -> Option<Solution<I>> {
-        let ops = SlgContextOps::new(program, self.max_size, self.expected_answers);
-        ops.make_solution(goal, self.forest.iter_answers(&ops, goal), should_continue)
+        let start = self.forest.stats.start_root_goal();
+        let ops = SlgContextOps::new(
+            program,
+            self.max_size,
+            self.expected_answers,
+            self.normalization,
+        );
+        let solution =
+            ops.make_solution(goal, self.forest.iter_answers(&ops, goal), should_continue);
+        let solution = self.forest.report_ambiguity(program, goal, solution);
+        self.forest.stats.finish_root_goal(start, goal);
+        self.forest.evict();
+        solution
     }
 
-    fn solve_multiple(
+    fn solve_explained(
         &mut self,
         program: &dyn RustIrDatabase<I>,
         goal: &UCanonical<InEnvironment<Goal<I>>>,
-        f: &mut dyn FnMut(SubstitutionResult<Canonical<ConstrainedSubst<I>>>, bool) -> bool,
-    ) -> bool {
-        let ops = SlgContextOps::new(program, self.max_size, self.expected_answers);
-        let mut answers = self.forest.iter_answers(&ops, goal);
-        loop {
-            let subst = match answers.next_answer(|| true) {
-                AnswerResult::Answer(answer) => {
-                    if !answer.ambiguous {
-                        SubstitutionResult::Definite(answer.subst)
-                    } else if answer
-                        .subst
-                        .value
-                        .subst
-                        .is_identity_subst(ops.program().interner())
-                    {
-                        SubstitutionResult::Floundered
-                    } else {
-                        SubstitutionResult::Ambiguous(answer.subst)
-                    }
-                }
-                AnswerResult::Floundered => SubstitutionResult::Floundered,
-                AnswerResult::NoMoreSolutions => {
-                    return true;
-                }
-                AnswerResult::QuantumExceeded => continue,
+    ) -> (Option<Solution<I>>, ProofTree<I>) {
+        let ops = SlgContextOps::new(
+            program,
+            self.max_size,
+            self.expected_answers,
+            self.normalization,
+        );
+        let mut forest = Forest::explaining();
+        let solution = ops.make_solution(goal, forest.iter_answers(&ops, goal), || true);
+        let solution = forest.report_ambiguity(program, goal, solution);
+        let table = forest.tables.index_of(goal).unwrap();
+        let mut proof_tree = forest.proof_tree(program.interner(), table);
+
+        // The root table may have several answers, which `make_solution`
+        // aggregates; make sure the tree agrees with the final solution.
+        if proof_tree.outcome != ProofOutcome::Floundered {
+            proof_tree.outcome = match &solution {
+                Some(Solution::Unique(_)) => ProofOutcome::Proven,
+                Some(Solution::Ambig(_)) => ProofOutcome::Ambiguous,
+                Some(Solution::Overflow(_)) => ProofOutcome::Overflowed,
+                None => ProofOutcome::Failed,
             };
+        }
+        (solution, proof_tree)
+    }
+
+    fn solve_answers<'a>(
+        &'a mut self,
+        program: &'a dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Answers<'a, I> {
+        let ops = SlgContextOps::new(
+            program,
+            self.max_size,
+            self.expected_answers,
+            self.normalization,
+        );
+        Answers::new(SlgAnswers::new(
+            AnswerForest::Owned(&mut self.forest),
+            ops,
+            goal,
+        ))
+    }
+
+    fn invalidate(&mut self, changed: &Dependencies<I>) -> bool {
+        self.forest.invalidate(changed);
+        true
+    }
+
+    fn clear(&mut self) {
+        self.forest.clear();
+    }
+
+    fn stats(&self) -> SolverStats<I> {
+        let mut stats = self.forest.stats.clone();
+        stats.live_tables = self.table_count();
+        stats.table_bytes = self.approximate_size();
+        stats
+    }
+}
+
+/// The forest in which `SlgAnswers` looks for answers.
+enum AnswerForest<'a, I: Interner> {
+    /// The forest of an `SLGSolver`.
+    Owned(&'a mut Forest<I>),
+
+    /// The forest of a local solver of a `ParallelSLGSolver`, whose tables
+    /// are published once we are done with it.
+    Shared(Box<Forest<I>>, &'a ParallelSLGSolver<I>),
+}
+
+impl<I: Interner> AnswerForest<'_, I> {
+    fn forest(&mut self) -> &mut Forest<I> {
+        match self {
+            AnswerForest::Owned(forest) => forest,
+            AnswerForest::Shared(forest, _) => forest,
+        }
+    }
+}
+
+/// The answers to the root goal of a forest, extracted one at a time as
+/// they are requested (see `Solver::solve_answers`).
+struct SlgAnswers<'a, I: Interner> {
+    forest: AnswerForest<'a, I>,
+    ops: SlgContextOps<'a, I>,
+    goal: UCanonical<InEnvironment<Goal<I>>>,
+    table: TableIndex,
+    answer: AnswerIndex,
+    start: RootGoalStart,
+}
+
+impl<'a, I: Interner> SlgAnswers<'a, I> {
+    fn new(
+        mut forest: AnswerForest<'a, I>,
+        ops: SlgContextOps<'a, I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Self {
+        let start = forest.forest().stats.start_root_goal();
+        let table = forest
+            .forest()
+            .get_or_create_table_for_ucanonical_goal(&ops, goal.clone());
+        SlgAnswers {
+            forest,
+            ops,
+            goal: goal.clone(),
+            table,
+            answer: AnswerIndex::ZERO,
+            start,
+        }
+    }
+}
+
+impl<I: Interner> Iterator for SlgAnswers<'_, I> {
+    type Item = SubstitutionResult<Canonical<ConstrainedSubst<I>>>;
 
-            if !f(subst, !answers.peek_answer(|| true).is_no_more_solutions()) {
-                return false;
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut answers = ForestSolver {
+            forest: self.forest.forest(),
+            context: &self.ops,
+            table: self.table,
+            answer: self.answer,
+        };
+        let result = loop {
+            match answers.next_answer(|| true) {
+                AnswerResult::QuantumExceeded => continue,
+                result => break result,
             }
+        };
+        self.answer = answers.answer;
+        match result {
+            AnswerResult::Answer(answer) => Some(if !answer.ambiguous {
+                SubstitutionResult::Definite(answer.subst)
+            } else if answer
+                .subst
+                .value
+                .subst
+                .is_identity_subst(self.ops.program().interner())
+            {
+                SubstitutionResult::Floundered
+            } else {
+                SubstitutionResult::Ambiguous(answer.subst)
+            }),
+            AnswerResult::Floundered => Some(SubstitutionResult::Floundered),
+            AnswerResult::NoMoreSolutions | AnswerResult::QuantumExceeded => None,
+        }
+    }
+}
+
+impl<I: Interner> Drop for SlgAnswers<'_, I> {
+    fn drop(&mut self) {
+        let forest = self.forest.forest();
+        forest.stats.finish_root_goal(self.start, &self.goal);
+        match &mut self.forest {
+            AnswerForest::Owned(forest) => forest.evict(),
+            AnswerForest::Shared(forest, solver) => solver.finish_local_forest(forest),
+        }
+    }
+}
+
+/// An SLG solver whose goals may be solved on several threads at once.
+///
+/// Each goal is solved in a forest of its own, but the forests share their
+/// completed tables: a table whose strands have all been pursued is
+/// published once its goal is solved, and other forests start from its
+/// answers rather than building the table again. Clones of a
+/// `ParallelSLGSolver` share their tables, so each thread can use a clone
+/// of its own; `solve_all` does this for a batch of goals.
+#[derive(Clone)]
+pub struct ParallelSLGSolver<I: Interner> {
+    tables: Arc<SharedTables<I>>,
+    stats: Arc<Mutex<SolverStats<I>>>,
+    max_size: usize,
+    expected_answers: Option<usize>,
+    normalization: NormalizationMode,
+    budget: TableBudget,
+}
+
+impl<I: Interner> ParallelSLGSolver<I> {
+    pub fn new(max_size: usize, expected_answers: Option<usize>) -> Self {
+        Self::with_budget(max_size, expected_answers, TableBudget::default())
+    }
+
+    /// Creates a solver that keeps its shared tables within `budget`.
+    pub fn with_budget(
+        max_size: usize,
+        expected_answers: Option<usize>,
+        budget: TableBudget,
+    ) -> Self {
+        Self {
+            tables: Arc::new(SharedTables::new()),
+            stats: Arc::new(Mutex::new(SolverStats::new())),
+            max_size,
+            expected_answers,
+            normalization: NormalizationMode::default(),
+            budget,
         }
     }
+
+    /// Uses `mode` for the inference tables of the goals this solver
+    /// solves; see `SLGSolver::with_normalization_mode`.
+    pub fn with_normalization_mode(mut self, mode: NormalizationMode) -> Self {
+        self.normalization = mode;
+        self
+    }
+
+    /// Keeps the statistics of the `max` most expensive root goals; see
+    /// `SLGSolver::with_max_expensive_goals`.
+    pub fn with_max_expensive_goals(self, max: usize) -> Self {
+        self.stats.lock().unwrap().max_expensive_goals = max;
+        self
+    }
+
+    /// Evicts shared tables until the solver is within its budget; see
+    /// `SLGSolver::gc`.
+    pub fn gc(&self) {
+        let evicted = self.tables.evict(self.budget);
+        self.stats.lock().unwrap().evictions += evicted;
+    }
+
+    /// Sets the budget of this solver. Clones made before keep their own.
+    pub fn set_budget(&mut self, budget: TableBudget) {
+        self.budget = budget;
+    }
+
+    /// The number of completed tables shared between threads.
+    pub fn shared_tables(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// A rough estimate of the memory the shared tables hold, in bytes.
+    pub fn approximate_size(&self) -> usize {
+        self.tables.approximate_size()
+    }
+
+    /// Solves `goal`, reusing and extending the shared tables. Unlike
+    /// `Solver::solve`, this only needs `&self`.
+    pub fn solve_shared(
+        &self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Option<Solution<I>> {
+        self.solve_shared_limited(program, goal, &|| true)
+    }
+
+    fn solve_shared_limited(
+        &self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+        should_continue: &dyn std::ops::Fn() -> bool,
+    ) -> Option<Solution<I>> {
+        let mut solver = self.local_solver();
+        let solution = solver.solve_limited(program, goal, should_continue);
+        self.finish_local_solver(solver);
+        solution
+    }
+
+    /// An `SLGSolver` whose forest starts from the shared tables.
+    fn local_solver(&self) -> SLGSolver<I> {
+        let mut forest = Forest::sharing(self.tables.clone());
+        forest.stats.max_expensive_goals = self.stats.lock().unwrap().max_expensive_goals;
+        SLGSolver {
+            forest,
+            max_size: self.max_size,
+            expected_answers: self.expected_answers,
+            normalization: self.normalization,
+        }
+    }
+
+    /// Publishes the tables completed by `solver`, and adds its statistics
+    /// to ours.
+    fn finish_local_solver(&self, mut solver: SLGSolver<I>) {
+        self.finish_local_forest(&mut solver.forest);
+    }
+
+    /// Publishes the tables completed in `forest`, and moves its statistics
+    /// to ours. Then evicts shared tables if we are over budget.
+    fn finish_local_forest(&self, forest: &mut Forest<I>) {
+        forest.publish_completed_tables();
+        let fresh = SolverStats::with_max_expensive_goals(forest.stats.max_expensive_goals);
+        let stats = std::mem::replace(&mut forest.stats, fresh);
+        self.stats.lock().unwrap().merge(stats);
+        self.gc();
+    }
+
+    /// Solves each of `goals` on up to `threads` threads, returning their
+    /// solutions in the same order.
+    pub fn solve_all(
+        &self,
+        program: &(dyn RustIrDatabase<I> + Sync),
+        goals: &[UCanonical<InEnvironment<Goal<I>>>],
+        threads: usize,
+    ) -> Vec<Option<Solution<I>>> {
+        let next_goal = AtomicUsize::new(0);
+        let mut solutions: Vec<Option<Solution<I>>> = goals.iter().map(|_| None).collect();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut solved = vec![];
+                        loop {
+                            let index = next_goal.fetch_add(1, Ordering::Relaxed);
+                            match goals.get(index) {
+                                Some(goal) => {
+                                    solved.push((index, self.solve_shared(program, goal)))
+                                }
+                                None => return solved,
+                            }
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                let solved = worker
+                    .join()
+                    .unwrap_or_else(|payload| panic::resume_unwind(payload));
+                for (index, solution) in solved {
+                    solutions[index] = solution;
+                }
+            }
+        });
+        solutions
+    }
+}
+
+impl<I: Interner> fmt::Debug for ParallelSLGSolver<I> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "ParallelSLGSolver")
+    }
+}
+
+impl<I: Interner> Solver<I> for ParallelSLGSolver<I> {
+    fn solve(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Option<Solution<I>> {
+        self.solve_shared(program, goal)
+    }
+
+    fn solve_limited(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+        should_continue: &dyn std::ops::Fn() -> bool,
+    ) -> Option<Solution<I>> {
+        self.solve_shared_limited(program, goal, should_continue)
+    }
+
+    fn solve_explained(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> (Option<Solution<I>>, ProofTree<I>) {
+        // Proof trees need the candidates of every table, which the shared
+        // tables do not keep.
+        SLGSolver::new(self.max_size, self.expected_answers)
+            .with_normalization_mode(self.normalization)
+            .solve_explained(program, goal)
+    }
+
+    fn solve_answers<'a>(
+        &'a mut self,
+        program: &'a dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Answers<'a, I> {
+        let forest = AnswerForest::Shared(Box::new(self.local_solver().forest), self);
+        let ops = SlgContextOps::new(
+            program,
+            self.max_size,
+            self.expected_answers,
+            self.normalization,
+        );
+        Answers::new(SlgAnswers::new(forest, ops, goal))
+    }
+
+    fn invalidate(&mut self, changed: &Dependencies<I>) -> bool {
+        self.tables
+            .retain(|table| !table.dependencies.affected_by(changed));
+        true
+    }
+
+    fn clear(&mut self) {
+        let tables = self.tables.len();
+        self.tables.retain(|_| false);
+        self.stats.lock().unwrap().evictions += tables;
+    }
+
+    fn stats(&self) -> SolverStats<I> {
+        let mut stats = self.stats.lock().unwrap().clone();
+        stats.live_tables = self.shared_tables();
+        stats.table_bytes = self.approximate_size();
+        stats
+    }
 }
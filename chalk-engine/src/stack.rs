@@ -105,6 +105,11 @@ impl<I: Interner> Stack<I> {
             .next()
     }
 
+    /// The tables on the stack, from the bottom up.
+    pub(super) fn tables(&self) -> impl Iterator<Item = TableIndex> + '_ {
+        self.stack.iter().map(|entry| entry.table)
+    }
+
     pub(super) fn top_of_stack_from(&self, depth: StackIndex) -> Range<StackIndex> {
         depth..StackIndex::from(self.stack.len())
     }
@@ -14,6 +14,10 @@ pub(crate) struct CanonicalStrand<I: Interner> {
     pub(crate) selected_subgoal: Option<SelectedSubgoal>,
 
     pub(crate) last_pursued_time: TimeStamp,
+
+    /// The candidate of the table that this strand originated from, if
+    /// the forest is explaining its answers.
+    pub(crate) candidate: Option<usize>,
 }
 
 pub(crate) struct Strand<I: Interner> {
@@ -25,6 +29,9 @@ pub(crate) struct Strand<I: Interner> {
     pub(crate) selected_subgoal: Option<SelectedSubgoal>,
 
     pub(crate) last_pursued_time: TimeStamp,
+
+    /// See `CanonicalStrand::candidate`.
+    pub(crate) candidate: Option<usize>,
 }
 
 #[derive(Clone, Debug)]
@@ -41,6 +48,10 @@ pub(crate) struct SelectedSubgoal {
     /// Maps the universes of the subgoal to the canonical universes
     /// used in the table
     pub(crate) universe_map: UniverseMap,
+
+    /// The environment clauses of the subgoal that the goal of the table
+    /// keeps, if it does not keep them all (see `EnvTrimmer`)
+    pub(crate) kept_clauses: Option<Vec<bool>>,
 }
 
 impl<I: Interner> Debug for Strand<I> {
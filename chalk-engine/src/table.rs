@@ -1,13 +1,15 @@
 use crate::index_struct;
 use crate::strand::CanonicalStrand;
-use crate::{Answer, AnswerMode};
-use rustc_hash::FxHashMap;
+use crate::{Answer, AnswerMode, TableIndex};
+use rustc_hash::{FxHashMap, FxHashSet};
 use std::collections::hash_map::Entry;
 use std::collections::VecDeque;
 use std::mem;
 
 use chalk_ir::interner::Interner;
-use chalk_ir::{AnswerSubst, Canonical, Goal, InEnvironment, UCanonical};
+use chalk_ir::{AnswerSubst, Canonical, Goal, InEnvironment, ProgramClause, UCanonical};
+use chalk_solve::dependencies::{Dependencies, ItemId};
+use chalk_solve::solve::Overflow;
 use tracing::{debug, info, instrument};
 
 #[derive(Debug)]
@@ -24,6 +26,18 @@ pub(crate) struct Table<I: Interner> {
     /// enough types specified for us to solve.
     floundered: bool,
 
+    /// True if one of our answers is ambiguous because some of the
+    /// subgoals of its strand floundered.
+    floundered_answers: bool,
+
+    /// The first subgoal or answer of ours that we gave up on because it
+    /// exceeded the solver's limits.
+    overflow: Option<Overflow<I>>,
+
+    /// The item that the database failed to look up while we were building
+    /// our strands. We are floundered in that case.
+    lookup_failure: Option<ItemId<I>>,
+
     /// Stores the answers that we have found thus far. When we get a request
     /// for an answer N, we will first check this vector.
     answers: Vec<Answer<I>>,
@@ -45,6 +59,77 @@ pub(crate) struct Table<I: Interner> {
     strands: VecDeque<CanonicalStrand<I>>,
 
     pub(crate) answer_mode: AnswerMode,
+
+    /// The candidates (program clauses, or the simplification of a
+    /// non-domain goal) that our strands originated from. This is only
+    /// recorded when the forest is explaining its answers.
+    candidates: Option<Vec<Candidate<I>>>,
+
+    /// True if we dropped our remaining strands because we found a
+    /// trivial answer (see `pursue_answer`).
+    pruned: bool,
+
+    /// The items of the program consulted while building our strands and
+    /// applying answers to them.
+    pub(crate) dependencies: Dependencies<I>,
+
+    /// The tables of the subgoals that our strands have selected. Our
+    /// answers may have been derived from theirs.
+    pub(crate) subgoal_tables: FxHashSet<TableIndex>,
+
+    /// When we were last looked up (see `Tables::touch`), for evicting
+    /// the least recently used tables.
+    pub(crate) last_used: u64,
+}
+
+/// What we learned while pursuing the strands that originated from one
+/// candidate for a table's goal.
+#[derive(Debug)]
+pub(crate) struct Candidate<I: Interner> {
+    /// The program clause, or `None` if the goal was simplified.
+    pub(crate) clause: Option<ProgramClause<I>>,
+
+    /// The tables for the subgoals that our strands selected, along with
+    /// whether the subgoal was positive (as opposed to negated).
+    pub(crate) subgoals: Vec<(TableIndex, bool)>,
+
+    /// True if one of our strands produced an unambiguous answer.
+    pub(crate) proven: bool,
+
+    /// True if one of our strands produced an ambiguous answer.
+    pub(crate) ambiguous: bool,
+
+    /// True if one of our strands had a subgoal flounder.
+    pub(crate) floundered: bool,
+}
+
+/// The answers of a table whose strands have all been pursued, so that no
+/// further answers can arise. A `ParallelSLGSolver` shares these between
+/// the forests of its threads (see `SharedTables`).
+#[derive(Debug)]
+pub(crate) struct CompletedTable<I: Interner> {
+    coinductive_goal: bool,
+    floundered: bool,
+    floundered_answers: bool,
+    answers: Vec<Answer<I>>,
+
+    /// An overflow of this table or of the tables of its subgoals.
+    overflow: Option<Overflow<I>>,
+
+    /// A failed lookup of this table or of the tables of its subgoals.
+    lookup_failure: Option<ItemId<I>>,
+
+    /// The items of the program that the answers depend upon, including
+    /// those consulted for the tables of subgoals.
+    pub(crate) dependencies: Dependencies<I>,
+}
+
+impl<I: Interner> CompletedTable<I> {
+    /// A rough estimate of the memory we hold, in bytes, in the same
+    /// terms as `Table::approximate_size`.
+    pub(crate) fn approximate_size(&self) -> usize {
+        mem::size_of::<Self>() + self.answers.capacity() * mem::size_of::<Answer<I>>()
+    }
 }
 
 index_struct! {
@@ -57,18 +142,135 @@ impl<I: Interner> Table<I> {
     pub(crate) fn new(
         table_goal: UCanonical<InEnvironment<Goal<I>>>,
         coinductive_goal: bool,
+        explain: bool,
     ) -> Table<I> {
         Table {
             table_goal,
             coinductive_goal,
             answers: Vec::new(),
             floundered: false,
+            floundered_answers: false,
+            overflow: None,
+            lookup_failure: None,
             answers_hash: FxHashMap::default(),
             strands: VecDeque::new(),
             answer_mode: AnswerMode::Complete,
+            candidates: if explain { Some(Vec::new()) } else { None },
+            pruned: false,
+            dependencies: Dependencies::new(),
+            subgoal_tables: FxHashSet::default(),
+            last_used: 0,
         }
     }
 
+    /// Creates a table for `table_goal` that holds the answers of a table
+    /// completed by another forest. It has no strands of its own.
+    pub(crate) fn from_completed(
+        table_goal: UCanonical<InEnvironment<Goal<I>>>,
+        completed: &CompletedTable<I>,
+    ) -> Table<I> {
+        let mut table = Table::new(table_goal, completed.coinductive_goal, false);
+        table.floundered = completed.floundered;
+        table.floundered_answers = completed.floundered_answers;
+        table.overflow = completed.overflow.clone();
+        table.lookup_failure = completed.lookup_failure;
+        table.answers = completed.answers.clone();
+        table.dependencies = completed.dependencies.clone();
+        table
+    }
+
+    /// True if all of our strands have been pursued, so that our answers
+    /// are final.
+    pub(crate) fn is_completed(&self) -> bool {
+        self.strands.is_empty()
+    }
+
+    /// Copies our answers out, to be shared with other forests. Only
+    /// meaningful once we are completed (see `is_completed`).
+    ///
+    /// As the shared table will not know about the tables of our subgoals,
+    /// `dependencies`, `overflow` and `lookup_failure` must include theirs.
+    pub(crate) fn to_completed(
+        &self,
+        dependencies: Dependencies<I>,
+        overflow: Option<Overflow<I>>,
+        lookup_failure: Option<ItemId<I>>,
+    ) -> CompletedTable<I> {
+        debug_assert!(self.is_completed());
+        CompletedTable {
+            coinductive_goal: self.coinductive_goal,
+            floundered: self.floundered,
+            floundered_answers: self.floundered_answers,
+            answers: self.answers.clone(),
+            overflow,
+            lookup_failure,
+            dependencies,
+        }
+    }
+
+    /// A rough estimate of the memory we hold, in bytes: our own size and
+    /// that of our answers, strands and candidates. The goals and
+    /// substitutions they contain are counted by their own size only, not
+    /// by the interned data they point to.
+    pub(crate) fn approximate_size(&self) -> usize {
+        mem::size_of::<Self>()
+            + self.answers.capacity() * mem::size_of::<Answer<I>>()
+            + self.answers_hash.capacity() * mem::size_of::<(Canonical<AnswerSubst<I>>, bool)>()
+            + self.strands.capacity() * mem::size_of::<CanonicalStrand<I>>()
+            + self.candidates.as_ref().map_or(0, |candidates| {
+                candidates.capacity() * mem::size_of::<Candidate<I>>()
+            })
+            + self.subgoal_tables.capacity() * mem::size_of::<TableIndex>()
+    }
+
+    /// Records that a subgoal or answer of ours exceeded the solver's
+    /// limits. Only the first overflow is kept.
+    pub(crate) fn record_overflow(&mut self, overflow: Overflow<I>) {
+        self.overflow.get_or_insert(overflow);
+    }
+
+    pub(crate) fn overflow(&self) -> Option<&Overflow<I>> {
+        self.overflow.as_ref()
+    }
+
+    /// Records a new candidate for this table's goal, returning its index
+    /// (or `None` if we are not explaining answers).
+    pub(crate) fn push_candidate(&mut self, clause: Option<ProgramClause<I>>) -> Option<usize> {
+        let candidates = self.candidates.as_mut()?;
+        candidates.push(Candidate {
+            clause,
+            subgoals: vec![],
+            proven: false,
+            ambiguous: false,
+            floundered: false,
+        });
+        Some(candidates.len() - 1)
+    }
+
+    pub(crate) fn candidate_mut(&mut self, index: Option<usize>) -> Option<&mut Candidate<I>> {
+        self.candidates.as_mut()?.get_mut(index?)
+    }
+
+    pub(crate) fn candidates(&self) -> &[Candidate<I>] {
+        self.candidates.as_deref().unwrap_or(&[])
+    }
+
+    /// True if a strand that originated from the given candidate may
+    /// still produce answers.
+    pub(crate) fn has_strands_for(&self, candidate: usize) -> bool {
+        self.strands
+            .iter()
+            .any(|strand| strand.candidate == Some(candidate))
+    }
+
+    pub(crate) fn is_pruned(&self) -> bool {
+        self.pruned
+    }
+
+    pub(crate) fn answers(&self) -> impl Iterator<Item = &Answer<I>> {
+        self.answers.iter()
+    }
+
     /// Push a strand to the back of the queue of strands to be processed.
     pub(crate) fn enqueue_strand(&mut self, strand: CanonicalStrand<I>) {
         self.strands.push_back(strand);
@@ -86,6 +288,13 @@ impl<I: Interner> Table<I> {
         mem::replace(&mut self.strands, VecDeque::new())
     }
 
+    /// Drops all remaining strands, because a trivial answer makes any
+    /// further answers uninteresting.
+    pub(crate) fn prune_strands(&mut self) {
+        self.strands = VecDeque::new();
+        self.pruned = true;
+    }
+
     /// Remove the next strand from the queue that meets the given criteria
     pub(crate) fn dequeue_next_strand_that(
         &mut self,
@@ -108,11 +317,34 @@ impl<I: Interner> Table<I> {
         self.answers = Default::default();
     }
 
+    /// Marks the table as floundered because the database failed to look up
+    /// `item` while we were building our strands.
+    pub(crate) fn mark_lookup_failed(&mut self, item: ItemId<I>) {
+        self.lookup_failure = Some(item);
+        self.mark_floundered();
+    }
+
+    pub(crate) fn lookup_failure(&self) -> Option<&ItemId<I>> {
+        self.lookup_failure.as_ref()
+    }
+
     /// Returns true if the table is floundered.
     pub(crate) fn is_floundered(&self) -> bool {
         self.floundered
     }
 
+    /// Records that one of our strands reached an answer with floundered
+    /// subgoals left.
+    pub(crate) fn record_floundered_answer(&mut self) {
+        self.floundered_answers = true;
+    }
+
+    /// Returns true if one of our answers is ambiguous because some of
+    /// its subgoals floundered.
+    pub(crate) fn has_floundered_answers(&self) -> bool {
+        self.floundered_answers
+    }
+
     /// Adds `answer` to our list of answers, unless it is already present.
     ///
     /// Returns true if `answer` was added.
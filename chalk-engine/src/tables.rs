@@ -1,7 +1,11 @@
-use crate::table::Table;
+use crate::solve::TableBudget;
+use crate::table::{CompletedTable, Table};
 use crate::TableIndex;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
 use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 
 use chalk_ir::interner::Interner;
 use chalk_ir::{Goal, InEnvironment, UCanonical};
@@ -15,6 +19,13 @@ pub(crate) struct Tables<I: Interner> {
     /// Table: as described above, stores the key information for each
     /// tree in the forest.
     tables: Vec<Table<I>>,
+
+    /// The indices of retired tables, which new tables reuse.
+    retired: Vec<TableIndex>,
+
+    /// The number of times tables were looked up, which orders the
+    /// `last_used` times of tables.
+    uses: u64,
 }
 
 impl<I: Interner> Tables<I> {
@@ -22,30 +33,67 @@ impl<I: Interner> Tables<I> {
         Tables {
             table_indices: FxHashMap::default(),
             tables: Vec::default(),
+            retired: Vec::default(),
+            uses: 0,
         }
     }
 
     /// The index that will be given to the next table to be inserted.
     pub(super) fn next_index(&self) -> TableIndex {
-        TableIndex {
-            value: self.tables.len(),
+        match self.retired.last() {
+            Some(&index) => index,
+            None => TableIndex {
+                value: self.tables.len(),
+            },
         }
     }
 
     pub(super) fn insert(&mut self, table: Table<I>) -> TableIndex {
         let goal = table.table_goal.clone();
         let index = self.next_index();
-        self.tables.push(table);
+        match self.retired.pop() {
+            Some(index) => self.tables[index.value] = table,
+            None => self.tables.push(table),
+        }
         self.table_indices.insert(goal, index);
+        self.touch(index);
         index
     }
 
+    /// Records that the table at `index` was just used.
+    pub(super) fn touch(&mut self, index: TableIndex) {
+        self.uses += 1;
+        self.tables[index.value].last_used = self.uses;
+    }
+
+    /// The number of tables that have not been retired.
+    pub(crate) fn len(&self) -> usize {
+        self.table_indices.len()
+    }
+
     pub(super) fn index_of(
         &self,
         literal: &UCanonical<InEnvironment<Goal<I>>>,
     ) -> Option<TableIndex> {
         self.table_indices.get(literal).cloned()
     }
+
+    /// The indices of the tables that have not been retired.
+    pub(super) fn live_indices(&self) -> impl Iterator<Item = TableIndex> + '_ {
+        self.table_indices.values().copied()
+    }
+
+    /// Forgets the table at `index`, so that the next request for its goal
+    /// creates a new table. The table's contents are dropped, and its
+    /// index is given to the next table to be inserted, so no table that
+    /// is kept may refer to it (see `Forest::retire_with_dependents`).
+    /// Until then, the slot keeps the goal and whether it is coinductive.
+    pub(super) fn retire(&mut self, index: TableIndex) {
+        let table = &mut self.tables[index.value];
+        self.table_indices.remove(&table.table_goal);
+        *table = Table::new(table.table_goal.clone(), table.coinductive_goal, false);
+        self.retired.push(index);
+    }
 }
 
 impl<I: Interner> Index<TableIndex> for Tables<I> {
@@ -70,3 +118,160 @@ impl<'a, I: Interner> IntoIterator for &'a mut Tables<I> {
         IntoIterator::into_iter(&mut self.tables)
     }
 }
+
+/// A table in `SharedTables`, along with when it was last used.
+struct SharedTable<I: Interner> {
+    table: Arc<CompletedTable<I>>,
+
+    /// The value of `SharedTables::uses` when the table was last looked
+    /// up, which orders the tables for `SharedTables::evict`.
+    last_used: AtomicU64,
+}
+
+/// One shard of `SharedTables`.
+type Shard<I> = RwLock<FxHashMap<UCanonical<InEnvironment<Goal<I>>>, SharedTable<I>>>;
+
+/// The number of shards that `SharedTables` splits its tables into.
+const SHARDS: usize = 16;
+
+/// The completed tables of a `ParallelSLGSolver`. The forest of each
+/// thread solving with it looks goals up here before building tables of
+/// its own, and publishes its completed tables here once it is done.
+///
+/// The tables are split into shards by the hash of their goal, each behind
+/// its own lock, so that threads rarely wait on one another.
+pub(crate) struct SharedTables<I: Interner> {
+    shards: Vec<Shard<I>>,
+
+    /// The number of times tables were looked up or added.
+    uses: AtomicU64,
+}
+
+impl<I: Interner> SharedTables<I> {
+    pub(crate) fn new() -> SharedTables<I> {
+        SharedTables {
+            shards: (0..SHARDS).map(|_| RwLock::default()).collect(),
+            uses: AtomicU64::new(0),
+        }
+    }
+
+    fn shard(&self, goal: &UCanonical<InEnvironment<Goal<I>>>) -> &Shard<I> {
+        let mut hasher = FxHasher::default();
+        goal.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    fn next_use(&self) -> u64 {
+        self.uses.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub(crate) fn get(
+        &self,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Option<Arc<CompletedTable<I>>> {
+        let shard = self.shard(goal).read().unwrap();
+        let shared = shard.get(goal)?;
+        shared.last_used.store(self.next_use(), Ordering::Relaxed);
+        Some(shared.table.clone())
+    }
+
+    pub(crate) fn contains(&self, goal: &UCanonical<InEnvironment<Goal<I>>>) -> bool {
+        self.shard(goal).read().unwrap().contains_key(goal)
+    }
+
+    /// Adds the completed table for `goal`, unless another thread has
+    /// already added one (which will have the same answers).
+    pub(crate) fn insert(
+        &self,
+        goal: UCanonical<InEnvironment<Goal<I>>>,
+        table: CompletedTable<I>,
+    ) {
+        self.shard(&goal)
+            .write()
+            .unwrap()
+            .entry(goal)
+            .or_insert_with(|| SharedTable {
+                table: Arc::new(table),
+                last_used: AtomicU64::new(self.next_use()),
+            });
+    }
+
+    /// Drops the tables for which `keep` returns false.
+    pub(crate) fn retain(&self, keep: impl Fn(&CompletedTable<I>) -> bool) {
+        for shard in &self.shards {
+            shard
+                .write()
+                .unwrap()
+                .retain(|_, shared| keep(&shared.table));
+        }
+    }
+
+    /// Drops the least recently used tables until we are within `budget`
+    /// again, returning how many were dropped. Completed tables do not
+    /// refer to one another, so unlike the tables of a `Forest`, each of
+    /// them can go on its own.
+    pub(crate) fn evict(&self, budget: TableBudget) -> usize {
+        let mut count = self.len();
+        let mut bytes = match budget.max_bytes {
+            Some(_) => self.approximate_size(),
+            None => 0,
+        };
+        if budget.allows(count, bytes) {
+            return 0;
+        }
+
+        let mut tables: Vec<(u64, usize)> = vec![];
+        for shard in &self.shards {
+            tables.extend(shard.read().unwrap().values().map(|shared| {
+                (
+                    shared.last_used.load(Ordering::Relaxed),
+                    shared.table.approximate_size(),
+                )
+            }));
+        }
+        tables.sort_unstable();
+
+        // Everything used no later than `cutoff` goes. Tables that other
+        // threads use in the meantime are used later than that, and stay.
+        let mut cutoff = 0;
+        for (last_used, size) in tables {
+            if budget.allows(count, bytes) {
+                break;
+            }
+            cutoff = last_used;
+            count -= 1;
+            bytes = bytes.saturating_sub(size);
+        }
+        let mut evicted = 0;
+        for shard in &self.shards {
+            shard.write().unwrap().retain(|_, shared| {
+                let keep = shared.last_used.load(Ordering::Relaxed) > cutoff;
+                evicted += usize::from(!keep);
+                keep
+            });
+        }
+        evicted
+    }
+
+    /// A rough estimate of the memory our tables hold, in bytes.
+    pub(crate) fn approximate_size(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .read()
+                    .unwrap()
+                    .values()
+                    .map(|shared| shared.table.approximate_size())
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().len())
+            .sum()
+    }
+}
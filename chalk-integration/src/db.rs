@@ -3,7 +3,7 @@ use crate::{
     interner::ChalkIr,
     lowering::lower_goal,
     program::Program,
-    query::{Lowering, LoweringDatabase},
+    query::{HasSolverCache, Lowering, LoweringDatabase, SolverCache},
     tls, SolverChoice,
 };
 use chalk_ir::{
@@ -12,24 +12,53 @@ use chalk_ir::{
     ProgramClause, ProgramClauses, Substitution, TraitId, Ty, TyKind, UCanonical,
     UnificationDatabase, Variances,
 };
+use chalk_solve::dependencies::{DependencyRecorder, ItemId};
 use chalk_solve::rust_ir::{
     AdtDatum, AdtRepr, AssociatedTyDatum, AssociatedTyValue, AssociatedTyValueId, ClosureKind,
     FnDefDatum, FnDefInputsAndOutputDatum, GeneratorDatum, GeneratorWitnessDatum, ImplDatum,
-    OpaqueTyDatum, TraitDatum, WellKnownTrait,
+    OpaqueTyDatum, TraitDatum, WellKnownAdt, WellKnownTrait,
 };
-use chalk_solve::{RustIrDatabase, Solution, SubstitutionResult};
-use salsa::Database;
+use chalk_solve::solve::Disagreement;
+use chalk_solve::{
+    Answers, LookupError, ProofTree, RustIrDatabase, Solution, SolverStats, SubstitutionResult,
+};
+use salsa::{Database, ParallelDatabase, Snapshot};
+use std::collections::HashSet;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[salsa::database(Lowering)]
 #[derive(Default)]
 pub struct ChalkDatabase {
     storage: salsa::Storage<Self>,
+    dependencies: Arc<DependencyRecorder<ChalkIr>>,
+    solver_cache: Arc<SolverCache>,
+    broken_items: Arc<Mutex<HashSet<ItemId<ChalkIr>>>>,
 }
 
 impl Database for ChalkDatabase {}
 
+/// Snapshots share the solver (and its cache) with the database they were
+/// taken from, so that goals can be solved on several threads, each with
+/// a snapshot of its own; see `SolverChoice::Parallel` for a solver that
+/// lets them do so at the same time.
+impl ParallelDatabase for ChalkDatabase {
+    fn snapshot(&self) -> Snapshot<Self> {
+        Snapshot::new(ChalkDatabase {
+            storage: self.storage.snapshot(),
+            dependencies: self.dependencies.clone(),
+            solver_cache: self.solver_cache.clone(),
+            broken_items: self.broken_items.clone(),
+        })
+    }
+}
+
+impl HasSolverCache for ChalkDatabase {
+    fn solver_cache(&self) -> &SolverCache {
+        &self.solver_cache
+    }
+}
+
 impl ChalkDatabase {
     pub fn with(program_text: &str, solver_choice: SolverChoice) -> Self {
         let mut db = ChalkDatabase::default();
@@ -52,9 +81,18 @@ impl ChalkDatabase {
         &self,
         goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
     ) -> Option<Solution<ChalkIr>> {
-        let solver = self.solver();
-        let solution = solver.lock().unwrap().solve(self, goal);
-        solution
+        self.solver().with(|solver| solver.solve(self, goal))
+    }
+
+    /// Solves a given goal, producing the solution along with a proof tree
+    /// that explains it. The proof tree is computed from scratch, without
+    /// using the solver's cache.
+    pub fn solve_explained(
+        &self,
+        goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
+    ) -> (Option<Solution<ChalkIr>>, ProofTree<ChalkIr>) {
+        self.solver()
+            .with(|solver| solver.solve_explained(self, goal))
     }
 
     /// Solves a given goal, producing the solution. This will do only
@@ -66,36 +104,87 @@ impl ChalkDatabase {
         goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
         f: &mut dyn FnMut(SubstitutionResult<Canonical<ConstrainedSubst<ChalkIr>>>, bool) -> bool,
     ) -> bool {
-        let solver = self.solver();
-        let solution = solver.lock().unwrap().solve_multiple(self, goal, f);
-        solution
+        self.solver()
+            .with(|solver| solver.solve_multiple(self, goal, f))
+    }
+
+    /// Solves a given goal, handing `f` an iterator over its answers. The
+    /// iterator finds each answer only once it is requested, and `f` may
+    /// stop at any time; the work done so far is cached for future
+    /// attempts.
+    pub fn with_answers<R>(
+        &self,
+        goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
+        f: impl FnOnce(&mut Answers<'_, ChalkIr>) -> R,
+    ) -> R {
+        self.solver().with(|solver| {
+            let mut answers = solver.solve_answers(self, goal);
+            f(&mut answers)
+        })
+    }
+
+    /// Returns the goals on which the solvers disagreed since the last
+    /// call, if the solver choice is `SolverChoice::Differential`.
+    pub fn take_disagreements(&self) -> Vec<Disagreement<ChalkIr>> {
+        self.solver().with(|solver| solver.take_disagreements())
+    }
+
+    /// The statistics collected by the solver over the goals solved so far.
+    pub fn solver_stats(&self) -> SolverStats<ChalkIr> {
+        self.solver().with(|solver| solver.stats())
+    }
+
+    /// Makes the fallible lookups of `item` (such as
+    /// `RustIrDatabase::try_trait_datum`) fail from now on, as if we could
+    /// not load it. Used to test how the solvers cope with such failures.
+    pub fn break_item(&self, item: ItemId<ChalkIr>) {
+        self.broken_items.lock().unwrap().insert(item);
+    }
+
+    fn record(&self, item: ItemId<ChalkIr>) {
+        self.dependencies.record(item);
+    }
+
+    /// Fails if `item` was broken with `break_item`.
+    fn check_lookup(&self, item: ItemId<ChalkIr>) -> Result<(), LookupError<ChalkIr>> {
+        if self.broken_items.lock().unwrap().contains(&item) {
+            self.record(item);
+            return Err(LookupError::new(item));
+        }
+        Ok(())
     }
 }
 
 impl UnificationDatabase<ChalkIr> for ChalkDatabase {
     fn fn_def_variance(&self, fn_def_id: FnDefId<ChalkIr>) -> Variances<ChalkIr> {
+        self.record(ItemId::FnDef(fn_def_id));
         self.program_ir().unwrap().fn_def_variance(fn_def_id)
     }
 
     fn adt_variance(&self, adt_id: AdtId<ChalkIr>) -> Variances<ChalkIr> {
+        self.record(ItemId::Adt(adt_id));
         self.program_ir().unwrap().adt_variance(adt_id)
     }
 }
 
 impl RustIrDatabase<ChalkIr> for ChalkDatabase {
     fn custom_clauses(&self) -> Vec<ProgramClause<ChalkIr>> {
+        self.record(ItemId::CustomClauses);
         self.program_ir().unwrap().custom_clauses()
     }
 
     fn associated_ty_data(&self, ty: AssocTypeId<ChalkIr>) -> Arc<AssociatedTyDatum<ChalkIr>> {
+        self.record(ItemId::AssociatedTy(ty));
         self.program_ir().unwrap().associated_ty_data(ty)
     }
 
     fn trait_datum(&self, id: TraitId<ChalkIr>) -> Arc<TraitDatum<ChalkIr>> {
+        self.record(ItemId::Trait(id));
         self.program_ir().unwrap().trait_datum(id)
     }
 
     fn impl_datum(&self, id: ImplId<ChalkIr>) -> Arc<ImplDatum<ChalkIr>> {
+        self.record(ItemId::Impl(id));
         self.program_ir().unwrap().impl_datum(id)
     }
 
@@ -103,22 +192,99 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         &self,
         id: AssociatedTyValueId<ChalkIr>,
     ) -> Arc<AssociatedTyValue<ChalkIr>> {
+        self.record(ItemId::AssociatedTyValue(id));
         self.program_ir().unwrap().associated_ty_values[&id].clone()
     }
 
     fn opaque_ty_data(&self, id: OpaqueTyId<ChalkIr>) -> Arc<OpaqueTyDatum<ChalkIr>> {
+        self.record(ItemId::OpaqueTy(id));
         self.program_ir().unwrap().opaque_ty_data(id)
     }
 
     fn hidden_opaque_type(&self, id: OpaqueTyId<ChalkIr>) -> Ty<ChalkIr> {
+        self.record(ItemId::OpaqueTy(id));
         self.program_ir().unwrap().hidden_opaque_type(id)
     }
 
+    fn try_associated_ty_data(
+        &self,
+        ty: AssocTypeId<ChalkIr>,
+    ) -> Result<Arc<AssociatedTyDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::AssociatedTy(ty))?;
+        Ok(self.associated_ty_data(ty))
+    }
+
+    fn try_trait_datum(
+        &self,
+        id: TraitId<ChalkIr>,
+    ) -> Result<Arc<TraitDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::Trait(id))?;
+        Ok(self.trait_datum(id))
+    }
+
+    fn try_adt_datum(
+        &self,
+        id: AdtId<ChalkIr>,
+    ) -> Result<Arc<AdtDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::Adt(id))?;
+        Ok(self.adt_datum(id))
+    }
+
+    fn try_generator_datum(
+        &self,
+        id: GeneratorId<ChalkIr>,
+    ) -> Result<Arc<GeneratorDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::Generator(id))?;
+        Ok(self.generator_datum(id))
+    }
+
+    fn try_generator_witness_datum(
+        &self,
+        id: GeneratorId<ChalkIr>,
+    ) -> Result<Arc<GeneratorWitnessDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::Generator(id))?;
+        Ok(self.generator_witness_datum(id))
+    }
+
+    fn try_fn_def_datum(
+        &self,
+        id: FnDefId<ChalkIr>,
+    ) -> Result<Arc<FnDefDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::FnDef(id))?;
+        Ok(self.fn_def_datum(id))
+    }
+
+    fn try_impl_datum(
+        &self,
+        id: ImplId<ChalkIr>,
+    ) -> Result<Arc<ImplDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::Impl(id))?;
+        Ok(self.impl_datum(id))
+    }
+
+    fn try_associated_ty_value(
+        &self,
+        id: AssociatedTyValueId<ChalkIr>,
+    ) -> Result<Arc<AssociatedTyValue<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::AssociatedTyValue(id))?;
+        Ok(self.associated_ty_value(id))
+    }
+
+    fn try_opaque_ty_data(
+        &self,
+        id: OpaqueTyId<ChalkIr>,
+    ) -> Result<Arc<OpaqueTyDatum<ChalkIr>>, LookupError<ChalkIr>> {
+        self.check_lookup(ItemId::OpaqueTy(id))?;
+        Ok(self.opaque_ty_data(id))
+    }
+
     fn adt_datum(&self, id: AdtId<ChalkIr>) -> Arc<AdtDatum<ChalkIr>> {
+        self.record(ItemId::Adt(id));
         self.program_ir().unwrap().adt_datum(id)
     }
 
     fn generator_datum(&self, id: GeneratorId<ChalkIr>) -> Arc<GeneratorDatum<ChalkIr>> {
+        self.record(ItemId::Generator(id));
         self.program_ir().unwrap().generator_datum(id)
     }
 
@@ -126,14 +292,17 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         &self,
         id: GeneratorId<ChalkIr>,
     ) -> Arc<GeneratorWitnessDatum<ChalkIr>> {
+        self.record(ItemId::Generator(id));
         self.program_ir().unwrap().generator_witness_datum(id)
     }
 
-    fn adt_repr(&self, id: AdtId<ChalkIr>) -> AdtRepr {
+    fn adt_repr(&self, id: AdtId<ChalkIr>) -> Arc<AdtRepr<ChalkIr>> {
+        self.record(ItemId::Adt(id));
         self.program_ir().unwrap().adt_repr(id)
     }
 
     fn fn_def_datum(&self, id: FnDefId<ChalkIr>) -> Arc<FnDefDatum<ChalkIr>> {
+        self.record(ItemId::FnDef(id));
         self.program_ir().unwrap().fn_def_datum(id)
     }
 
@@ -143,33 +312,42 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         generic_args: &[GenericArg<ChalkIr>],
         binders: &CanonicalVarKinds<ChalkIr>,
     ) -> Vec<ImplId<ChalkIr>> {
+        self.record(ItemId::TraitImpls(trait_id));
         self.program_ir()
             .unwrap()
             .impls_for_trait(trait_id, generic_args, binders)
     }
 
     fn local_impls_to_coherence_check(&self, trait_id: TraitId<ChalkIr>) -> Vec<ImplId<ChalkIr>> {
+        self.record(ItemId::TraitImpls(trait_id));
         self.program_ir()
             .unwrap()
             .local_impls_to_coherence_check(trait_id)
     }
 
     fn impl_provided_for(&self, auto_trait_id: TraitId<ChalkIr>, ty: &TyKind<ChalkIr>) -> bool {
+        self.record(ItemId::TraitImpls(auto_trait_id));
         self.program_ir()
             .unwrap()
             .impl_provided_for(auto_trait_id, ty)
     }
 
     fn well_known_trait_id(&self, well_known_trait: WellKnownTrait) -> Option<TraitId<ChalkIr>> {
+        self.record(ItemId::WellKnownTrait(well_known_trait));
         self.program_ir()
             .unwrap()
             .well_known_trait_id(well_known_trait)
     }
 
+    fn well_known_adt_id(&self, well_known_adt: WellKnownAdt) -> Option<AdtId<ChalkIr>> {
+        self.record(ItemId::WellKnownAdt(well_known_adt));
+        self.program_ir().unwrap().well_known_adt_id(well_known_adt)
+    }
+
     fn program_clauses_for_env(
         &self,
         environment: &Environment<ChalkIr>,
-    ) -> ProgramClauses<ChalkIr> {
+    ) -> Result<ProgramClauses<ChalkIr>, LookupError<ChalkIr>> {
         chalk_solve::program_clauses_for_env(self, environment)
     }
 
@@ -177,7 +355,8 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         &ChalkIr
     }
 
-    fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> bool {
+    fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> Option<bool> {
+        self.record(ItemId::Trait(trait_id));
         self.program_ir().unwrap().is_object_safe(trait_id)
     }
 
@@ -186,6 +365,7 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         closure_id: ClosureId<ChalkIr>,
         substs: &Substitution<ChalkIr>,
     ) -> Binders<FnDefInputsAndOutputDatum<ChalkIr>> {
+        self.record(ItemId::Closure(closure_id));
         self.program_ir()
             .unwrap()
             .closure_inputs_and_output(closure_id, substs)
@@ -196,6 +376,7 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         closure_id: ClosureId<ChalkIr>,
         substs: &Substitution<ChalkIr>,
     ) -> ClosureKind {
+        self.record(ItemId::Closure(closure_id));
         self.program_ir().unwrap().closure_kind(closure_id, substs)
     }
 
@@ -204,6 +385,7 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         closure_id: ClosureId<ChalkIr>,
         substs: &Substitution<ChalkIr>,
     ) -> Binders<Ty<ChalkIr>> {
+        self.record(ItemId::Closure(closure_id));
         self.program_ir()
             .unwrap()
             .closure_upvars(closure_id, substs)
@@ -214,6 +396,7 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         closure_id: ClosureId<ChalkIr>,
         substs: &Substitution<ChalkIr>,
     ) -> Substitution<ChalkIr> {
+        self.record(ItemId::Closure(closure_id));
         self.program_ir()
             .unwrap()
             .closure_fn_substitution(closure_id, substs)
@@ -223,6 +406,10 @@ impl RustIrDatabase<ChalkIr> for ChalkDatabase {
         self
     }
 
+    fn dependency_recorder(&self) -> Option<&DependencyRecorder<ChalkIr>> {
+        Some(&self.dependencies)
+    }
+
     fn trait_name(&self, trait_id: TraitId<ChalkIr>) -> String {
         self.program_ir().unwrap().trait_name(trait_id)
     }
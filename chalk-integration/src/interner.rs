@@ -1,10 +1,11 @@
 use crate::tls;
 use chalk_ir::interner::{HasInterner, Interner};
 use chalk_ir::{
-    AdtId, AliasTy, AssocTypeId, CanonicalVarKind, CanonicalVarKinds, ConstData, Constraint,
-    Constraints, FnDefId, Goals, InEnvironment, Lifetime, OpaqueTy, OpaqueTyId,
-    ProgramClauseImplication, ProgramClauses, ProjectionTy, QuantifiedWhereClauses,
-    SeparatorTraitRef, Substitution, TraitId, Ty, TyData, VariableKind, VariableKinds, Variances,
+    AdtId, AliasTy, AssocTypeId, CanonicalVarKind, CanonicalVarKinds, ConstData, ConstEvalError,
+    ConstValue, Constraint, Constraints, FnDefId, Goals, InEnvironment, Lifetime, OpaqueTy,
+    OpaqueTyId, ProgramClauseImplication, ProgramClauses, ProjectionTy, QuantifiedWhereClauses,
+    SeparatorTraitRef, Substitution, TraitId, Ty, TyData, UnevaluatedConst, VariableKind,
+    VariableKinds, Variances,
 };
 use chalk_ir::{
     GenericArg, GenericArgData, Goal, GoalData, LifetimeData, ProgramClause, ProgramClauseData,
@@ -15,9 +16,13 @@ use std::fmt::Debug;
 use std::sync::Arc;
 use string_cache::DefaultAtom;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub type Identifier = DefaultAtom;
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RawId {
     pub index: u32,
 }
@@ -28,7 +33,61 @@ impl Debug for RawId {
     }
 }
 
+/// An unevaluated const expression, e.g. `{ N + 1 }`. Parameters refer to the
+/// substitution of the enclosing `UnevaluatedConst` by index.
+#[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ChalkConstExpr {
+    Value(u32),
+    Param(usize),
+    Add(Arc<ChalkConstExpr>, Arc<ChalkConstExpr>),
+    Sub(Arc<ChalkConstExpr>, Arc<ChalkConstExpr>),
+    Mul(Arc<ChalkConstExpr>, Arc<ChalkConstExpr>),
+}
+
+impl ChalkConstExpr {
+    fn evaluate(&self, substitution: &Substitution<ChalkIr>) -> Result<u32, ConstEvalError> {
+        let interner = &ChalkIr;
+        let binary = |a: &ChalkConstExpr, b: &ChalkConstExpr, op: fn(u32, u32) -> Option<u32>| {
+            let (a, b) = (a.evaluate(substitution)?, b.evaluate(substitution)?);
+            op(a, b).ok_or(ConstEvalError::Failed)
+        };
+        match self {
+            ChalkConstExpr::Value(value) => Ok(*value),
+            ChalkConstExpr::Param(index) => {
+                let constant = substitution.at(interner, *index).assert_const_ref(interner);
+                let data = constant.data(interner);
+                match &data.value {
+                    ConstValue::Concrete(concrete) => Ok(concrete.interned),
+                    ConstValue::Unevaluated(unevaluated) => {
+                        interner.try_evaluate_const(data.ty.interned(), unevaluated)
+                    }
+                    ConstValue::BoundVar(_)
+                    | ConstValue::InferenceVar(_)
+                    | ConstValue::Placeholder(_) => Err(ConstEvalError::TooGeneric),
+                }
+            }
+            ChalkConstExpr::Add(a, b) => binary(a, b, u32::checked_add),
+            ChalkConstExpr::Sub(a, b) => binary(a, b, u32::checked_sub),
+            ChalkConstExpr::Mul(a, b) => binary(a, b, u32::checked_mul),
+        }
+    }
+}
+
+impl Debug for ChalkConstExpr {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChalkConstExpr::Value(value) => write!(fmt, "{}", value),
+            ChalkConstExpr::Param(index) => write!(fmt, "?{}", index),
+            ChalkConstExpr::Add(a, b) => write!(fmt, "({:?} + {:?})", a, b),
+            ChalkConstExpr::Sub(a, b) => write!(fmt, "({:?} - {:?})", a, b),
+            ChalkConstExpr::Mul(a, b) => write!(fmt, "({:?} * {:?})", a, b),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ChalkFnAbi {
     Rust,
     C,
@@ -44,6 +103,7 @@ impl Interner for ChalkIr {
     type InternedLifetime = LifetimeData<ChalkIr>;
     type InternedConst = Arc<ConstData<ChalkIr>>;
     type InternedConcreteConst = u32;
+    type InternedUnevaluatedConst = ChalkConstExpr;
     type InternedGenericArg = GenericArgData<ChalkIr>;
     type InternedGoal = Arc<GoalData<ChalkIr>>;
     type InternedGoals = Vec<Goal<ChalkIr>>;
@@ -220,6 +280,11 @@ impl Interner for ChalkIr {
         tls::with_current_program(|prog| Some(prog?.debug_variances(variances, fmt)))
     }
 
+    #[cfg(feature = "serde")]
+    fn serde_interner() -> Option<Self> {
+        Some(ChalkIr)
+    }
+
     fn intern_ty(&self, ty: TyData<ChalkIr>) -> Arc<TyData<ChalkIr>> {
         Arc::new(ty)
     }
@@ -248,6 +313,14 @@ impl Interner for ChalkIr {
         c1 == c2
     }
 
+    fn try_evaluate_const(
+        &self,
+        _ty: &Arc<TyData<ChalkIr>>,
+        constant: &UnevaluatedConst<ChalkIr>,
+    ) -> Result<u32, ConstEvalError> {
+        constant.interned.evaluate(&constant.substitution)
+    }
+
     fn intern_generic_arg(&self, generic_arg: GenericArgData<ChalkIr>) -> GenericArgData<ChalkIr> {
         generic_arg
     }
@@ -11,10 +11,12 @@ pub mod query;
 pub mod test_macros;
 pub mod tls;
 
-use chalk_engine::solve::SLGSolver;
+use chalk_engine::solve::{ParallelSLGSolver, SLGSolver};
 use chalk_ir::interner::HasInterner;
 use chalk_ir::Binders;
 use chalk_recursive::RecursiveSolver;
+use chalk_solve::infer::NormalizationMode;
+use chalk_solve::solve::{DifferentialSolver, DEFAULT_MAX_EXPENSIVE_GOALS};
 use chalk_solve::Solver;
 use interner::ChalkIr;
 
@@ -50,12 +52,37 @@ pub enum SolverChoice {
     SLG {
         max_size: usize,
         expected_answers: Option<usize>,
+        normalization: NormalizationMode,
+        max_expensive_goals: usize,
     },
     /// Run the recursive solver.
     Recursive {
         overflow_depth: usize,
         caching_enabled: bool,
         max_size: usize,
+        normalization: NormalizationMode,
+        max_expensive_goals: usize,
+    },
+    /// Run the SLG solver, sharing its completed tables between the threads
+    /// that solve goals with it instead of locking it (see
+    /// `ParallelSLGSolver`).
+    Parallel {
+        max_size: usize,
+        expected_answers: Option<usize>,
+        normalization: NormalizationMode,
+        max_expensive_goals: usize,
+    },
+    /// Run both the SLG and the recursive solver on every goal, answering
+    /// with the SLG solver's solution and recording where the two disagree
+    /// (see `DifferentialSolver`). Each solver has limits of its own: the
+    /// SLG solver gets `slg_max_size`, the recursive one `recursive_max_size`
+    /// and `overflow_depth`.
+    Differential {
+        slg_max_size: usize,
+        recursive_max_size: usize,
+        overflow_depth: usize,
+        normalization: NormalizationMode,
+        max_expensive_goals: usize,
     },
 }
 
@@ -65,6 +92,8 @@ impl SolverChoice {
         SolverChoice::SLG {
             max_size,
             expected_answers,
+            normalization: NormalizationMode::default(),
+            max_expensive_goals: DEFAULT_MAX_EXPENSIVE_GOALS,
         }
     }
 
@@ -73,12 +102,31 @@ impl SolverChoice {
         SolverChoice::slg(10, None)
     }
 
+    /// Returns specific parameters for the SLG solver shared between
+    /// threads.
+    pub fn parallel(max_size: usize, expected_answers: Option<usize>) -> Self {
+        SolverChoice::Parallel {
+            max_size,
+            expected_answers,
+            normalization: NormalizationMode::default(),
+            max_expensive_goals: DEFAULT_MAX_EXPENSIVE_GOALS,
+        }
+    }
+
+    /// Returns the default parameters for the SLG solver shared between
+    /// threads.
+    pub fn parallel_default() -> Self {
+        SolverChoice::parallel(10, None)
+    }
+
     /// Returns the default recursive solver setup.
     pub fn recursive_default() -> Self {
         SolverChoice::Recursive {
             overflow_depth: 100,
             caching_enabled: true,
             max_size: 30,
+            normalization: NormalizationMode::default(),
+            max_expensive_goals: DEFAULT_MAX_EXPENSIVE_GOALS,
         }
     }
 
@@ -88,23 +136,125 @@ impl SolverChoice {
             overflow_depth,
             caching_enabled: true,
             max_size,
+            normalization: NormalizationMode::default(),
+            max_expensive_goals: DEFAULT_MAX_EXPENSIVE_GOALS,
+        }
+    }
+
+    /// Returns the default setup for comparing the two solvers.
+    pub fn differential_default() -> Self {
+        SolverChoice::Differential {
+            slg_max_size: 10,
+            recursive_max_size: 30,
+            overflow_depth: 100,
+            normalization: NormalizationMode::default(),
+            max_expensive_goals: DEFAULT_MAX_EXPENSIVE_GOALS,
         }
     }
 
-    pub fn into_solver(self) -> Box<dyn Solver<ChalkIr>> {
+    /// Returns the same solver setup, but normalizing alias types as
+    /// described by `mode`.
+    pub fn with_normalization_mode(mut self, mode: NormalizationMode) -> Self {
+        match &mut self {
+            SolverChoice::SLG { normalization, .. }
+            | SolverChoice::Parallel { normalization, .. }
+            | SolverChoice::Recursive { normalization, .. }
+            | SolverChoice::Differential { normalization, .. } => *normalization = mode,
+        }
+        self
+    }
+
+    /// Returns the same solver setup, but keeping the statistics of the
+    /// `max` root goals that took the longest to solve (see
+    /// `SolverStats::expensive_goals`).
+    pub fn with_max_expensive_goals(mut self, max: usize) -> Self {
+        match &mut self {
+            SolverChoice::SLG {
+                max_expensive_goals,
+                ..
+            }
+            | SolverChoice::Parallel {
+                max_expensive_goals,
+                ..
+            }
+            | SolverChoice::Recursive {
+                max_expensive_goals,
+                ..
+            }
+            | SolverChoice::Differential {
+                max_expensive_goals,
+                ..
+            } => *max_expensive_goals = max,
+        }
+        self
+    }
+
+    /// The solver for `SolverChoice::Parallel`, which can be shared between
+    /// threads rather than put behind a lock.
+    pub fn into_parallel_solver(self) -> Option<ParallelSLGSolver<ChalkIr>> {
+        match self {
+            SolverChoice::Parallel {
+                max_size,
+                expected_answers,
+                normalization,
+                max_expensive_goals,
+            } => Some(
+                ParallelSLGSolver::new(max_size, expected_answers)
+                    .with_normalization_mode(normalization)
+                    .with_max_expensive_goals(max_expensive_goals),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn into_solver(self) -> Box<dyn Solver<ChalkIr> + Send> {
         match self {
             SolverChoice::SLG {
                 max_size,
                 expected_answers,
-            } => Box::new(SLGSolver::new(max_size, expected_answers)),
+                normalization,
+                max_expensive_goals,
+            } => Box::new(
+                SLGSolver::new(max_size, expected_answers)
+                    .with_normalization_mode(normalization)
+                    .with_max_expensive_goals(max_expensive_goals),
+            ),
+            SolverChoice::Parallel {
+                max_size,
+                expected_answers,
+                normalization,
+                max_expensive_goals,
+            } => Box::new(
+                ParallelSLGSolver::new(max_size, expected_answers)
+                    .with_normalization_mode(normalization)
+                    .with_max_expensive_goals(max_expensive_goals),
+            ),
             SolverChoice::Recursive {
                 overflow_depth,
                 caching_enabled,
                 max_size,
-            } => Box::new(RecursiveSolver::new(
+                normalization,
+                max_expensive_goals,
+            } => Box::new(
+                RecursiveSolver::new(overflow_depth, max_size, caching_enabled)
+                    .with_normalization_mode(normalization)
+                    .with_max_expensive_goals(max_expensive_goals),
+            ),
+            SolverChoice::Differential {
+                slg_max_size,
+                recursive_max_size,
                 overflow_depth,
-                max_size,
-                caching_enabled,
+                normalization,
+                max_expensive_goals,
+            } => Box::new(DifferentialSolver::new(
+                SolverChoice::slg(slg_max_size, None)
+                    .with_normalization_mode(normalization)
+                    .with_max_expensive_goals(max_expensive_goals)
+                    .into_solver(),
+                SolverChoice::recursive(recursive_max_size, overflow_depth)
+                    .with_normalization_mode(normalization)
+                    .with_max_expensive_goals(max_expensive_goals)
+                    .into_solver(),
             )),
         }
     }
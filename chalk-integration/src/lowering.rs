@@ -10,11 +10,12 @@ use chalk_parse::ast::*;
 use chalk_solve::rust_ir::{self, IntoWhereClauses};
 use program_lowerer::ProgramLowerer;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 use string_cache::DefaultAtom as Atom;
 use tracing::debug;
 
 use crate::error::RustIrError;
-use crate::interner::{ChalkFnAbi, ChalkIr};
+use crate::interner::{ChalkConstExpr, ChalkFnAbi, ChalkIr};
 use crate::program::Program as LoweredProgram;
 use crate::{Identifier as Ident, TypeSort};
 use env::*;
@@ -98,6 +99,7 @@ lower_param_map!(ClosureDefn, None);
 lower_param_map!(Impl, None);
 lower_param_map!(AssocTyDefn, None);
 lower_param_map!(AssocTyValue, None);
+lower_param_map!(TraitMethodDefn, None);
 lower_param_map!(Clause, None);
 lower_param_map!(
     TraitDefn,
@@ -111,6 +113,28 @@ fn get_type_of_u32() -> chalk_ir::Ty<ChalkIr> {
     chalk_ir::TyKind::Scalar(chalk_ir::Scalar::Uint(chalk_ir::UintTy::U32)).intern(&ChalkIr)
 }
 
+/// Const literals and expressions don't say what their type is, so they
+/// are lowered as `u32`. When one is passed to a const parameter, it has
+/// the type of that parameter instead.
+fn with_parameter_type(
+    param: &chalk_ir::VariableKind<ChalkIr>,
+    arg: chalk_ir::GenericArg<ChalkIr>,
+) -> chalk_ir::GenericArg<ChalkIr> {
+    let interner = &ChalkIr;
+    if let (chalk_ir::VariableKind::Const(ty), Some(constant)) = (param, arg.constant(interner)) {
+        let value = &constant.data(interner).value;
+        if let chalk_ir::ConstValue::Concrete(_) | chalk_ir::ConstValue::Unevaluated(_) = value {
+            return chalk_ir::ConstData {
+                ty: ty.clone(),
+                value: value.clone(),
+            }
+            .intern(interner)
+            .cast(interner);
+        }
+    }
+    arg
+}
+
 impl Lower for VariableKind {
     type Lowered = chalk_ir::WithKind<ChalkIr, Ident>;
     fn lower(&self) -> Self::Lowered {
@@ -128,7 +152,13 @@ impl Lower for VariableKind {
                 n,
             ),
             VariableKind::Lifetime(n) => (chalk_ir::VariableKind::Lifetime, n),
-            VariableKind::Const(ref n) => (chalk_ir::VariableKind::Const(get_type_of_u32()), n),
+            VariableKind::Const(ref n, ref ty) => (
+                chalk_ir::VariableKind::Const(match ty {
+                    Some(ty) => chalk_ir::TyKind::Scalar(ty.lower()).intern(&ChalkIr),
+                    None => get_type_of_u32(),
+                }),
+                n,
+            ),
         };
 
         chalk_ir::WithKind::new(kind, n.str.clone())
@@ -218,8 +248,19 @@ impl LowerWithEnv for DomainGoal {
                     ty: ty.lower(env)?,
                 })]
             }
-            DomainGoal::TyWellFormed { ty } => vec![chalk_ir::DomainGoal::WellFormed(
-                chalk_ir::WellFormed::Ty(ty.lower(env)?),
+            DomainGoal::TyWellFormed { ty } => {
+                let well_formed = match ty {
+                    // `WellFormed(N)` where `N` is a const parameter is about the constant.
+                    Ty::Id { name } => match env.lookup_generic_arg(name)?.constant(interner) {
+                        Some(constant) => chalk_ir::WellFormed::Const(constant.clone()),
+                        None => chalk_ir::WellFormed::Ty(ty.lower(env)?),
+                    },
+                    _ => chalk_ir::WellFormed::Ty(ty.lower(env)?),
+                };
+                vec![chalk_ir::DomainGoal::WellFormed(well_formed)]
+            }
+            DomainGoal::ConstWellFormed { value } => vec![chalk_ir::DomainGoal::WellFormed(
+                chalk_ir::WellFormed::Const(value.lower(env)?),
             )],
             DomainGoal::TraitRefWellFormed { trait_ref } => vec![chalk_ir::DomainGoal::WellFormed(
                 chalk_ir::WellFormed::Trait(trait_ref.lower(env)?),
@@ -248,6 +289,10 @@ impl LowerWithEnv for DomainGoal {
             DomainGoal::ObjectSafe { id } => {
                 vec![chalk_ir::DomainGoal::ObjectSafe(env.lookup_trait(id)?)]
             }
+            DomainGoal::ConstHasType { value, ty } => vec![chalk_ir::DomainGoal::ConstHasType(
+                value.lower(env)?,
+                ty.lower(env)?,
+            )],
         })
     }
 }
@@ -321,14 +366,15 @@ impl LowerWithEnv for (&AdtDefn, chalk_ir::AdtId<ChalkIr>) {
     }
 }
 
-impl Lower for AdtRepr {
-    type Lowered = rust_ir::AdtRepr;
+impl LowerWithEnv for AdtRepr {
+    type Lowered = rust_ir::AdtRepr<ChalkIr>;
 
-    fn lower(&self) -> Self::Lowered {
-        rust_ir::AdtRepr {
+    fn lower(&self, env: &Env) -> LowerResult<Self::Lowered> {
+        Ok(rust_ir::AdtRepr {
             repr_c: self.repr_c,
             repr_packed: self.repr_packed,
-        }
+            int: self.int.as_ref().map(|i| i.lower(env)).transpose()?,
+        })
     }
 }
 
@@ -474,6 +520,14 @@ impl LowerWithEnv for TraitBound {
             }
         }
 
+        let parameters = k
+            .binders
+            .binders
+            .iter(interner)
+            .zip(parameters)
+            .map(|(binder, param)| with_parameter_type(binder, param))
+            .collect();
+
         Ok(rust_ir::TraitBound {
             trait_id,
             args_no_self: parameters,
@@ -511,6 +565,13 @@ impl LowerWithEnv for AliasEqBound {
             }
         }
 
+        let args: Vec<_> = lookup
+            .addl_variable_kinds
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| with_parameter_type(param, arg))
+            .collect();
+
         Ok(rust_ir::AliasEqBound {
             trait_bound,
             associated_ty_id: lookup.id,
@@ -624,7 +685,7 @@ impl LowerWithEnv for ProjectionTy {
             substitution: trait_substitution,
         } = trait_ref.lower(env)?;
         let lookup = env.lookup_associated_ty(trait_id, name)?;
-        let mut args: Vec<_> = args
+        let args: Vec<_> = args
             .iter()
             .map(|a| a.lower(env))
             .collect::<LowerResult<_>>()?;
@@ -647,6 +708,13 @@ impl LowerWithEnv for ProjectionTy {
             }
         }
 
+        let mut args: Vec<_> = lookup
+            .addl_variable_kinds
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| with_parameter_type(param, arg))
+            .collect();
+
         args.extend(trait_substitution.iter(interner).cloned());
 
         Ok(chalk_ir::ProjectionTy {
@@ -732,6 +800,14 @@ impl LowerWithEnv for Ty {
                                 })?;
                             }
                         }
+                        let substitution = chalk_ir::Substitution::from_iter(
+                            interner,
+                            $k.binders
+                                .binders
+                                .iter(interner)
+                                .zip(substitution.iter(interner))
+                                .map(|(param, arg)| with_parameter_type(param, arg.clone())),
+                        );
                         chalk_ir::TyKind::$tykind($id, substitution).intern(interner)
                     }};
                 }
@@ -833,6 +909,58 @@ impl LowerWithEnv for Const {
                 value: chalk_ir::ConstValue::Concrete(chalk_ir::ConcreteConst { interned: *value }),
             }
             .intern(interner)),
+            Const::Expr(expr) => {
+                let mut params = vec![];
+                let interned = lower_const_expr(expr, &mut params);
+                let args = params
+                    .into_iter()
+                    .map(|name| Const::Id(name).lower(env))
+                    .collect::<LowerResult<Vec<_>>>()?;
+                Ok(chalk_ir::ConstData {
+                    ty: get_type_of_u32(),
+                    value: chalk_ir::ConstValue::Unevaluated(chalk_ir::UnevaluatedConst {
+                        interned,
+                        substitution: Substitution::from_iter(interner, args),
+                    }),
+                }
+                .intern(interner))
+            }
+        }
+    }
+}
+
+/// Lowers a const expression, replacing each distinct parameter by its index
+/// in `params`.
+fn lower_const_expr(expr: &ConstExpr, params: &mut Vec<Identifier>) -> ChalkConstExpr {
+    let mut binary = |a: &ConstExpr, b: &ConstExpr| {
+        (
+            Arc::new(lower_const_expr(a, params)),
+            Arc::new(lower_const_expr(b, params)),
+        )
+    };
+    match expr {
+        ConstExpr::Id(name) => {
+            let index = params
+                .iter()
+                .position(|param| param.str == name.str)
+                .unwrap_or_else(|| {
+                    params.push(name.clone());
+                    params.len() - 1
+                });
+            ChalkConstExpr::Param(index)
+        }
+        ConstExpr::Value(value) => ChalkConstExpr::Value(*value),
+        ConstExpr::Add(a, b) => {
+            let (a, b) = binary(a, b);
+            ChalkConstExpr::Add(a, b)
+        }
+        ConstExpr::Sub(a, b) => {
+            let (a, b) = binary(a, b);
+            ChalkConstExpr::Sub(a, b)
+        }
+        ConstExpr::Mul(a, b) => {
+            let (a, b) = binary(a, b);
+            ChalkConstExpr::Mul(a, b)
         }
     }
 }
@@ -922,6 +1050,7 @@ impl LowerWithEnv for (&Impl, ImplId<ChalkIr>, &AssociatedTyValueIds) {
         Ok(rust_ir::ImplDatum {
             polarity,
             binders,
+            id: *impl_id,
             impl_type: impl_.impl_type.lower(),
             associated_ty_value_ids,
         })
@@ -968,6 +1097,25 @@ impl LowerWithEnv for Clause {
     }
 }
 
+impl LowerWithEnv for TraitMethodDefn {
+    type Lowered = chalk_ir::Binders<rust_ir::TraitMethodDatum<ChalkIr>>;
+
+    fn lower(&self, env: &Env) -> LowerResult<Self::Lowered> {
+        env.in_binders(self.all_parameters(), |env| {
+            Ok(rust_ir::TraitMethodDatum {
+                receiver: self.receiver.as_ref().map(|ty| ty.lower(env)).transpose()?,
+                argument_types: self
+                    .argument_types
+                    .iter()
+                    .map(|ty| ty.lower(env))
+                    .collect::<LowerResult<_>>()?,
+                return_type: self.return_type.lower(env)?,
+                where_clauses: self.where_clauses.lower(env)?,
+            })
+        })
+    }
+}
+
 impl LowerWithEnv for (&TraitDefn, chalk_ir::TraitId<ChalkIr>) {
     type Lowered = rust_ir::TraitDatum<ChalkIr>;
 
@@ -988,6 +1136,16 @@ impl LowerWithEnv for (&TraitDefn, chalk_ir::TraitId<ChalkIr>) {
 
             Ok(rust_ir::TraitDatumBound {
                 where_clauses: trait_defn.where_clauses.lower(env)?,
+                methods: trait_defn
+                    .method_defns
+                    .iter()
+                    .map(|defn| defn.lower(env))
+                    .collect::<LowerResult<_>>()?,
+                associated_consts: trait_defn
+                    .assoc_const_defns
+                    .iter()
+                    .map(|defn| defn.ty.lower(env))
+                    .collect::<LowerResult<_>>()?,
             })
         })?;
 
@@ -1131,6 +1289,22 @@ impl Lower for WellKnownTrait {
             WellKnownTrait::Unsize => rust_ir::WellKnownTrait::Unsize,
             WellKnownTrait::Unpin => rust_ir::WellKnownTrait::Unpin,
             WellKnownTrait::CoerceUnsized => rust_ir::WellKnownTrait::CoerceUnsized,
+            WellKnownTrait::DiscriminantKind => rust_ir::WellKnownTrait::DiscriminantKind,
+            WellKnownTrait::Generator => rust_ir::WellKnownTrait::Generator,
+            WellKnownTrait::DispatchFromDyn => rust_ir::WellKnownTrait::DispatchFromDyn,
+            WellKnownTrait::Pointee => rust_ir::WellKnownTrait::Pointee,
+            WellKnownTrait::Tuple => rust_ir::WellKnownTrait::Tuple,
+            WellKnownTrait::FnPtr => rust_ir::WellKnownTrait::FnPtr,
+        }
+    }
+}
+
+impl Lower for WellKnownAdt {
+    type Lowered = rust_ir::WellKnownAdt;
+
+    fn lower(&self) -> Self::Lowered {
+        match self {
+            WellKnownAdt::DynMetadata => rust_ir::WellKnownAdt::DynMetadata,
         }
     }
 }
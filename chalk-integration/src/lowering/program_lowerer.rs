@@ -8,6 +8,7 @@ use chalk_solve::rust_ir::{
     self, Anonymize, AssociatedTyValueId, GeneratorDatum, GeneratorInputOutputDatum,
     GeneratorWitnessDatum, GeneratorWitnessExistential, OpaqueTyDatum, OpaqueTyDatumBound,
 };
+use chalk_solve::simplified_type::ImplIndex;
 use rust_ir::IntoWhereClauses;
 use std::collections::{BTreeMap, HashSet};
 use std::sync::Arc;
@@ -39,6 +40,7 @@ pub(super) struct ProgramLowerer {
     trait_kinds: TraitKinds,
     opaque_ty_kinds: OpaqueTyVariableKinds,
     object_safe_traits: HashSet<TraitId<ChalkIr>>,
+    computed_object_safety_traits: HashSet<TraitId<ChalkIr>>,
     foreign_ty_ids: ForeignIds,
 }
 
@@ -117,6 +119,9 @@ impl ProgramLowerer {
                     if defn.flags.object_safe {
                         self.object_safe_traits.insert(id);
                     }
+                    if defn.flags.compute_object_safety {
+                        self.computed_object_safety_traits.insert(id);
+                    }
                 }
                 Item::OpaqueTyDefn(defn) => {
                     let type_kind = defn.lower_type_kind()?;
@@ -151,6 +156,7 @@ impl ProgramLowerer {
         let mut closure_upvars = BTreeMap::new();
         let mut trait_data = BTreeMap::new();
         let mut well_known_traits = BTreeMap::new();
+        let mut well_known_adts = BTreeMap::new();
         let mut impl_data = BTreeMap::new();
         let mut associated_ty_data = BTreeMap::new();
         let mut associated_ty_values = BTreeMap::new();
@@ -185,7 +191,10 @@ impl ProgramLowerer {
                     let identifier = d.name.clone();
                     let adt_id = AdtId(raw_id);
                     adt_data.insert(adt_id, Arc::new((d, adt_id).lower(&empty_env)?));
-                    adt_reprs.insert(adt_id, d.repr.lower());
+                    adt_reprs.insert(adt_id, Arc::new(d.repr.lower(&empty_env)?));
+                    if let Some(well_known) = d.well_known {
+                        well_known_adts.insert(well_known.lower(), adt_id);
+                    }
                     let n_params = d.all_parameters().len();
                     let variances = match d.variances.clone() {
                         Some(v) => {
@@ -464,6 +473,12 @@ impl ProgramLowerer {
             }
         }
 
+        let mut impl_index = ImplIndex::new();
+        for (&impl_id, impl_datum) in &impl_data {
+            let trait_ref = &impl_datum.binders.skip_binders().trait_ref;
+            impl_index.insert(&ChalkIr, impl_id, trait_ref);
+        }
+
         Ok(LoweredProgram {
             adt_ids: self.adt_ids,
             fn_def_ids: self.fn_def_ids,
@@ -487,7 +502,9 @@ impl ProgramLowerer {
             generator_witness_data,
             trait_data,
             well_known_traits,
+            well_known_adts,
             impl_data,
+            impl_index,
             associated_ty_values,
             associated_ty_data,
             opaque_ty_ids: self.opaque_ty_ids,
@@ -496,6 +513,7 @@ impl ProgramLowerer {
             hidden_opaque_types,
             custom_clauses,
             object_safe_traits: self.object_safe_traits,
+            computed_object_safety_traits: self.computed_object_safety_traits,
             foreign_ty_ids: self.foreign_ty_ids,
         })
     }
@@ -8,13 +8,15 @@ use chalk_ir::{
     OpaqueTyId, ProgramClause, ProgramClauseImplication, ProgramClauses, ProjectionTy,
     Substitution, TraitId, Ty, TyKind, Variances,
 };
+use chalk_solve::dependencies::{Dependencies, ItemId};
 use chalk_solve::rust_ir::{
     AdtDatum, AdtRepr, AssociatedTyDatum, AssociatedTyValue, AssociatedTyValueId, ClosureKind,
     FnDefDatum, FnDefInputsAndOutputDatum, GeneratorDatum, GeneratorWitnessDatum, ImplDatum,
-    ImplType, OpaqueTyDatum, TraitDatum, WellKnownTrait,
+    ImplType, OpaqueTyDatum, TraitDatum, WellKnownAdt, WellKnownTrait,
 };
+use chalk_solve::simplified_type::ImplIndex;
 use chalk_solve::split::Split;
-use chalk_solve::RustIrDatabase;
+use chalk_solve::{LookupError, RustIrDatabase};
 use std::collections::{BTreeMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
@@ -59,7 +61,7 @@ pub struct Program {
     /// For each ADT:
     pub adt_data: BTreeMap<AdtId<ChalkIr>, Arc<AdtDatum<ChalkIr>>>,
 
-    pub adt_reprs: BTreeMap<AdtId<ChalkIr>, AdtRepr>,
+    pub adt_reprs: BTreeMap<AdtId<ChalkIr>, Arc<AdtRepr<ChalkIr>>>,
 
     pub fn_def_data: BTreeMap<FnDefId<ChalkIr>, Arc<FnDefDatum<ChalkIr>>>,
 
@@ -72,6 +74,10 @@ pub struct Program {
     /// For each impl:
     pub impl_data: BTreeMap<ImplId<ChalkIr>, Arc<ImplDatum<ChalkIr>>>,
 
+    /// The impls of `impl_data`, indexed by trait and self type. Used by
+    /// `impls_for_trait`.
+    pub impl_index: ImplIndex<ChalkIr>,
+
     /// For each associated ty value `type Foo = XXX` found in an impl:
     pub associated_ty_values:
         BTreeMap<AssociatedTyValueId<ChalkIr>, Arc<AssociatedTyValue<ChalkIr>>>,
@@ -94,6 +100,9 @@ pub struct Program {
     /// For each trait lang item
     pub well_known_traits: BTreeMap<WellKnownTrait, TraitId<ChalkIr>>,
 
+    /// For each ADT lang item
+    pub well_known_adts: BTreeMap<WellKnownAdt, AdtId<ChalkIr>>,
+
     /// For each associated ty declaration `type Foo` found in a trait:
     pub associated_ty_data: BTreeMap<AssocTypeId<ChalkIr>, Arc<AssociatedTyDatum<ChalkIr>>>,
 
@@ -103,11 +112,102 @@ pub struct Program {
     /// Store the traits marked with `#[object_safe]`
     pub object_safe_traits: HashSet<TraitId<ChalkIr>>,
 
+    /// Store the traits marked with `#[compute_object_safety]`, whose
+    /// object safety is left to the solver
+    pub computed_object_safety_traits: HashSet<TraitId<ChalkIr>>,
+
     /// For each foreign type `extern { type A; }`
     pub foreign_ty_ids: BTreeMap<Identifier, ForeignDefId<ChalkIr>>,
 }
 
 impl Program {
+    /// Returns the items whose definitions differ between `previous` and
+    /// this program, i.e. those whose lookups may give different results.
+    /// Adding, removing or changing an impl also changes the set of impls
+    /// of its trait.
+    pub fn changed_items(&self, previous: &Program) -> Dependencies<ChalkIr> {
+        let mut changed = Dependencies::new();
+
+        for id in changed_keys(&previous.trait_data, &self.trait_data) {
+            changed.insert(ItemId::Trait(id));
+        }
+        for &id in previous
+            .object_safe_traits
+            .symmetric_difference(&self.object_safe_traits)
+            .chain(
+                previous
+                    .computed_object_safety_traits
+                    .symmetric_difference(&self.computed_object_safety_traits),
+            )
+        {
+            changed.insert(ItemId::Trait(id));
+        }
+        for id in changed_keys(&previous.adt_data, &self.adt_data)
+            .chain(changed_keys(&previous.adt_reprs, &self.adt_reprs))
+            .chain(changed_keys(&previous.adt_variances, &self.adt_variances))
+        {
+            changed.insert(ItemId::Adt(id));
+        }
+        for id in changed_keys(&previous.fn_def_data, &self.fn_def_data).chain(changed_keys(
+            &previous.fn_def_variances,
+            &self.fn_def_variances,
+        )) {
+            changed.insert(ItemId::FnDef(id));
+        }
+        for id in changed_keys(&previous.closure_upvars, &self.closure_upvars)
+            .chain(changed_keys(
+                &previous.closure_inputs_and_output,
+                &self.closure_inputs_and_output,
+            ))
+            .chain(changed_keys(
+                &previous.closure_closure_kind,
+                &self.closure_closure_kind,
+            ))
+        {
+            changed.insert(ItemId::Closure(id));
+        }
+        for id in changed_keys(&previous.generator_data, &self.generator_data).chain(changed_keys(
+            &previous.generator_witness_data,
+            &self.generator_witness_data,
+        )) {
+            changed.insert(ItemId::Generator(id));
+        }
+        for id in changed_keys(&previous.opaque_ty_data, &self.opaque_ty_data).chain(changed_keys(
+            &previous.hidden_opaque_types,
+            &self.hidden_opaque_types,
+        )) {
+            changed.insert(ItemId::OpaqueTy(id));
+        }
+        for id in changed_keys(&previous.associated_ty_data, &self.associated_ty_data) {
+            changed.insert(ItemId::AssociatedTy(id));
+        }
+        for id in changed_keys(&previous.associated_ty_values, &self.associated_ty_values) {
+            changed.insert(ItemId::AssociatedTyValue(id));
+        }
+        for id in changed_keys(&previous.impl_data, &self.impl_data) {
+            changed.insert(ItemId::Impl(id));
+            for datum in previous
+                .impl_data
+                .get(&id)
+                .into_iter()
+                .chain(self.impl_data.get(&id))
+            {
+                changed.insert(ItemId::TraitImpls(datum.trait_id()));
+            }
+        }
+        for well_known in changed_keys(&previous.well_known_traits, &self.well_known_traits) {
+            changed.insert(ItemId::WellKnownTrait(well_known));
+        }
+        for well_known in changed_keys(&previous.well_known_adts, &self.well_known_adts) {
+            changed.insert(ItemId::WellKnownAdt(well_known));
+        }
+        if previous.custom_clauses != self.custom_clauses {
+            changed.insert(ItemId::CustomClauses);
+        }
+
+        changed
+    }
+
     /// Returns the ids for all impls declared in this crate.
     pub(crate) fn local_impl_ids(&self) -> Vec<ImplId<ChalkIr>> {
         self.impl_data
@@ -118,6 +218,19 @@ impl Program {
     }
 }
 
+/// The keys that are present in only one of `previous` and `current`, or
+/// that map to different values in them.
+fn changed_keys<'a, K: Copy + Ord, V: PartialEq>(
+    previous: &'a BTreeMap<K, V>,
+    current: &'a BTreeMap<K, V>,
+) -> impl Iterator<Item = K> + 'a {
+    previous
+        .keys()
+        .chain(current.keys())
+        .copied()
+        .filter(move |key| previous.get(key) != current.get(key))
+}
+
 impl tls::DebugContext for Program {
     fn debug_adt_id(
         &self,
@@ -426,8 +539,8 @@ impl RustIrDatabase<ChalkIr> for Program {
         self.generator_witness_data[&id].clone()
     }
 
-    fn adt_repr(&self, id: AdtId<ChalkIr>) -> AdtRepr {
-        self.adt_reprs[&id]
+    fn adt_repr(&self, id: AdtId<ChalkIr>) -> Arc<AdtRepr<ChalkIr>> {
+        self.adt_reprs[&id].clone()
     }
 
     fn fn_def_datum(&self, id: FnDefId<ChalkIr>) -> Arc<FnDefDatum<ChalkIr>> {
@@ -441,21 +554,19 @@ impl RustIrDatabase<ChalkIr> for Program {
         _binders: &CanonicalVarKinds<ChalkIr>,
     ) -> Vec<ImplId<ChalkIr>> {
         let interner = self.interner();
-        self.impl_data
-            .iter()
-            .filter(|(_, impl_datum)| {
-                let trait_ref = &impl_datum.binders.skip_binders().trait_ref;
-                trait_id == trait_ref.trait_id && {
-                    assert_eq!(trait_ref.substitution.len(interner), parameters.len());
-                    <[_] as CouldMatch<[_]>>::could_match(
-                        &parameters,
-                        interner,
-                        self.unification_database(),
-                        &trait_ref.substitution.as_slice(interner),
-                    )
-                }
+        self.impl_index
+            .candidates(interner, trait_id, parameters)
+            .into_iter()
+            .filter(|impl_id| {
+                let trait_ref = &self.impl_data[impl_id].binders.skip_binders().trait_ref;
+                assert_eq!(trait_ref.substitution.len(interner), parameters.len());
+                <[_] as CouldMatch<[_]>>::could_match(
+                    &parameters,
+                    interner,
+                    self.unification_database(),
+                    &trait_ref.substitution.as_slice(interner),
+                )
             })
-            .map(|(&impl_id, _)| impl_id)
             .collect()
     }
 
@@ -520,10 +631,14 @@ impl RustIrDatabase<ChalkIr> for Program {
         self.well_known_traits.get(&well_known_trait).copied()
     }
 
+    fn well_known_adt_id(&self, well_known_adt: WellKnownAdt) -> Option<AdtId<ChalkIr>> {
+        self.well_known_adts.get(&well_known_adt).copied()
+    }
+
     fn program_clauses_for_env(
         &self,
         environment: &chalk_ir::Environment<ChalkIr>,
-    ) -> ProgramClauses<ChalkIr> {
+    ) -> Result<ProgramClauses<ChalkIr>, LookupError<ChalkIr>> {
         chalk_solve::program_clauses_for_env(self, environment)
     }
 
@@ -531,8 +646,14 @@ impl RustIrDatabase<ChalkIr> for Program {
         &ChalkIr
     }
 
-    fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> bool {
-        self.object_safe_traits.contains(&trait_id)
+    fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> Option<bool> {
+        if self.object_safe_traits.contains(&trait_id) {
+            Some(true)
+        } else if self.computed_object_safety_traits.contains(&trait_id) {
+            None
+        } else {
+            Some(false)
+        }
     }
 
     // For all the closure functions: this is different than how rustc does it.
@@ -8,6 +8,7 @@ use crate::program::Program;
 use crate::program_environment::ProgramEnvironment;
 use crate::tls;
 use crate::SolverChoice;
+use chalk_engine::solve::ParallelSLGSolver;
 use chalk_ir::{Substitution, TraitId};
 use chalk_solve::clauses::builder::ClauseBuilder;
 use chalk_solve::clauses::program_clauses::ToProgramClauses;
@@ -26,7 +27,7 @@ use std::sync::Mutex;
 
 #[salsa::query_group(Lowering)]
 pub trait LoweringDatabase:
-    RustIrDatabase<ChalkIr> + Database + Upcast<dyn RustIrDatabase<ChalkIr>>
+    RustIrDatabase<ChalkIr> + Database + Upcast<dyn RustIrDatabase<ChalkIr>> + HasSolverCache
 {
     #[salsa::input]
     fn program_text(&self) -> Arc<String>;
@@ -52,12 +53,84 @@ pub trait LoweringDatabase:
 
     /// Creates the solver we can use to solve goals. This solver
     /// stores intermediate, cached state, which is why it is behind a
-    /// mutex. Moreover, if the set of program clauses change, that
-    /// cached state becomes invalid, so the query is marked as
-    /// volatile, thus ensuring that the solver is recreated in every
-    /// revision (i.e., each time source program changes).
+    /// mutex unless it can be shared (see `SharedSolver`). When the program
+    /// changes, the solver of the previous revision is reused after
+    /// invalidating the parts of its cache that depend on the items that
+    /// changed (see `SolverCache`).
     // HACK: salsa requires that queries return types that implement `Eq`
-    fn solver(&self) -> ArcEq<Mutex<Box<dyn Solver<ChalkIr>>>>;
+    fn solver(&self) -> ArcEq<SharedSolver>;
+}
+
+/// The solver handed out by the `solver` query. Solvers keep their caches
+/// in `&mut self`, so queries usually take turns using them; the solver of
+/// `SolverChoice::Parallel` shares its completed tables between threads
+/// instead, so that queries from several threads can use it at once.
+#[derive(Debug)]
+pub enum SharedSolver {
+    Locked(Mutex<Box<dyn Solver<ChalkIr> + Send>>),
+    Parallel(ParallelSLGSolver<ChalkIr>),
+}
+
+impl SharedSolver {
+    fn new(choice: SolverChoice) -> Self {
+        match choice.into_parallel_solver() {
+            Some(solver) => SharedSolver::Parallel(solver),
+            None => SharedSolver::Locked(Mutex::new(choice.into_solver())),
+        }
+    }
+
+    /// Runs `op` with the solver, once the other queries are done with it
+    /// if it is locked. The parallel solver is not locked: `op` gets a
+    /// clone of it, which shares its tables.
+    pub fn with<R>(&self, op: impl FnOnce(&mut dyn Solver<ChalkIr>) -> R) -> R {
+        match self {
+            SharedSolver::Locked(solver) => op(&mut **solver.lock().unwrap()),
+            SharedSolver::Parallel(solver) => op(&mut solver.clone()),
+        }
+    }
+}
+
+/// Gives the `solver` query access to the solver of the previous revision.
+pub trait HasSolverCache {
+    fn solver_cache(&self) -> &SolverCache;
+}
+
+/// Holds on to the solver handed out by the `solver` query, along with the
+/// program it was last used with, so that its cache can survive program
+/// edits that don't affect it.
+#[derive(Default)]
+pub struct SolverCache {
+    cached: Mutex<Option<CachedSolver>>,
+}
+
+struct CachedSolver {
+    choice: SolverChoice,
+    program: Arc<Program>,
+    solver: ArcEq<SharedSolver>,
+}
+
+impl SolverCache {
+    /// Returns a solver for `program`, reusing the cached one if it was
+    /// created for the same `choice` and can invalidate the results that
+    /// depend on the items changed since its last use.
+    fn solver_for(&self, choice: SolverChoice, program: Arc<Program>) -> ArcEq<SharedSolver> {
+        let mut cached = self.cached.lock().unwrap();
+        if let Some(cached) = cached.as_mut().filter(|cached| cached.choice == choice) {
+            let changed = program.changed_items(&cached.program);
+            if changed.is_empty() || cached.solver.with(|solver| solver.invalidate(&changed)) {
+                cached.program = program;
+                return cached.solver.clone();
+            }
+        }
+
+        let solver = ArcEq::new(SharedSolver::new(choice));
+        *cached = Some(CachedSolver {
+            choice,
+            program,
+            solver: solver.clone(),
+        });
+        solver
+    }
 }
 
 // Needed to go from dyn LoweringDatabase -> dyn RustIrDatabase
@@ -146,7 +219,7 @@ fn coherence(
     let program = db.program_ir()?;
     let solver_choice = db.solver_choice();
     let priorities_map = tls::set_current_program(&program, || -> Result<_, ChalkError> {
-        let solver_builder = || solver_choice.into_solver();
+        let solver_builder = || -> Box<dyn Solver<ChalkIr>> { solver_choice.into_solver() };
         let priorities_map: Result<BTreeMap<_, _>, ChalkError> = program
             .trait_data
             .keys()
@@ -171,7 +244,7 @@ fn checked_program(db: &dyn LoweringDatabase) -> Result<Arc<Program>, ChalkError
 
     let solver_choice = db.solver_choice();
     let () = tls::set_current_program(&program, || -> Result<(), ChalkError> {
-        let solver_builder = || solver_choice.into_solver();
+        let solver_builder = || -> Box<dyn Solver<ChalkIr>> { solver_choice.into_solver() };
         let solver: wf::WfSolver<ChalkIr> = wf::WfSolver::new(db.upcast(), &solver_builder);
         for &id in program.adt_data.keys() {
             solver.verify_adt_decl(id)?;
@@ -248,8 +321,10 @@ fn environment(db: &dyn LoweringDatabase) -> Result<Arc<ProgramEnvironment>, Cha
     Ok(Arc::new(ProgramEnvironment::new(program_clauses)))
 }
 
-fn solver(db: &dyn LoweringDatabase) -> ArcEq<Mutex<Box<dyn Solver<ChalkIr>>>> {
-    db.salsa_runtime().report_untracked_read();
+fn solver(db: &dyn LoweringDatabase) -> ArcEq<SharedSolver> {
     let choice = db.solver_choice();
-    ArcEq::new(Mutex::new(choice.into_solver()))
+    match db.program_ir() {
+        Ok(program) => db.solver_cache().solver_for(choice, program),
+        Err(_) => ArcEq::new(SharedSolver::new(choice)),
+    }
 }
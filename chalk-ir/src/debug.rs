@@ -73,6 +73,12 @@ impl<I: Interner> Debug for ConcreteConst<I> {
     }
 }
 
+impl<I: Interner> Debug for UnevaluatedConst<I> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(fmt, "{:?}{:?}", self.interned, self.substitution)
+    }
+}
+
 impl<I: Interner> Debug for GenericArg<I> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
         I::debug_generic_arg(self, fmt).unwrap_or_else(|| write!(fmt, "{:?}", self.interned))
@@ -353,6 +359,7 @@ impl<I: Interner> Debug for ConstData<I> {
             ConstValue::InferenceVar(var) => write!(fmt, "{:?}", var),
             ConstValue::Placeholder(index) => write!(fmt, "{:?}", index),
             ConstValue::Concrete(evaluated) => write!(fmt, "{:?}", evaluated),
+            ConstValue::Unevaluated(unevaluated) => write!(fmt, "{:?}", unevaluated),
         }
     }
 }
@@ -791,6 +798,7 @@ impl<I: Interner> Debug for WellFormed<I> {
         match self {
             WellFormed::Trait(t) => write!(fmt, "WellFormed({:?})", t.with_colon()),
             WellFormed::Ty(t) => write!(fmt, "WellFormed({:?})", t),
+            WellFormed::Const(c) => write!(fmt, "WellFormed({:?})", c),
         }
     }
 }
@@ -812,6 +820,7 @@ impl<I: Interner> Debug for DomainGoal<I> {
             DomainGoal::DownstreamType(n) => write!(fmt, "DownstreamType({:?})", n),
             DomainGoal::Reveal => write!(fmt, "Reveal"),
             DomainGoal::ObjectSafe(n) => write!(fmt, "ObjectSafe({:?})", n),
+            DomainGoal::ConstHasType(c, ty) => write!(fmt, "ConstHasType({:?}, {:?})", c, ty),
         }
     }
 }
@@ -946,8 +955,21 @@ impl<I: Interner, T: Debug> Debug for WithKind<I, T> {
 impl<I: Interner> Debug for Constraint<I> {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
         match self {
-            Constraint::LifetimeOutlives(a, b) => write!(fmt, "{:?}: {:?}", a, b),
-            Constraint::TypeOutlives(ty, lifetime) => write!(fmt, "{:?}: {:?}", ty, lifetime),
+            Constraint::LifetimeOutlives(a, b, _) => write!(fmt, "{:?}: {:?}", a, b),
+            Constraint::TypeOutlives(ty, lifetime, _) => write!(fmt, "{:?}: {:?}", ty, lifetime),
+        }
+    }
+}
+
+impl<I: Interner> Debug for ConstraintOrigin<I> {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> Result<(), Error> {
+        match self {
+            ConstraintOrigin::Builtin => write!(fmt, "builtin"),
+            ConstraintOrigin::ImplWhereClause(impl_id) => {
+                write!(fmt, "where clause on {:?}", impl_id)
+            }
+            ConstraintOrigin::WellFormed(ty) => write!(fmt, "well-formedness of {:?}", ty),
+            ConstraintOrigin::ImpliedBound(from_env) => write!(fmt, "implied by {:?}", from_env),
         }
     }
 }
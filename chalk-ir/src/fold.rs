@@ -582,6 +582,14 @@ where
                 }),
             }
             .intern(folder.interner())),
+            ConstValue::Unevaluated(unevaluated) => Ok(ConstData {
+                ty: fold_ty()?,
+                value: ConstValue::Unevaluated(UnevaluatedConst {
+                    interned: unevaluated.interned.clone(),
+                    substitution: unevaluated.substitution.fold_with(folder, outer_binder)?,
+                }),
+            }
+            .intern(folder.interner())),
         }
     }
 }
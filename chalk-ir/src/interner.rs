@@ -37,11 +37,58 @@ use crate::VariableKinds;
 use crate::Variance;
 use crate::Variances;
 use crate::{Const, ConstData};
+use crate::{ConstEvalError, UnevaluatedConst};
 use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+
+/// An [`Interner`] whose plain-data associated types (ids, ABIs and constant
+/// values) can be serialized, which the serde implementations of the types
+/// in chalk-ir require. It is implemented for every such interner; the
+/// associated types of this trait are only there so that the bounds hold
+/// wherever `I: SerdeInterner` does.
+#[cfg(feature = "serde")]
+pub trait SerdeInterner:
+    Interner<
+    DefId = <Self as SerdeInterner>::SerdeDefId,
+    InternedAdtId = <Self as SerdeInterner>::SerdeAdtId,
+    FnAbi = <Self as SerdeInterner>::SerdeFnAbi,
+    InternedConcreteConst = <Self as SerdeInterner>::SerdeConcreteConst,
+    InternedUnevaluatedConst = <Self as SerdeInterner>::SerdeUnevaluatedConst,
+>
+{
+    /// Always `Self::DefId`.
+    type SerdeDefId: Serialize + DeserializeOwned;
+    /// Always `Self::InternedAdtId`.
+    type SerdeAdtId: Serialize + DeserializeOwned;
+    /// Always `Self::FnAbi`.
+    type SerdeFnAbi: Serialize + DeserializeOwned;
+    /// Always `Self::InternedConcreteConst`.
+    type SerdeConcreteConst: Serialize + DeserializeOwned;
+    /// Always `Self::InternedUnevaluatedConst`.
+    type SerdeUnevaluatedConst: Serialize + DeserializeOwned;
+}
+
+#[cfg(feature = "serde")]
+impl<I: Interner> SerdeInterner for I
+where
+    I::DefId: Serialize + DeserializeOwned,
+    I::InternedAdtId: Serialize + DeserializeOwned,
+    I::FnAbi: Serialize + DeserializeOwned,
+    I::InternedConcreteConst: Serialize + DeserializeOwned,
+    I::InternedUnevaluatedConst: Serialize + DeserializeOwned,
+{
+    type SerdeDefId = I::DefId;
+    type SerdeAdtId = I::InternedAdtId;
+    type SerdeFnAbi = I::FnAbi;
+    type SerdeConcreteConst = I::InternedConcreteConst;
+    type SerdeUnevaluatedConst = I::InternedUnevaluatedConst;
+}
+
 /// A "interner" encapsulates the concrete representation of
 /// certain "core types" from chalk-ir. All the types in chalk-ir are
 /// parameterized by a `I: Interner`, and so (e.g.) if they want to
@@ -57,7 +104,10 @@ use std::sync::Arc;
 /// (e.g., `SourceI` and `TargetI`) -- even if those type parameters
 /// wind up being mapped to the same underlying type families in the
 /// end.
-pub trait Interner: Debug + Copy + Eq + Ord + Hash {
+///
+/// Interners and the types they intern must be `Send + Sync`, so that
+/// solvers can share their results between threads.
+pub trait Interner: Debug + Copy + Eq + Ord + Hash + Send + Sync {
     /// "Interned" representation of types.  In normal user code,
     /// `Self::InternedType` is not referenced. Instead, we refer to
     /// `Ty<Self>`, which wraps this type.
@@ -67,7 +117,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     /// converted back (by the [`ty_data`] method). The interned form
     /// must also introduce indirection, either via a `Box`, `&`, or
     /// other pointer type.
-    type InternedType: Debug + Clone + Eq + Hash;
+    type InternedType: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of lifetimes.  In normal user code,
     /// `Self::InternedLifetime` is not referenced. Instead, we refer to
@@ -76,7 +126,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     /// An `InternedLifetime` must be something that can be created
     /// from a `LifetimeData` (by the [`intern_lifetime`] method) and
     /// then later converted back (by the [`lifetime_data`] method).
-    type InternedLifetime: Debug + Clone + Eq + Hash;
+    type InternedLifetime: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of const expressions. In normal user code,
     /// `Self::InternedConst` is not referenced. Instead, we refer to
@@ -85,7 +135,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     /// An `InternedConst` must be something that can be created
     /// from a `ConstData` (by the [`intern_const`] method) and
     /// then later converted back (by the [`const_data`] method).
-    type InternedConst: Debug + Clone + Eq + Hash;
+    type InternedConst: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of an evaluated const value.
     /// `Self::InternedConcreteConst` is not referenced. Instead,
@@ -94,7 +144,16 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     /// `InternedConcreteConst` instances are not created by chalk,
     /// it can only make a query asking about equality of two
     /// evaluated consts.
-    type InternedConcreteConst: Debug + Clone + Eq + Hash;
+    type InternedConcreteConst: Debug + Clone + Eq + Hash + Send + Sync;
+
+    /// "Interned" representation of a const expression that has not been
+    /// evaluated yet. `Self::InternedUnevaluatedConst` is not referenced.
+    /// Instead, we refer to `UnevaluatedConst<Self>`, which wraps this type.
+    ///
+    /// Like `InternedConcreteConst`, these are not created by chalk; it
+    /// only asks the interner to evaluate them (see
+    /// [`try_evaluate_const`](Self::try_evaluate_const)).
+    type InternedUnevaluatedConst: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a "generic parameter", which can
     /// be either a type or a lifetime.  In normal user code,
@@ -103,7 +162,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedType` is created by `intern_generic_arg` and can be
     /// converted back to its underlying data via `generic_arg_data`.
-    type InternedGenericArg: Debug + Clone + Eq + Hash;
+    type InternedGenericArg: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a "goal".  In normal user code,
     /// `Self::InternedGoal` is not referenced. Instead, we refer to
@@ -111,7 +170,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedGoal` is created by `intern_goal` and can be
     /// converted back to its underlying data via `goal_data`.
-    type InternedGoal: Debug + Clone + Eq + Hash;
+    type InternedGoal: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of goals.  In normal user code,
     /// `Self::InternedGoals` is not referenced. Instead, we refer to
@@ -119,7 +178,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedGoals` is created by `intern_goals` and can be
     /// converted back to its underlying data via `goals_data`.
-    type InternedGoals: Debug + Clone + Eq + Hash;
+    type InternedGoals: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a "substitution".  In normal user code,
     /// `Self::InternedSubstitution` is not referenced. Instead, we refer to
@@ -127,7 +186,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedSubstitution` is created by `intern_substitution` and can be
     /// converted back to its underlying data via `substitution_data`.
-    type InternedSubstitution: Debug + Clone + Eq + Hash;
+    type InternedSubstitution: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of program clauses.  In normal user code,
     /// `Self::InternedProgramClauses` is not referenced. Instead, we refer to
@@ -135,7 +194,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedProgramClauses` is created by `intern_program_clauses` and can be
     /// converted back to its underlying data via `program_clauses_data`.
-    type InternedProgramClauses: Debug + Clone + Eq + Hash;
+    type InternedProgramClauses: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a "program clause".  In normal user code,
     /// `Self::InternedProgramClause` is not referenced. Instead, we refer to
@@ -143,7 +202,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedProgramClause` is created by `intern_program_clause` and can be
     /// converted back to its underlying data via `program_clause_data`.
-    type InternedProgramClause: Debug + Clone + Eq + Hash;
+    type InternedProgramClause: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of quantified where clauses.
     /// In normal user code, `Self::InternedQuantifiedWhereClauses` is not referenced.
@@ -151,7 +210,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedQuantifiedWhereClauses` is created by `intern_quantified_where_clauses`
     /// and can be converted back to its underlying data via `quantified_where_clauses_data`.
-    type InternedQuantifiedWhereClauses: Debug + Clone + Eq + Hash;
+    type InternedQuantifiedWhereClauses: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of variable kinds.
     /// In normal user code, `Self::InternedVariableKinds` is not referenced.
@@ -159,7 +218,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedVariableKinds` is created by `intern_generic_arg_kinds`
     /// and can be converted back to its underlying data via `variable_kinds_data`.
-    type InternedVariableKinds: Debug + Clone + Eq + Hash;
+    type InternedVariableKinds: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of variable kinds with universe index.
     /// In normal user code, `Self::InternedCanonicalVarKinds` is not referenced.
@@ -168,7 +227,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     /// An `InternedCanonicalVarKinds` is created by
     /// `intern_canonical_var_kinds` and can be converted back
     /// to its underlying data via `canonical_var_kinds_data`.
-    type InternedCanonicalVarKinds: Debug + Clone + Eq + Hash;
+    type InternedCanonicalVarKinds: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of region constraints.
     /// In normal user code, `Self::InternedConstraints` is not referenced.
@@ -176,7 +235,7 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     ///
     /// An `InternedConstraints` is created by `intern_constraints`
     /// and can be converted back to its underlying data via `constraints_data`.
-    type InternedConstraints: Debug + Clone + Eq + Hash;
+    type InternedConstraints: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// "Interned" representation of a list of `chalk_ir::Variance`.
     /// In normal user code, `Self::InternedVariances` is not referenced.
@@ -185,19 +244,19 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
     /// An `InternedVariances` is created by
     /// `intern_variances` and can be converted back
     /// to its underlying data via `variances_data`.
-    type InternedVariances: Debug + Clone + Eq + Hash;
+    type InternedVariances: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// The core "id" type used for trait-ids and the like.
-    type DefId: Debug + Copy + Eq + Ord + Hash;
+    type DefId: Debug + Copy + Eq + Ord + Hash + Send + Sync;
 
     /// The ID type for ADTs
-    type InternedAdtId: Debug + Copy + Eq + Ord + Hash;
+    type InternedAdtId: Debug + Copy + Eq + Ord + Hash + Send + Sync;
 
     /// Representation of identifiers.
-    type Identifier: Debug + Clone + Eq + Hash;
+    type Identifier: Debug + Clone + Eq + Hash + Send + Sync;
 
     /// Representation of function ABI (e.g. calling convention).
-    type FnAbi: Debug + Copy + Eq + Hash;
+    type FnAbi: Debug + Copy + Eq + Hash + Send + Sync;
 
     /// Prints the debug representation of a type-kind-id.
     /// Returns `None` to fallback to the default debug output.
@@ -467,6 +526,19 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
         None
     }
 
+    /// Returns the interner to use when serializing or deserializing
+    /// values interned by `Self`, which are written out as their data
+    /// (e.g. a `Ty` as its `TyKind`) and interned again when read back.
+    /// Serde offers no way to pass an interner along, so like the `debug_*`
+    /// methods this has no receiver: interners that carry no state can
+    /// return themselves, others would typically look up the current one in
+    /// a thread-local. Returns `None` by default, which makes
+    /// (de)serialization fail.
+    #[cfg(feature = "serde")]
+    fn serde_interner() -> Option<Self> {
+        None
+    }
+
     /// Create an "interned" type from `ty`. This is not normally
     /// invoked directly; instead, you invoke `TyKind::intern` (which
     /// will ultimately call this method).
@@ -501,6 +573,20 @@ pub trait Interner: Debug + Copy + Eq + Ord + Hash {
         c2: &Self::InternedConcreteConst,
     ) -> bool;
 
+    /// Tries to evaluate an unevaluated const of type `ty`. This is called
+    /// during unification, so the substitution of `constant` may still
+    /// contain inference variables, placeholders or other unevaluated
+    /// consts; in that case, return `Err(ConstEvalError::TooGeneric)` and
+    /// unification will be ambiguous.
+    #[allow(unused_variables)]
+    fn try_evaluate_const(
+        &self,
+        ty: &Self::InternedType,
+        constant: &UnevaluatedConst<Self>,
+    ) -> Result<Self::InternedConcreteConst, ConstEvalError> {
+        Err(ConstEvalError::TooGeneric)
+    }
+
     /// Create an "interned" parameter from `data`. This is not
     /// normally invoked directly; instead, you invoke
     /// `GenericArgData::intern` (which will ultimately call this
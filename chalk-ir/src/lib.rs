@@ -13,10 +13,16 @@ use crate::visit::{ControlFlow, SuperVisit, Visit, VisitExt, Visitor};
 use chalk_derive::{Fold, HasInterner, SuperVisit, Visit, Zip};
 use std::marker::PhantomData;
 
+#[cfg(feature = "serde")]
+use interner::SerdeInterner;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
 pub use crate::debug::SeparatorTraitRef;
 
 /// Uninhabited (empty) type, used in combination with `PhantomData`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Void {}
 
 /// Many of our internal operations (e.g., unification) are an attempt
@@ -36,6 +42,7 @@ pub enum FallibleOrFloundered<T> {
 /// Indicates that the attempted operation has "no solution" -- i.e.,
 /// cannot be performed.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NoSolution;
 
 /// Error type for the `UnificationOps::program_clauses` method --
@@ -72,8 +79,12 @@ use interner::{HasInterner, Interner};
 pub mod could_match;
 pub mod debug;
 
+#[cfg(feature = "serde")]
+mod serialize;
+
 /// Variance
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Variance {
     /// a <: b
     Covariant,
@@ -147,6 +158,11 @@ impl Variance {
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 /// The set of assumptions we've made so far, and the current number of
 /// universal (forall) quantifiers we're within.
 pub struct Environment<I: Interner> {
@@ -197,6 +213,14 @@ impl<I: Interner> Environment<I> {
 
 /// A goal with an environment to solve it in.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(
+        serialize = "G: Serialize, G::Interner: SerdeInterner",
+        deserialize = "G: Deserialize<'de>, G::Interner: SerdeInterner"
+    ))
+)]
 #[allow(missing_docs)]
 pub struct InEnvironment<G: HasInterner> {
     pub environment: Environment<G::Interner>,
@@ -236,6 +260,7 @@ impl<G: HasInterner> HasInterner for InEnvironment<G> {
 
 /// Different signed int types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(missing_docs)]
 pub enum IntTy {
     Isize,
@@ -248,6 +273,7 @@ pub enum IntTy {
 
 /// Different unsigned int types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(missing_docs)]
 pub enum UintTy {
     Usize,
@@ -260,6 +286,7 @@ pub enum UintTy {
 
 /// Different kinds of float types.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(missing_docs)]
 pub enum FloatTy {
     F32,
@@ -268,6 +295,7 @@ pub enum FloatTy {
 
 /// Types of scalar values.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(missing_docs)]
 pub enum Scalar {
     Bool,
@@ -279,6 +307,7 @@ pub enum Scalar {
 
 /// Whether a function is safe or not.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Safety {
     /// Safe
     Safe,
@@ -288,6 +317,7 @@ pub enum Safety {
 
 /// Whether a type is mutable or not.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Mutability {
     /// Mutable
     Mut,
@@ -303,6 +333,7 @@ pub enum Mutability {
 /// `Goal(!U1)` (the quantifier was moved to the environment and replaced with a universe index)
 /// See https://rustc-dev-guide.rust-lang.org/borrow_check/region_inference.html#placeholders-and-universes for more.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct UniverseIndex {
     /// The counter for the universe index, starts with 0.
     pub counter: usize,
@@ -359,6 +390,11 @@ impl UniverseMap {
 
 /// The id for an Abstract Data Type (i.e. structs, unions and enums).
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct AdtId<I: Interner>(pub I::InternedAdtId);
 
 /// The id of a trait definition; could be used to load the trait datum by
@@ -366,14 +402,29 @@ pub struct AdtId<I: Interner>(pub I::InternedAdtId);
 ///
 /// [`trait_datum`]: ../chalk_solve/trait.RustIrDatabase.html#tymethod.trait_datum
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct TraitId<I: Interner>(pub I::DefId);
 
 /// The id for an impl.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ImplId<I: Interner>(pub I::DefId);
 
 /// Id for a specific clause.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ClauseId<I: Interner>(pub I::DefId);
 
 /// The id for the associated type member of a trait. The details of the type
@@ -381,26 +432,56 @@ pub struct ClauseId<I: Interner>(pub I::DefId);
 ///
 /// [`associated_ty_data`]: ../chalk_solve/trait.RustIrDatabase.html#tymethod.associated_ty_data
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct AssocTypeId<I: Interner>(pub I::DefId);
 
 /// Id for an opaque type.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct OpaqueTyId<I: Interner>(pub I::DefId);
 
 /// Function definition id.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct FnDefId<I: Interner>(pub I::DefId);
 
 /// Id for Rust closures.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ClosureId<I: Interner>(pub I::DefId);
 
 /// Id for Rust generators.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct GeneratorId<I: Interner>(pub I::DefId);
 
 /// Id for foreign types.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ForeignDefId<I: Interner>(pub I::DefId);
 
 impl_debugs!(ImplId, ClauseId);
@@ -527,6 +608,11 @@ impl<I: Interner> Ty<I> {
 
 /// Contains the data for a Ty
 #[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct TyData<I: Interner> {
     /// The kind
     pub kind: TyKind<I>,
@@ -534,6 +620,11 @@ pub struct TyData<I: Interner> {
 
 /// Type data, which holds the actual type information.
 #[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum TyKind<I: Interner> {
     /// Abstract data types, i.e., structs, unions, or enumerations.
     /// For example, a type like `Vec<T>`.
@@ -665,6 +756,7 @@ impl<I: Interner> TyKind<I> {
 /// they appear in the *innermost* binder enclosing the `...`. The
 /// indices identify the location *within* that binder.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BoundVar {
     /// Debruijn index, which identifies the binder.
     pub debruijn: DebruijnIndex,
@@ -756,6 +848,7 @@ impl BoundVar {
 ///
 /// [de Bruijn index]: https://en.wikipedia.org/wiki/De_Bruijn_index
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DebruijnIndex {
     depth: u32,
 }
@@ -914,6 +1007,11 @@ impl DebruijnIndex {
 /// a bound type with debruijn index 1 (i.e., skipping through one
 /// level of binder).
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct DynTy<I: Interner> {
     /// The unknown self type.
     pub bounds: Binders<QuantifiedWhereClauses<I>>,
@@ -931,6 +1029,7 @@ where
 
 /// A type, lifetime or constant whose value is being inferred.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct InferenceVar {
     index: u32,
 }
@@ -969,6 +1068,11 @@ impl InferenceVar {
 
 /// A function signature.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, HasInterner, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct FnSig<I: Interner> {
     pub abi: I::FnAbi,
@@ -977,6 +1081,11 @@ pub struct FnSig<I: Interner> {
 }
 /// A wrapper for the substs on a Fn.
 #[derive(Clone, PartialEq, Eq, Hash, HasInterner, Fold, Visit)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct FnSubst<I: Interner>(pub Substitution<I>);
 
 impl<I: Interner> Copy for FnSubst<I> where I::InternedSubstitution: Copy {}
@@ -984,6 +1093,11 @@ impl<I: Interner> Copy for FnSubst<I> where I::InternedSubstitution: Copy {}
 /// for<'a...'z> X -- all binders are instantiated at once,
 /// and we use deBruijn indices within `self.ty`
 #[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct FnPointer<I: Interner> {
     pub num_binders: usize,
@@ -1067,12 +1181,18 @@ impl<I: Interner> Const<I> {
             ConstValue::InferenceVar(_) => false,
             ConstValue::Placeholder(_) => false,
             ConstValue::Concrete(_) => false,
+            ConstValue::Unevaluated(_) => false,
         }
     }
 }
 
 /// Constant data, containing the constant's type and value.
 #[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ConstData<I: Interner> {
     /// Type that holds the constant.
     pub ty: Ty<I>,
@@ -1082,6 +1202,11 @@ pub struct ConstData<I: Interner> {
 
 /// A constant value, not necessarily concrete.
 #[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum ConstValue<I: Interner> {
     /// Bound var (e.g. a parameter).
     BoundVar(BoundVar),
@@ -1091,9 +1216,17 @@ pub enum ConstValue<I: Interner> {
     Placeholder(PlaceholderIndex),
     /// Concrete constant value.
     Concrete(ConcreteConst<I>),
+    /// Constant expression that has not been evaluated yet, e.g. `{ N + 1 }`.
+    Unevaluated(UnevaluatedConst<I>),
 }
 
-impl<I: Interner> Copy for ConstValue<I> where I::InternedConcreteConst: Copy {}
+impl<I: Interner> Copy for ConstValue<I>
+where
+    I::InternedConcreteConst: Copy,
+    I::InternedUnevaluatedConst: Copy,
+    I::InternedSubstitution: Copy,
+{
+}
 
 impl<I: Interner> ConstData<I> {
     /// Wraps the constant data in a `Const`.
@@ -1105,6 +1238,11 @@ impl<I: Interner> ConstData<I> {
 /// Concrete constant, whose value is known (as opposed to
 /// inferred constants and placeholders).
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ConcreteConst<I: Interner> {
     /// The interned constant.
     pub interned: I::InternedConcreteConst,
@@ -1117,6 +1255,40 @@ impl<I: Interner> ConcreteConst<I> {
     }
 }
 
+/// Constant expression whose value is not known yet, e.g. `{ N + 1 }` or
+/// `{ size_of::<T>() }`. Chalk does not look into the expression itself; it
+/// asks the interner to evaluate it (see [`Interner::try_evaluate_const`])
+/// once the generic arguments it refers to are known.
+#[derive(Clone, PartialEq, Eq, Hash, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub struct UnevaluatedConst<I: Interner> {
+    /// The interned expression.
+    pub interned: I::InternedUnevaluatedConst,
+    /// The generic arguments that the expression refers to.
+    pub substitution: Substitution<I>,
+}
+
+impl<I: Interner> Copy for UnevaluatedConst<I>
+where
+    I::InternedUnevaluatedConst: Copy,
+    I::InternedSubstitution: Copy,
+{
+}
+
+/// The reason why an unevaluated constant could not be evaluated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConstEvalError {
+    /// The constant depends on generic arguments that are not known yet, so
+    /// it may become evaluatable later on.
+    TooGeneric,
+    /// Evaluating the constant failed, e.g. because of an overflow.
+    Failed,
+}
+
 /// A Rust lifetime.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, HasInterner)]
 pub struct Lifetime<I: Interner> {
@@ -1177,6 +1349,11 @@ impl<I: Interner> Lifetime<I> {
 
 /// Lifetime data, including what kind of lifetime it is and what it points to.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum LifetimeData<I: Interner> {
     /// See TyKind::BoundVar.
     BoundVar(BoundVar),
@@ -1209,6 +1386,7 @@ impl<I: Interner> LifetimeData<I> {
 /// Two indexes are required, the one of the universe itself
 /// and the relative index inside the universe.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PlaceholderIndex {
     /// Index *of* the universe.
     pub ui: UniverseIndex,
@@ -1247,6 +1425,7 @@ impl PlaceholderIndex {
 /// `Index` impl for. `i` would have a `TyVariableKind` of `Integer` to guide the
 /// inference process.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[allow(missing_docs)]
 pub enum TyVariableKind {
     General,
@@ -1256,6 +1435,11 @@ pub enum TyVariableKind {
 
 /// The "kind" of variable. Type, lifetime or constant.
 #[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub enum VariableKind<I: Interner> {
     Ty(TyVariableKind),
@@ -1365,6 +1549,11 @@ impl<I: Interner> GenericArg<I> {
 
 /// Generic arguments data.
 #[derive(Clone, PartialEq, Eq, Hash, Visit, Fold, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum GenericArgData<I: Interner> {
     /// Type argument
     Ty(Ty<I>),
@@ -1391,6 +1580,14 @@ impl<I: Interner> GenericArgData<I> {
 
 /// A value with an associated variable kind.
 #[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(
+        serialize = "I: SerdeInterner, T: Serialize",
+        deserialize = "I: SerdeInterner, T: Deserialize<'de>"
+    ))
+)]
 pub struct WithKind<I: Interner, T> {
     /// The associated variable kind.
     pub kind: VariableKind<I>,
@@ -1450,6 +1647,11 @@ pub type CanonicalVarKind<I: Interner> = WithKind<I, UniverseIndex>;
 
 /// An alias, which is a trait indirection such as a projection or opaque type.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum AliasTy<I: Interner> {
     /// An associated type projection.
     Projection(ProjectionTy<I>),
@@ -1476,6 +1678,11 @@ impl<I: Interner> AliasTy<I> {
 
 /// A projection `<P0 as TraitName<P1..Pn>>::AssocItem<Pn+1..Pm>`.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ProjectionTy<I: Interner> {
     /// The id for the associated type member.
     pub associated_ty_id: AssocTypeId<I>,
@@ -1498,6 +1705,11 @@ impl<I: Interner> ProjectionTy<I> {
 
 /// An opaque type `opaque type T<..>: Trait = HiddenTy`.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct OpaqueTy<I: Interner> {
     /// The id for the opaque type.
     pub opaque_ty_id: OpaqueTyId<I>,
@@ -1514,6 +1726,11 @@ impl<I: Interner> Copy for OpaqueTy<I> where I::InternedSubstitution: Copy {}
 /// - `<P0 as Trait<P1..Pn>>` (e.g. `i32 as Copy`), which casts the type to
 ///   that specific trait.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct TraitRef<I: Interner> {
     /// The trait id.
     pub trait_id: TraitId<I>,
@@ -1551,6 +1768,11 @@ impl<I: Interner> TraitRef<I> {
 /// Lifetime outlives, which for `'a: 'b`` checks that the lifetime `'a`
 /// is a superset of the value of `'b`.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct LifetimeOutlives<I: Interner> {
     pub a: Lifetime<I>,
@@ -1562,6 +1784,11 @@ impl<I: Interner> Copy for LifetimeOutlives<I> where I::InternedLifetime: Copy {
 /// Type outlives, which for `T: 'a` checks that the type `T`
 /// lives at least as long as the lifetime `'a`
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct TypeOutlives<I: Interner> {
     /// The type which must outlive the given lifetime.
     pub ty: Ty<I>,
@@ -1578,6 +1805,11 @@ where
 
 /// Where clauses that can be written by a Rust programmer.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, SuperVisit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum WhereClause<I: Interner> {
     /// Type implements a trait.
     Implemented(TraitRef<I>),
@@ -1599,6 +1831,11 @@ where
 
 /// Checks whether a type or trait ref is well-formed.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum WellFormed<I: Interner> {
     /// A predicate which is true when some trait ref is well-formed.
     /// For example, given the following trait definitions:
@@ -1626,17 +1863,29 @@ pub enum WellFormed<I: Interner> {
     ///
     /// then we have the following rule: `WellFormedTy(Set<K>) :- Implemented(K: Hash)`.
     Ty(Ty<I>),
+
+    /// A predicate which is true when some constant is well-formed. Concrete
+    /// constants and const parameters are always well-formed; an unevaluated
+    /// constant such as `{ N + 1 }` is well-formed if it can be evaluated, or
+    /// if the environment says so.
+    Const(Const<I>),
 }
 
 impl<I: Interner> Copy for WellFormed<I>
 where
     I::InternedType: Copy,
+    I::InternedConst: Copy,
     I::InternedSubstitution: Copy,
 {
 }
 
 /// Checks whether a type or trait ref can be derived from the contents of the environment.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum FromEnv<I: Interner> {
     /// A predicate which enables deriving everything which should be true if we *know* that
     /// some trait ref is well-formed. For example given the above trait definitions, we can use
@@ -1676,6 +1925,11 @@ where
 /// logical statement. As much as possible, the Chalk solver should avoid
 /// decomposing this enum, and instead treat its values opaquely.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, SuperVisit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum DomainGoal<I: Interner> {
     /// Simple goal that is true if the where clause is true.
     Holds(WhereClause<I>),
@@ -1745,6 +1999,10 @@ pub enum DomainGoal<I: Interner> {
 
     /// Used to indicate that a trait is object safe.
     ObjectSafe(TraitId<I>),
+
+    /// True if the constant has the given type, e.g. `ConstHasType(N, usize)`
+    /// for a parameter declared as `const N: usize`.
+    ConstHasType(Const<I>, Ty<I>),
 }
 
 impl<I: Interner> Copy for DomainGoal<I>
@@ -1752,6 +2010,7 @@ where
     I::InternedSubstitution: Copy,
     I::InternedLifetime: Copy,
     I::InternedType: Copy,
+    I::InternedConst: Copy,
 {
 }
 
@@ -1835,6 +2094,11 @@ impl<I: Interner> DomainGoal<I> {
 
 /// Equality goal: tries to prove that two values are equal.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct EqGoal<I: Interner> {
     pub a: GenericArg<I>,
@@ -1845,6 +2109,11 @@ impl<I: Interner> Copy for EqGoal<I> where I::InternedGenericArg: Copy {}
 
 /// Subtype goal: tries to prove that `a` is a subtype of `b`
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct SubtypeGoal<I: Interner> {
     pub a: Ty<I>,
@@ -1858,6 +2127,11 @@ impl<I: Interner> Copy for SubtypeGoal<I> where I::InternedType: Copy {}
 /// **match it to an impl** and that impl has a `type Foo = V` where
 /// `U = V`.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct Normalize<I: Interner> {
     pub alias: AliasTy<I>,
@@ -1873,6 +2147,11 @@ where
 
 /// Proves **equality** between an alias and a type.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 #[allow(missing_docs)]
 pub struct AliasEq<I: Interner> {
     pub alias: AliasTy<I>,
@@ -1898,6 +2177,14 @@ impl<I: Interner> HasInterner for AliasEq<I> {
 /// (IOW, we use deBruijn indices, where binders are introduced in reverse order
 /// of `self.binders`.)
 #[derive(Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(
+        serialize = "T: Serialize, T::Interner: SerdeInterner",
+        deserialize = "T: Deserialize<'de>, T::Interner: SerdeInterner"
+    ))
+)]
 pub struct Binders<T: HasInterner> {
     /// The binders that quantify over the value.
     pub binders: VariableKinds<T::Interner>,
@@ -2123,6 +2410,11 @@ where
 /// `conditions = cond_1 && cond_2 && ...` is the conjunction of the individual
 /// conditions.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ProgramClauseImplication<I: Interner> {
     /// The consequence of the clause, which holds if the conditions holds.
     pub consequence: DomainGoal<I>,
@@ -2130,7 +2422,9 @@ pub struct ProgramClauseImplication<I: Interner> {
     /// The condition goals that should hold.
     pub conditions: Goals<I>,
 
-    /// The lifetime constraints that should be proven.
+    /// The lifetime constraints that should be proven. They hold in the
+    /// environment of the goal that the clause is used to prove, whatever
+    /// environment they carry.
     pub constraints: Constraints<I>,
 
     /// The relative priority of the implication.
@@ -2139,6 +2433,7 @@ pub struct ProgramClauseImplication<I: Interner> {
 
 /// Specifies how important an implication is.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ClausePriority {
     /// High priority, the solver should prioritize this.
     High,
@@ -2159,6 +2454,11 @@ impl std::ops::BitAnd for ClausePriority {
 
 /// Contains the data for a program clause.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ProgramClauseData<I: Interner>(pub Binders<ProgramClauseImplication<I>>);
 
 impl<I: Interner> ProgramClauseImplication<I> {
@@ -2292,6 +2592,14 @@ impl<I: Interner> Variances<I> {
 /// first appearance; the kind/universe of the variable is recorded in the
 /// `binders` field.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(
+        serialize = "T: Serialize, T::Interner: SerdeInterner",
+        deserialize = "T: Deserialize<'de>, T::Interner: SerdeInterner"
+    ))
+)]
 pub struct Canonical<T: HasInterner> {
     /// The item that is canonicalized.
     pub value: T,
@@ -2311,6 +2619,14 @@ impl<T: HasInterner> HasInterner for Canonical<T> {
 ///
 /// To produce one of these values, use the `u_canonicalize` method.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound(
+        serialize = "T: Serialize, T::Interner: SerdeInterner",
+        deserialize = "T: Deserialize<'de>, T::Interner: SerdeInterner"
+    ))
+)]
 pub struct UCanonical<T: HasInterner> {
     /// The wrapped `Canonical`.
     pub canonical: Canonical<T>,
@@ -2472,6 +2788,11 @@ where
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 /// A general goal; this is the full range of questions you can pose to Chalk.
 pub enum GoalData<I: Interner> {
     /// Introduces a binding at depth 0, shifting other bindings up
@@ -2511,6 +2832,7 @@ impl<I: Interner> Copy for GoalData<I>
 where
     I::InternedType: Copy,
     I::InternedLifetime: Copy,
+    I::InternedConst: Copy,
     I::InternedGenericArg: Copy,
     I::InternedSubstitution: Copy,
     I::InternedGoal: Copy,
@@ -2529,6 +2851,7 @@ impl<I: Interner> GoalData<I> {
 
 /// Kinds of quantifiers in the logic, such as `forall` and `exists`.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum QuantifierKind {
     /// Universal quantifier `ForAll`.
     ///
@@ -2555,20 +2878,69 @@ pub enum QuantifierKind {
 /// for later checking. This allows for decoupling between type and region
 /// checking in the compiler.
 #[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum Constraint<I: Interner> {
     /// Outlives constraint `'a: 'b`, indicating that the value of `'a` must be
     /// a superset of the value of `'b`.
-    LifetimeOutlives(Lifetime<I>, Lifetime<I>),
+    LifetimeOutlives(Lifetime<I>, Lifetime<I>, ConstraintOrigin<I>),
 
     /// Type outlives constraint `T: 'a`, indicating that the type `T` must live
     /// at least as long as the value of `'a`.
-    TypeOutlives(Ty<I>, Lifetime<I>),
+    TypeOutlives(Ty<I>, Lifetime<I>, ConstraintOrigin<I>),
 }
 
 impl<I: Interner> Copy for Constraint<I>
 where
     I::InternedLifetime: Copy,
     I::InternedType: Copy,
+    I::InternedSubstitution: Copy,
+{
+}
+
+impl<I: Interner> Constraint<I> {
+    /// Where this constraint came from.
+    pub fn origin(&self) -> &ConstraintOrigin<I> {
+        match self {
+            Constraint::LifetimeOutlives(_, _, origin) | Constraint::TypeOutlives(_, _, origin) => {
+                origin
+            }
+        }
+    }
+}
+
+/// Records why a region constraint was produced, so that region checking can
+/// report the item that is ultimately responsible for it.
+#[derive(Clone, PartialEq, Eq, Hash, Fold, Visit, HasInterner, Zip)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub enum ConstraintOrigin<I: Interner> {
+    /// A builtin rule, e.g. an outlives goal that arose from relating two
+    /// lifetimes during unification.
+    Builtin,
+
+    /// A where clause on the given impl.
+    ImplWhereClause(ImplId<I>),
+
+    /// A where clause on the definition of the given type (an ADT or fn
+    /// def), which must hold for the type to be well-formed.
+    WellFormed(Ty<I>),
+
+    /// A where clause on the definition of a type or trait that is known to
+    /// hold because the given fact is in the environment (an implied bound).
+    ImpliedBound(FromEnv<I>),
+}
+
+impl<I: Interner> Copy for ConstraintOrigin<I>
+where
+    I::InternedType: Copy,
+    I::InternedSubstitution: Copy,
 {
 }
 
@@ -2871,6 +3243,11 @@ interned_slice!(
 /// and the constraints represents any region constraints that must
 /// additionally be solved.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct ConstrainedSubst<I: Interner> {
     /// The substitution that is being constrained.
     ///
@@ -2883,6 +3260,11 @@ pub struct ConstrainedSubst<I: Interner> {
 
 /// The resulting substitution after solving a goal.
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct AnswerSubst<I: Interner> {
     /// The substitution result.
     ///
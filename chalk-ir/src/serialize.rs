@@ -0,0 +1,69 @@
+//! Serde support for the interned types, which are (de)serialized through
+//! the interner returned by [`Interner::serde_interner`]. All other types
+//! derive their implementations when the `serde` feature is enabled.
+
+use crate::interner::{Interner, SerdeInterner};
+use crate::*;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+fn serde_interner<I: Interner>() -> Result<I, &'static str> {
+    I::serde_interner().ok_or("interner does not support serialization")
+}
+
+/// Serializes an interned value as its data (obtained with `$data`), and
+/// deserializes it by interning the data with `$ty::new`.
+macro_rules! interned {
+    ($ty:ident, $data:ident => $data_ty:ident) => {
+        impl<I: SerdeInterner> Serialize for $ty<I> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let interner = serde_interner::<I>().map_err(ser::Error::custom)?;
+                self.$data(&interner).serialize(serializer)
+            }
+        }
+
+        impl<'de, I: SerdeInterner> Deserialize<'de> for $ty<I> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let interner = serde_interner::<I>().map_err(de::Error::custom)?;
+                let data = $data_ty::deserialize(deserializer)?;
+                Ok($ty::new(&interner, data))
+            }
+        }
+    };
+}
+
+interned!(Ty, kind => TyKind);
+interned!(Lifetime, data => LifetimeData);
+interned!(Const, data => ConstData);
+interned!(GenericArg, data => GenericArgData);
+interned!(Goal, data => GoalData);
+interned!(ProgramClause, data => ProgramClauseData);
+
+/// Serializes an interned sequence as a sequence of its elements, and
+/// deserializes it by interning the elements with `$seq::$from`.
+macro_rules! interned_seq {
+    ($seq:ident, $from:ident => $elem:ty) => {
+        impl<I: SerdeInterner> Serialize for $seq<I> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let interner = serde_interner::<I>().map_err(ser::Error::custom)?;
+                serializer.collect_seq(self.iter(&interner))
+            }
+        }
+
+        impl<'de, I: SerdeInterner> Deserialize<'de> for $seq<I> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let interner = serde_interner::<I>().map_err(de::Error::custom)?;
+                let elements = Vec::<$elem>::deserialize(deserializer)?;
+                Ok($seq::$from(&interner, elements))
+            }
+        }
+    };
+}
+
+interned_seq!(QuantifiedWhereClauses, from_iter => QuantifiedWhereClause<I>);
+interned_seq!(ProgramClauses, from_iter => ProgramClause<I>);
+interned_seq!(VariableKinds, from_iter => VariableKind<I>);
+interned_seq!(CanonicalVarKinds, from_iter => CanonicalVarKind<I>);
+interned_seq!(Goals, from_iter => Goal<I>);
+interned_seq!(Constraints, from_iter => InEnvironment<Constraint<I>>);
+interned_seq!(Substitution, from_iter => GenericArg<I>);
+interned_seq!(Variances, from => Variance);
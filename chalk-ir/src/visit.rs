@@ -436,6 +436,9 @@ impl<I: Interner> SuperVisit<I> for Const<I> {
                 visitor.visit_free_placeholder(*universe, outer_binder)
             }
             ConstValue::Concrete(_) => ControlFlow::CONTINUE,
+            ConstValue::Unevaluated(unevaluated) => {
+                unevaluated.substitution.visit_with(visitor, outer_binder)
+            }
         }
     }
 }
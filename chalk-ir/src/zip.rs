@@ -325,6 +325,7 @@ eq_zip!(I => GeneratorId<I>);
 eq_zip!(I => ForeignDefId<I>);
 eq_zip!(I => FnDefId<I>);
 eq_zip!(I => ClosureId<I>);
+eq_zip!(I => ImplId<I>);
 eq_zip!(I => QuantifierKind);
 eq_zip!(I => PhantomData<I>);
 eq_zip!(I => PlaceholderIndex);
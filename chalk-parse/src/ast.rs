@@ -43,6 +43,12 @@ pub struct AdtDefn {
     pub flags: AdtFlags,
     pub repr: AdtRepr,
     pub variances: Option<Vec<Variance>>,
+    pub well_known: Option<WellKnownAdt>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WellKnownAdt {
+    DynMetadata,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -82,6 +88,14 @@ pub enum AdtKind {
 pub struct AdtRepr {
     pub repr_c: bool,
     pub repr_packed: bool,
+    pub int: Option<Ty>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AdtReprAttr {
+    C,
+    Packed,
+    Int(Ty),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -127,6 +141,8 @@ pub struct TraitDefn {
     pub variable_kinds: Vec<VariableKind>,
     pub where_clauses: Vec<QuantifiedWhereClause>,
     pub assoc_ty_defns: Vec<AssocTyDefn>,
+    pub assoc_const_defns: Vec<AssocConstDefn>,
+    pub method_defns: Vec<TraitMethodDefn>,
     pub flags: TraitFlags,
     pub well_known: Option<WellKnownTrait>,
 }
@@ -143,6 +159,12 @@ pub enum WellKnownTrait {
     Unsize,
     Unpin,
     CoerceUnsized,
+    DiscriminantKind,
+    Generator,
+    DispatchFromDyn,
+    Pointee,
+    Tuple,
+    FnPtr,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -154,6 +176,7 @@ pub struct TraitFlags {
     pub non_enumerable: bool,
     pub coinductive: bool,
     pub object_safe: bool,
+    pub compute_object_safety: bool,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -164,6 +187,29 @@ pub struct AssocTyDefn {
     pub where_clauses: Vec<QuantifiedWhereClause>,
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TraitMethodDefn {
+    pub name: Identifier,
+    pub variable_kinds: Vec<VariableKind>,
+    pub receiver: Option<Ty>,
+    pub argument_types: Vec<Ty>,
+    pub return_type: Ty,
+    pub where_clauses: Vec<QuantifiedWhereClause>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AssocConstDefn {
+    pub name: Identifier,
+    pub ty: Ty,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TraitItem {
+    AssocTy(AssocTyDefn),
+    AssocConst(AssocConstDefn),
+    Method(TraitMethodDefn),
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct OpaqueTyDefn {
     pub ty: Ty,
@@ -179,7 +225,8 @@ pub enum VariableKind {
     IntegerTy(Identifier),
     FloatTy(Identifier),
     Lifetime(Identifier),
-    Const(Identifier),
+    /// A const parameter, with its type (`u32` if not given).
+    Const(Identifier, Option<ScalarType>),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -194,6 +241,17 @@ pub enum GenericArg {
 pub enum Const {
     Id(Identifier),
     Value(u32),
+    Expr(ConstExpr),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// An unevaluated const expression, e.g. `N + 1` in `[T; { N + 1 }]`.
+pub enum ConstExpr {
+    Id(Identifier),
+    Value(u32),
+    Add(Box<ConstExpr>, Box<ConstExpr>),
+    Sub(Box<ConstExpr>, Box<ConstExpr>),
+    Mul(Box<ConstExpr>, Box<ConstExpr>),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -434,6 +492,7 @@ pub enum DomainGoal {
     Normalize { projection: ProjectionTy, ty: Ty },
     TraitRefWellFormed { trait_ref: TraitRef },
     TyWellFormed { ty: Ty },
+    ConstWellFormed { value: Const },
     TyFromEnv { ty: Ty },
     TraitRefFromEnv { trait_ref: TraitRef },
     IsLocal { ty: Ty },
@@ -444,6 +503,7 @@ pub enum DomainGoal {
     DownstreamType { ty: Ty },
     Reveal,
     ObjectSafe { id: Identifier },
+    ConstHasType { value: Const, ty: Ty },
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
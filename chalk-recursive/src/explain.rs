@@ -0,0 +1,146 @@
+use crate::{Solution, UCanonicalGoal};
+use chalk_ir::interner::Interner;
+use chalk_ir::{Fallible, ProgramClause};
+use chalk_solve::solve::{ProofCandidate, ProofOutcome, ProofTree};
+use rustc_hash::FxHashMap;
+
+/// Builds up a `ProofTree` while the recursive solver works. The solver
+/// alternates between goals and candidates (goal -> clause -> subgoal ->
+/// clause -> ...), so we keep a single stack of the frames that are
+/// currently in progress.
+pub(crate) struct ProofTreeBuilder<I: Interner> {
+    stack: Vec<Frame<I>>,
+
+    /// The most recent tree built for each goal we finished. When a goal
+    /// is answered from the cache, we reuse its tree from here.
+    finished: FxHashMap<UCanonicalGoal<I>, ProofTree<I>>,
+
+    root: Option<ProofTree<I>>,
+}
+
+enum Frame<I: Interner> {
+    Goal(ProofTree<I>),
+    Candidate(ProofCandidate<I>),
+}
+
+impl<I: Interner> ProofTreeBuilder<I> {
+    pub(crate) fn new() -> Self {
+        ProofTreeBuilder {
+            stack: vec![],
+            finished: FxHashMap::default(),
+            root: None,
+        }
+    }
+
+    /// Begins exploring a new goal.
+    pub(crate) fn start_goal(&mut self, goal: &UCanonicalGoal<I>) {
+        self.stack.push(Frame::Goal(ProofTree::leaf(
+            goal.clone(),
+            ProofOutcome::Incomplete,
+        )));
+    }
+
+    /// Called at the start of every iteration on the goal at the top of the
+    /// stack. Only the final iteration of a cyclic goal is recorded.
+    pub(crate) fn restart_goal(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Goal(tree)) => {
+                tree.outcome = ProofOutcome::Incomplete;
+                tree.candidates.clear();
+            }
+            _ => panic!("restart_goal: no goal in progress"),
+        }
+    }
+
+    /// Records that the goal at the top of the stack floundered.
+    pub(crate) fn mark_floundered(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Goal(tree)) => tree.outcome = ProofOutcome::Floundered,
+            _ => panic!("mark_floundered: no goal in progress"),
+        }
+    }
+
+    /// Finishes the goal at the top of the stack with the given result.
+    pub(crate) fn finish_goal(&mut self, result: &Fallible<Solution<I>>) {
+        let mut tree = match self.stack.pop() {
+            Some(Frame::Goal(tree)) => tree,
+            _ => panic!("finish_goal: no goal in progress"),
+        };
+        if tree.outcome != ProofOutcome::Floundered {
+            tree.outcome = outcome_of(result);
+        }
+        self.finished.insert(tree.goal.clone(), tree.clone());
+        self.push_subgoal(tree);
+    }
+
+    /// Records a goal that was answered from the cache.
+    pub(crate) fn cached_goal(&mut self, goal: &UCanonicalGoal<I>, result: &Fallible<Solution<I>>) {
+        let tree = match self.finished.get(goal) {
+            Some(tree) => tree.clone(),
+            None => ProofTree::leaf(goal.clone(), outcome_of(result)),
+        };
+        self.push_subgoal(tree);
+    }
+
+    /// Records a goal that was not explored because the stack was full.
+    pub(crate) fn overflowed_goal(&mut self, goal: &UCanonicalGoal<I>) {
+        self.push_subgoal(ProofTree::leaf(goal.clone(), ProofOutcome::Overflowed));
+    }
+
+    /// Records a goal that is already being explored further up the stack.
+    pub(crate) fn cyclic_goal(&mut self, goal: &UCanonicalGoal<I>) {
+        self.push_subgoal(ProofTree::leaf(goal.clone(), ProofOutcome::Cycle));
+    }
+
+    /// Begins applying a candidate to the goal at the top of the stack.
+    pub(crate) fn start_candidate(&mut self, clause: Option<&ProgramClause<I>>) {
+        self.stack.push(Frame::Candidate(ProofCandidate {
+            clause: clause.cloned(),
+            outcome: ProofOutcome::Incomplete,
+            subgoals: vec![],
+        }));
+    }
+
+    /// Finishes the candidate at the top of the stack with the given result.
+    pub(crate) fn finish_candidate(&mut self, result: &Fallible<Solution<I>>) {
+        let mut candidate = match self.stack.pop() {
+            Some(Frame::Candidate(candidate)) => candidate,
+            _ => panic!("finish_candidate: no candidate in progress"),
+        };
+        candidate.outcome = outcome_of(result);
+        match self.stack.last_mut() {
+            Some(Frame::Goal(tree)) => tree.candidates.push(candidate),
+            _ => panic!("finish_candidate: candidate without a goal"),
+        }
+    }
+
+    /// Returns the tree for the root goal, once it has been finished.
+    pub(crate) fn into_root(self) -> Option<ProofTree<I>> {
+        assert!(self.stack.is_empty());
+        self.root
+    }
+
+    fn push_subgoal(&mut self, tree: ProofTree<I>) {
+        match self.stack.last_mut() {
+            Some(Frame::Candidate(candidate)) => {
+                // Ambiguous obligations are retried by `Fulfill`, so the same
+                // subgoal can be proven more than once; keep the latest attempt.
+                match candidate.subgoals.iter_mut().find(|t| t.goal == tree.goal) {
+                    Some(existing) => *existing = tree,
+                    None => candidate.subgoals.push(tree),
+                }
+            }
+            Some(Frame::Goal(_)) => panic!("push_subgoal: subgoal without a candidate"),
+            None => self.root = Some(tree),
+        }
+    }
+}
+
+fn outcome_of<I: Interner>(result: &Fallible<Solution<I>>) -> ProofOutcome {
+    match result {
+        Ok(Solution::Unique(_)) => ProofOutcome::Proven,
+        Ok(Solution::Ambig(_)) => ProofOutcome::Ambiguous,
+        Ok(Solution::Overflow(_)) => ProofOutcome::Overflowed,
+        Err(_) => ProofOutcome::Failed,
+    }
+}
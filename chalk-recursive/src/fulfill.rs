@@ -1,5 +1,5 @@
-use crate::solve::SolveDatabase;
-use crate::{Guidance, Minimums, Solution};
+use crate::solve::{SolveDatabase, Step};
+use crate::{Cause, Guidance, Solution, UCanonicalGoal};
 use chalk_ir::cast::Cast;
 use chalk_ir::fold::Fold;
 use chalk_ir::interner::{HasInterner, Interner};
@@ -11,25 +11,12 @@ use chalk_ir::{
     QuantifierKind, Substitution, SubtypeGoal, UCanonical, UnificationDatabase, UniverseMap,
     Variance,
 };
-use chalk_solve::debug_span;
+use chalk_solve::solve::{AmbiguityCause, Overflow, OverflowLimit};
 use rustc_hash::FxHashSet;
 use std::fmt::Debug;
+use std::mem;
 use tracing::{debug, instrument};
 
-enum Outcome {
-    Complete,
-    Incomplete,
-}
-
-impl Outcome {
-    fn is_complete(&self) -> bool {
-        match *self {
-            Outcome::Complete => true,
-            _ => false,
-        }
-    }
-}
-
 /// A goal that must be resolved
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum Obligation<I: Interner> {
@@ -44,19 +31,42 @@ enum Obligation<I: Interner> {
 }
 
 /// When proving a leaf goal, we record the free variables that appear within it
-/// so that we can update inference state accordingly.
+/// so that we can update inference state accordingly once we have its solution.
 #[derive(Clone, Debug)]
-struct PositiveSolution<I: Interner> {
+struct PositiveGoal<I: Interner> {
     free_vars: Vec<GenericArg<I>>,
     universes: UniverseMap,
-    solution: Solution<I>,
 }
 
 /// When refuting a goal, there's no impact on inference state.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum NegativeSolution {
+#[derive(Clone, Debug)]
+enum NegativeSolution<I: Interner> {
     Refuted,
-    Ambiguous,
+    Ambiguous(Cause<I>),
+}
+
+/// Where `Fulfill::resume` picks up the work of solving our obligations.
+enum State<I: Interner> {
+    /// About to start another round of trying to solve the obligations, if
+    /// the last one taught us anything.
+    StartRound,
+
+    /// Working through the obligations of the current round.
+    Round,
+
+    /// Waiting for the solution to an obligation we are trying to prove.
+    Proving(Obligation<I>, PositiveGoal<I>),
+
+    /// Waiting for the solution to an obligation we are trying to refute.
+    Refuting(Obligation<I>),
+
+    /// None of the obligations is solved, and we did not learn anything
+    /// definite: looking for an obligation to take (tentative) suggestions
+    /// from. We will suggest the given substitution.
+    Suggest(Canonical<Substitution<I>>),
+
+    /// Waiting for the solution to an obligation we take suggestions from.
+    Suggesting(Canonical<Substitution<I>>, PositiveGoal<I>),
 }
 
 pub(super) trait RecursiveInferenceTable<I: Interner> {
@@ -131,13 +141,10 @@ pub(super) trait RecursiveInferenceTable<I: Interner> {
 /// of type inference in general. But when solving trait constraints, *fresh*
 /// `Fulfill` instances will be created to solve canonicalized, free-standing
 /// goals, and transport what was learned back to the outer context.
-pub(super) struct Fulfill<
-    's,
-    I: Interner,
-    Solver: SolveDatabase<I>,
-    Infer: RecursiveInferenceTable<I>,
-> {
-    solver: &'s mut Solver,
+///
+/// A `Fulfill` does not solve the canonicalized goals itself: `resume` hands
+/// them to its caller, and is called again with their solutions.
+pub(super) struct Fulfill<I: Interner, Infer: RecursiveInferenceTable<I>> {
     subst: Substitution<I>,
     infer: Infer,
 
@@ -152,27 +159,59 @@ pub(super) struct Fulfill<
     /// refuted. In such a case the solution will be either `CannotProve`, or `Err`
     /// in the case where some other goal leads to an error.
     cannot_prove: bool,
+
+    /// A goal that we gave up on because it exceeded the solver's limits,
+    /// and that keeps us from finding a unique solution. If set, we report
+    /// the overflow rather than an ambiguous solution.
+    overflow: Option<Overflow<I>>,
+
+    /// Why the obligations that remain after `fulfill` are ambiguous.
+    ambiguity_cause: Cause<I>,
+
+    state: State<I>,
+
+    /// The obligations of the current round that we have found to be
+    /// ambiguous, and will retry in the next round.
+    ambiguous: Vec<Obligation<I>>,
+
+    /// Whether the current round has taught us anything about our inference
+    /// variables.
+    progress: bool,
+
+    /// The first obligation of the current round that was left ambiguous
+    /// because solving it overflowed, and why the obligations left in this
+    /// round are ambiguous.
+    round_overflow: Option<Overflow<I>>,
+    round_cause: Cause<I>,
 }
 
-impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I>>
-    Fulfill<'s, I, Solver, Infer>
-{
+impl<I: Interner, Infer: RecursiveInferenceTable<I>> Fulfill<I, Infer> {
+    fn new(infer: Infer, subst: Substitution<I>) -> Self {
+        Fulfill {
+            infer,
+            subst,
+            obligations: vec![],
+            constraints: FxHashSet::default(),
+            cannot_prove: false,
+            overflow: None,
+            ambiguity_cause: Cause::default(),
+            state: State::StartRound,
+            ambiguous: vec![],
+            progress: true,
+            round_overflow: None,
+            round_cause: Cause::default(),
+        }
+    }
+
     #[instrument(level = "debug", skip(solver, infer))]
-    pub(super) fn new_with_clause(
-        solver: &'s mut Solver,
+    pub(super) fn new_with_clause<S: SolveDatabase<I>>(
+        solver: &mut S,
         infer: Infer,
         subst: Substitution<I>,
         canonical_goal: InEnvironment<DomainGoal<I>>,
         clause: &Binders<ProgramClauseImplication<I>>,
     ) -> Fallible<Self> {
-        let mut fulfill = Fulfill {
-            solver,
-            infer,
-            subst,
-            obligations: vec![],
-            constraints: FxHashSet::default(),
-            cannot_prove: false,
-        };
+        let mut fulfill = Fulfill::new(infer, subst);
 
         let ProgramClauseImplication {
             consequence,
@@ -181,16 +220,20 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
             priority: _,
         } = fulfill
             .infer
-            .instantiate_binders_existentially(fulfill.solver.interner(), clause);
+            .instantiate_binders_existentially(solver.interner(), clause);
 
         debug!(?consequence, ?conditions, ?constraints);
+        // The constraints of the clause hold in the environment of the goal.
         fulfill
             .constraints
-            .extend(constraints.as_slice(fulfill.interner()).to_owned());
+            .extend(constraints.iter(solver.interner()).map(|constraint| {
+                InEnvironment::new(&canonical_goal.environment, constraint.goal.clone())
+            }));
 
         debug!("the subst is {:?}", fulfill.subst);
 
         if let Err(e) = fulfill.unify(
+            solver,
             &canonical_goal.environment,
             Variance::Invariant,
             &canonical_goal.goal,
@@ -200,8 +243,10 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
         }
 
         // if so, toss in all of its premises
-        for condition in conditions.as_slice(fulfill.solver.interner()) {
-            if let Err(e) = fulfill.push_goal(&canonical_goal.environment, condition.clone()) {
+        for condition in conditions.as_slice(solver.interner()) {
+            if let Err(e) =
+                fulfill.push_goal(solver, &canonical_goal.environment, condition.clone())
+            {
                 return Err(e);
             }
         }
@@ -209,53 +254,47 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
         Ok(fulfill)
     }
 
-    pub(super) fn new_with_simplification(
-        solver: &'s mut Solver,
+    pub(super) fn new_with_simplification<S: SolveDatabase<I>>(
+        solver: &mut S,
         infer: Infer,
         subst: Substitution<I>,
         canonical_goal: InEnvironment<Goal<I>>,
     ) -> Fallible<Self> {
-        let mut fulfill = Fulfill {
-            solver,
-            infer,
-            subst,
-            obligations: vec![],
-            constraints: FxHashSet::default(),
-            cannot_prove: false,
-        };
+        let mut fulfill = Fulfill::new(infer, subst);
 
-        if let Err(e) = fulfill.push_goal(&canonical_goal.environment, canonical_goal.goal.clone())
-        {
+        if let Err(e) = fulfill.push_goal(
+            solver,
+            &canonical_goal.environment,
+            canonical_goal.goal.clone(),
+        ) {
             return Err(e);
         }
 
         Ok(fulfill)
     }
 
-    fn push_obligation(&mut self, obligation: Obligation<I>) {
+    fn push_obligation<S: SolveDatabase<I>>(&mut self, solver: &mut S, obligation: Obligation<I>) {
         // truncate to avoid overflows
-        match &obligation {
-            Obligation::Prove(goal) => {
-                if self
-                    .infer
-                    .needs_truncation(self.solver.interner(), self.solver.max_size(), goal)
-                {
-                    // the goal is too big. Record that we should return Ambiguous
-                    self.cannot_prove = true;
-                    return;
-                }
-            }
-            Obligation::Refute(goal) => {
-                if self
-                    .infer
-                    .needs_truncation(self.solver.interner(), self.solver.max_size(), goal)
-                {
-                    // the goal is too big. Record that we should return Ambiguous
-                    self.cannot_prove = true;
-                    return;
-                }
-            }
+        let goal = match &obligation {
+            Obligation::Prove(goal) | Obligation::Refute(goal) => goal,
         };
+        let max_size = solver.max_size();
+        if self
+            .infer
+            .needs_truncation(solver.interner(), max_size, goal)
+        {
+            // the goal is too big. Record that we should return Overflow
+            solver.stats().overflows += 1;
+            self.cannot_prove = true;
+            if self.overflow.is_none() {
+                let (goal, _) = self.infer.canonicalize(solver.interner(), goal);
+                self.overflow = Some(Overflow {
+                    goal,
+                    limit: OverflowLimit::Size(max_size),
+                });
+            }
+            return;
+        }
         self.obligations.push(obligation);
     }
 
@@ -263,8 +302,9 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
     ///
     /// Wraps `InferenceTable::unify`; any resulting normalizations are added
     /// into our list of pending obligations with the given environment.
-    pub(super) fn unify<T>(
+    pub(super) fn unify<S: SolveDatabase<I>, T>(
         &mut self,
+        solver: &mut S,
         environment: &Environment<I>,
         variance: Variance,
         a: &T,
@@ -274,8 +314,8 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
         T: ?Sized + Zip<I> + Debug,
     {
         let goals = self.infer.unify(
-            self.solver.interner(),
-            self.solver.db().unification_database(),
+            solver.interner(),
+            solver.db().unification_database(),
             environment,
             variance,
             a,
@@ -284,62 +324,62 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
         debug!("unify({:?}, {:?}) succeeded", a, b);
         debug!("unify: goals={:?}", goals);
         for goal in goals {
-            let goal = goal.cast(self.solver.interner());
-            self.push_obligation(Obligation::Prove(goal));
+            let goal = goal.cast(solver.interner());
+            self.push_obligation(solver, Obligation::Prove(goal));
         }
         Ok(())
     }
 
     /// Create obligations for the given goal in the given environment. This may
     /// ultimately create any number of obligations.
-    #[instrument(level = "debug", skip(self))]
-    pub(super) fn push_goal(
+    #[instrument(level = "debug", skip(self, solver))]
+    pub(super) fn push_goal<S: SolveDatabase<I>>(
         &mut self,
+        solver: &mut S,
         environment: &Environment<I>,
         goal: Goal<I>,
     ) -> Fallible<()> {
-        let interner = self.interner();
+        let interner = *solver.interner();
+        let interner = &interner;
         match goal.data(interner) {
             GoalData::Quantified(QuantifierKind::ForAll, subgoal) => {
                 let subgoal = self
                     .infer
-                    .instantiate_binders_universally(self.solver.interner(), subgoal);
-                self.push_goal(environment, subgoal)?;
+                    .instantiate_binders_universally(solver.interner(), subgoal);
+                self.push_goal(solver, environment, subgoal)?;
             }
             GoalData::Quantified(QuantifierKind::Exists, subgoal) => {
                 let subgoal = self
                     .infer
-                    .instantiate_binders_existentially(self.solver.interner(), subgoal);
-                self.push_goal(environment, subgoal)?;
+                    .instantiate_binders_existentially(solver.interner(), subgoal);
+                self.push_goal(solver, environment, subgoal)?;
             }
             GoalData::Implies(wc, subgoal) => {
                 let new_environment =
                     &environment.add_clauses(interner, wc.iter(interner).cloned());
-                self.push_goal(new_environment, subgoal.clone())?;
+                self.push_goal(solver, new_environment, subgoal.clone())?;
             }
             GoalData::All(goals) => {
                 for subgoal in goals.as_slice(interner) {
-                    self.push_goal(environment, subgoal.clone())?;
+                    self.push_goal(solver, environment, subgoal.clone())?;
                 }
             }
             GoalData::Not(subgoal) => {
                 let in_env = InEnvironment::new(environment, subgoal.clone());
-                self.push_obligation(Obligation::Refute(in_env));
+                self.push_obligation(solver, Obligation::Refute(in_env));
             }
             GoalData::DomainGoal(_) => {
                 let in_env = InEnvironment::new(environment, goal);
-                self.push_obligation(Obligation::Prove(in_env));
+                self.push_obligation(solver, Obligation::Prove(in_env));
             }
             GoalData::EqGoal(EqGoal { a, b }) => {
-                self.unify(&environment, Variance::Invariant, &a, &b)?;
+                self.unify(solver, &environment, Variance::Invariant, &a, &b)?;
             }
             GoalData::SubtypeGoal(SubtypeGoal { a, b }) => {
-                if a.inference_var(self.interner()).is_some()
-                    && b.inference_var(self.interner()).is_some()
-                {
+                if a.inference_var(interner).is_some() && b.inference_var(interner).is_some() {
                     self.cannot_prove = true;
                 } else {
-                    self.unify(&environment, Variance::Covariant, &a, &b)?;
+                    self.unify(solver, &environment, Variance::Covariant, &a, &b)?;
                 }
             }
             GoalData::CannotProve => {
@@ -350,46 +390,48 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
         Ok(())
     }
 
-    #[instrument(level = "debug", skip(self, minimums))]
+    /// Canonicalizes a goal we want to prove, so that it can be solved.
+    #[instrument(level = "debug", skip(self, interner))]
     fn prove(
         &mut self,
+        interner: &I,
         wc: &InEnvironment<Goal<I>>,
-        minimums: &mut Minimums,
-    ) -> Fallible<PositiveSolution<I>> {
-        let interner = self.solver.interner();
+    ) -> (UCanonicalGoal<I>, PositiveGoal<I>) {
         let (quantified, free_vars) = self.infer.canonicalize(interner, &wc);
         let (quantified, universes) = self.infer.u_canonicalize(interner, &quantified);
-        let result = self.solver.solve_goal(quantified, minimums);
-        Ok(PositiveSolution {
-            free_vars,
-            universes,
-            solution: result?,
-        })
+        (
+            quantified,
+            PositiveGoal {
+                free_vars,
+                universes,
+            },
+        )
     }
 
-    fn refute(&mut self, goal: &InEnvironment<Goal<I>>) -> Fallible<NegativeSolution> {
-        let canonicalized = match self
-            .infer
-            .invert_then_canonicalize(self.solver.interner(), goal)
-        {
-            Some(v) => v,
-            None => {
-                // Treat non-ground negatives as ambiguous. Note that, as inference
-                // proceeds, we may wind up with more information here.
-                return Ok(NegativeSolution::Ambiguous);
-            }
-        };
+    /// Canonicalizes a goal we want to refute, so that it can be solved, or
+    /// returns `None` if that is not possible yet.
+    fn refute(&mut self, interner: &I, goal: &InEnvironment<Goal<I>>) -> Option<UCanonicalGoal<I>> {
+        // Treat non-ground negatives as ambiguous (see `refuted`). Note
+        // that, as inference proceeds, we may wind up with more information
+        // here.
+        let canonicalized = self.infer.invert_then_canonicalize(interner, goal)?;
+        let (quantified, _) = self.infer.u_canonicalize(interner, &canonicalized);
+        Some(quantified)
+    }
 
+    /// What the solution to a goal we tried to refute tells us.
+    fn refuted(&mut self, solution: Fallible<Solution<I>>) -> Fallible<NegativeSolution<I>> {
         // Negate the result
-        let (quantified, _) = self
-            .infer
-            .u_canonicalize(self.solver.interner(), &canonicalized);
-        let mut minimums = Minimums::new(); // FIXME -- minimums here seems wrong
-        if let Ok(solution) = self.solver.solve_goal(quantified, &mut minimums) {
-            if solution.is_unique() {
-                Err(NoSolution)
-            } else {
-                Ok(NegativeSolution::Ambiguous)
+        if let Ok(solution) = solution {
+            match solution {
+                Solution::Unique(_) => Err(NoSolution),
+                Solution::Ambig(guidance) => {
+                    Ok(NegativeSolution::Ambiguous(guidance.cause().clone()))
+                }
+                Solution::Overflow(solution_overflow) => {
+                    self.round_overflow.get_or_insert(solution_overflow);
+                    Ok(NegativeSolution::Ambiguous(AmbiguityCause::Overflow.into()))
+                }
             }
         } else {
             Ok(NegativeSolution::Refuted)
@@ -404,147 +446,248 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
     /// `universes` stores the mapping back into our
     /// universes. Second, the free variables that appear within can
     /// be mapped into our variables with `free_vars`.
-    fn apply_solution(
+    fn apply_solution<S: SolveDatabase<I>>(
         &mut self,
-        free_vars: Vec<GenericArg<I>>,
-        universes: UniverseMap,
+        solver: &mut S,
+        goal: PositiveGoal<I>,
         subst: Canonical<ConstrainedSubst<I>>,
     ) {
         use chalk_solve::infer::ucanonicalize::UniverseMapExt;
-        let subst = universes.map_from_canonical(self.interner(), &subst);
-        let ConstrainedSubst { subst, constraints } = self
-            .infer
-            .instantiate_canonical(self.solver.interner(), &subst);
+        let PositiveGoal {
+            free_vars,
+            universes,
+        } = goal;
+        let interner = *solver.interner();
+        let interner = &interner;
+        let subst = universes.map_from_canonical(interner, &subst);
+        let ConstrainedSubst { subst, constraints } =
+            self.infer.instantiate_canonical(interner, &subst);
 
         debug!(
             "fulfill::apply_solution: adding constraints {:?}",
             constraints
         );
         self.constraints
-            .extend(constraints.as_slice(self.interner()).to_owned());
+            .extend(constraints.as_slice(interner).to_owned());
 
         // We use the empty environment for unification here because we're
         // really just doing a substitution on unconstrained variables, which is
         // guaranteed to succeed without generating any new constraints.
-        let empty_env = &Environment::new(self.solver.interner());
+        let empty_env = &Environment::new(interner);
 
         for (i, free_var) in free_vars.into_iter().enumerate() {
-            let subst_value = subst.at(self.interner(), i);
-            self.unify(empty_env, Variance::Invariant, &free_var, subst_value)
-                .unwrap_or_else(|err| {
-                    panic!(
-                        "apply_solution failed with free_var={:?}, subst_value={:?}: {:?}",
-                        free_var, subst_value, err
-                    );
-                });
+            let subst_value = subst.at(interner, i);
+            self.unify(
+                solver,
+                empty_env,
+                Variance::Invariant,
+                &free_var,
+                subst_value,
+            )
+            .unwrap_or_else(|err| {
+                panic!(
+                    "apply_solution failed with free_var={:?}, subst_value={:?}: {:?}",
+                    free_var, subst_value, err
+                );
+            });
         }
     }
 
-    fn fulfill(&mut self, minimums: &mut Minimums) -> Fallible<Outcome> {
-        debug_span!("fulfill", obligations=?self.obligations);
-
-        // Try to solve all the obligations. We do this via a fixed-point
-        // iteration. We try to solve each obligation in turn. Anything which is
-        // successful, we drop; anything ambiguous, we retain in the
-        // `obligations` array. This process is repeated so long as we are
-        // learning new things about our inference state.
-        let mut obligations = Vec::with_capacity(self.obligations.len());
-        let mut progress = true;
-
-        while progress {
-            progress = false;
-            debug!("start of round, {} obligations", self.obligations.len());
-
-            // Take the list of `obligations` to solve this round and replace it
-            // with an empty vector. Iterate through each obligation to solve
-            // and solve it if we can. If not (because of ambiguity), then push
-            // it back onto `self.to_prove` for next round. Note that
-            // `solve_one` may also push onto the `self.to_prove` list
-            // directly.
-            assert!(obligations.is_empty());
-            while let Some(obligation) = self.obligations.pop() {
-                let ambiguous = match obligation {
-                    Obligation::Prove(ref wc) => {
-                        let PositiveSolution {
-                            free_vars,
-                            universes,
-                            solution,
-                        } = self.prove(wc, minimums)?;
-
-                        if let Some(constrained_subst) = solution.definite_subst(self.interner()) {
-                            // If the substitution is empty, we won't actually make any progress by applying it!
-                            // So we need to check this to prevent endless loops.
-                            // (An ambiguous solution with empty substitution
-                            // can probably not happen in valid code, but it can
-                            // happen e.g. when there are overlapping impls.)
-                            if !constrained_subst.value.subst.is_empty(self.interner())
-                                || !constrained_subst
-                                    .value
-                                    .constraints
-                                    .is_empty(self.interner())
-                            {
-                                self.apply_solution(free_vars, universes, constrained_subst);
-                                progress = true;
+    /// Keeps an obligation that we could not solve this round for the next.
+    fn retain(&mut self, obligation: Obligation<I>, cause: Cause<I>) {
+        debug!("ambiguous result: {:?}", obligation);
+        self.round_cause = mem::take(&mut self.round_cause).merge(cause);
+        self.ambiguous.push(obligation);
+    }
+
+    /// Try to fulfill all pending obligations and build the resulting
+    /// solution. The returned solution will transform `subst` substitution with
+    /// the outcome of type inference by updating the replacements it provides.
+    ///
+    /// Whenever we need the solution to some goal, we return `Step::Prove` or
+    /// `Step::Refute` with it, and expect to be resumed with its solution.
+    /// We must be resumed with `None` at first.
+    pub(super) fn resume<S: SolveDatabase<I>>(
+        &mut self,
+        solver: &mut S,
+        mut solution: Option<Fallible<Solution<I>>>,
+    ) -> Step<I, Fallible<Solution<I>>> {
+        let interner = *solver.interner();
+        let interner = &interner;
+        loop {
+            match mem::replace(&mut self.state, State::Round) {
+                // Try to solve all the obligations. We do this via a
+                // fixed-point iteration. We try to solve each obligation in
+                // turn. Anything which is successful, we drop; anything
+                // ambiguous, we retain in the `obligations` array. This
+                // process is repeated so long as we are learning new things
+                // about our inference state.
+                State::StartRound => {
+                    if !self.progress {
+                        if let Some(solution) = self.finish(interner) {
+                            return Step::Done(Ok(solution));
+                        }
+                        continue;
+                    }
+                    self.progress = false;
+                    self.round_overflow = None;
+                    self.round_cause = Cause::default();
+                    debug!("start of round, {} obligations", self.obligations.len());
+                }
+
+                // Take the obligations to solve this round one at a time.
+                // Anything we cannot solve (because of ambiguity) is pushed
+                // onto `ambiguous`, for the next round. Note that applying
+                // a solution may also push onto `obligations` directly.
+                State::Round => match self.obligations.pop() {
+                    Some(obligation @ Obligation::Prove(_)) => {
+                        let (goal, positive) = match &obligation {
+                            Obligation::Prove(wc) => self.prove(interner, wc),
+                            Obligation::Refute(_) => unreachable!(),
+                        };
+                        self.state = State::Proving(obligation, positive);
+                        return Step::Prove(goal);
+                    }
+                    Some(obligation @ Obligation::Refute(_)) => {
+                        let goal = match &obligation {
+                            Obligation::Refute(goal) => self.refute(interner, goal),
+                            Obligation::Prove(_) => unreachable!(),
+                        };
+                        match goal {
+                            Some(goal) => {
+                                self.state = State::Refuting(obligation);
+                                return Step::Refute(goal);
                             }
+                            None => self.retain(obligation, AmbiguityCause::Floundered.into()),
                         }
+                    }
+                    None => {
+                        self.obligations.append(&mut self.ambiguous);
+                        debug!("end of round, {} obligations left", self.obligations.len());
+                        self.state = State::StartRound;
+                    }
+                },
 
-                        solution.is_ambig()
+                State::Proving(obligation, positive) => {
+                    let solution = match solution.take().expect("resumed without a solution") {
+                        Ok(solution) => solution,
+                        Err(e) => return Step::Done(Err(e)),
+                    };
+
+                    if let Some(constrained_subst) = solution.definite_subst(interner) {
+                        // If the substitution is empty, we won't actually make any progress by applying it!
+                        // So we need to check this to prevent endless loops.
+                        // (An ambiguous solution with empty substitution
+                        // can probably not happen in valid code, but it can
+                        // happen e.g. when there are overlapping impls.)
+                        if !constrained_subst.value.subst.is_empty(interner)
+                            || !constrained_subst.value.constraints.is_empty(interner)
+                        {
+                            self.apply_solution(solver, positive, constrained_subst);
+                            self.progress = true;
+                        }
                     }
-                    Obligation::Refute(ref goal) => {
-                        let answer = self.refute(goal)?;
-                        answer == NegativeSolution::Ambiguous
+
+                    if let Solution::Overflow(solution_overflow) = &solution {
+                        self.round_overflow
+                            .get_or_insert_with(|| solution_overflow.clone());
+                    }
+                    if solution.is_ambig() {
+                        self.retain(obligation, solution.ambiguity_cause());
                     }
-                };
+                }
 
-                if ambiguous {
-                    debug!("ambiguous result: {:?}", obligation);
-                    obligations.push(obligation);
+                State::Refuting(obligation) => {
+                    let solution = solution.take().expect("resumed without a solution");
+                    match self.refuted(solution) {
+                        Ok(NegativeSolution::Refuted) => {}
+                        Ok(NegativeSolution::Ambiguous(cause)) => self.retain(obligation, cause),
+                        Err(e) => return Step::Done(Err(e)),
+                    }
                 }
-            }
 
-            self.obligations.extend(obligations.drain(..));
-            debug!("end of round, {} obligations left", self.obligations.len());
-        }
+                // We go one last time through the positive obligations, this
+                // time applying even *tentative* inference suggestions, so
+                // that we can yield these upwards as our own suggestions.
+                // There are no particular guarantees about *which*
+                // obligation we derive suggestions from.
+                State::Suggest(canonical_subst) => match self.obligations.pop() {
+                    Some(Obligation::Prove(goal)) => {
+                        let (goal, positive) = self.prove(interner, &goal);
+                        self.state = State::Suggesting(canonical_subst, positive);
+                        return Step::Prove(goal);
+                    }
+                    Some(Obligation::Refute(_)) => self.state = State::Suggest(canonical_subst),
+                    None => return Step::Done(Ok(self.no_guidance())),
+                },
 
-        // At the end of this process, `self.obligations` should have
-        // all of the ambiguous obligations, and `obligations` should
-        // be empty.
-        assert!(obligations.is_empty());
+                State::Suggesting(canonical_subst, positive) => {
+                    let solution = solution
+                        .take()
+                        .expect("resumed without a solution")
+                        .unwrap();
+                    if let Some(constrained_subst) = solution.constrained_subst(interner) {
+                        self.apply_solution(solver, positive, constrained_subst);
+                        return Step::Done(Ok(Solution::Ambig(Guidance::Suggested(
+                            canonical_subst,
+                            self.ambiguity_cause.clone(),
+                        ))));
+                    }
+                    self.state = State::Suggest(canonical_subst);
+                }
+            }
+        }
+    }
 
-        if self.obligations.is_empty() {
-            Ok(Outcome::Complete)
-        } else {
-            Ok(Outcome::Incomplete)
+    /// The solution when we have no guidance to give: an overflow if that
+    /// is what kept us from solving the goal, or else an ambiguous solution.
+    fn no_guidance(&self) -> Solution<I> {
+        match &self.overflow {
+            Some(overflow) => {
+                debug!("Goal cannot be proven because of an overflow, returning overflow");
+                Solution::Overflow(overflow.clone())
+            }
+            None => Solution::Ambig(Guidance::Unknown(self.ambiguity_cause.clone())),
         }
     }
 
-    /// Try to fulfill all pending obligations and build the resulting
-    /// solution. The returned solution will transform `subst` substitution with
-    /// the outcome of type inference by updating the replacements it provides.
-    pub(super) fn solve(mut self, minimums: &mut Minimums) -> Fallible<Solution<I>> {
-        let outcome = match self.fulfill(minimums) {
-            Ok(o) => o,
-            Err(e) => return Err(e),
-        };
+    /// Builds our solution once the last round of `resume` has taught us
+    /// nothing new. Returns `None` if we are to look for suggestions first.
+    fn finish(&mut self, interner: &I) -> Option<Solution<I>> {
+        // At the end of this process, `self.obligations` should have all of
+        // the ambiguous obligations.
+        assert!(self.ambiguous.is_empty());
+
+        // Overflows while pushing obligations are permanent, so they take
+        // precedence over those of the final round.
+        if self.overflow.is_none() {
+            self.overflow = self.round_overflow.take();
+        }
+        self.ambiguity_cause = mem::take(&mut self.round_cause);
+        if self.overflow.is_some() {
+            // Any guidance we give is still worth having, but it is only
+            // ambiguous because of the overflow.
+            self.ambiguity_cause = AmbiguityCause::Overflow.into();
+        }
 
         if self.cannot_prove {
             debug!("Goal cannot be proven (cannot_prove = true), returning ambiguous");
-            return Ok(Solution::Ambig(Guidance::Unknown));
+            return Some(self.no_guidance());
         }
 
-        if outcome.is_complete() {
+        if self.obligations.is_empty() {
             // No obligations remain, so we have definitively solved our goals,
             // and the current inference state is the unique way to solve them.
 
-            let constraints = Constraints::from_iter(self.interner(), self.constraints.clone());
+            let constraints = Constraints::from_iter(interner, self.constraints.clone());
             let constrained = self.infer.canonicalize(
-                self.solver.interner(),
+                interner,
                 &ConstrainedSubst {
-                    subst: self.subst,
+                    subst: self.subst.clone(),
                     constraints,
                 },
             );
-            return Ok(Solution::Unique(constrained.0));
+            return Some(Solution::Unique(constrained.0));
         }
 
         // Otherwise, we have (positive or negative) obligations remaining, but
@@ -552,37 +695,13 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
         // need to determine how to package up what we learned about type
         // inference as an ambiguous solution.
 
-        let canonical_subst = self.infer.canonicalize(self.solver.interner(), &self.subst);
+        let canonical_subst = self.infer.canonicalize(interner, &self.subst);
 
-        if canonical_subst
-            .0
-            .value
-            .is_identity_subst(self.solver.interner())
-        {
-            // In this case, we didn't learn *anything* definitively. So now, we
-            // go one last time through the positive obligations, this time
-            // applying even *tentative* inference suggestions, so that we can
-            // yield these upwards as our own suggestions. There are no
-            // particular guarantees about *which* obligaiton we derive
-            // suggestions from.
-
-            while let Some(obligation) = self.obligations.pop() {
-                if let Obligation::Prove(goal) = obligation {
-                    let PositiveSolution {
-                        free_vars,
-                        universes,
-                        solution,
-                    } = self.prove(&goal, minimums).unwrap();
-                    if let Some(constrained_subst) =
-                        solution.constrained_subst(self.solver.interner())
-                    {
-                        self.apply_solution(free_vars, universes, constrained_subst);
-                        return Ok(Solution::Ambig(Guidance::Suggested(canonical_subst.0)));
-                    }
-                }
-            }
-
-            Ok(Solution::Ambig(Guidance::Unknown))
+        if canonical_subst.0.value.is_identity_subst(interner) {
+            // In this case, we didn't learn *anything* definitively, so we
+            // look for suggestions (see `State::Suggest`).
+            self.state = State::Suggest(canonical_subst.0);
+            None
         } else {
             // While we failed to prove the goal, we still learned that
             // something had to hold. Here's an example where this happens:
@@ -604,11 +723,10 @@ impl<'s, I: Interner, Solver: SolveDatabase<I>, Infer: RecursiveInferenceTable<I
             // for sure what `T` must be (it could be either `Foo<Bar>` or
             // `Foo<Baz>`, but we *can* say for sure that it must be of the
             // form `Foo<?0>`.
-            Ok(Solution::Ambig(Guidance::Definite(canonical_subst.0)))
+            Some(Solution::Ambig(Guidance::Definite(
+                canonical_subst.0,
+                self.ambiguity_cause.clone(),
+            )))
         }
     }
-
-    fn interner(&self) -> &I {
-        self.solver.interner()
-    }
 }
@@ -1,14 +1,24 @@
 use crate::search_graph::DepthFirstNumber;
 use chalk_ir::interner::Interner;
 use chalk_ir::{
-    Canonical, ConstrainedSubst, Constraints, Goal, InEnvironment, Substitution, UCanonical,
+    Canonical, ConstrainedSubst, Constraints, DomainGoal, Goal, InEnvironment, Substitution,
+    UCanonical,
 };
+use chalk_solve::solve::{AmbiguityCause, Overflow};
+use chalk_solve::RustIrDatabase;
 use std::fmt;
 use tracing::debug;
 
+#[cfg(feature = "serde")]
+use {
+    chalk_ir::interner::SerdeInterner,
+    serde::{Deserialize, Serialize},
+};
+
 pub type UCanonicalGoal<I> = UCanonical<InEnvironment<Goal<I>>>;
 
 mod combine;
+mod explain;
 mod fulfill;
 mod recursive;
 mod search_graph;
@@ -38,6 +48,11 @@ impl Minimums {
 
 /// A (possible) solution for a proposed goal.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum Solution<I: Interner> {
     /// The goal indeed holds, and there is a unique value for all existential
     /// variables. In this case, we also record a set of lifetime constraints
@@ -49,24 +64,136 @@ pub enum Solution<I: Interner> {
     /// constraints, since we have not "committed" to any particular solution
     /// yet.
     Ambig(Guidance<I>),
+
+    /// Solving the goal exceeded the solver's limits.
+    Overflow(Overflow<I>),
 }
 
 /// When a goal holds ambiguously (e.g., because there are multiple possible
 /// solutions), we issue a set of *guidance* back to type inference.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum Guidance<I: Interner> {
     /// The existential variables *must* have the given values if the goal is
     /// ever to hold, but that alone isn't enough to guarantee the goal will
     /// actually hold.
-    Definite(Canonical<Substitution<I>>),
+    Definite(Canonical<Substitution<I>>, Cause<I>),
 
     /// There are multiple plausible values for the existentials, but the ones
     /// here are suggested as the preferred choice heuristically. These should
     /// be used for inference fallback only.
-    Suggested(Canonical<Substitution<I>>),
+    Suggested(Canonical<Substitution<I>>, Cause<I>),
 
     /// There's no useful information to feed back to type inference
-    Unknown,
+    Unknown(Cause<I>),
+}
+
+impl<I: Interner> Guidance<I> {
+    pub(crate) fn cause(&self) -> &Cause<I> {
+        match self {
+            Guidance::Definite(_, cause)
+            | Guidance::Suggested(_, cause)
+            | Guidance::Unknown(cause) => cause,
+        }
+    }
+}
+
+// As for `chalk_solve::Guidance`, the cause is left out: solutions that
+// only differ in why they are ambiguous must not keep us iterating on a
+// cycle.
+impl<I: Interner> PartialEq for Guidance<I> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Guidance::Definite(a, _), Guidance::Definite(b, _))
+            | (Guidance::Suggested(a, _), Guidance::Suggested(b, _)) => a == b,
+            (Guidance::Unknown(_), Guidance::Unknown(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<I: Interner> Eq for Guidance<I> {}
+
+/// Why a solution is ambiguous, as far as we know while solving.
+///
+/// Finding out whether a goal is ambiguous because several impls apply to
+/// it (see `AmbiguityCause::of_goal`) means unifying it with each of them,
+/// which is too slow to do for every ambiguous subgoal. Instead, we note
+/// the goals that were ambiguous for no reason we know of, and only look
+/// into them once a solution is handed out (see `resolve`).
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub struct Cause<I: Interner> {
+    known: AmbiguityCause<I>,
+
+    /// Goals that may explain the ambiguity if `known` does not. As
+    /// `of_goal` can only tell us about impls and unknown self types,
+    /// these are only kept while `known` is `AmbiguityCause::Other`.
+    unexplained: Vec<UCanonical<InEnvironment<DomainGoal<I>>>>,
+}
+
+impl<I: Interner> Cause<I> {
+    /// Combines the causes of two ambiguous solutions for the same goal
+    /// (see `AmbiguityCause::merge`).
+    pub(crate) fn merge(self, other: Cause<I>) -> Cause<I> {
+        let known = self.known.merge(other.known);
+        let mut unexplained = vec![];
+        if known == AmbiguityCause::Other {
+            unexplained = self.unexplained;
+            for goal in other.unexplained {
+                if !unexplained.contains(&goal) {
+                    unexplained.push(goal);
+                }
+            }
+        }
+        Cause { known, unexplained }
+    }
+
+    /// Notes that `goal` may explain the ambiguity, unless we already know
+    /// of a better reason.
+    pub(crate) fn or_goal(mut self, goal: &UCanonical<InEnvironment<DomainGoal<I>>>) -> Cause<I> {
+        if self.known == AmbiguityCause::Other && !self.unexplained.contains(goal) {
+            self.unexplained.push(goal.clone());
+        }
+        self
+    }
+
+    /// The cause we know of, without looking into any goals.
+    pub(crate) fn known(&self) -> &AmbiguityCause<I> {
+        &self.known
+    }
+
+    /// Finds out what the goals we noted tell us, and combines that with
+    /// what we know.
+    pub(crate) fn resolve(self, db: &dyn RustIrDatabase<I>) -> AmbiguityCause<I> {
+        self.unexplained
+            .iter()
+            .filter_map(|goal| AmbiguityCause::of_goal(db, goal))
+            .fold(self.known, AmbiguityCause::merge)
+    }
+}
+
+impl<I: Interner> From<AmbiguityCause<I>> for Cause<I> {
+    fn from(known: AmbiguityCause<I>) -> Self {
+        Cause {
+            known,
+            unexplained: vec![],
+        }
+    }
+}
+
+impl<I: Interner> Default for Cause<I> {
+    fn default() -> Self {
+        Cause::from(AmbiguityCause::Other)
+    }
 }
 
 impl<I: Interner> Solution<I> {
@@ -103,28 +230,66 @@ impl<I: Interner> Solution<I> {
             other.display(interner)
         );
 
+        // An overflow means we don't know what other candidates there may
+        // be, so it beats everything else.
+        if let Solution::Overflow(_) = self {
+            return self;
+        }
+        if let Solution::Overflow(_) = other {
+            return other;
+        }
+
         // Otherwise, always downgrade to Ambig:
 
+        let cause = self.ambiguity_cause().merge(other.ambiguity_cause());
         let guidance = match (self.into_guidance(), other.into_guidance()) {
-            (Definite(ref subst1), Definite(ref subst2)) if subst1 == subst2 => {
-                Definite(subst1.clone())
+            (Definite(ref subst1, _), Definite(ref subst2, _)) if subst1 == subst2 => {
+                Definite(subst1.clone(), cause)
             }
-            (Suggested(ref subst1), Suggested(ref subst2)) if subst1 == subst2 => {
-                Suggested(subst1.clone())
+            (Suggested(ref subst1, _), Suggested(ref subst2, _)) if subst1 == subst2 => {
+                Suggested(subst1.clone(), cause)
             }
-            _ => Unknown,
+            _ => Unknown(cause),
         };
         Solution::Ambig(guidance)
     }
 
+    /// Why this solution is ambiguous; a unique solution has no particular
+    /// cause, but may become ambiguous when combined with another.
+    pub(crate) fn ambiguity_cause(&self) -> Cause<I> {
+        match self {
+            Solution::Unique(_) => Cause::default(),
+            Solution::Ambig(guidance) => guidance.cause().clone(),
+            Solution::Overflow(_) => AmbiguityCause::Overflow.into(),
+        }
+    }
+
+    /// Replaces the cause of an ambiguous solution.
+    pub(crate) fn with_ambiguity_cause(self, cause: Cause<I>) -> Solution<I> {
+        match self {
+            Solution::Ambig(Guidance::Definite(subst, _)) => {
+                Solution::Ambig(Guidance::Definite(subst, cause))
+            }
+            Solution::Ambig(Guidance::Suggested(subst, _)) => {
+                Solution::Ambig(Guidance::Suggested(subst, cause))
+            }
+            Solution::Ambig(Guidance::Unknown(_)) => Solution::Ambig(Guidance::Unknown(cause)),
+            solution => solution,
+        }
+    }
+
     /// View this solution purely in terms of type inference guidance
     pub(crate) fn into_guidance(self) -> Guidance<I> {
         match self {
-            Solution::Unique(constrained) => Guidance::Definite(Canonical {
-                value: constrained.value.subst,
-                binders: constrained.binders,
-            }),
+            Solution::Unique(constrained) => Guidance::Definite(
+                Canonical {
+                    value: constrained.value.subst,
+                    binders: constrained.binders,
+                },
+                Cause::default(),
+            ),
             Solution::Ambig(guidance) => guidance,
+            Solution::Overflow(_) => Guidance::Unknown(AmbiguityCause::Overflow.into()),
         }
     }
 
@@ -132,8 +297,8 @@ impl<I: Interner> Solution<I> {
     pub(crate) fn constrained_subst(&self, interner: &I) -> Option<Canonical<ConstrainedSubst<I>>> {
         match *self {
             Solution::Unique(ref constrained) => Some(constrained.clone()),
-            Solution::Ambig(Guidance::Definite(ref canonical))
-            | Solution::Ambig(Guidance::Suggested(ref canonical)) => {
+            Solution::Ambig(Guidance::Definite(ref canonical, _))
+            | Solution::Ambig(Guidance::Suggested(ref canonical, _)) => {
                 let value = ConstrainedSubst {
                     subst: canonical.value.clone(),
                     constraints: Constraints::empty(interner),
@@ -143,7 +308,7 @@ impl<I: Interner> Solution<I> {
                     binders: canonical.binders.clone(),
                 })
             }
-            Solution::Ambig(_) => None,
+            Solution::Ambig(_) | Solution::Overflow(_) => None,
         }
     }
 
@@ -152,7 +317,7 @@ impl<I: Interner> Solution<I> {
     pub(crate) fn definite_subst(&self, interner: &I) -> Option<Canonical<ConstrainedSubst<I>>> {
         match self {
             Solution::Unique(constrained) => Some(constrained.clone()),
-            Solution::Ambig(Guidance::Definite(canonical)) => {
+            Solution::Ambig(Guidance::Definite(canonical, _)) => {
                 let value = ConstrainedSubst {
                     subst: canonical.value.clone(),
                     constraints: Constraints::empty(interner),
@@ -175,7 +340,7 @@ impl<I: Interner> Solution<I> {
 
     pub(crate) fn is_ambig(&self) -> bool {
         match *self {
-            Solution::Ambig(_) => true,
+            Solution::Ambig(_) | Solution::Overflow(_) => true,
             _ => false,
         }
     }
@@ -198,17 +363,30 @@ impl<'a, I: Interner> fmt::Display for SolutionDisplay<'a, I> {
         let SolutionDisplay { solution, interner } = self;
         match solution {
             Solution::Unique(constrained) => write!(f, "Unique; {}", constrained.display(interner)),
-            Solution::Ambig(Guidance::Definite(subst)) => write!(
-                f,
-                "Ambiguous; definite substitution {}",
-                subst.display(interner)
-            ),
-            Solution::Ambig(Guidance::Suggested(subst)) => write!(
+            Solution::Ambig(guidance) => {
+                match guidance {
+                    Guidance::Definite(subst, _) => write!(
+                        f,
+                        "Ambiguous; definite substitution {}",
+                        subst.display(interner)
+                    )?,
+                    Guidance::Suggested(subst, _) => write!(
+                        f,
+                        "Ambiguous; suggested substitution {}",
+                        subst.display(interner)
+                    )?,
+                    Guidance::Unknown(_) => write!(f, "Ambiguous; no inference guidance")?,
+                }
+                match guidance.cause().known() {
+                    AmbiguityCause::Other => Ok(()),
+                    cause => write!(f, "; {}", cause),
+                }
+            }
+            Solution::Overflow(overflow) => write!(
                 f,
-                "Ambiguous; suggested substitution {}",
-                subst.display(interner)
+                "Overflow; {} reached by {:?}",
+                overflow.limit, overflow.goal.value.goal
             ),
-            Solution::Ambig(Guidance::Unknown) => write!(f, "Ambiguous; no inference guidance"),
         }
     }
 }
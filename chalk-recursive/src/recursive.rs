@@ -1,16 +1,33 @@
+use crate::explain::ProofTreeBuilder;
 use crate::search_graph::DepthFirstNumber;
-use crate::search_graph::SearchGraph;
-use crate::solve::{SolveDatabase, SolveIteration};
+use crate::search_graph::{Cache, SearchGraph};
+use crate::solve::{SolveDatabase, SolveIteration, Step};
 use crate::stack::{Stack, StackDepth};
 use crate::{combine, Guidance, Minimums, Solution, UCanonicalGoal};
 use chalk_ir::interner::Interner;
 use chalk_ir::Fallible;
-use chalk_ir::{Canonical, ConstrainedSubst, Constraints, Goal, InEnvironment, UCanonical};
-use chalk_solve::{coinductive_goal::IsCoinductive, RustIrDatabase};
+use chalk_ir::{
+    Canonical, ClausePriority, ConstrainedSubst, Constraints, Environment, Goal, InEnvironment,
+    UCanonical,
+};
+use chalk_solve::dependencies::{Dependencies, DependencyRecorder};
+use chalk_solve::env_trim::{keep_clauses, restore_clauses, EnvTrimmer};
+use chalk_solve::infer::NormalizationMode;
+use chalk_solve::solve::{Answers, Overflow, OverflowLimit};
+use chalk_solve::{
+    coinductive_goal::IsCoinductive, ProofTree, RustIrDatabase, SolverStats, SubstitutionResult,
+};
 use rustc_hash::FxHashMap;
 use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 use tracing::debug;
 use tracing::{info, instrument};
+#[cfg(feature = "serde")]
+use {
+    crate::search_graph::CachedResult,
+    chalk_ir::interner::SerdeInterner,
+    serde::{Deserialize, Deserializer, Serializer},
+};
 
 struct RecursiveContext<I: Interner> {
     stack: Stack,
@@ -21,13 +38,27 @@ struct RecursiveContext<I: Interner> {
 
     /// The "cache" stores results for goals that we have completely solved.
     /// Things are added to the cache when we have completely processed their
-    /// result.
-    cache: FxHashMap<UCanonicalGoal<I>, Fallible<Solution<I>>>,
+    /// result, along with the items they depend upon (see `invalidate`).
+    cache: Cache<I>,
 
     /// The maximum size for goals.
     max_size: usize,
 
     caching_enabled: bool,
+
+    /// How alias types are treated during unification.
+    normalization: NormalizationMode,
+
+    /// Records a proof tree while solving, if we were asked to explain
+    /// the solution.
+    proof_tree: Option<ProofTreeBuilder<I>>,
+
+    /// What we have done so far, for `Solver::stats`.
+    stats: SolverStats<I>,
+
+    /// Drops the environment clauses that cannot affect a goal before we
+    /// look it up.
+    env_trimmer: EnvTrimmer<I>,
 }
 
 /// A Solver is the basic context in which you can propose goals for a given
@@ -40,6 +71,79 @@ struct Solver<'me, I: Interner> {
     context: &'me mut RecursiveContext<I>,
 }
 
+/// A goal whose table is on the stack, i.e. one that we are in the middle
+/// of solving.
+struct Frame<I: Interner> {
+    goal: UCanonicalGoal<I>,
+    depth: StackDepth,
+    dfn: DepthFirstNumber,
+
+    /// The iteration computing the current solution to the goal (see
+    /// `Solver::finish_iteration`), and the minimums of the subgoals it has
+    /// solved so far.
+    iteration: SolveIteration<I>,
+    minimums: Minimums,
+
+    /// Whether the goal that needs our solution is trying to refute us, in
+    /// which case our minimums do not count towards its own.
+    refuted: bool,
+
+    /// The environment clauses that were dropped from the goal before we
+    /// looked it up, which its solution has to get back.
+    dropped: Option<DroppedClauses<I>>,
+}
+
+/// The environment of a goal whose table only keeps its first `kept`
+/// clauses (see `Solver::trim_environment`).
+struct DroppedClauses<I: Interner> {
+    environment: Environment<I>,
+    kept: usize,
+}
+
+impl<I: Interner> DroppedClauses<I> {
+    /// Gives the constraints of the solution to the trimmed goal back the
+    /// clauses that were dropped from its environment, so that the trimming
+    /// stays invisible to the goal that needs the solution.
+    fn restore(&self, interner: &I, solution: Fallible<Solution<I>>) -> Fallible<Solution<I>> {
+        match solution {
+            Ok(Solution::Unique(Canonical { value, binders })) => {
+                // The constraints are in terms of the variables of the
+                // solution, which the substitution maps the goal to.
+                let environment = value.subst.apply(&self.environment, interner);
+                let constraints = value.constraints.iter(interner).map(|constraint| {
+                    InEnvironment::new(
+                        &restore_clauses(
+                            interner,
+                            &constraint.environment,
+                            self.kept,
+                            &environment,
+                        ),
+                        constraint.goal.clone(),
+                    )
+                });
+                Ok(Solution::Unique(Canonical {
+                    value: ConstrainedSubst {
+                        constraints: Constraints::from_iter(interner, constraints),
+                        subst: value.subst,
+                    },
+                    binders,
+                }))
+            }
+            solution => solution,
+        }
+    }
+}
+
+/// What `Solver::lookup_goal` knows about a goal.
+enum Lookup<I: Interner> {
+    /// Its solution, e.g. because it is in the cache.
+    Solved(Fallible<Solution<I>>),
+
+    /// Nothing yet: it has been pushed onto the stack, so that we can solve
+    /// it.
+    Pushed(Frame<I>),
+}
+
 pub struct RecursiveSolver<I: Interner> {
     ctx: Box<RecursiveContext<I>>,
 }
@@ -54,6 +158,47 @@ impl<I: Interner> RecursiveSolver<I> {
             )),
         }
     }
+
+    /// Uses `mode` for the inference tables of the goals this solver
+    /// solves (see `NormalizationMode`). Goals are solved with eager
+    /// normalization by default.
+    pub fn with_normalization_mode(mut self, mode: NormalizationMode) -> Self {
+        self.ctx.normalization = mode;
+        self
+    }
+
+    /// Keeps the statistics of the `max` root goals that took the longest
+    /// to solve (see `SolverStats::expensive_goals`), rather than of
+    /// `DEFAULT_MAX_EXPENSIVE_GOALS`.
+    pub fn with_max_expensive_goals(mut self, max: usize) -> Self {
+        self.ctx.stats.max_expensive_goals = max;
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<I: SerdeInterner> RecursiveSolver<I> {
+    /// Serializes the results cached by this solver, along with the items
+    /// of the program that each of them depends upon, so that they can be
+    /// reloaded with [`load_cache`](Self::load_cache), e.g. in a later run.
+    /// Values are serialized through [`Interner::serde_interner`].
+    pub fn save_cache<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.ctx.cache)
+    }
+
+    /// Adds results saved with [`save_cache`](Self::save_cache) to this
+    /// solver's cache, replacing those it already has for the same goals.
+    /// The results must have been computed against the same program as the
+    /// one they will be used with; if it has changed since, the affected
+    /// results can be dropped with [`invalidate`](chalk_solve::Solver::invalidate).
+    pub fn load_cache<'de, D: Deserializer<'de>>(
+        &mut self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        let entries: Vec<(UCanonicalGoal<I>, CachedResult<I>)> = Vec::deserialize(deserializer)?;
+        self.ctx.cache.extend(entries);
+        Ok(())
+    }
 }
 
 impl<I: Interner> fmt::Debug for RecursiveSolver<I> {
@@ -90,9 +235,49 @@ impl<I: Interner> RecursiveContext<I> {
             cache: FxHashMap::default(),
             max_size,
             caching_enabled,
+            normalization: NormalizationMode::default(),
+            proof_tree: None,
+            stats: SolverStats::new(),
+            env_trimmer: EnvTrimmer::new(),
         }
     }
 
+    /// Creates a fresh context with the same parameters as `self`, which
+    /// records a proof tree for the goals it solves.
+    fn explaining(&self) -> Self {
+        let mut context = RecursiveContext::new(
+            self.stack.overflow_depth(),
+            self.max_size,
+            self.caching_enabled,
+        );
+        context.normalization = self.normalization;
+        context.proof_tree = Some(ProofTreeBuilder::new());
+        context
+    }
+
+    /// Removes the cached results that depend on any of the `changed` items.
+    fn invalidate(&mut self, changed: &Dependencies<I>) {
+        assert!(self.stack.is_empty());
+        let before = self.cache.len();
+        self.cache
+            .retain(|_goal, (_solution, dependencies)| !dependencies.affected_by(changed));
+        self.env_trimmer.invalidate(changed);
+        debug!(
+            "invalidate: dropped {} of {} cached results",
+            before - self.cache.len(),
+            before
+        );
+    }
+
+    /// Forgets the goals we were in the middle of solving when a panic
+    /// unwound through us, so that we can go on to solve other goals.
+    /// Only goals whose solutions were complete have been cached, so the
+    /// cache can be kept.
+    fn abandon(&mut self) {
+        self.stack.clear();
+        self.search_graph.clear();
+    }
+
     pub(crate) fn solver<'me>(
         &'me mut self,
         program: &'me dyn RustIrDatabase<I>,
@@ -126,101 +311,122 @@ impl<'me, I: Interner> Solver<'me, I> {
     ) -> Fallible<Solution<I>> {
         debug!("solve_root_goal(canonical_goal={:?})", canonical_goal);
         assert!(self.context.stack.is_empty());
+        let start = self.context.stats.start_root_goal();
+        let _unwind_guard = self.recorder().map(DependencyRecorder::unwind_guard);
         let minimums = &mut Minimums::new();
-        self.solve_goal(canonical_goal.clone(), minimums)
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.solve_goal(canonical_goal.clone(), minimums)
+        }))
+        .unwrap_or_else(|payload| {
+            self.context.abandon();
+            panic::resume_unwind(payload)
+        });
+        self.context.stats.finish_root_goal(start, canonical_goal);
+        result
     }
 
-    #[instrument(level = "debug", skip(self))]
-    fn solve_new_subgoal(
+    /// Attempt to solve a goal that has been fully broken down into leaf form
+    /// and canonicalized. This is where the action really happens, and is the
+    /// place where we would perform caching in rustc (and may eventually do in Chalk).
+    ///
+    /// Rather than solving the subgoals we come across recursively, we keep
+    /// the goals we are in the middle of solving in `frames`, so that deep
+    /// goals do not overflow the native stack.
+    fn solve_goal(
         &mut self,
-        canonical_goal: UCanonicalGoal<I>,
-        depth: StackDepth,
-        dfn: DepthFirstNumber,
-    ) -> Minimums {
-        // We start with `answer = None` and try to solve the goal. At the end of the iteration,
-        // `answer` will be updated with the result of the solving process. If we detect a cycle
-        // during the solving process, we cache `answer` and try to solve the goal again. We repeat
-        // until we reach a fixed point for `answer`.
-        // Considering the partial order:
-        // - None < Some(Unique) < Some(Ambiguous)
-        // - None < Some(CannotProve)
-        // the function which maps the loop iteration to `answer` is a nondecreasing function
-        // so this function will eventually be constant and the loop terminates.
+        goal: UCanonicalGoal<I>,
+        minimums: &mut Minimums,
+    ) -> Fallible<Solution<I>> {
+        let mut frames = match self.lookup_goal(goal, minimums) {
+            Lookup::Solved(result) => return result,
+            Lookup::Pushed(frame) => vec![frame],
+        };
+        let mut solution = None;
         loop {
-            let minimums = &mut Minimums::new();
-            let (current_answer, current_prio) = self.solve_iteration(&canonical_goal, minimums);
-
-            debug!(
-                "solve_new_subgoal: loop iteration result = {:?} with minimums {:?}",
-                current_answer, minimums
-            );
+            let frame = frames.last_mut().unwrap();
+            let (subgoal, refuted) = match frame.iteration.resume(self, solution.take()) {
+                Step::Prove(subgoal) => (subgoal, false),
+                Step::Refute(subgoal) => (subgoal, true),
+                Step::Done((answer, priority)) => {
+                    if self.finish_iteration(frame, answer, priority) {
+                        continue;
+                    }
+                    let mut frame = frames.pop().unwrap();
+                    let mut unused = Minimums::new();
+                    let parent_minimums = match frames.last_mut() {
+                        None => &mut *minimums,
+                        Some(_) if frame.refuted => &mut unused,
+                        Some(parent) => &mut parent.minimums,
+                    };
+                    let dropped = frame.dropped.take();
+                    let mut result = self.pop_goal(frame, parent_minimums);
+                    if let Some(dropped) = dropped {
+                        result = dropped.restore(self.program.interner(), result);
+                    }
+                    if frames.is_empty() {
+                        return result;
+                    }
+                    solution = Some(result);
+                    continue;
+                }
+            };
 
-            if !self.context.stack[depth].read_and_reset_cycle_flag() {
-                // None of our subgoals depended on us directly.
-                // We can return.
-                self.context.search_graph[dfn].solution = current_answer;
-                self.context.search_graph[dfn].solution_priority = current_prio;
-                return *minimums;
+            let mut unused = Minimums::new();
+            let subgoal_minimums = if refuted {
+                &mut unused
+            } else {
+                &mut frame.minimums
+            };
+            match self.lookup_goal(subgoal, subgoal_minimums) {
+                Lookup::Solved(result) => solution = Some(result),
+                Lookup::Pushed(mut subgoal_frame) => {
+                    subgoal_frame.refuted = refuted;
+                    frames.push(subgoal_frame);
+                }
             }
+        }
+    }
 
-            let old_answer = &self.context.search_graph[dfn].solution;
-            let old_prio = self.context.search_graph[dfn].solution_priority;
-
-            let (current_answer, current_prio) = combine::with_priorities_for_goal(
-                self.program.interner(),
-                &canonical_goal.canonical.value.goal,
-                old_answer.clone(),
-                old_prio,
-                current_answer,
-                current_prio,
-            );
-
-            // Some of our subgoals depended on us. We need to re-run
-            // with the current answer.
-            if self.context.search_graph[dfn].solution == current_answer {
-                // Reached a fixed point.
-                return *minimums;
+    /// Looks for the solution to `goal` in the cache and the search graph,
+    /// or pushes it onto the stack if we have to solve it. Both are keyed
+    /// by the goal without the environment clauses that cannot affect it.
+    #[instrument(level = "info", skip(self, minimums))]
+    fn lookup_goal(&mut self, goal: UCanonicalGoal<I>, minimums: &mut Minimums) -> Lookup<I> {
+        let (goal, dropped) = self.trim_environment(goal);
+        match (self.lookup_trimmed_goal(goal, minimums), dropped) {
+            (Lookup::Solved(result), Some(dropped)) => {
+                Lookup::Solved(dropped.restore(self.program.interner(), result))
             }
-
-            let current_answer_is_ambig = match &current_answer {
-                Ok(s) => s.is_ambig(),
-                Err(_) => false,
-            };
-
-            self.context.search_graph[dfn].solution = current_answer;
-            self.context.search_graph[dfn].solution_priority = current_prio;
-
-            // Subtle: if our current answer is ambiguous, we can just stop, and
-            // in fact we *must* -- otherwise, we sometimes fail to reach a
-            // fixed point. See `multiple_ambiguous_cycles` for more.
-            if current_answer_is_ambig {
-                return *minimums;
+            (Lookup::Pushed(mut frame), dropped) => {
+                frame.dropped = dropped;
+                Lookup::Pushed(frame)
             }
-
-            // Otherwise: rollback the search tree and try again.
-            self.context.search_graph.rollback_to(dfn + 1);
+            (lookup, None) => lookup,
         }
     }
-}
 
-impl<'me, I: Interner> SolveDatabase<I> for Solver<'me, I> {
-    /// Attempt to solve a goal that has been fully broken down into leaf form
-    /// and canonicalized. This is where the action really happens, and is the
-    /// place where we would perform caching in rustc (and may eventually do in Chalk).
-    #[instrument(level = "info", skip(self, minimums))]
-    fn solve_goal(
+    fn lookup_trimmed_goal(
         &mut self,
         goal: UCanonicalGoal<I>,
         minimums: &mut Minimums,
-    ) -> Fallible<Solution<I>> {
+    ) -> Lookup<I> {
         // First check the cache.
-        if let Some(value) = self.context.cache.get(&goal) {
+        if let Some((value, dependencies)) = self.context.cache.get(&goal) {
             debug!("solve_reduced_goal: cache hit, value={:?}", value);
-            return value.clone();
+            self.context.stats.cache_hits += 1;
+            if let Some(recorder) = self.recorder() {
+                recorder.record_all(dependencies);
+            }
+            if let Some(proof_tree) = &mut self.context.proof_tree {
+                proof_tree.cached_goal(&goal, value);
+            }
+            return Lookup::Solved(value.clone());
         }
 
         // Next, check if the goal is in the search tree already.
         if let Some(dfn) = self.context.search_graph.lookup(&goal) {
+            self.context.stats.cache_hits += 1;
+
             // Check if this table is still on the stack.
             if let Some(depth) = self.context.search_graph[dfn].stack_depth {
                 // Is this a coinductive goal? If so, that is success,
@@ -228,70 +434,261 @@ impl<'me, I: Interner> SolveDatabase<I> for Solver<'me, I> {
                 // not tabled.
                 //
                 // XXX how does caching with coinduction work?
+                if let Some(proof_tree) = &mut self.context.proof_tree {
+                    proof_tree.cyclic_goal(&goal);
+                }
+
                 if self.context.stack.coinductive_cycle_from(depth) {
                     let value = ConstrainedSubst {
                         subst: goal.trivial_substitution(self.program.interner()),
                         constraints: Constraints::empty(self.program.interner()),
                     };
                     debug!("applying coinductive semantics");
-                    return Ok(Solution::Unique(Canonical {
+                    return Lookup::Solved(Ok(Solution::Unique(Canonical {
                         value,
                         binders: goal.canonical.binders,
-                    }));
+                    })));
                 }
 
                 self.context.stack[depth].flag_cycle();
             }
 
+            // If the table depends on an overflow, so do we.
+            let depends_on_overflow = match self.context.search_graph[dfn].stack_depth {
+                Some(depth) => self.context.stack.depends_on_overflow(depth),
+                None => self.context.search_graph[dfn].depends_on_overflow,
+            };
+            if depends_on_overflow {
+                self.context.stack.mark_overflowed();
+            }
+
             minimums.update_from(self.context.search_graph[dfn].links);
+            if let Some(recorder) = self.recorder() {
+                recorder.record_all(&self.context.search_graph[dfn].dependencies);
+            }
 
             // Return the solution from the table.
             let previous_solution = self.context.search_graph[dfn].solution.clone();
             let previous_solution_priority = self.context.search_graph[dfn].solution_priority;
+            if self.context.search_graph[dfn].stack_depth.is_none() {
+                if let Some(proof_tree) = &mut self.context.proof_tree {
+                    proof_tree.cached_goal(&goal, &previous_solution);
+                }
+            }
             info!(
                 "solve_goal: cycle detected, previous solution {:?} with prio {:?}",
                 previous_solution, previous_solution_priority
             );
-            previous_solution
+            Lookup::Solved(previous_solution)
+        } else if self.context.stack.is_full() {
+            // We cannot go any deeper; give up on this goal rather than
+            // pushing it.
+            info!("solve_goal: overflow depth reached");
+            self.context.stats.overflows += 1;
+            self.context.stack.mark_overflowed();
+            if let Some(proof_tree) = &mut self.context.proof_tree {
+                proof_tree.overflowed_goal(&goal);
+            }
+            Lookup::Solved(Ok(Solution::Overflow(Overflow {
+                goal: goal.canonical,
+                limit: OverflowLimit::Depth(self.context.stack.overflow_depth()),
+            })))
         } else {
             // Otherwise, push the goal onto the stack and create a table.
             // The initial result for this table is error.
+            self.context.stats.tables += 1;
+            self.context.stats.cache_misses += 1;
+            if let Some(recorder) = self.recorder() {
+                recorder.start();
+            }
             let coinductive_goal = goal.is_coinductive(self.program);
             let depth = self.context.stack.push(coinductive_goal);
             let dfn = self.context.search_graph.insert(&goal, depth);
-            let subgoal_minimums = self.solve_new_subgoal(goal, depth, dfn);
-            self.context.search_graph[dfn].links = subgoal_minimums;
-            self.context.search_graph[dfn].stack_depth = None;
-            self.context.stack.pop(depth);
-            minimums.update_from(subgoal_minimums);
-
-            // Read final result from table.
-            let result = self.context.search_graph[dfn].solution.clone();
-            let priority = self.context.search_graph[dfn].solution_priority;
-
-            // If processing this subgoal did not involve anything
-            // outside of its subtree, then we can promote it to the
-            // cache now. This is a sort of hack to alleviate the
-            // worst of the repeated work that we do during tabling.
-            if subgoal_minimums.positive >= dfn {
-                if self.context.caching_enabled {
-                    self.context
-                        .search_graph
-                        .move_to_cache(dfn, &mut self.context.cache);
-                    debug!("solve_reduced_goal: SCC head encountered, moving to cache");
-                } else {
-                    debug!(
-                        "solve_reduced_goal: SCC head encountered, rolling back as caching disabled"
-                    );
-                    self.context.search_graph.rollback_to(dfn);
-                }
+            if let Some(proof_tree) = &mut self.context.proof_tree {
+                proof_tree.start_goal(&goal);
             }
+            Lookup::Pushed(Frame {
+                iteration: self.start_iteration(&goal),
+                goal,
+                depth,
+                dfn,
+                minimums: Minimums::new(),
+                refuted: false,
+                dropped: None,
+            })
+        }
+    }
+
+    /// Drops the environment clauses that cannot affect `goal`, so that it
+    /// shares cache entries with the goals that only differ from it in
+    /// those, and returns what was dropped.
+    fn trim_environment(
+        &mut self,
+        mut goal: UCanonicalGoal<I>,
+    ) -> (UCanonicalGoal<I>, Option<DroppedClauses<I>>) {
+        let value = &mut goal.canonical.value;
+        let kept = match self
+            .context
+            .env_trimmer
+            .relevant_clauses(self.program, value)
+        {
+            Some(kept) => kept,
+            None => return (goal, None),
+        };
+        let dropped = DroppedClauses {
+            environment: value.environment.clone(),
+            kept: kept.iter().filter(|&&kept| kept).count(),
+        };
+        *value = keep_clauses(self.program.interner(), value, &kept);
+        (goal, Some(dropped))
+    }
+
+    fn start_iteration(&mut self, goal: &UCanonicalGoal<I>) -> SolveIteration<I> {
+        if let Some(proof_tree) = &mut self.context.proof_tree {
+            proof_tree.restart_goal();
+        }
+        SolveIteration::new(self, goal)
+    }
+
+    /// Records the answer that an iteration found for the goal of `frame`,
+    /// and starts another iteration if that is needed to reach a fixed point,
+    /// returning whether it did.
+    ///
+    /// We start with `answer = None` and try to solve the goal. At the end of the iteration,
+    /// `answer` will be updated with the result of the solving process. If we detect a cycle
+    /// during the solving process, we cache `answer` and try to solve the goal again. We repeat
+    /// until we reach a fixed point for `answer`.
+    /// Considering the partial order:
+    /// - None < Some(Unique) < Some(Ambiguous)
+    /// - None < Some(CannotProve)
+    /// the function which maps the loop iteration to `answer` is a nondecreasing function
+    /// so this function will eventually be constant and the loop terminates.
+    #[instrument(level = "debug", skip(self, frame), fields(goal = ?frame.goal))]
+    fn finish_iteration(
+        &mut self,
+        frame: &mut Frame<I>,
+        current_answer: Fallible<Solution<I>>,
+        current_prio: ClausePriority,
+    ) -> bool {
+        let Frame { depth, dfn, .. } = *frame;
+        debug!(
+            "finish_iteration: loop iteration result = {:?} with minimums {:?}",
+            current_answer, frame.minimums
+        );
+
+        if !self.context.stack[depth].read_and_reset_cycle_flag() {
+            // None of our subgoals depended on us directly.
+            // We can return.
+            self.context.search_graph[dfn].solution = current_answer;
+            self.context.search_graph[dfn].solution_priority = current_prio;
+            return false;
+        }
+
+        let old_answer = &self.context.search_graph[dfn].solution;
+        let old_prio = self.context.search_graph[dfn].solution_priority;
+
+        let (current_answer, current_prio) = combine::with_priorities_for_goal(
+            self.program.interner(),
+            &frame.goal.canonical.value.goal,
+            old_answer.clone(),
+            old_prio,
+            current_answer,
+            current_prio,
+        );
+
+        // Some of our subgoals depended on us. We need to re-run
+        // with the current answer.
+        if self.context.search_graph[dfn].solution == current_answer {
+            // Reached a fixed point.
+            return false;
+        }
+
+        let current_answer_is_ambig = match &current_answer {
+            Ok(s) => s.is_ambig(),
+            Err(_) => false,
+        };
+
+        self.context.search_graph[dfn].solution = current_answer;
+        self.context.search_graph[dfn].solution_priority = current_prio;
+
+        // Subtle: if our current answer is ambiguous, we can just stop, and
+        // in fact we *must* -- otherwise, we sometimes fail to reach a
+        // fixed point. See `multiple_ambiguous_cycles` for more.
+        if current_answer_is_ambig {
+            return false;
+        }
+
+        // Otherwise: rollback the search tree and try again.
+        self.context.search_graph.rollback_to(dfn + 1);
+        frame.minimums = Minimums::new();
+        frame.iteration = self.start_iteration(&frame.goal);
+        true
+    }
+
+    /// Pops the goal of `frame`, whose table has reached its final answer,
+    /// off the stack, and returns that answer.
+    fn pop_goal(&mut self, frame: Frame<I>, minimums: &mut Minimums) -> Fallible<Solution<I>> {
+        let Frame {
+            depth,
+            dfn,
+            minimums: subgoal_minimums,
+            ..
+        } = frame;
+        if let Some(recorder) = self.recorder() {
+            self.context.search_graph[dfn].dependencies = recorder.finish();
+        }
+        self.context.search_graph[dfn].links = subgoal_minimums;
+        self.context.search_graph[dfn].stack_depth = None;
+        let depends_on_overflow = self.context.stack.depends_on_overflow(depth);
+        self.context.search_graph[dfn].depends_on_overflow = depends_on_overflow;
+        self.context.stack.pop(depth);
+        minimums.update_from(subgoal_minimums);
+
+        // Read final result from table.
+        let result = self.context.search_graph[dfn].solution.clone();
+        if result.is_ok() {
+            self.context.stats.answers += 1;
+        }
+        let priority = self.context.search_graph[dfn].solution_priority;
+        if let Some(proof_tree) = &mut self.context.proof_tree {
+            proof_tree.finish_goal(&result);
+        }
 
-            info!("solve_goal: solution = {:?} prio {:?}", result, priority);
-            result
+        // If processing this subgoal did not involve anything
+        // outside of its subtree, then we can promote it to the
+        // cache now. This is a sort of hack to alleviate the
+        // worst of the repeated work that we do during tabling.
+        if subgoal_minimums.positive >= dfn {
+            if depends_on_overflow {
+                // Solving this goal at a shallower depth could have given a
+                // different result, which must not end up in the cache. As
+                // the head of its cycle, this goal was on the stack when any
+                // goal after it overflowed, so this covers them too.
+                debug!("solve_reduced_goal: SCC head encountered, rolling back after overflow");
+                self.context.search_graph.rollback_to(dfn);
+            } else if self.context.caching_enabled {
+                self.context
+                    .search_graph
+                    .move_to_cache(dfn, &mut self.context.cache);
+                debug!("solve_reduced_goal: SCC head encountered, moving to cache");
+            } else {
+                debug!(
+                    "solve_reduced_goal: SCC head encountered, rolling back as caching disabled"
+                );
+                self.context.search_graph.rollback_to(dfn);
+            }
         }
+
+        info!("solve_goal: solution = {:?} prio {:?}", result, priority);
+        result
     }
 
+    fn recorder(&self) -> Option<&'me DependencyRecorder<I>> {
+        self.program.dependency_recorder()
+    }
+}
+
+impl<'me, I: Interner> SolveDatabase<I> for Solver<'me, I> {
     fn interner(&self) -> &I {
         &self.program.interner()
     }
@@ -303,6 +700,18 @@ impl<'me, I: Interner> SolveDatabase<I> for Solver<'me, I> {
     fn max_size(&self) -> usize {
         self.context.max_size
     }
+
+    fn normalization_mode(&self) -> NormalizationMode {
+        self.context.normalization
+    }
+
+    fn proof_tree(&mut self) -> Option<&mut ProofTreeBuilder<I>> {
+        self.context.proof_tree.as_mut()
+    }
+
+    fn stats(&mut self) -> &mut SolverStats<I> {
+        &mut self.context.stats
+    }
 }
 
 impl<I: Interner> chalk_solve::Solver<I> for RecursiveSolver<I> {
@@ -315,14 +724,7 @@ impl<I: Interner> chalk_solve::Solver<I> for RecursiveSolver<I> {
             .solver(program)
             .solve_root_goal(goal)
             .ok()
-            .map(|s| match s {
-                Solution::Unique(c) => chalk_solve::Solution::Unique(c),
-                Solution::Ambig(g) => chalk_solve::Solution::Ambig(match g {
-                    Guidance::Definite(g) => chalk_solve::Guidance::Definite(g),
-                    Guidance::Suggested(g) => chalk_solve::Guidance::Suggested(g),
-                    Guidance::Unknown => chalk_solve::Guidance::Unknown,
-                }),
-            })
+            .map(|solution| into_solve_solution(program, solution))
     }
 
     fn solve_limited(
@@ -336,25 +738,101 @@ impl<I: Interner> chalk_solve::Solver<I> for RecursiveSolver<I> {
             .solver(program)
             .solve_root_goal(goal)
             .ok()
-            .map(|s| match s {
-                Solution::Unique(c) => chalk_solve::Solution::Unique(c),
-                Solution::Ambig(g) => chalk_solve::Solution::Ambig(match g {
-                    Guidance::Definite(g) => chalk_solve::Guidance::Definite(g),
-                    Guidance::Suggested(g) => chalk_solve::Guidance::Suggested(g),
-                    Guidance::Unknown => chalk_solve::Guidance::Unknown,
-                }),
-            })
+            .map(|solution| into_solve_solution(program, solution))
     }
 
-    fn solve_multiple(
+    fn solve_explained(
         &mut self,
-        _program: &dyn RustIrDatabase<I>,
-        _goal: &UCanonical<InEnvironment<Goal<I>>>,
-        _f: &mut dyn FnMut(
-            chalk_solve::SubstitutionResult<Canonical<ConstrainedSubst<I>>>,
-            bool,
-        ) -> bool,
-    ) -> bool {
-        unimplemented!("Recursive solver doesn't support multiple answers")
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> (Option<chalk_solve::Solution<I>>, ProofTree<I>) {
+        let mut ctx = self.ctx.explaining();
+        let solution = ctx.solver(program).solve_root_goal(goal);
+        let proof_tree = ctx
+            .proof_tree
+            .take()
+            .and_then(|proof_tree| proof_tree.into_root())
+            .expect("root goal was not recorded in the proof tree");
+        (
+            solution
+                .ok()
+                .map(|solution| into_solve_solution(program, solution)),
+            proof_tree,
+        )
     }
+
+    /// The recursive solver does not enumerate the answers to a goal, so
+    /// this yields the one solution that `solve` finds, if any.
+    fn solve_answers<'a>(
+        &'a mut self,
+        program: &'a dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Answers<'a, I> {
+        let solution = self.ctx.solver(program).solve_root_goal(goal).ok();
+        let interner = program.interner();
+        Answers::new(
+            solution
+                .map(|solution| into_substitution_result(interner, solution))
+                .into_iter(),
+        )
+    }
+
+    fn invalidate(&mut self, changed: &Dependencies<I>) -> bool {
+        self.ctx.invalidate(changed);
+        true
+    }
+
+    fn clear(&mut self) {
+        assert!(self.ctx.stack.is_empty());
+        self.ctx.cache.clear();
+        self.ctx.env_trimmer.clear();
+    }
+
+    fn stats(&self) -> SolverStats<I> {
+        self.ctx.stats.clone()
+    }
+}
+
+fn into_solve_solution<I: Interner>(
+    program: &dyn RustIrDatabase<I>,
+    solution: Solution<I>,
+) -> chalk_solve::Solution<I> {
+    match solution {
+        Solution::Unique(c) => chalk_solve::Solution::Unique(c),
+        Solution::Ambig(g) => chalk_solve::Solution::Ambig(match g {
+            Guidance::Definite(g, cause) => {
+                chalk_solve::Guidance::Definite(g, cause.resolve(program))
+            }
+            Guidance::Suggested(g, cause) => {
+                chalk_solve::Guidance::Suggested(g, cause.resolve(program))
+            }
+            Guidance::Unknown(cause) => chalk_solve::Guidance::Unknown(cause.resolve(program)),
+        }),
+        Solution::Overflow(overflow) => chalk_solve::Solution::Overflow(overflow),
+    }
+}
+
+/// The answer that `solution` gives. We have no substitution to give for
+/// a goal without inference guidance, or one that overflowed.
+fn into_substitution_result<I: Interner>(
+    interner: &I,
+    solution: Solution<I>,
+) -> SubstitutionResult<Canonical<ConstrainedSubst<I>>> {
+    let guidance = match solution {
+        Solution::Unique(constrained_subst) => {
+            return SubstitutionResult::Definite(constrained_subst)
+        }
+        Solution::Ambig(Guidance::Definite(subst, _))
+        | Solution::Ambig(Guidance::Suggested(subst, _)) => subst,
+        Solution::Ambig(Guidance::Unknown(_)) | Solution::Overflow(_) => {
+            return SubstitutionResult::Floundered
+        }
+    };
+    SubstitutionResult::Ambiguous(Canonical {
+        binders: guidance.binders,
+        value: ConstrainedSubst {
+            subst: guidance.value,
+            constraints: Constraints::empty(interner),
+        },
+    })
 }
@@ -6,9 +6,16 @@ use std::usize;
 use super::stack::StackDepth;
 use crate::{Minimums, Solution, UCanonicalGoal};
 use chalk_ir::{interner::Interner, ClausePriority, Fallible, NoSolution};
+use chalk_solve::dependencies::Dependencies;
 use rustc_hash::FxHashMap;
 use tracing::{debug, instrument};
 
+/// Completely solved goals, along with the items their solutions depend upon.
+pub(super) type Cache<I> = FxHashMap<UCanonicalGoal<I>, CachedResult<I>>;
+
+/// The solution of a completely solved goal, and the items it depends upon.
+pub(super) type CachedResult<I> = (Fallible<Solution<I>>, Dependencies<I>);
+
 /// The "search graph" stores in-progress goals that are still
 /// being solved.
 pub(super) struct SearchGraph<I: Interner> {
@@ -36,6 +43,16 @@ pub(super) struct Node<I: Interner> {
     /// from the stack, it contains the DFN of the minimal ancestor
     /// that the table reached (or MAX if no cycle was encountered).
     pub(crate) links: Minimums,
+
+    /// The items consulted while solving this node, including those
+    /// consulted for its subgoals. Only complete once the node is popped
+    /// from the stack.
+    pub(crate) dependencies: Dependencies<I>,
+
+    /// Whether the solution depends on a goal that overflowed the stack, in
+    /// which case it is not cached (see `Stack::mark_overflowed`). Only set
+    /// once the node is popped from the stack.
+    pub(crate) depends_on_overflow: bool,
 }
 
 impl<I: Interner> SearchGraph<I> {
@@ -70,6 +87,8 @@ impl<I: Interner> SearchGraph<I> {
             solution_priority: ClausePriority::High,
             stack_depth: Some(stack_depth),
             links: Minimums { positive: dfn },
+            dependencies: Dependencies::new(),
+            depends_on_overflow: false,
         };
         self.nodes.push(node);
         let previous_index = self.indices.insert(goal.clone(), dfn);
@@ -84,20 +103,28 @@ impl<I: Interner> SearchGraph<I> {
         self.nodes.truncate(dfn.index);
     }
 
+    /// Removes all nodes, without caching any of their solutions.
+    pub(crate) fn clear(&mut self) {
+        self.indices.clear();
+        self.nodes.clear();
+    }
+
     /// Removes all nodes with a depth-first-number greater than or
     /// equal to `dfn`, adding their final solutions into the cache.
-    #[instrument(level = "debug", skip(self))]
-    pub(crate) fn move_to_cache(
-        &mut self,
-        dfn: DepthFirstNumber,
-        cache: &mut FxHashMap<UCanonicalGoal<I>, Fallible<Solution<I>>>,
-    ) {
+    ///
+    /// The nodes after `dfn` are part of a cycle through `dfn` (otherwise
+    /// they would have been cached already), so their solutions may depend
+    /// on anything the node at `dfn` depends upon; they are cached with
+    /// its dependencies.
+    #[instrument(level = "debug", skip(self, cache))]
+    pub(crate) fn move_to_cache(&mut self, dfn: DepthFirstNumber, cache: &mut Cache<I>) {
         self.indices.retain(|_key, value| *value < dfn);
+        let dependencies = self[dfn].dependencies.clone();
         for node in self.nodes.drain(dfn.index..) {
             assert!(node.stack_depth.is_none());
             assert!(node.links.positive >= dfn);
             debug!("caching solution {:#?} for {:#?}", node.solution, node.goal);
-            cache.insert(node.goal, node.solution);
+            cache.insert(node.goal, (node.solution, dependencies.clone()));
         }
     }
 }
@@ -1,48 +1,106 @@
 use super::combine;
+use super::explain::ProofTreeBuilder;
 use super::fulfill::{Fulfill, RecursiveInferenceTable};
-use crate::{Guidance, Minimums, Solution, UCanonicalGoal};
+use crate::{Guidance, Solution, UCanonicalGoal};
 use chalk_ir::fold::Fold;
 use chalk_ir::interner::{HasInterner, Interner};
 use chalk_ir::visit::Visit;
 use chalk_ir::zip::Zip;
 use chalk_ir::{
-    Binders, Canonical, ClausePriority, DomainGoal, Environment, Fallible, Floundered, GenericArg,
-    Goal, GoalData, InEnvironment, NoSolution, ProgramClause, ProgramClauseData,
-    ProgramClauseImplication, Substitution, UCanonical, UnificationDatabase, UniverseMap, Variance,
+    Binders, Canonical, ClausePriority, DomainGoal, Environment, Fallible, GenericArg, Goal,
+    GoalData, InEnvironment, NoSolution, ProgramClause, ProgramClauseData, Substitution,
+    UCanonical, UnificationDatabase, UniverseMap, Variance,
 };
-use chalk_solve::clauses::program_clauses_for_goal;
+use chalk_solve::clauses::ClauseError;
 use chalk_solve::debug_span;
-use chalk_solve::infer::{InferenceTable, ParameterEnaVariableExt};
-use chalk_solve::{solve::truncate, RustIrDatabase};
+use chalk_solve::infer::{InferenceTable, NormalizationMode, ParameterEnaVariableExt};
+use chalk_solve::solve::{truncate, AmbiguityCause};
+use chalk_solve::{RustIrDatabase, SolverStats};
 use std::fmt::Debug;
+use std::vec;
 use tracing::{debug, instrument};
 
 pub(super) trait SolveDatabase<I: Interner>: Sized {
-    fn solve_goal(
-        &mut self,
-        goal: UCanonical<InEnvironment<Goal<I>>>,
-        minimums: &mut Minimums,
-    ) -> Fallible<Solution<I>>;
-
     fn max_size(&self) -> usize;
 
+    /// How inference tables created for goals treat alias types.
+    fn normalization_mode(&self) -> NormalizationMode;
+
+    /// The proof tree being recorded, if we were asked to explain the
+    /// solution.
+    fn proof_tree(&mut self) -> Option<&mut ProofTreeBuilder<I>>;
+
+    /// The statistics we are collecting (see `Solver::stats`).
+    fn stats(&mut self) -> &mut SolverStats<I>;
+
     fn interner(&self) -> &I;
 
     fn db(&self) -> &dyn RustIrDatabase<I>;
 }
 
-/// The `solve_iteration` method -- implemented for any type that implements
-/// `SolveDb`.
-pub(super) trait SolveIteration<I: Interner>: SolveDatabase<I> {
-    /// Executes one iteration of the recursive solver, computing the current
-    /// solution to the given canonical goal. This is used as part of a loop in
-    /// the case of cyclic goals.
-    #[instrument(level = "debug", skip(self))]
-    fn solve_iteration(
-        &mut self,
+/// What a computation that was suspended because it needs the solution to
+/// some goal asks of its caller, before it can be resumed with that solution.
+pub(super) enum Step<I: Interner, T> {
+    /// Solve this goal, which is part of ours, so that cycles through it
+    /// affect our own solution.
+    Prove(UCanonicalGoal<I>),
+
+    /// Solve this goal, which we are trying to refute.
+    Refute(UCanonicalGoal<I>),
+
+    /// We are done, with this result.
+    Done(T),
+}
+
+/// One iteration of the recursive solver, computing the current solution to
+/// a canonical goal. This is used as part of a loop in the case of cyclic
+/// goals.
+///
+/// Rather than solving the subgoals that it comes across itself, an
+/// iteration returns them from `resume`, and is resumed with their
+/// solutions. This lets the solver keep the goals it is working on in a
+/// stack of its own, rather than on the native one.
+pub(super) struct SolveIteration<I: Interner> {
+    state: IterationState<I>,
+}
+
+enum IterationState<I: Interner> {
+    /// "Domain" goals (i.e., leaf goals that are Rust-specific) are always
+    /// solved via some form of implication. We can either apply assumptions
+    /// from our environment (i.e. where clauses), or from the lowered
+    /// program, which includes fallback clauses. We try each approach in
+    /// turn.
+    Clauses(ClauseSearch<I>),
+
+    /// Other goals are simplified into domain goals.
+    Simplification(Fulfill<I, RecursiveInferenceTableImpl<I>>),
+
+    /// We already know the result.
+    Done(Option<(Fallible<Solution<I>>, ClausePriority)>),
+}
+
+/// See whether we can solve a goal by implication on any of the given
+/// clauses. If multiple such solutions are possible, we attempt to combine
+/// them.
+struct ClauseSearch<I: Interner> {
+    canonical_goal: UCanonical<InEnvironment<DomainGoal<I>>>,
+
+    /// The clauses that we have not tried yet.
+    clauses: vec::IntoIter<ProgramClause<I>>,
+
+    /// The clause we are trying (modus ponens! that is: we try to apply an
+    /// implication by proving its premises), and its priority.
+    candidate: Option<(Fulfill<I, RecursiveInferenceTableImpl<I>>, ClausePriority)>,
+
+    cur_solution: Option<(Solution<I>, ClausePriority)>,
+}
+
+impl<I: Interner> SolveIteration<I> {
+    #[instrument(level = "debug", skip(solver))]
+    pub(super) fn new<S: SolveDatabase<I>>(
+        solver: &mut S,
         canonical_goal: &UCanonicalGoal<I>,
-        minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority) {
+    ) -> Self {
         let UCanonical {
             universes,
             canonical:
@@ -52,7 +110,7 @@ pub(super) trait SolveIteration<I: Interner>: SolveDatabase<I> {
                 },
         } = canonical_goal.clone();
 
-        match goal.data(self.interner()) {
+        let state = match goal.data(solver.interner()) {
             GoalData::DomainGoal(domain_goal) => {
                 let canonical_goal = UCanonical {
                     universes,
@@ -65,26 +123,38 @@ pub(super) trait SolveIteration<I: Interner>: SolveDatabase<I> {
                     },
                 };
 
-                // "Domain" goals (i.e., leaf goals that are Rust-specific) are
-                // always solved via some form of implication. We can either
-                // apply assumptions from our environment (i.e. where clauses),
-                // or from the lowered program, which includes fallback
-                // clauses. We try each approach in turn:
-
-                let (prog_solution, prog_prio) = {
-                    debug_span!("prog_clauses");
-
-                    let prog_clauses = self.program_clauses_for_goal(&canonical_goal);
-                    match prog_clauses {
-                        Ok(clauses) => self.solve_from_clauses(&canonical_goal, clauses, minimums),
-                        Err(Floundered) => {
-                            (Ok(Solution::Ambig(Guidance::Unknown)), ClausePriority::High)
+                match program_clauses_for_goal(solver, &canonical_goal) {
+                    Ok(clauses) => IterationState::Clauses(ClauseSearch {
+                        canonical_goal,
+                        clauses: clauses.into_iter(),
+                        candidate: None,
+                        cur_solution: None,
+                    }),
+                    Err(ClauseError::Floundered) => {
+                        if let Some(proof_tree) = solver.proof_tree() {
+                            proof_tree.mark_floundered();
                         }
+                        // We usually flounder because the self type is
+                        // unknown; say so if that is the case.
+                        let cause =
+                            AmbiguityCause::of_self_type(solver.interner(), &canonical_goal)
+                                .unwrap_or(AmbiguityCause::Floundered);
+                        IterationState::Done(Some((
+                            Ok(Solution::Ambig(Guidance::Unknown(cause.into()))),
+                            ClausePriority::High,
+                        )))
                     }
-                };
-                debug!(?prog_solution);
-
-                (prog_solution, prog_prio)
+                    Err(ClauseError::LookupFailed(error)) => {
+                        if let Some(proof_tree) = solver.proof_tree() {
+                            proof_tree.mark_floundered();
+                        }
+                        let cause = AmbiguityCause::LookupFailed(error.item);
+                        IterationState::Done(Some((
+                            Ok(Solution::Ambig(Guidance::Unknown(cause.into()))),
+                            ClausePriority::High,
+                        )))
+                    }
+                }
             }
 
             _ => {
@@ -96,128 +166,186 @@ pub(super) trait SolveIteration<I: Interner>: SolveDatabase<I> {
                     },
                 };
 
-                self.solve_via_simplification(&canonical_goal, minimums)
+                if let Some(proof_tree) = solver.proof_tree() {
+                    proof_tree.start_candidate(None);
+                }
+                let (infer, subst, goal) = new_inference_table(solver, &canonical_goal);
+                match Fulfill::new_with_simplification(solver, infer, subst, goal) {
+                    Ok(fulfill) => IterationState::Simplification(fulfill),
+                    Err(e) => {
+                        let result = Err(e);
+                        if let Some(proof_tree) = solver.proof_tree() {
+                            proof_tree.finish_candidate(&result);
+                        }
+                        IterationState::Done(Some((result, ClausePriority::High)))
+                    }
+                }
             }
-        }
+        };
+        SolveIteration { state }
     }
-}
-
-impl<S, I> SolveIteration<I> for S
-where
-    S: SolveDatabase<I>,
-    I: Interner,
-{
-}
 
-/// Helper methods for `solve_iteration`, private to this module.
-trait SolveIterationHelpers<I: Interner>: SolveDatabase<I> {
-    #[instrument(level = "debug", skip(self, minimums))]
-    fn solve_via_simplification(
+    /// Continues the iteration, with the solution to the goal that the last
+    /// call asked for, if any.
+    pub(super) fn resume<S: SolveDatabase<I>>(
         &mut self,
-        canonical_goal: &UCanonicalGoal<I>,
-        minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority) {
-        let (infer, subst, goal) = self.new_inference_table(canonical_goal);
-        match Fulfill::new_with_simplification(self, infer, subst, goal) {
-            Ok(fulfill) => (fulfill.solve(minimums), ClausePriority::High),
-            Err(e) => (Err(e), ClausePriority::High),
+        solver: &mut S,
+        solution: Option<Fallible<Solution<I>>>,
+    ) -> Step<I, (Fallible<Solution<I>>, ClausePriority)> {
+        match &mut self.state {
+            IterationState::Clauses(search) => search.resume(solver, solution),
+            IterationState::Simplification(fulfill) => match fulfill.resume(solver, solution) {
+                Step::Prove(goal) => Step::Prove(goal),
+                Step::Refute(goal) => Step::Refute(goal),
+                Step::Done(result) => {
+                    if let Some(proof_tree) = solver.proof_tree() {
+                        proof_tree.finish_candidate(&result);
+                    }
+                    Step::Done((result, ClausePriority::High))
+                }
+            },
+            IterationState::Done(result) => {
+                Step::Done(result.take().expect("iteration resumed after it was done"))
+            }
         }
     }
+}
 
-    /// See whether we can solve a goal by implication on any of the given
-    /// clauses. If multiple such solutions are possible, we attempt to combine
-    /// them.
-    fn solve_from_clauses<C>(
+impl<I: Interner> ClauseSearch<I> {
+    fn resume<S: SolveDatabase<I>>(
         &mut self,
-        canonical_goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
-        clauses: C,
-        minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority)
-    where
-        C: IntoIterator<Item = ProgramClause<I>>,
-    {
-        let mut cur_solution = None;
-        for program_clause in clauses {
-            debug_span!("solve_from_clauses", clause = ?program_clause);
+        solver: &mut S,
+        mut solution: Option<Fallible<Solution<I>>>,
+    ) -> Step<I, (Fallible<Solution<I>>, ClausePriority)> {
+        loop {
+            if let Some((fulfill, priority)) = &mut self.candidate {
+                let priority = *priority;
+                match fulfill.resume(solver, solution.take()) {
+                    Step::Prove(goal) => return Step::Prove(goal),
+                    Step::Refute(goal) => return Step::Refute(goal),
+                    Step::Done(result) => {
+                        self.candidate = None;
+                        self.finish_candidate(solver, result, priority);
+                    }
+                }
+            }
 
-            // If we have a completely ambiguous answer, it's not going to get better, so stop
-            if cur_solution == Some((Solution::Ambig(Guidance::Unknown), ClausePriority::High)) {
-                return (Ok(Solution::Ambig(Guidance::Unknown)), ClausePriority::High);
+            // If we have a completely ambiguous answer, or an overflow, it's
+            // not going to get better, so stop
+            if let Some((Solution::Ambig(Guidance::Unknown(_)), ClausePriority::High))
+            | Some((Solution::Overflow(_), ClausePriority::High)) = &self.cur_solution
+            {
+                return Step::Done(self.finish());
             }
 
-            let ProgramClauseData(implication) = program_clause.data(self.interner());
-            let res = self.solve_via_implication(canonical_goal, implication, minimums);
-
-            if let (Ok(solution), priority) = res {
-                debug!(?solution, ?priority, "Ok");
-                cur_solution = Some(match cur_solution {
-                    None => (solution, priority),
-                    Some((cur, cur_priority)) => combine::with_priorities(
-                        self.interner(),
-                        &canonical_goal.canonical.value.goal,
-                        cur,
-                        cur_priority,
-                        solution,
-                        priority,
-                    ),
-                });
-            } else {
-                debug!("Error");
+            let program_clause = match self.clauses.next() {
+                Some(program_clause) => program_clause,
+                None => return Step::Done(self.finish()),
+            };
+            debug_span!("solve_from_clauses", clause = ?program_clause);
+
+            if let Some(proof_tree) = solver.proof_tree() {
+                proof_tree.start_candidate(Some(&program_clause));
+            }
+            let ProgramClauseData(implication) = program_clause.data(solver.interner());
+            let (infer, subst, goal) = new_inference_table(solver, &self.canonical_goal);
+            match Fulfill::new_with_clause(solver, infer, subst, goal, implication) {
+                Ok(fulfill) => {
+                    self.candidate = Some((fulfill, implication.skip_binders().priority));
+                }
+                Err(e) => self.finish_candidate(solver, Err(e), ClausePriority::High),
             }
         }
-        cur_solution.map_or((Err(NoSolution), ClausePriority::High), |(s, p)| (Ok(s), p))
     }
 
-    /// Modus ponens! That is: try to apply an implication by proving its premises.
-    #[instrument(level = "info", skip(self, minimums))]
-    fn solve_via_implication(
+    fn finish_candidate<S: SolveDatabase<I>>(
         &mut self,
-        canonical_goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
-        clause: &Binders<ProgramClauseImplication<I>>,
-        minimums: &mut Minimums,
-    ) -> (Fallible<Solution<I>>, ClausePriority) {
-        let (infer, subst, goal) = self.new_inference_table(canonical_goal);
-        match Fulfill::new_with_clause(self, infer, subst, goal, clause) {
-            Ok(fulfill) => (fulfill.solve(minimums), clause.skip_binders().priority),
-            Err(e) => (Err(e), ClausePriority::High),
+        solver: &mut S,
+        result: Fallible<Solution<I>>,
+        priority: ClausePriority,
+    ) {
+        if let Some(proof_tree) = solver.proof_tree() {
+            proof_tree.finish_candidate(&result);
+        }
+
+        if let Ok(solution) = result {
+            debug!(?solution, ?priority, "Ok");
+            self.cur_solution = Some(match self.cur_solution.take() {
+                None => (solution, priority),
+                Some((cur, cur_priority)) => combine::with_priorities(
+                    solver.interner(),
+                    &self.canonical_goal.canonical.value.goal,
+                    cur,
+                    cur_priority,
+                    solution,
+                    priority,
+                ),
+            });
+        } else {
+            debug!("Error");
         }
     }
 
-    fn new_inference_table<T: Fold<I, Result = T> + HasInterner<Interner = I> + Clone>(
-        &self,
-        ucanonical_goal: &UCanonical<InEnvironment<T>>,
-    ) -> (
-        RecursiveInferenceTableImpl<I>,
-        Substitution<I>,
-        InEnvironment<T::Result>,
-    ) {
-        let (infer, subst, canonical_goal) = InferenceTable::from_canonical(
-            self.interner(),
-            ucanonical_goal.universes,
-            &ucanonical_goal.canonical,
-        );
-        let infer = RecursiveInferenceTableImpl { infer };
-        (infer, subst, canonical_goal)
+    fn finish(&mut self) -> (Fallible<Solution<I>>, ClausePriority) {
+        let result = match self.cur_solution.take() {
+            Some((solution, priority)) => {
+                let solution = self.with_goal_ambiguity_cause(solution);
+                (Ok(solution), priority)
+            }
+            None => (Err(NoSolution), ClausePriority::High),
+        };
+        debug!(prog_solution = ?result.0);
+        result
     }
 
-    fn program_clauses_for_goal(
-        &self,
-        canonical_goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
-    ) -> Result<Vec<ProgramClause<I>>, Floundered> {
-        program_clauses_for_goal(
-            self.db(),
-            &canonical_goal.canonical.value.environment,
-            &canonical_goal.canonical.value.goal,
-            &canonical_goal.canonical.binders,
-        )
+    /// If `solution` is ambiguous for no particular reason that its
+    /// subgoals told us, the goal itself may tell us why: e.g. because
+    /// several impls apply to it. Looking into that is costly, so we only
+    /// note the goal here (see `Cause`).
+    fn with_goal_ambiguity_cause(&self, solution: Solution<I>) -> Solution<I> {
+        match &solution {
+            Solution::Ambig(guidance) => {
+                let cause = guidance.cause().clone().or_goal(&self.canonical_goal);
+                solution.with_ambiguity_cause(cause)
+            }
+            _ => solution,
+        }
     }
 }
 
-impl<S, I> SolveIterationHelpers<I> for S
+fn new_inference_table<S, I, T>(
+    solver: &S,
+    ucanonical_goal: &UCanonical<InEnvironment<T>>,
+) -> (
+    RecursiveInferenceTableImpl<I>,
+    Substitution<I>,
+    InEnvironment<T::Result>,
+)
 where
     S: SolveDatabase<I>,
     I: Interner,
+    T: Fold<I, Result = T> + HasInterner<Interner = I> + Clone,
 {
+    let (mut infer, subst, canonical_goal) = InferenceTable::from_canonical(
+        solver.interner(),
+        ucanonical_goal.universes,
+        &ucanonical_goal.canonical,
+    );
+    infer.set_normalization_mode(solver.normalization_mode());
+    let infer = RecursiveInferenceTableImpl { infer };
+    (infer, subst, canonical_goal)
+}
+
+fn program_clauses_for_goal<S: SolveDatabase<I>, I: Interner>(
+    solver: &S,
+    canonical_goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
+) -> Result<Vec<ProgramClause<I>>, ClauseError<I>> {
+    chalk_solve::clauses::program_clauses_for_goal(
+        solver.db(),
+        &canonical_goal.canonical.value.environment,
+        &canonical_goal.canonical.value.goal,
+        &canonical_goal.canonical.binders,
+    )
 }
 
 struct RecursiveInferenceTableImpl<I: Interner> {
@@ -7,6 +7,10 @@ pub(crate) struct Stack {
     // program: Arc<ProgramEnvironment>,
     entries: Vec<StackEntry>,
     overflow_depth: usize,
+
+    /// The goals at depths below this one depend on a goal that was given
+    /// up on because the stack was full (see `mark_overflowed`).
+    overflowed_len: usize,
 }
 
 #[derive(Copy, Clone, Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
@@ -32,24 +36,31 @@ impl Stack {
             // program: program.clone(),
             entries: vec![],
             overflow_depth,
+            overflowed_len: 0,
         }
     }
 
+    pub(crate) fn overflow_depth(&self) -> usize {
+        self.overflow_depth
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
 
+    /// True if no more goals can be pushed without exceeding the overflow
+    /// depth.
+    pub(crate) fn is_full(&self) -> bool {
+        self.entries.len() >= self.overflow_depth
+    }
+
     pub(crate) fn push(&mut self, coinductive_goal: bool) -> StackDepth {
         let depth = StackDepth {
             depth: self.entries.len(),
         };
 
-        if depth.depth >= self.overflow_depth {
-            // This shoudl perhaps be a result or something, though
-            // really I'd prefer to move to subgoal abstraction for
-            // guaranteeing termination. -nmatsakis
-            panic!("overflow depth reached")
-        }
+        // Callers check `is_full` first, and report an overflow instead.
+        assert!(!self.is_full(), "overflow depth reached");
 
         self.entries.push(StackEntry {
             coinductive_goal,
@@ -65,6 +76,26 @@ impl Stack {
             "mismatched stack push/pop"
         );
         self.entries.pop();
+        self.overflowed_len = self.overflowed_len.min(depth.depth);
+    }
+
+    /// Pops every goal at once, e.g. because we are giving up on them.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.overflowed_len = 0;
+    }
+
+    /// Records that all the goals on the stack depend on a goal that
+    /// overflowed it. Their solutions depend on how deep in the stack they
+    /// were solved, so they must not be cached.
+    pub(crate) fn mark_overflowed(&mut self) {
+        self.overflowed_len = self.entries.len();
+    }
+
+    /// True if the goal at the given depth depends on a goal that overflowed
+    /// the stack (see `mark_overflowed`).
+    pub(crate) fn depends_on_overflow(&self, depth: StackDepth) -> bool {
+        depth.depth < self.overflowed_len
     }
 
     /// True if all the goals from the top of the stack down to (and
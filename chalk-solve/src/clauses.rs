@@ -2,14 +2,16 @@ use self::builder::ClauseBuilder;
 use self::env_elaborator::elaborate_env_clauses;
 use self::program_clauses::ToProgramClauses;
 use crate::goal_builder::GoalBuilder;
+use crate::rust_ir::AssociatedTyDatum;
 use crate::split::Split;
-use crate::RustIrDatabase;
+use crate::{LookupError, RustIrDatabase};
 use chalk_ir::cast::{Cast, Caster};
 use chalk_ir::could_match::CouldMatch;
 use chalk_ir::interner::Interner;
 use chalk_ir::*;
 use rustc_hash::FxHashSet;
 use std::iter;
+use std::sync::Arc;
 use tracing::{debug, instrument};
 
 pub mod builder;
@@ -17,16 +19,57 @@ mod builtin_traits;
 mod dyn_ty;
 mod env_elaborator;
 mod generalize;
+mod object_safety;
 pub mod program_clauses;
 
+/// Why we could not enumerate the program clauses for a goal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClauseError<I: Interner> {
+    /// The goal is too general for us to enumerate the clauses that could
+    /// prove it (see [`Floundered`]).
+    Floundered,
+
+    /// The database could not produce the data for an item that the
+    /// clauses are built from (see the `try_*` methods of
+    /// [`RustIrDatabase`]).
+    LookupFailed(LookupError<I>),
+}
+
+impl<I: Interner> From<Floundered> for ClauseError<I> {
+    fn from(_: Floundered) -> Self {
+        ClauseError::Floundered
+    }
+}
+
+impl<I: Interner> From<LookupError<I>> for ClauseError<I> {
+    fn from(error: LookupError<I>) -> Self {
+        ClauseError::LookupFailed(error)
+    }
+}
+
+/// Looks up the datum of an associated type, making sure that the datum of
+/// its trait can be looked up as well: the clauses for the associated type
+/// (and the `Split` methods) consult both.
+fn associated_ty_datum<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    id: AssocTypeId<I>,
+) -> Result<Arc<AssociatedTyDatum<I>>, LookupError<I>> {
+    let associated_ty_datum = db.try_associated_ty_data(id)?;
+    db.try_trait_datum(associated_ty_datum.trait_id)?;
+    Ok(associated_ty_datum)
+}
+
 // yields the types "contained" in `app_ty`
-fn constituent_types<I: Interner>(db: &dyn RustIrDatabase<I>, ty: &TyKind<I>) -> Vec<Ty<I>> {
+fn constituent_types<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    ty: &TyKind<I>,
+) -> Result<Vec<Ty<I>>, LookupError<I>> {
     let interner = db.interner();
 
-    match ty {
+    Ok(match ty {
         // For non-phantom_data adts we collect its variants/fields
-        TyKind::Adt(adt_id, substitution) if !db.adt_datum(*adt_id).flags.phantom_data => {
-            let adt_datum = &db.adt_datum(*adt_id);
+        TyKind::Adt(adt_id, substitution) if !db.try_adt_datum(*adt_id)?.flags.phantom_data => {
+            let adt_datum = &db.try_adt_datum(*adt_id)?;
             let adt_datum_bound = adt_datum.binders.substitute(interner, substitution);
             adt_datum_bound
                 .variants
@@ -50,7 +93,7 @@ fn constituent_types<I: Interner>(db: &dyn RustIrDatabase<I>, ty: &TyKind<I>) ->
         TyKind::Str | TyKind::Never | TyKind::Scalar(_) => Vec::new(),
 
         TyKind::Generator(generator_id, substitution) => {
-            let generator_datum = &db.generator_datum(*generator_id);
+            let generator_datum = &db.try_generator_datum(*generator_id)?;
             let generator_datum_bound = generator_datum
                 .input_output
                 .substitute(interner, &substitution);
@@ -79,7 +122,7 @@ fn constituent_types<I: Interner>(db: &dyn RustIrDatabase<I>, ty: &TyKind<I>) ->
         TyKind::AssociatedType(_, _) => {
             panic!("constituent_types of associated types are unknown!")
         }
-    }
+    })
 }
 
 /// FIXME(#505) update comments for ADTs
@@ -118,17 +161,15 @@ pub fn push_auto_trait_impls<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     auto_trait_id: TraitId<I>,
     ty: &TyKind<I>,
-) -> Result<(), Floundered> {
+) -> Result<(), ClauseError<I>> {
     let interner = builder.interner();
+    let auto_trait_datum = builder.db.try_trait_datum(auto_trait_id)?;
 
     // Must be an auto trait.
-    assert!(builder.db.trait_datum(auto_trait_id).is_auto_trait());
+    assert!(auto_trait_datum.is_auto_trait());
 
     // Auto traits never have generic parameters of their own (apart from `Self`).
-    assert_eq!(
-        builder.db.trait_datum(auto_trait_id).binders.len(interner),
-        1
-    );
+    assert_eq!(auto_trait_datum.binders.len(interner), 1);
 
     // If there is a `impl AutoTrait for Foo<..>` or `impl !AutoTrait
     // for Foo<..>`, where `Foo` is the adt we're looking at, then
@@ -156,7 +197,7 @@ pub fn push_auto_trait_impls<I: Interner>(
             builder.push_fact(auto_trait_ref);
             Ok(())
         }
-        TyKind::InferenceVar(_, _) | TyKind::BoundVar(_) => Err(Floundered),
+        TyKind::InferenceVar(_, _) | TyKind::BoundVar(_) => Err(ClauseError::Floundered),
 
         // auto traits are not implemented for foreign types
         TyKind::Foreign(_) => Ok(()),
@@ -174,12 +215,12 @@ pub fn push_auto_trait_impls<I: Interner>(
         }
 
         TyKind::GeneratorWitness(generator_id, _) => {
-            push_auto_trait_impls_generator_witness(builder, auto_trait_id, *generator_id);
+            push_auto_trait_impls_generator_witness(builder, auto_trait_id, *generator_id)?;
             Ok(())
         }
 
         TyKind::OpaqueType(opaque_ty_id, _) => {
-            push_auto_trait_impls_opaque(builder, auto_trait_id, *opaque_ty_id);
+            push_auto_trait_impls_opaque(builder, auto_trait_id, *opaque_ty_id)?;
             Ok(())
         }
 
@@ -191,7 +232,7 @@ pub fn push_auto_trait_impls<I: Interner>(
 
         // app_ty implements AutoTrait if all constituents of app_ty implement AutoTrait
         _ => {
-            let conditions = constituent_types(builder.db, ty).into_iter().map(mk_ref);
+            let conditions = constituent_types(builder.db, ty)?.into_iter().map(mk_ref);
 
             builder.push_clause(consequence, conditions);
             Ok(())
@@ -219,18 +260,16 @@ pub fn push_auto_trait_impls_opaque<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     auto_trait_id: TraitId<I>,
     opaque_id: OpaqueTyId<I>,
-) {
-    let opaque_ty_datum = &builder.db.opaque_ty_data(opaque_id);
+) -> Result<(), LookupError<I>> {
+    let opaque_ty_datum = &builder.db.try_opaque_ty_data(opaque_id)?;
     let interner = builder.interner();
+    let auto_trait_datum = builder.db.try_trait_datum(auto_trait_id)?;
 
     // Must be an auto trait.
-    assert!(builder.db.trait_datum(auto_trait_id).is_auto_trait());
+    assert!(auto_trait_datum.is_auto_trait());
 
     // Auto traits never have generic parameters of their own (apart from `Self`).
-    assert_eq!(
-        builder.db.trait_datum(auto_trait_id).binders.len(interner),
-        1
-    );
+    assert_eq!(auto_trait_datum.binders.len(interner), 1);
 
     let hidden_ty = builder.db.hidden_opaque_type(opaque_id);
     let binders = opaque_ty_datum.bound.clone();
@@ -253,6 +292,7 @@ pub fn push_auto_trait_impls_opaque<I: Interner>(
             }),
         );
     });
+    Ok(())
 }
 
 #[instrument(level = "debug", skip(builder))]
@@ -260,18 +300,16 @@ pub fn push_auto_trait_impls_generator_witness<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     auto_trait_id: TraitId<I>,
     generator_id: GeneratorId<I>,
-) {
-    let witness_datum = builder.db.generator_witness_datum(generator_id);
+) -> Result<(), LookupError<I>> {
+    let witness_datum = builder.db.try_generator_witness_datum(generator_id)?;
     let interner = builder.interner();
+    let auto_trait_datum = builder.db.try_trait_datum(auto_trait_id)?;
 
     // Must be an auto trait.
-    assert!(builder.db.trait_datum(auto_trait_id).is_auto_trait());
+    assert!(auto_trait_datum.is_auto_trait());
 
     // Auto traits never have generic parameters of their own (apart from `Self`).
-    assert_eq!(
-        builder.db.trait_datum(auto_trait_id).binders.len(interner),
-        1
-    );
+    assert_eq!(auto_trait_datum.binders.len(interner), 1);
 
     // Push binders for the generator generic parameters. These can be used by
     // both upvars and witness types
@@ -332,7 +370,8 @@ pub fn push_auto_trait_impls_generator_witness<I: Interner>(
         // GeneratorWitnessType: AutoTrait :- forall<...> ...
         // where 'forall<...> ...' is the goal described above.
         builder.push_clause(auto_trait_ref, std::iter::once(witness_goal));
-    })
+    });
+    Ok(())
 }
 
 /// Given some goal `goal` that must be proven, along with
@@ -346,7 +385,7 @@ pub fn program_clauses_for_goal<'db, I: Interner>(
     environment: &Environment<I>,
     goal: &DomainGoal<I>,
     binders: &CanonicalVarKinds<I>,
-) -> Result<Vec<ProgramClause<I>>, Floundered> {
+) -> Result<Vec<ProgramClause<I>>, ClauseError<I>> {
     let interner = db.interner();
 
     let custom_clauses = db.custom_clauses().into_iter();
@@ -356,7 +395,7 @@ pub fn program_clauses_for_goal<'db, I: Interner>(
     let clauses: Vec<ProgramClause<I>> = custom_clauses
         .chain(clauses_that_could_match)
         .chain(
-            db.program_clauses_for_env(environment)
+            db.program_clauses_for_env(environment)?
                 .iter(interner)
                 .cloned(),
         )
@@ -382,7 +421,7 @@ fn program_clauses_that_could_match<I: Interner>(
     // passes the canonical goal; the SLG solver instantiates the goal first.
     // (See #568.)
     binders: &CanonicalVarKinds<I>,
-) -> Result<Vec<ProgramClause<I>>, Floundered> {
+) -> Result<Vec<ProgramClause<I>>, ClauseError<I>> {
     let interner = db.interner();
     let mut clauses: Vec<ProgramClause<I>> = vec![];
     let builder = &mut ClauseBuilder::new(db, &mut clauses);
@@ -392,7 +431,7 @@ fn program_clauses_that_could_match<I: Interner>(
             let self_ty = trait_ref.self_type_parameter(interner);
 
             let trait_id = trait_ref.trait_id;
-            let trait_datum = db.trait_datum(trait_id);
+            let trait_datum = db.try_trait_datum(trait_id)?;
 
             match self_ty.kind(interner) {
                 TyKind::Alias(alias) => {
@@ -405,12 +444,12 @@ fn program_clauses_that_could_match<I: Interner>(
 
                 _ if self_ty.is_general_var(interner, binders) => {
                     if trait_datum.is_non_enumerable_trait() || trait_datum.is_auto_trait() {
-                        return Err(Floundered);
+                        return Err(ClauseError::Floundered);
                     }
                 }
 
                 TyKind::OpaqueType(opaque_ty_id, _) => {
-                    db.opaque_ty_data(*opaque_ty_id)
+                    db.try_opaque_ty_data(*opaque_ty_id)?
                         .to_program_clauses(builder, environment);
                 }
 
@@ -426,16 +465,16 @@ fn program_clauses_that_could_match<I: Interner>(
                     // Fn(&u8): Clone)`, we would still generate two clauses that are
                     // totally irrelevant to that goal, because they let us prove other
                     // things but not `Clone`.
-                    dyn_ty::build_dyn_self_ty_clauses(db, builder, self_ty.clone())
+                    dyn_ty::build_dyn_self_ty_clauses(db, builder, self_ty.clone())?
                 }
 
                 // We don't actually do anything here, but we need to record the types when logging
                 TyKind::Adt(adt_id, _) => {
-                    let _ = db.adt_datum(*adt_id);
+                    db.try_adt_datum(*adt_id)?;
                 }
 
                 TyKind::FnDef(fn_def_id, _) => {
-                    let _ = db.fn_def_datum(*fn_def_id);
+                    db.try_fn_def_datum(*fn_def_id)?;
                 }
 
                 _ => {}
@@ -450,13 +489,12 @@ fn program_clauses_that_could_match<I: Interner>(
                 trait_ref.substitution.as_slice(interner),
                 binders,
             ) {
-                db.impl_datum(impl_id)
+                db.try_impl_datum(impl_id)?
                     .to_program_clauses(builder, environment);
             }
 
             // If this is a `Foo: Send` (or any auto-trait), then add
             // the automatic impls for `Foo`.
-            let trait_datum = db.trait_datum(trait_id);
             if trait_datum.is_auto_trait() {
                 let generalized = generalize::Generalize::apply(db.interner(), trait_ref);
                 builder.push_binders(&generalized, |builder, trait_ref| {
@@ -473,6 +511,7 @@ fn program_clauses_that_could_match<I: Interner>(
         }
         DomainGoal::Holds(WhereClause::AliasEq(alias_eq)) => match &alias_eq.alias {
             AliasTy::Projection(proj) => {
+                let associated_ty_datum = associated_ty_datum(db, proj.associated_ty_id)?;
                 let trait_self_ty = db
                     .trait_ref_from_projection(proj)
                     .self_type_parameter(interner);
@@ -486,23 +525,22 @@ fn program_clauses_that_could_match<I: Interner>(
                         return Ok(clauses);
                     }
                     TyKind::OpaqueType(opaque_ty_id, _) => {
-                        db.opaque_ty_data(*opaque_ty_id)
+                        db.try_opaque_ty_data(*opaque_ty_id)?
                             .to_program_clauses(builder, environment);
                     }
                     // If the self type is a `dyn trait` type, generate program-clauses
                     // for any associated type bindings it contains.
                     // FIXME: see the fixme for the analogous code for Implemented goals.
                     TyKind::Dyn(_) => {
-                        dyn_ty::build_dyn_self_ty_clauses(db, builder, trait_self_ty.clone())
+                        dyn_ty::build_dyn_self_ty_clauses(db, builder, trait_self_ty.clone())?
                     }
                     _ => {}
                 }
 
-                db.associated_ty_data(proj.associated_ty_id)
-                    .to_program_clauses(builder, environment)
+                associated_ty_datum.to_program_clauses(builder, environment)
             }
             AliasTy::Opaque(opaque_ty) => db
-                .opaque_ty_data(opaque_ty.opaque_ty_id)
+                .try_opaque_ty_data(opaque_ty.opaque_ty_id)?
                 .to_program_clauses(builder, environment),
         },
         DomainGoal::Holds(WhereClause::LifetimeOutlives(..)) => {
@@ -515,7 +553,7 @@ fn program_clauses_that_could_match<I: Interner>(
                         })),
                         Some(InEnvironment::new(
                             environment,
-                            Constraint::LifetimeOutlives(a, b),
+                            Constraint::LifetimeOutlives(a, b, ConstraintOrigin::Builtin),
                         )),
                     );
                 })
@@ -531,7 +569,7 @@ fn program_clauses_that_could_match<I: Interner>(
                         })),
                         Some(InEnvironment::new(
                             environment,
-                            Constraint::TypeOutlives(ty, lifetime),
+                            Constraint::TypeOutlives(ty, lifetime, ConstraintOrigin::Builtin),
                         )),
                     )
                 })
@@ -539,19 +577,29 @@ fn program_clauses_that_could_match<I: Interner>(
         }
         DomainGoal::WellFormed(WellFormed::Trait(trait_ref))
         | DomainGoal::LocalImplAllowed(trait_ref) => {
-            db.trait_datum(trait_ref.trait_id)
+            db.try_trait_datum(trait_ref.trait_id)?
                 .to_program_clauses(builder, environment);
         }
         DomainGoal::ObjectSafe(trait_id) => {
-            if builder.db.is_object_safe(*trait_id) {
-                builder.push_fact(DomainGoal::ObjectSafe(*trait_id));
-            }
+            object_safety::add_object_safe_program_clauses(db, builder, *trait_id)?;
         }
         DomainGoal::WellFormed(WellFormed::Ty(ty))
         | DomainGoal::IsUpstream(ty)
         | DomainGoal::DownstreamType(ty)
         | DomainGoal::IsFullyVisible(ty)
         | DomainGoal::IsLocal(ty) => match_ty(builder, environment, ty)?,
+        DomainGoal::WellFormed(WellFormed::Const(constant)) => match_const(builder, constant)?,
+        DomainGoal::ConstHasType(constant, _) => {
+            // A constant has exactly the type it was created with:
+            // `ConstHasType(c, T)` where `T` is the type of `c`.
+            let interner = builder.interner();
+            let ty = constant.data(interner).ty.clone();
+            let generalized = generalize::Generalize::apply(
+                interner,
+                &DomainGoal::ConstHasType(constant.clone(), ty),
+            );
+            builder.push_binders(&generalized, |builder, goal| builder.push_fact(goal));
+        }
         DomainGoal::FromEnv(_) => (), // Computed in the environment
         DomainGoal::Normalize(Normalize { alias, ty: _ }) => match alias {
             AliasTy::Projection(proj) => {
@@ -566,11 +614,11 @@ fn program_clauses_that_could_match<I: Interner>(
                 //     type Item = Bar; // <-- associated type value
                 // }
                 // ```
-                let associated_ty_datum = db.associated_ty_data(proj.associated_ty_id);
+                let associated_ty_datum = associated_ty_datum(db, proj.associated_ty_id)?;
                 let trait_id = associated_ty_datum.trait_id;
                 let trait_parameters = db.trait_parameters_from_projection(proj);
 
-                let trait_datum = db.trait_datum(trait_id);
+                let trait_datum = db.try_trait_datum(trait_id)?;
 
                 let self_ty = alias.self_type_parameter(interner);
 
@@ -580,7 +628,7 @@ fn program_clauses_that_could_match<I: Interner>(
                 if (self_ty.is_general_var(interner, binders))
                     && trait_datum.is_non_enumerable_trait()
                 {
-                    return Err(Floundered);
+                    return Err(ClauseError::Floundered);
                 }
 
                 if let Some(well_known) = trait_datum.well_known {
@@ -595,7 +643,7 @@ fn program_clauses_that_could_match<I: Interner>(
                     trait_id,
                     trait_parameters,
                     binders,
-                );
+                )?;
 
                 if environment.has_compatible_clause(interner) {
                     push_clauses_for_compatible_normalize(
@@ -604,7 +652,7 @@ fn program_clauses_that_could_match<I: Interner>(
                         interner,
                         trait_id,
                         proj.associated_ty_id,
-                    );
+                    )?;
                 }
             }
             AliasTy::Opaque(_) => (),
@@ -630,8 +678,8 @@ fn push_clauses_for_compatible_normalize<I: Interner>(
     interner: &I,
     trait_id: TraitId<I>,
     associated_ty_id: AssocTypeId<I>,
-) {
-    let trait_datum = db.trait_datum(trait_id);
+) -> Result<(), LookupError<I>> {
+    let trait_datum = db.try_trait_datum(trait_id)?;
     let trait_binders = trait_datum.binders.map_ref(|b| &b.where_clauses);
     builder.push_binders(&trait_binders, |builder, where_clauses| {
         let projection = ProjectionTy {
@@ -670,6 +718,7 @@ fn push_clauses_for_compatible_normalize<I: Interner>(
             }
         });
     });
+    Ok(())
 }
 
 /// Generate program clauses from the associated-type values
@@ -693,12 +742,12 @@ fn push_program_clauses_for_associated_type_values_in_impls_of<I: Interner>(
     trait_id: TraitId<I>,
     trait_parameters: &[GenericArg<I>],
     binders: &CanonicalVarKinds<I>,
-) {
+) -> Result<(), LookupError<I>> {
     for impl_id in builder
         .db
         .impls_for_trait(trait_id, trait_parameters, binders)
     {
-        let impl_datum = builder.db.impl_datum(impl_id);
+        let impl_datum = builder.db.try_impl_datum(impl_id)?;
         if !impl_datum.is_positive() {
             continue;
         }
@@ -706,11 +755,13 @@ fn push_program_clauses_for_associated_type_values_in_impls_of<I: Interner>(
         debug!(?impl_id);
 
         for &atv_id in &impl_datum.associated_ty_value_ids {
-            let atv = builder.db.associated_ty_value(atv_id);
+            let atv = builder.db.try_associated_ty_value(atv_id)?;
             debug!(?atv_id, ?atv);
+            associated_ty_datum(builder.db, atv.associated_ty_id)?;
             atv.to_program_clauses(builder, environment);
         }
     }
+    Ok(())
 }
 
 fn push_alias_implemented_clause<I: Interner>(
@@ -827,25 +878,24 @@ fn match_ty<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     environment: &Environment<I>,
     ty: &Ty<I>,
-) -> Result<(), Floundered> {
+) -> Result<(), ClauseError<I>> {
     let interner = builder.interner();
     Ok(match ty.kind(interner) {
         TyKind::Adt(adt_id, _) => builder
             .db
-            .adt_datum(*adt_id)
+            .try_adt_datum(*adt_id)?
             .to_program_clauses(builder, environment),
         TyKind::OpaqueType(opaque_ty_id, _) => builder
             .db
-            .opaque_ty_data(*opaque_ty_id)
+            .try_opaque_ty_data(*opaque_ty_id)?
             .to_program_clauses(builder, environment),
         TyKind::Error => {}
-        TyKind::AssociatedType(type_id, _) => builder
-            .db
-            .associated_ty_data(*type_id)
-            .to_program_clauses(builder, environment),
+        TyKind::AssociatedType(type_id, _) => {
+            associated_ty_datum(builder.db, *type_id)?.to_program_clauses(builder, environment)
+        }
         TyKind::FnDef(fn_def_id, _) => builder
             .db
-            .fn_def_datum(*fn_def_id)
+            .try_fn_def_datum(*fn_def_id)?
             .to_program_clauses(builder, environment),
         TyKind::Tuple(_, _)
         | TyKind::Scalar(_)
@@ -862,18 +912,18 @@ fn match_ty<I: Interner>(
         TyKind::Placeholder(_) => {
             builder.push_clause(WellFormed::Ty(ty.clone()), Some(FromEnv::Ty(ty.clone())));
         }
-        TyKind::Alias(AliasTy::Projection(proj)) => builder
-            .db
-            .associated_ty_data(proj.associated_ty_id)
-            .to_program_clauses(builder, environment),
+        TyKind::Alias(AliasTy::Projection(proj)) => {
+            associated_ty_datum(builder.db, proj.associated_ty_id)?
+                .to_program_clauses(builder, environment)
+        }
         TyKind::Alias(AliasTy::Opaque(opaque_ty)) => builder
             .db
-            .opaque_ty_data(opaque_ty.opaque_ty_id)
+            .try_opaque_ty_data(opaque_ty.opaque_ty_id)?
             .to_program_clauses(builder, environment),
         TyKind::Function(_quantified_ty) => {
             builder.push_fact(WellFormed::Ty(ty.clone()));
         }
-        TyKind::BoundVar(_) | TyKind::InferenceVar(_, _) => return Err(Floundered),
+        TyKind::BoundVar(_) | TyKind::InferenceVar(_, _) => return Err(ClauseError::Floundered),
         TyKind::Dyn(dyn_ty) => {
             // FIXME(#203)
             // - Object safety? (not needed with RFC 2027)
@@ -908,24 +958,58 @@ fn match_ty<I: Interner>(
     })
 }
 
+/// Examine `WellFormed(c)` goals for a constant `c`:
+///
+/// - concrete constants and const parameters are always well-formed;
+/// - an unevaluated constant is well-formed if the interner can evaluate
+///   it. If it is too generic to be evaluated, it can only be well-formed
+///   because the environment says so.
+fn match_const<I: Interner>(
+    builder: &mut ClauseBuilder<'_, I>,
+    constant: &Const<I>,
+) -> Result<(), Floundered> {
+    let interner = builder.interner();
+    let ConstData { ty, value } = constant.data(interner);
+    match value {
+        ConstValue::Concrete(_) | ConstValue::Placeholder(_) => {
+            builder.push_fact(WellFormed::Const(constant.clone()))
+        }
+        ConstValue::Unevaluated(unevaluated) => {
+            if interner
+                .try_evaluate_const(ty.interned(), unevaluated)
+                .is_ok()
+            {
+                let generalized =
+                    generalize::Generalize::apply(interner, &WellFormed::Const(constant.clone()));
+                builder.push_binders(&generalized, |builder, goal| builder.push_fact(goal));
+            }
+        }
+        ConstValue::BoundVar(_) | ConstValue::InferenceVar(_) => return Err(Floundered),
+    }
+    Ok(())
+}
+
 fn match_alias_ty<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     environment: &Environment<I>,
     alias: &AliasTy<I>,
-) {
+) -> Result<(), LookupError<I>> {
     match alias {
-        AliasTy::Projection(projection_ty) => builder
-            .db
-            .associated_ty_data(projection_ty.associated_ty_id)
-            .to_program_clauses(builder, environment),
+        AliasTy::Projection(projection_ty) => {
+            associated_ty_datum(builder.db, projection_ty.associated_ty_id)?
+                .to_program_clauses(builder, environment)
+        }
         _ => (),
     }
+    Ok(())
 }
 
+/// The clauses of `environment`, together with the bounds they imply. Fails
+/// if an item that the environment mentions cannot be looked up.
 pub fn program_clauses_for_env<'db, I: Interner>(
     db: &'db dyn RustIrDatabase<I>,
     environment: &Environment<I>,
-) -> ProgramClauses<I> {
+) -> Result<ProgramClauses<I>, LookupError<I>> {
     let mut last_round = environment
         .clauses
         .as_slice(db.interner())
@@ -940,7 +1024,7 @@ pub fn program_clauses_for_env<'db, I: Interner>(
             &last_round.drain().collect::<Vec<_>>(),
             &mut next_round,
             environment,
-        );
+        )?;
         last_round.extend(
             next_round
                 .drain()
@@ -948,5 +1032,5 @@ pub fn program_clauses_for_env<'db, I: Interner>(
         );
     }
 
-    ProgramClauses::from_iter(db.interner(), closure)
+    Ok(ProgramClauses::from_iter(db.interner(), closure))
 }
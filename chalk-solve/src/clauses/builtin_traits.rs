@@ -1,11 +1,17 @@
-use super::{builder::ClauseBuilder, generalize};
+use super::{builder::ClauseBuilder, generalize, ClauseError};
 use crate::{CanonicalVarKinds, Interner, RustIrDatabase, TraitRef, WellKnownTrait};
-use chalk_ir::{Floundered, Substitution, Ty};
+use chalk_ir::{Substitution, Ty};
 
 mod clone;
 mod copy;
+mod discriminant_kind;
+mod dispatch_from_dyn;
 mod fn_family;
+mod fn_ptr;
+mod generator;
+mod pointee;
 mod sized;
+mod tuple;
 mod unsize;
 
 /// For well known traits we have special hard-coded impls, either as an
@@ -16,7 +22,7 @@ pub fn add_builtin_program_clauses<I: Interner>(
     well_known: WellKnownTrait,
     trait_ref: &TraitRef<I>,
     binders: &CanonicalVarKinds<I>,
-) -> Result<(), Floundered> {
+) -> Result<(), ClauseError<I>> {
     // If `trait_ref` contains bound vars, we want to universally quantify them.
     // `Generalize` collects them for us.
     let generalized = generalize::Generalize::apply(db.interner(), trait_ref);
@@ -27,7 +33,7 @@ pub fn add_builtin_program_clauses<I: Interner>(
 
         match well_known {
             WellKnownTrait::Sized => {
-                sized::add_sized_program_clauses(db, builder, &trait_ref, ty, binders)
+                sized::add_sized_program_clauses(db, builder, &trait_ref, ty, binders)?
             }
             WellKnownTrait::Copy => {
                 copy::add_copy_program_clauses(db, builder, &trait_ref, ty, binders)
@@ -39,7 +45,19 @@ pub fn add_builtin_program_clauses<I: Interner>(
                 fn_family::add_fn_trait_program_clauses(db, builder, well_known, self_ty)?
             }
             WellKnownTrait::Unsize => {
-                unsize::add_unsize_program_clauses(db, builder, &trait_ref, ty)
+                unsize::add_unsize_program_clauses(db, builder, &trait_ref, ty)?
+            }
+            WellKnownTrait::DiscriminantKind => {
+                discriminant_kind::add_discriminant_clauses(db, builder, self_ty)?
+            }
+            WellKnownTrait::Generator => {
+                generator::add_generator_program_clauses(db, builder, self_ty)?
+            }
+            WellKnownTrait::Pointee => pointee::add_pointee_program_clauses(db, builder, self_ty)?,
+            WellKnownTrait::Tuple => tuple::add_tuple_program_clauses(builder, &trait_ref, ty)?,
+            WellKnownTrait::FnPtr => fn_ptr::add_fn_ptr_program_clauses(db, builder, self_ty)?,
+            WellKnownTrait::DispatchFromDyn => {
+                dispatch_from_dyn::add_dispatch_from_dyn_program_clauses(db, builder, &trait_ref)
             }
             // There are no builtin impls provided for the following traits:
             WellKnownTrait::Unpin | WellKnownTrait::Drop | WellKnownTrait::CoerceUnsized => (),
@@ -55,7 +73,7 @@ pub fn add_builtin_assoc_program_clauses<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     well_known: WellKnownTrait,
     self_ty: Ty<I>,
-) -> Result<(), Floundered> {
+) -> Result<(), ClauseError<I>> {
     match well_known {
         WellKnownTrait::FnOnce => {
             // If `self_ty` contains bound vars, we want to universally quantify them.
@@ -63,8 +81,35 @@ pub fn add_builtin_assoc_program_clauses<I: Interner>(
             let generalized = generalize::Generalize::apply(db.interner(), &self_ty);
 
             builder.push_binders(&generalized, |builder, self_ty| {
-                fn_family::add_fn_trait_program_clauses(db, builder, well_known, self_ty)?;
-                Ok(())
+                fn_family::add_fn_trait_program_clauses(db, builder, well_known, self_ty)
+            })
+        }
+        WellKnownTrait::DiscriminantKind => {
+            let generalized = generalize::Generalize::apply(db.interner(), &self_ty);
+
+            builder.push_binders(&generalized, |builder, self_ty| {
+                discriminant_kind::add_discriminant_clauses(db, builder, self_ty)
+            })
+        }
+        WellKnownTrait::Generator => {
+            let generalized = generalize::Generalize::apply(db.interner(), &self_ty);
+
+            builder.push_binders(&generalized, |builder, self_ty| {
+                generator::add_generator_program_clauses(db, builder, self_ty)
+            })
+        }
+        WellKnownTrait::Pointee => {
+            let generalized = generalize::Generalize::apply(db.interner(), &self_ty);
+
+            builder.push_binders(&generalized, |builder, self_ty| {
+                pointee::add_pointee_program_clauses(db, builder, self_ty)
+            })
+        }
+        WellKnownTrait::FnPtr => {
+            let generalized = generalize::Generalize::apply(db.interner(), &self_ty);
+
+            builder.push_binders(&generalized, |builder, self_ty| {
+                fn_ptr::add_fn_ptr_program_clauses(db, builder, self_ty)
             })
         }
         _ => Ok(()),
@@ -0,0 +1,88 @@
+use crate::clauses::{ClauseBuilder, ClauseError};
+use crate::rust_ir::{AdtKind, WellKnownTrait};
+use crate::{Interner, RustIrDatabase, TraitRef};
+use chalk_ir::{AliasTy, IntTy, Normalize, ProjectionTy, Scalar, Substitution, Ty, TyKind, UintTy};
+
+/// Handles clauses for `DiscriminantKind`. Every type implements the trait,
+/// so we always push the fact `Implemented(T: DiscriminantKind)`.
+///
+/// If the discriminant type of `self_ty` is known, we also push a clause of
+/// the form `Normalize(<T as DiscriminantKind>::Discriminant -> D)`, where `D`
+/// is the integer type given by the `repr` of an enum (`isize` by default),
+/// and `u8` for every other type whose discriminant is known.
+pub fn add_discriminant_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    self_ty: Ty<I>,
+) -> Result<(), ClauseError<I>> {
+    let interner = db.interner();
+
+    let trait_id = db
+        .well_known_trait_id(WellKnownTrait::DiscriminantKind)
+        .unwrap();
+    let substitution = Substitution::from1(interner, self_ty.clone());
+    builder.push_fact(TraitRef {
+        trait_id,
+        substitution: substitution.clone(),
+    });
+
+    let discriminant_ty = match self_ty.kind(interner) {
+        TyKind::Adt(adt_id, _) => {
+            let adt_datum = db.try_adt_datum(*adt_id)?;
+            if adt_datum.kind == AdtKind::Enum {
+                db.adt_repr(*adt_id)
+                    .int
+                    .clone()
+                    .unwrap_or_else(|| TyKind::Scalar(Scalar::Int(IntTy::Isize)).intern(interner))
+            } else {
+                TyKind::Scalar(Scalar::Uint(UintTy::U8)).intern(interner)
+            }
+        }
+
+        TyKind::Scalar(_)
+        | TyKind::Tuple(..)
+        | TyKind::Array(..)
+        | TyKind::Slice(_)
+        | TyKind::Raw(..)
+        | TyKind::Ref(..)
+        | TyKind::FnDef(..)
+        | TyKind::Str
+        | TyKind::Never
+        | TyKind::Closure(..)
+        | TyKind::Generator(..)
+        | TyKind::GeneratorWitness(..)
+        | TyKind::Foreign(_)
+        | TyKind::Function(_)
+        | TyKind::Dyn(_) => TyKind::Scalar(Scalar::Uint(UintTy::U8)).intern(interner),
+
+        // We can't tell which type this is yet.
+        TyKind::BoundVar(_) | TyKind::InferenceVar(..) => return Err(ClauseError::Floundered),
+
+        // The discriminant of these types is not known (an opaque type may
+        // hide an enum with any repr), so the projection stays unnormalized.
+        TyKind::Placeholder(_)
+        | TyKind::OpaqueType(..)
+        | TyKind::AssociatedType(..)
+        | TyKind::Alias(_)
+        | TyKind::Error => return Ok(()),
+    };
+
+    let trait_datum = db.trait_datum(trait_id);
+    assert_eq!(
+        trait_datum.associated_ty_ids.len(),
+        1,
+        "DiscriminantKind trait should have exactly one associated type, found {:?}",
+        trait_datum.associated_ty_ids
+    );
+    let associated_ty_id = trait_datum.associated_ty_ids[0];
+
+    builder.push_fact(Normalize {
+        alias: AliasTy::Projection(ProjectionTy {
+            associated_ty_id,
+            substitution,
+        }),
+        ty: discriminant_ty,
+    });
+
+    Ok(())
+}
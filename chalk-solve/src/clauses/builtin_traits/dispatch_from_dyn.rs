@@ -0,0 +1,64 @@
+use crate::clauses::ClauseBuilder;
+use crate::rust_ir::WellKnownTrait;
+use crate::{Interner, RustIrDatabase, TraitRef};
+use chalk_ir::{Substitution, TyKind};
+
+/// Handles clauses for `DispatchFromDyn` on builtin pointer types. For
+/// references and raw pointers with matching mutability (and lifetime),
+/// we push the clauses:
+///
+/// - `Implemented(&'a T: DispatchFromDyn<&'a U>) :- Implemented(T: Unsize<U>)`
+/// - `Implemented(&'a mut T: DispatchFromDyn<&'a mut U>) :- Implemented(T: Unsize<U>)`
+/// - `Implemented(*const T: DispatchFromDyn<*const U>) :- Implemented(T: Unsize<U>)`
+/// - `Implemented(*mut T: DispatchFromDyn<*mut U>) :- Implemented(T: Unsize<U>)`
+///
+/// Impls for user-defined smart pointers are checked in `wf.rs`.
+pub fn add_dispatch_from_dyn_program_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    trait_ref: &TraitRef<I>,
+) {
+    let interner = db.interner();
+
+    let source_ty = trait_ref.self_type_parameter(interner);
+    let target_ty = trait_ref
+        .substitution
+        .at(interner, 1)
+        .assert_ty_ref(interner);
+
+    let unsize_trait_id = match db.well_known_trait_id(WellKnownTrait::Unsize) {
+        Some(id) => id,
+        None => return,
+    };
+
+    // Rebuild the target with the source's lifetime, so that the head of the
+    // clause only unifies with the goal if both lifetimes are equal.
+    let (source_pointee, target_pointee, target_ty) =
+        match (source_ty.kind(interner), target_ty.kind(interner)) {
+            (TyKind::Ref(m_a, lifetime, a), TyKind::Ref(m_b, _, b)) if m_a == m_b => (
+                a,
+                b,
+                TyKind::Ref(*m_b, lifetime.clone(), b.clone()).intern(interner),
+            ),
+            (TyKind::Raw(m_a, a), TyKind::Raw(m_b, b)) if m_a == m_b => (a, b, target_ty.clone()),
+            _ => return,
+        };
+
+    let unsize_goal = TraitRef {
+        trait_id: unsize_trait_id,
+        substitution: Substitution::from_iter(
+            interner,
+            [source_pointee.clone(), target_pointee.clone()]
+                .iter()
+                .cloned(),
+        ),
+    };
+
+    builder.push_clause(
+        TraitRef {
+            trait_id: trait_ref.trait_id,
+            substitution: Substitution::from_iter(interner, [source_ty, target_ty].iter().cloned()),
+        },
+        Some(unsize_goal),
+    );
+}
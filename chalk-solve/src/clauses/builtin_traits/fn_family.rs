@@ -1,10 +1,9 @@
-use crate::clauses::ClauseBuilder;
+use crate::clauses::{ClauseBuilder, ClauseError};
 use crate::rust_ir::{ClosureKind, FnDefInputsAndOutputDatum, WellKnownTrait};
 use crate::{Interner, RustIrDatabase, TraitRef};
 use chalk_ir::cast::Cast;
 use chalk_ir::{
-    AliasTy, Binders, Floundered, Normalize, ProjectionTy, Safety, Substitution, TraitId, Ty,
-    TyKind,
+    AliasTy, Binders, Normalize, ProjectionTy, Safety, Substitution, TraitId, Ty, TyKind,
 };
 
 fn push_clauses<I: Interner>(
@@ -86,13 +85,13 @@ pub fn add_fn_trait_program_clauses<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     well_known: WellKnownTrait,
     self_ty: Ty<I>,
-) -> Result<(), Floundered> {
+) -> Result<(), ClauseError<I>> {
     let interner = db.interner();
     let trait_id = db.well_known_trait_id(well_known).unwrap();
 
     match self_ty.kind(interner) {
         TyKind::FnDef(fn_def_id, substitution) => {
-            let fn_def_datum = builder.db.fn_def_datum(*fn_def_id);
+            let fn_def_datum = builder.db.try_fn_def_datum(*fn_def_id)?;
             if fn_def_datum.sig.safety == Safety::Safe && !fn_def_datum.sig.variadic {
                 let bound = fn_def_datum
                     .binders
@@ -156,7 +155,7 @@ pub fn add_fn_trait_program_clauses<I: Interner>(
             Ok(())
         }
         // Function traits are non-enumerable
-        TyKind::InferenceVar(..) | TyKind::Alias(..) => Err(Floundered),
+        TyKind::InferenceVar(..) | TyKind::Alias(..) => Err(ClauseError::Floundered),
         _ => Ok(()),
     }
 }
@@ -0,0 +1,68 @@
+use crate::clauses::{ClauseBuilder, ClauseError};
+use crate::rust_ir::WellKnownTrait;
+use crate::{Interner, RustIrDatabase, TraitRef};
+use chalk_ir::{AliasTy, Normalize, ProjectionTy, Substitution, Ty, TyKind};
+
+/// Handles clauses for `FnPtr`, which is implemented exactly by function
+/// pointer types, whatever their signature. If `self_ty` is a function
+/// pointer `for<'a..> fn(A0, .., An) -> R`, we push the clauses:
+///
+/// - `Implemented(for<'a..> fn(A0, .., An) -> R: FnPtr)`
+/// - `forall<'a..> Normalize(<for<'a..> fn(A0, .., An) -> R as FnPtr>::Args -> (A0, .., An))`
+/// - `forall<'a..> Normalize(<for<'a..> fn(A0, .., An) -> R as FnPtr>::Output -> R)`
+pub fn add_fn_ptr_program_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    self_ty: Ty<I>,
+) -> Result<(), ClauseError<I>> {
+    let interner = db.interner();
+
+    match self_ty.kind(interner) {
+        TyKind::Function(fn_val) => {
+            let trait_id = db.well_known_trait_id(WellKnownTrait::FnPtr).unwrap();
+            let trait_datum = db.trait_datum(trait_id);
+            assert_eq!(
+                trait_datum.associated_ty_ids.len(),
+                2,
+                "FnPtr trait should have exactly two associated types, found {:?}",
+                trait_datum.associated_ty_ids
+            );
+
+            let substitution = Substitution::from1(interner, self_ty.clone());
+            builder.push_fact(TraitRef {
+                trait_id,
+                substitution: substitution.clone(),
+            });
+
+            let args_id = trait_datum.associated_ty_ids[0];
+            let output_id = trait_datum.associated_ty_ids[1];
+            builder.push_binders(&fn_val.as_binders(interner), |builder, sig| {
+                // The last parameter represents the function return type
+                let (args, output) = sig.0.as_slice(interner).split_at(sig.0.len(interner) - 1);
+                let args = Substitution::from_iter(interner, args);
+                let output_ty = output[0].assert_ty_ref(interner).clone();
+
+                builder.push_fact(Normalize {
+                    alias: AliasTy::Projection(ProjectionTy {
+                        associated_ty_id: args_id,
+                        substitution: substitution.clone(),
+                    }),
+                    ty: TyKind::Tuple(args.len(interner), args).intern(interner),
+                });
+                builder.push_fact(Normalize {
+                    alias: AliasTy::Projection(ProjectionTy {
+                        associated_ty_id: output_id,
+                        substitution: substitution.clone(),
+                    }),
+                    ty: output_ty,
+                });
+            });
+            Ok(())
+        }
+        // We can't tell whether this is a function pointer yet.
+        TyKind::InferenceVar(..) | TyKind::BoundVar(_) | TyKind::Alias(..) => {
+            Err(ClauseError::Floundered)
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,76 @@
+use crate::clauses::{ClauseBuilder, ClauseError};
+use crate::rust_ir::WellKnownTrait;
+use crate::{Interner, RustIrDatabase, TraitRef};
+use chalk_ir::cast::Cast;
+use chalk_ir::{AliasTy, Normalize, ProjectionTy, Substitution, Ty, TyKind};
+
+/// Handles clauses for `Generator`.
+/// If `self_ty` is a generator `G` with resume type `R`, yield type `Y` and
+/// return type `O`, we push the clauses:
+///
+/// - `Implemented(G: Generator<R>)`
+/// - `Normalize(<G as Generator<R>>::Yield -> Y)`
+/// - `Normalize(<G as Generator<R>>::Return -> O)`
+pub fn add_generator_program_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    self_ty: Ty<I>,
+) -> Result<(), ClauseError<I>> {
+    let interner = db.interner();
+
+    match self_ty.kind(interner) {
+        TyKind::Generator(generator_id, substitution) => {
+            let generator_datum = db.try_generator_datum(*generator_id)?;
+            let input_output = generator_datum
+                .input_output
+                .substitute(interner, substitution);
+
+            let trait_id = db.well_known_trait_id(WellKnownTrait::Generator).unwrap();
+            let trait_datum = db.trait_datum(trait_id);
+            assert_eq!(
+                trait_datum.associated_ty_ids.len(),
+                2,
+                "Generator trait should have exactly two associated types, found {:?}",
+                trait_datum.associated_ty_ids
+            );
+
+            let substitution = Substitution::from_iter(
+                interner,
+                &[
+                    self_ty.clone().cast(interner),
+                    input_output.resume_type.cast(interner),
+                ],
+            );
+
+            builder.push_fact(TraitRef {
+                trait_id,
+                substitution: substitution.clone(),
+            });
+
+            let yield_id = trait_datum.associated_ty_ids[0];
+            builder.push_fact(Normalize {
+                alias: AliasTy::Projection(ProjectionTy {
+                    associated_ty_id: yield_id,
+                    substitution: substitution.clone(),
+                }),
+                ty: input_output.yield_type,
+            });
+
+            let return_id = trait_datum.associated_ty_ids[1];
+            builder.push_fact(Normalize {
+                alias: AliasTy::Projection(ProjectionTy {
+                    associated_ty_id: return_id,
+                    substitution,
+                }),
+                ty: input_output.return_type,
+            });
+
+            Ok(())
+        }
+        // The generator trait is non-enumerable
+        TyKind::InferenceVar(..) | TyKind::BoundVar(_) | TyKind::Alias(..) => {
+            Err(ClauseError::Floundered)
+        }
+        _ => Ok(()),
+    }
+}
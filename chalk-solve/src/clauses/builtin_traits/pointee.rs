@@ -0,0 +1,164 @@
+use crate::clauses::ClauseBuilder;
+use crate::clauses::ClauseError;
+use crate::rust_ir::{AdtKind, WellKnownAdt, WellKnownTrait};
+use crate::{Interner, LookupError, RustIrDatabase, TraitRef};
+use chalk_ir::{
+    AliasTy, AssocTypeId, Normalize, ProjectionTy, Scalar, Substitution, Ty, TyKind, UintTy,
+};
+
+/// Returns `<ty as Pointee>::Metadata`.
+fn metadata_projection<I: Interner>(
+    interner: &I,
+    metadata_id: AssocTypeId<I>,
+    ty: Ty<I>,
+) -> AliasTy<I> {
+    AliasTy::Projection(ProjectionTy {
+        associated_ty_id: metadata_id,
+        substitution: Substitution::from1(interner, ty),
+    })
+}
+
+/// Returns the type whose metadata `self_ty` shares, i.e. the last field of a
+/// struct or the last element of a tuple. Returns `None` if `self_ty` has no
+/// such tail, in which case it is sized.
+fn tail_ty<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    self_ty: &Ty<I>,
+) -> Result<Option<Ty<I>>, LookupError<I>> {
+    let interner = db.interner();
+    Ok(match self_ty.kind(interner) {
+        TyKind::Adt(adt_id, substitution) => {
+            let adt_datum = db.try_adt_datum(*adt_id)?;
+            if adt_datum.kind != AdtKind::Struct {
+                return Ok(None);
+            }
+            // We have a struct so we're guaranteed one variant
+            adt_datum
+                .binders
+                .map_ref(|b| &b.variants)
+                .substitute(interner, substitution)
+                .into_iter()
+                .take(1)
+                .flat_map(|mut v| v.fields.pop())
+                .next()
+        }
+        TyKind::Tuple(_, substitution) => substitution
+            .iter(interner)
+            .last()
+            .map(|ty| ty.assert_ty_ref(interner).clone()),
+        _ => None,
+    })
+}
+
+/// Handles clauses for `Pointee`. Every type implements the trait, so we
+/// always push the fact `Implemented(T: Pointee)`.
+///
+/// For the associated type `Metadata`, we push a clause
+/// `Normalize(<T as Pointee>::Metadata -> M)` where `M` is
+///
+/// - `()` for sized types,
+/// - `usize` for slices and `str`,
+/// - `DynMetadata<dyn Trait>` for `dyn Trait`,
+/// - `<Tail as Pointee>::Metadata` for structs and tuples, where `Tail` is
+///   the type of their last field (mirroring how `Sized` and `Unsize` look
+///   at struct tails).
+///
+/// For type parameters, opaque types and the like, we can only tell that
+/// the metadata is `()` if they are `Sized`.
+pub fn add_pointee_program_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    self_ty: Ty<I>,
+) -> Result<(), ClauseError<I>> {
+    let interner = db.interner();
+
+    let trait_id = db.well_known_trait_id(WellKnownTrait::Pointee).unwrap();
+    builder.push_fact(TraitRef {
+        trait_id,
+        substitution: Substitution::from1(interner, self_ty.clone()),
+    });
+
+    let trait_datum = db.trait_datum(trait_id);
+    assert_eq!(
+        trait_datum.associated_ty_ids.len(),
+        1,
+        "Pointee trait should have exactly one associated type, found {:?}",
+        trait_datum.associated_ty_ids
+    );
+    let metadata_id = trait_datum.associated_ty_ids[0];
+    let alias = metadata_projection(interner, metadata_id, self_ty.clone());
+    let unit = TyKind::Tuple(0, Substitution::empty(interner)).intern(interner);
+
+    match self_ty.kind(interner) {
+        TyKind::Adt(..) | TyKind::Tuple(..) => match tail_ty(db, &self_ty)? {
+            // The metadata of a struct or tuple is the metadata of its tail:
+            // `Normalize(<S as Pointee>::Metadata -> M) :-
+            //     Normalize(<Tail as Pointee>::Metadata -> M)`
+            Some(tail) => builder.push_bound_ty(|builder, metadata_ty| {
+                builder.push_clause(
+                    Normalize {
+                        alias,
+                        ty: metadata_ty.clone(),
+                    },
+                    Some(Normalize {
+                        alias: metadata_projection(interner, metadata_id, tail),
+                        ty: metadata_ty,
+                    }),
+                );
+            }),
+            None => builder.push_fact(Normalize { alias, ty: unit }),
+        },
+
+        TyKind::Slice(_) | TyKind::Str => builder.push_fact(Normalize {
+            alias,
+            ty: TyKind::Scalar(Scalar::Uint(UintTy::Usize)).intern(interner),
+        }),
+
+        TyKind::Dyn(_) => {
+            if let Some(dyn_metadata_id) = db.well_known_adt_id(WellKnownAdt::DynMetadata) {
+                builder.push_fact(Normalize {
+                    alias,
+                    ty: TyKind::Adt(
+                        dyn_metadata_id,
+                        Substitution::from1(interner, self_ty.clone()),
+                    )
+                    .intern(interner),
+                });
+            }
+        }
+
+        TyKind::Scalar(_)
+        | TyKind::Array(..)
+        | TyKind::Raw(..)
+        | TyKind::Ref(..)
+        | TyKind::FnDef(..)
+        | TyKind::Never
+        | TyKind::Closure(..)
+        | TyKind::Generator(..)
+        | TyKind::GeneratorWitness(..)
+        | TyKind::Foreign(_)
+        | TyKind::Function(_) => builder.push_fact(Normalize { alias, ty: unit }),
+
+        // We can't tell which type this is yet.
+        TyKind::BoundVar(_) | TyKind::InferenceVar(..) => return Err(ClauseError::Floundered),
+
+        // `Normalize(<T as Pointee>::Metadata -> ()) :- T: Sized`
+        TyKind::Placeholder(_) | TyKind::OpaqueType(..) | TyKind::AssociatedType(..) => {
+            if let Some(sized_id) = db.well_known_trait_id(WellKnownTrait::Sized) {
+                builder.push_clause(
+                    Normalize { alias, ty: unit },
+                    Some(TraitRef {
+                        trait_id: sized_id,
+                        substitution: Substitution::from1(interner, self_ty.clone()),
+                    }),
+                );
+            }
+        }
+
+        // The metadata of these types is not known, so the projection
+        // stays unnormalized.
+        TyKind::Alias(_) | TyKind::Error => {}
+    }
+
+    Ok(())
+}
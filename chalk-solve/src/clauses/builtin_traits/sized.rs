@@ -3,7 +3,7 @@ use std::iter;
 use crate::clauses::builtin_traits::needs_impl_for_tys;
 use crate::clauses::ClauseBuilder;
 use crate::rust_ir::AdtKind;
-use crate::{Interner, RustIrDatabase, TraitRef};
+use crate::{Interner, LookupError, RustIrDatabase, TraitRef};
 use chalk_ir::{AdtId, CanonicalVarKinds, Substitution, TyKind, TyVariableKind, VariableKind};
 
 fn push_adt_sized_conditions<I: Interner>(
@@ -12,13 +12,13 @@ fn push_adt_sized_conditions<I: Interner>(
     trait_ref: &TraitRef<I>,
     adt_id: AdtId<I>,
     substitution: &Substitution<I>,
-) {
-    let adt_datum = db.adt_datum(adt_id);
+) -> Result<(), LookupError<I>> {
+    let adt_datum = db.try_adt_datum(adt_id)?;
 
     // WF ensures that all enums are Sized, so we only have to consider structs.
     if adt_datum.kind != AdtKind::Struct {
         builder.push_fact(trait_ref.clone());
-        return;
+        return Ok(());
     }
 
     let interner = db.interner();
@@ -34,6 +34,7 @@ fn push_adt_sized_conditions<I: Interner>(
         .flat_map(|mut v| v.fields.pop());
 
     needs_impl_for_tys(db, builder, trait_ref, last_field_ty);
+    Ok(())
 }
 
 fn push_tuple_sized_conditions<I: Interner>(
@@ -70,10 +71,10 @@ pub fn add_sized_program_clauses<I: Interner>(
     trait_ref: &TraitRef<I>,
     ty: &TyKind<I>,
     binders: &CanonicalVarKinds<I>,
-) {
+) -> Result<(), LookupError<I>> {
     match ty {
         TyKind::Adt(adt_id, substitution) => {
-            push_adt_sized_conditions(db, builder, trait_ref, *adt_id, substitution)
+            push_adt_sized_conditions(db, builder, trait_ref, *adt_id, substitution)?
         }
         TyKind::Tuple(arity, substitution) => {
             push_tuple_sized_conditions(db, builder, trait_ref, *arity, substitution)
@@ -113,4 +114,5 @@ pub fn add_sized_program_clauses<I: Interner>(
         | TyKind::Dyn(_)
         | TyKind::Alias(_) => {}
     }
+    Ok(())
 }
@@ -0,0 +1,25 @@
+use crate::clauses::ClauseBuilder;
+use crate::{Interner, TraitRef};
+use chalk_ir::{Floundered, TyKind};
+
+/// Handles clauses for the `Tuple` marker trait, which is implemented exactly
+/// by tuple types:
+///
+/// ```notrust
+/// Implemented((T0, .., Tn): Tuple)
+/// ```
+pub fn add_tuple_program_clauses<I: Interner>(
+    builder: &mut ClauseBuilder<'_, I>,
+    trait_ref: &TraitRef<I>,
+    ty: &TyKind<I>,
+) -> Result<(), Floundered> {
+    match ty {
+        TyKind::Tuple(..) => builder.push_fact(trait_ref.clone()),
+        // We can't tell whether this is a tuple yet.
+        TyKind::BoundVar(_) | TyKind::InferenceVar(..) | TyKind::Alias(_) => {
+            return Err(Floundered)
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -1,15 +1,17 @@
 use std::collections::HashSet;
 use std::iter;
 
+use crate::clauses::dyn_ty::super_traits;
 use crate::clauses::ClauseBuilder;
 use crate::rust_ir::AdtKind;
-use crate::{Interner, RustIrDatabase, TraitRef, WellKnownTrait};
+use crate::{Interner, LookupError, RustIrDatabase, TraitRef, WellKnownTrait};
 use chalk_ir::{
     cast::Cast,
     interner::HasInterner,
     visit::{ControlFlow, SuperVisit, Visit, Visitor},
-    Binders, Const, ConstValue, DebruijnIndex, DomainGoal, DynTy, EqGoal, Goal, LifetimeOutlives,
-    QuantifiedWhereClauses, Substitution, TraitId, Ty, TyKind, TypeOutlives, WhereClause,
+    AliasEq, AliasTy, Binders, BoundVar, Const, ConstValue, DebruijnIndex, DomainGoal, DynTy,
+    EqGoal, Goal, Lifetime, LifetimeOutlives, QuantifiedWhereClause, QuantifiedWhereClauses,
+    Substitution, TraitId, Ty, TyKind, TypeOutlives, WhereClause,
 };
 
 struct UnsizeParameterCollector<'a, I: Interner> {
@@ -134,30 +136,188 @@ fn uses_outer_binder_params<I: Interner>(
         .is_break()
 }
 
-fn principal_id<'a, I: Interner>(
+/// The principal trait of a trait object (if any) and its auto traits.
+type PrincipalAndAutoTraitIds<I> = (Option<TraitId<I>>, Vec<TraitId<I>>);
+
+/// Splits the traits of a trait object into its principal trait and its auto
+/// traits.
+fn principal_and_auto_trait_ids<I: Interner>(
     db: &dyn RustIrDatabase<I>,
-    bounds: &'a Binders<QuantifiedWhereClauses<I>>,
-) -> Option<TraitId<I>> {
+    bounds: &Binders<QuantifiedWhereClauses<I>>,
+) -> Result<PrincipalAndAutoTraitIds<I>, LookupError<I>> {
     let interner = db.interner();
 
-    bounds
+    let mut principal_id = None;
+    let mut auto_trait_ids = Vec::new();
+    for id in bounds
         .skip_binders()
         .iter(interner)
         .filter_map(|b| b.trait_id())
-        .find(|&id| !db.trait_datum(id).is_auto_trait())
+    {
+        if db.try_trait_datum(id)?.is_auto_trait() {
+            auto_trait_ids.push(id);
+        } else if principal_id.is_none() {
+            principal_id = Some(id);
+        }
+    }
+    Ok((principal_id, auto_trait_ids))
 }
 
-fn auto_trait_ids<'a, I: Interner>(
-    db: &'a dyn RustIrDatabase<I>,
-    bounds: &'a Binders<QuantifiedWhereClauses<I>>,
-) -> impl Iterator<Item = TraitId<I>> + 'a {
+/// Pushes the clauses for upcasting a trait object `dyn Sub + AutoX + 'a` to
+/// `dyn Super + AutoY + 'b`, where `Super` is a supertrait of `Sub`. There is
+/// one clause for each way in which `Super` is reached from `Sub`.
+///
+/// As with coercions between trait objects with the same principal, we build
+/// the upcasted source type and require it to be equal to the target type. In
+/// the upcasted type:
+///
+/// - the principal `Sub<..>` is replaced with the corresponding `Super<..>`;
+/// - only the associated type bindings for `Super` are kept. These are the
+///   bindings that appear on the source type itself, plus those implied by
+///   the where clauses of `Sub` and of the traits between `Sub` and `Super`
+///   (e.g. `trait Sub: Super<Assoc = u32>`);
+/// - auto traits that are not in the target are dropped;
+/// - the lifetime is the one of the target, and we require `'a: 'b`.
+#[allow(clippy::too_many_arguments)]
+fn push_dyn_upcast_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    trait_ref: &TraitRef<I>,
+    (bounds_a, principal_a): (&Binders<QuantifiedWhereClauses<I>>, TraitId<I>),
+    (target_ty, principal_b): (&Ty<I>, TraitId<I>),
+    auto_trait_ids_b: &[TraitId<I>],
+    lifetime_b: &Lifetime<I>,
+    lifetime_outlives_goal: Goal<I>,
+) -> Result<(), LookupError<I>> {
     let interner = db.interner();
 
-    bounds
+    // Whether `bound` is an associated type binding for the target principal.
+    let binds_principal_b = |bound: &WhereClause<I>| match bound {
+        WhereClause::AliasEq(AliasEq {
+            alias: AliasTy::Projection(proj),
+            ..
+        }) => Ok(db.try_associated_ty_data(proj.associated_ty_id)?.trait_id == principal_b),
+        _ => Ok(false),
+    };
+
+    // All the supertraits of `Sub` (including `Sub` itself), quantified over
+    // the parameters of `Sub`, and over any higher-ranked lifetimes that were
+    // introduced while walking the supertraits.
+    let super_trait_refs = super_traits(db, principal_a)?;
+
+    // The associated type bindings for `Super` implied by the where clauses
+    // of `Sub` and its supertraits, quantified in the same way. Inside the
+    // where clauses, `Self` is `^1.0`, because we are one binder in.
+    let mut implied_bindings: Vec<Binders<QuantifiedWhereClause<I>>> = Vec::new();
+    for q_super_trait_ref in super_trait_refs.clone() {
+        let trait_datum = db.trait_datum(q_super_trait_ref.skip_binders().skip_binders().trait_id);
+        let where_clauses = &trait_datum.binders.skip_binders().where_clauses;
+        for (i, qwc) in where_clauses.iter().enumerate() {
+            let wc = qwc.skip_binders();
+            let binds_self = match wc {
+                WhereClause::AliasEq(AliasEq { alias, .. }) => {
+                    alias.self_type_parameter(interner).bound_var(interner)
+                        == Some(BoundVar::new(DebruijnIndex::ONE, 0))
+                }
+                _ => false,
+            };
+            if !binds_self || !binds_principal_b(wc)? {
+                continue;
+            }
+
+            let where_clause = trait_datum
+                .binders
+                .map_ref(|bound| bound.where_clauses[i].clone());
+            implied_bindings.push(q_super_trait_ref.map_ref(|q_trait_ref| {
+                q_trait_ref
+                    .map_ref(|trait_ref| where_clause.substitute(interner, &trait_ref.substitution))
+                    .fuse_binders(interner)
+            }));
+        }
+    }
+
+    // Which of the bounds of the source type bind associated types of `Super`.
+    let source_binds_principal_b = bounds_a
         .skip_binders()
         .iter(interner)
-        .filter_map(|clause| clause.trait_id())
-        .filter(move |&id| db.trait_datum(id).is_auto_trait())
+        .map(|bound| binds_principal_b(bound.skip_binders()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for q_super_trait_ref in super_trait_refs {
+        if q_super_trait_ref.skip_binders().skip_binders().trait_id != principal_b {
+            continue;
+        }
+
+        // Build the bounds of the upcasted type. The bounds of the source
+        // type live under the `dyn` binder for `Self`; the principal bound
+        // `Sub<..>` has its own binders for higher-ranked lifetimes, which
+        // we fuse with the binders of the supertrait ref.
+        let new_bounds = bounds_a.map_ref(|bounds| {
+            let q_principal_ref = bounds
+                .iter(interner)
+                .find(|bound| bound.trait_id() == Some(principal_a))
+                .unwrap()
+                .map_ref(|bound| match bound {
+                    WhereClause::Implemented(trait_ref) => trait_ref.clone(),
+                    _ => unreachable!(),
+                });
+
+            // Substitutes the parameters of the principal into something that
+            // is quantified over the parameters of `Sub`.
+            let rebind = |q_value: &Binders<Binders<WhereClause<I>>>| {
+                q_principal_ref
+                    .map_ref(|principal_ref| {
+                        q_value.substitute(interner, &principal_ref.substitution)
+                    })
+                    .fuse_binders(interner)
+            };
+
+            let new_principal = rebind(&q_super_trait_ref.map_ref(|q_trait_ref| {
+                q_trait_ref.map_ref(|trait_ref| WhereClause::Implemented(trait_ref.clone()))
+            }));
+
+            let mut new_bounds = vec![new_principal];
+            new_bounds.extend(
+                implied_bindings.iter().map(rebind).chain(
+                    bounds
+                        .iter(interner)
+                        .zip(&source_binds_principal_b)
+                        .filter(|(_, &binds)| binds)
+                        .map(|(bound, _)| bound.clone()),
+                ),
+            );
+            new_bounds.dedup();
+            new_bounds.extend(
+                bounds
+                    .iter(interner)
+                    .filter(|bound| match bound.trait_id() {
+                        Some(trait_id) => auto_trait_ids_b.contains(&trait_id),
+                        None => false,
+                    })
+                    .cloned(),
+            );
+            QuantifiedWhereClauses::from_iter(interner, new_bounds)
+        });
+
+        let new_source_ty = TyKind::Dyn(DynTy {
+            bounds: new_bounds,
+            lifetime: lifetime_b.clone(),
+        })
+        .intern(interner);
+
+        // Check that the upcasted source is equal to target
+        let eq_goal: Goal<I> = EqGoal {
+            a: new_source_ty.cast(interner),
+            b: target_ty.clone().cast(interner),
+        }
+        .cast(interner);
+
+        builder.push_clause(
+            trait_ref.clone(),
+            [eq_goal, lifetime_outlives_goal.clone()].iter(),
+        );
+    }
+    Ok(())
 }
 
 pub fn add_unsize_program_clauses<I: Interner>(
@@ -165,7 +325,7 @@ pub fn add_unsize_program_clauses<I: Interner>(
     builder: &mut ClauseBuilder<'_, I>,
     trait_ref: &TraitRef<I>,
     _ty: &TyKind<I>,
-) {
+) -> Result<(), LookupError<I>> {
     let interner = db.interner();
 
     let source_ty = trait_ref.self_type_parameter(interner);
@@ -198,19 +358,40 @@ pub fn add_unsize_program_clauses<I: Interner>(
                 lifetime: lifetime_b,
             }),
         ) => {
-            let principal_a = principal_id(db, bounds_a);
-            let principal_b = principal_id(db, bounds_b);
+            let (principal_a, auto_trait_ids_a) = principal_and_auto_trait_ids(db, bounds_a)?;
+            let (principal_b, auto_trait_ids_b) = principal_and_auto_trait_ids(db, bounds_b)?;
 
-            let auto_trait_ids_a: Vec<_> = auto_trait_ids(db, bounds_a).collect();
-            let auto_trait_ids_b: Vec<_> = auto_trait_ids(db, bounds_b).collect();
-
-            let may_apply = principal_a == principal_b
-                && auto_trait_ids_b
-                    .iter()
-                    .all(|id_b| auto_trait_ids_a.iter().any(|id_a| id_a == id_b));
+            let may_apply = auto_trait_ids_b
+                .iter()
+                .all(|id_b| auto_trait_ids_a.iter().any(|id_a| id_a == id_b));
 
             if !may_apply {
-                return;
+                return Ok(());
+            }
+
+            // Check that source lifetime outlives target lifetime
+            let lifetime_outlives_goal: Goal<I> = WhereClause::LifetimeOutlives(LifetimeOutlives {
+                a: lifetime_a.clone(),
+                b: lifetime_b.clone(),
+            })
+            .cast(interner);
+
+            match (principal_a, principal_b) {
+                // dyn Sub + AutoX + 'a -> dyn Super + AutoY + 'b
+                (Some(principal_a), Some(principal_b)) if principal_a != principal_b => {
+                    return push_dyn_upcast_clauses(
+                        db,
+                        builder,
+                        trait_ref,
+                        (bounds_a, principal_a),
+                        (target_ty, principal_b),
+                        &auto_trait_ids_b,
+                        lifetime_b,
+                        lifetime_outlives_goal,
+                    );
+                }
+                (principal_a, principal_b) if principal_a != principal_b => return Ok(()),
+                _ => {}
             }
 
             // COMMENT FROM RUSTC:
@@ -264,13 +445,6 @@ pub fn add_unsize_program_clauses<I: Interner>(
             }
             .cast(interner);
 
-            // Check that source lifetime outlives target lifetime
-            let lifetime_outlives_goal: Goal<I> = WhereClause::LifetimeOutlives(LifetimeOutlives {
-                a: lifetime_a.clone(),
-                b: lifetime_b.clone(),
-            })
-            .cast(interner);
-
             builder.push_clause(trait_ref.clone(), [eq_goal, lifetime_outlives_goal].iter());
         }
 
@@ -327,15 +501,15 @@ pub fn add_unsize_program_clauses<I: Interner>(
         // Adt<T> -> Adt<U>
         (TyKind::Adt(adt_id_a, substitution_a), TyKind::Adt(adt_id_b, substitution_b)) => {
             if adt_id_a != adt_id_b {
-                return;
+                return Ok(());
             }
 
             let adt_id = *adt_id_a;
-            let adt_datum = db.adt_datum(adt_id);
+            let adt_datum = db.try_adt_datum(adt_id)?;
 
             // Unsizing of enums is not allowed
             if adt_datum.kind == AdtKind::Enum {
-                return;
+                return Ok(());
             }
 
             // We have a `struct` so we're guaranteed a single variant
@@ -349,7 +523,7 @@ pub fn add_unsize_program_clauses<I: Interner>(
                 .len();
 
             if fields_len == 0 {
-                return;
+                return Ok(());
             }
 
             let adt_tail_field = adt_datum
@@ -362,7 +536,7 @@ pub fn add_unsize_program_clauses<I: Interner>(
                 outer_binder_parameters_used(interner, &adt_tail_field);
 
             if unsize_parameter_candidates.is_empty() {
-                return;
+                return Ok(());
             }
             // Ensure none of the other fields mention the parameters used
             // in unsizing.
@@ -375,7 +549,7 @@ pub fn add_unsize_program_clauses<I: Interner>(
                     .map_ref(|bound| &bound.variants.last().unwrap().fields[..fields_len - 1]),
                 &unsize_parameter_candidates,
             ) {
-                return;
+                return Ok(());
             }
 
             let parameters_a = substitution_a.as_slice(interner);
@@ -431,7 +605,7 @@ pub fn add_unsize_program_clauses<I: Interner>(
         // (.., T) -> (.., U)
         (TyKind::Tuple(arity_a, substitution_a), TyKind::Tuple(arity_b, substitution_b)) => {
             if arity_a != arity_b || *arity_a == 0 {
-                return;
+                return Ok(());
             }
             let arity = arity_a;
 
@@ -477,4 +651,5 @@ pub fn add_unsize_program_clauses<I: Interner>(
 
         _ => (),
     }
+    Ok(())
 }
@@ -1,7 +1,7 @@
 use rustc_hash::FxHashSet;
 
 use super::{builder::ClauseBuilder, generalize};
-use crate::RustIrDatabase;
+use crate::{LookupError, RustIrDatabase};
 use chalk_ir::{
     cast::Cast, fold::shift::Shift, interner::Interner, Binders, BoundVar, DebruijnIndex, TraitId,
     TraitRef, Ty, TyKind, WhereClause,
@@ -38,11 +38,11 @@ pub(super) fn build_dyn_self_ty_clauses<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     builder: &mut ClauseBuilder<'_, I>,
     self_ty: Ty<I>,
-) {
+) -> Result<(), LookupError<I>> {
     let interner = db.interner();
     let dyn_ty = match self_ty.kind(interner) {
         TyKind::Dyn(dyn_ty) => dyn_ty,
-        _ => return,
+        _ => return Ok(()),
     };
     let generalized_dyn_ty = generalize::Generalize::apply(db.interner(), dyn_ty);
 
@@ -65,18 +65,22 @@ pub(super) fn build_dyn_self_ty_clauses<I: Interner>(
             // ```
             let qwc = exists_qwc.substitute(interner, &[self_ty.clone().cast(interner)]);
 
-            builder.push_binders(&qwc, |builder, wc| match &wc {
-                // For the implemented traits, we need to elaborate super traits and add where clauses from the trait
-                WhereClause::Implemented(trait_ref) => {
-                    push_dyn_ty_impl_clauses(db, builder, trait_ref.clone())
+            builder.push_binders(&qwc, |builder, wc| {
+                match &wc {
+                    // For the implemented traits, we need to elaborate super traits and add where clauses from the trait
+                    WhereClause::Implemented(trait_ref) => {
+                        push_dyn_ty_impl_clauses(db, builder, trait_ref.clone())?
+                    }
+                    // Associated item bindings are just taken as facts (?)
+                    WhereClause::AliasEq(_) => builder.push_fact(wc),
+                    WhereClause::LifetimeOutlives(..) => {}
+                    WhereClause::TypeOutlives(..) => {}
                 }
-                // Associated item bindings are just taken as facts (?)
-                WhereClause::AliasEq(_) => builder.push_fact(wc),
-                WhereClause::LifetimeOutlives(..) => {}
-                WhereClause::TypeOutlives(..) => {}
-            });
+                Ok(())
+            })?;
         }
-    });
+        Ok(())
+    })
 }
 
 /// Generate `Implemented` clauses for a `dyn Trait` type. We need to generate
@@ -86,7 +90,7 @@ fn push_dyn_ty_impl_clauses<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     builder: &mut ClauseBuilder<'_, I>,
     trait_ref: TraitRef<I>,
-) {
+) -> Result<(), LookupError<I>> {
     let interner = db.interner();
     // We have some `dyn Trait`, and some `trait SuperTrait: WC`
     // which is a super trait of `Trait` (including actually
@@ -94,7 +98,7 @@ fn push_dyn_ty_impl_clauses<I: Interner>(
     // `Implemented(dyn Trait: SuperTrait) :- WC`.
 
     let super_trait_refs =
-        super_traits(db, trait_ref.trait_id).substitute(interner, &trait_ref.substitution);
+        super_traits(db, trait_ref.trait_id)?.substitute(interner, &trait_ref.substitution);
 
     for q_super_trait_ref in super_trait_refs {
         builder.push_binders(&q_super_trait_ref, |builder, super_trait_ref| {
@@ -105,15 +109,20 @@ fn push_dyn_ty_impl_clauses<I: Interner>(
             builder.push_clause(super_trait_ref, wc);
         });
     }
+    Ok(())
 }
 
+/// The trait refs of a trait and of all its supertraits, quantified over the
+/// parameters of the trait (see `super_traits`).
+type SuperTraitRefs<I> = Binders<Vec<Binders<TraitRef<I>>>>;
+
 pub fn super_traits<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     trait_id: TraitId<I>,
-) -> Binders<Vec<Binders<TraitRef<I>>>> {
+) -> Result<SuperTraitRefs<I>, LookupError<I>> {
     let interner = db.interner();
     let mut seen_traits = FxHashSet::default();
-    let trait_datum = db.trait_datum(trait_id);
+    let trait_datum = db.try_trait_datum(trait_id)?;
     let trait_ref = Binders::empty(
         db.interner(),
         TraitRef {
@@ -125,22 +134,22 @@ pub fn super_traits<I: Interner>(
         },
     );
     let mut trait_refs = Vec::new();
-    go(db, trait_ref, &mut seen_traits, &mut trait_refs);
+    go(db, trait_ref, &mut seen_traits, &mut trait_refs)?;
 
     fn go<I: Interner>(
         db: &dyn RustIrDatabase<I>,
         trait_ref: Binders<TraitRef<I>>,
         seen_traits: &mut FxHashSet<TraitId<I>>,
         trait_refs: &mut Vec<Binders<TraitRef<I>>>,
-    ) {
+    ) -> Result<(), LookupError<I>> {
         let interner = db.interner();
         let trait_id = trait_ref.skip_binders().trait_id;
         // Avoid cycles
         if !seen_traits.insert(trait_id) {
-            return;
+            return Ok(());
         }
         trait_refs.push(trait_ref.clone());
-        let trait_datum = db.trait_datum(trait_id);
+        let trait_datum = db.try_trait_datum(trait_id)?;
         let super_trait_refs = trait_datum
             .binders
             .map_ref(|td| {
@@ -177,10 +186,14 @@ pub fn super_traits<I: Interner>(
             // binders of super_trait_ref.
             let actual_binders = Binders::new(trait_ref.binders.clone(), q_super_trait_ref);
             let q_super_trait_ref = actual_binders.fuse_binders(interner);
-            go(db, q_super_trait_ref, seen_traits, trait_refs);
+            go(db, q_super_trait_ref, seen_traits, trait_refs)?;
         }
         seen_traits.remove(&trait_id);
+        Ok(())
     }
 
-    Binders::new(trait_datum.binders.binders.clone(), trait_refs)
+    Ok(Binders::new(
+        trait_datum.binders.binders.clone(),
+        trait_refs,
+    ))
 }
@@ -1,12 +1,12 @@
 use super::program_clauses::ToProgramClauses;
 use crate::clauses::builder::ClauseBuilder;
-use crate::clauses::{match_alias_ty, match_ty};
+use crate::clauses::{associated_ty_datum, match_alias_ty, match_ty, ClauseError};
 use crate::DomainGoal;
 use crate::FromEnv;
 use crate::ProgramClause;
-use crate::RustIrDatabase;
 use crate::Ty;
 use crate::{debug_span, TyKind};
+use crate::{LookupError, RustIrDatabase};
 use chalk_ir::interner::Interner;
 use chalk_ir::visit::{ControlFlow, Visit, Visitor};
 use chalk_ir::{DebruijnIndex, Environment};
@@ -19,18 +19,24 @@ use tracing::instrument;
 /// For example, when `T: Clone` is in the environment, we can prove
 /// `T: Copy` by adding the clauses from `trait Clone`, which includes
 /// the rule `FromEnv(T: Copy) :- FromEnv(T: Clone)
+///
+/// Fails if an item that the environment mentions cannot be looked up, as
+/// the bounds it implies would then be missing.
 pub(super) fn elaborate_env_clauses<I: Interner>(
     db: &dyn RustIrDatabase<I>,
     in_clauses: &[ProgramClause<I>],
     out: &mut FxHashSet<ProgramClause<I>>,
     environment: &Environment<I>,
-) {
+) -> Result<(), LookupError<I>> {
     let mut this_round = vec![];
-    in_clauses.visit_with(
+    if let ControlFlow::Break(error) = in_clauses.visit_with(
         &mut EnvElaborator::new(db, &mut this_round, environment),
         DebruijnIndex::INNERMOST,
-    );
+    ) {
+        return Err(error);
+    }
     out.extend(this_round);
+    Ok(())
 }
 
 struct EnvElaborator<'me, I: Interner> {
@@ -54,7 +60,7 @@ impl<'me, I: Interner> EnvElaborator<'me, I> {
 }
 
 impl<'me, I: Interner> Visitor<'me, I> for EnvElaborator<'me, I> {
-    type BreakTy = ();
+    type BreakTy = LookupError<I>;
 
     fn as_dyn(&mut self) -> &mut dyn Visitor<'me, I, BreakTy = Self::BreakTy> {
         self
@@ -63,11 +69,18 @@ impl<'me, I: Interner> Visitor<'me, I> for EnvElaborator<'me, I> {
     fn interner(&self) -> &'me I {
         self.db.interner()
     }
+
     #[instrument(level = "debug", skip(self, _outer_binder))]
-    fn visit_ty(&mut self, ty: &Ty<I>, _outer_binder: DebruijnIndex) -> ControlFlow<()> {
+    fn visit_ty(
+        &mut self,
+        ty: &Ty<I>,
+        _outer_binder: DebruijnIndex,
+    ) -> ControlFlow<LookupError<I>> {
         match ty.kind(self.interner()) {
             TyKind::Alias(alias_ty) => {
-                match_alias_ty(&mut self.builder, self.environment, alias_ty)
+                if let Err(error) = match_alias_ty(&mut self.builder, self.environment, alias_ty) {
+                    return ControlFlow::Break(error);
+                }
             }
             TyKind::Placeholder(_) => {}
 
@@ -78,10 +91,12 @@ impl<'me, I: Interner> Visitor<'me, I> for EnvElaborator<'me, I> {
             TyKind::Function(_) | TyKind::BoundVar(_) | TyKind::InferenceVar(_, _) => (),
 
             _ => {
-                // This shouldn't fail because of the above clauses
-                match_ty(&mut self.builder, self.environment, &ty)
-                    .map_err(|_| ())
-                    .unwrap()
+                match match_ty(&mut self.builder, self.environment, &ty) {
+                    Ok(()) => {}
+                    Err(ClauseError::LookupFailed(error)) => return ControlFlow::Break(error),
+                    // This shouldn't happen because of the above clauses
+                    Err(ClauseError::Floundered) => panic!("unexpected floundering"),
+                }
             }
         }
         ControlFlow::CONTINUE
@@ -91,12 +106,15 @@ impl<'me, I: Interner> Visitor<'me, I> for EnvElaborator<'me, I> {
         &mut self,
         domain_goal: &DomainGoal<I>,
         outer_binder: DebruijnIndex,
-    ) -> ControlFlow<()> {
+    ) -> ControlFlow<LookupError<I>> {
         if let DomainGoal::FromEnv(from_env) = domain_goal {
             debug_span!("visit_domain_goal", ?from_env);
             match from_env {
                 FromEnv::Trait(trait_ref) => {
-                    let trait_datum = self.db.trait_datum(trait_ref.trait_id);
+                    let trait_datum = match self.db.try_trait_datum(trait_ref.trait_id) {
+                        Ok(trait_datum) => trait_datum,
+                        Err(error) => return ControlFlow::Break(error),
+                    };
 
                     trait_datum.to_program_clauses(&mut self.builder, self.environment);
 
@@ -104,9 +122,11 @@ impl<'me, I: Interner> Visitor<'me, I> for EnvElaborator<'me, I> {
                     // things about `<T as Iterator>::Item`, so push those
                     // implied bounds too:
                     for &associated_ty_id in &trait_datum.associated_ty_ids {
-                        self.db
-                            .associated_ty_data(associated_ty_id)
-                            .to_program_clauses(&mut self.builder, self.environment);
+                        match associated_ty_datum(self.db, associated_ty_id) {
+                            Ok(associated_ty_datum) => associated_ty_datum
+                                .to_program_clauses(&mut self.builder, self.environment),
+                            Err(error) => return ControlFlow::Break(error),
+                        }
                     }
                     ControlFlow::CONTINUE
                 }
@@ -0,0 +1,382 @@
+use super::builder::ClauseBuilder;
+use crate::rust_ir::{TraitDatumBound, TraitMethodDatum, WellKnownTrait};
+use crate::{LookupError, RustIrDatabase};
+use chalk_ir::cast::Cast;
+use chalk_ir::interner::Interner;
+use chalk_ir::visit::{ControlFlow, SuperVisit, Visit, Visitor};
+use chalk_ir::*;
+use rustc_hash::FxHashSet;
+
+/// Generates the clause for `ObjectSafe(Trait)`. If the database knows
+/// whether `Trait` is object safe, this is simply a fact (or nothing).
+/// Otherwise we compute object safety from the trait definition, mirroring
+/// rustc's object safety rules: a trait is object safe if
+///
+/// - it is not `Sized` and does not require `Self: Sized`;
+/// - its where clauses only mention `Self` as the self type;
+/// - it has no associated consts, and its associated types have no
+///   parameters of their own;
+/// - all of its direct supertraits are object safe;
+/// - every method that does not require `Self: Sized` has no type or const
+///   parameters of its own, only mentions `Self` in its receiver or through
+///   associated types of the trait and its supertraits, and has a
+///   dispatchable receiver.
+///
+/// A receiver of type `Self` is always dispatchable. Any other receiver `R`
+/// is dispatchable if it can be coerced to the receiver of a trait object,
+/// i.e. if the following goal holds (mirroring rustc's
+/// `receiver_is_dispatchable`):
+///
+/// ```notrust
+/// forall<P0..Pn, M0..Mm, U> {
+///     if (FromEnv(Self: Trait<P1..Pn>), Self: Unsize<U>, FromEnv(U: Trait<P1..Pn>), WC) {
+///         R: DispatchFromDyn<R[Self := U]>
+///     }
+/// }
+/// ```
+///
+/// where `P0..Pn` are the parameters of the trait (`P0` is `Self`),
+/// `M0..Mm` the lifetime parameters of the method and `WC` its where
+/// clauses. So the clause looks like:
+///
+/// ```notrust
+/// ObjectSafe(Trait) :- ObjectSafe(Super0), .., ObjectSafe(SuperK), G0, .., Gj
+/// ```
+///
+/// with one goal `G` of the above form per method with a non-`Self` receiver.
+pub(super) fn add_object_safe_program_clauses<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    builder: &mut ClauseBuilder<'_, I>,
+    trait_id: TraitId<I>,
+) -> Result<(), LookupError<I>> {
+    match db.is_object_safe(trait_id) {
+        Some(true) => {
+            builder.push_fact(DomainGoal::ObjectSafe(trait_id));
+            return Ok(());
+        }
+        Some(false) => return Ok(()),
+        None => {}
+    }
+
+    let interner = db.interner();
+    let trait_datum = db.try_trait_datum(trait_id)?;
+    let sized_trait_id = db.well_known_trait_id(WellKnownTrait::Sized);
+    let trait_bound = trait_datum.binders.skip_binders();
+
+    if trait_datum.well_known == Some(WellKnownTrait::Sized) {
+        return Ok(());
+    }
+
+    // Within the trait's binders, `Self` is `^0.0`; within the binders of a
+    // where clause it is `^1.0`.
+    if trait_bound.where_clauses.iter().any(|wc| {
+        requires_self_sized(interner, sized_trait_id, wc, DebruijnIndex::ONE)
+            || where_clause_references_self(interner, wc)
+    }) {
+        return Ok(());
+    }
+
+    if !trait_bound.associated_consts.is_empty() {
+        return Ok(());
+    }
+
+    // `Self` may be mentioned in method signatures through the associated
+    // types of the trait and its supertraits.
+    let num_trait_params = trait_datum.binders.len(interner);
+    let mut self_assoc_tys = FxHashSet::default();
+    for super_trait_id in supertraits_and_self(db, trait_id)? {
+        for &assoc_ty_id in &db.try_trait_datum(super_trait_id)?.associated_ty_ids {
+            self_assoc_tys.insert(assoc_ty_id);
+        }
+    }
+    for &assoc_ty_id in &trait_datum.associated_ty_ids {
+        let assoc_ty_datum = db.try_associated_ty_data(assoc_ty_id)?;
+        if assoc_ty_datum.binders.len(interner) > num_trait_params {
+            return Ok(());
+        }
+    }
+
+    let supertrait_goals =
+        trait_bound
+            .where_clauses
+            .iter()
+            .filter_map(|wc| match wc.skip_binders() {
+                WhereClause::Implemented(trait_ref)
+                    if trait_ref.self_type_parameter(interner)
+                        == self_ty_at(interner, DebruijnIndex::ONE) =>
+                {
+                    Some(DomainGoal::ObjectSafe(trait_ref.trait_id).cast::<Goal<I>>(interner))
+                }
+                _ => None,
+            });
+
+    let mut receiver_goals = vec![];
+    for method in &trait_bound.methods {
+        // Within the method's where clauses, `Self` is `^2.0` (trait binders,
+        // method binders, where clause binders).
+        let method_bound = method.skip_binders();
+        if method_bound
+            .where_clauses
+            .iter()
+            .any(|wc| requires_self_sized(interner, sized_trait_id, wc, DebruijnIndex::new(2)))
+        {
+            continue;
+        }
+
+        // Generic methods cannot be called through a vtable.
+        if method
+            .binders
+            .iter(interner)
+            .any(|kind| !matches!(kind, VariableKind::Lifetime))
+        {
+            return Ok(());
+        }
+
+        // Nor can methods whose signature needs to know the erased type.
+        let mut self_finder = SelfFinder {
+            interner,
+            self_depth: DebruijnIndex::ONE,
+            allowed_assoc_tys: &self_assoc_tys,
+        };
+        if method_bound
+            .argument_types
+            .visit_with(&mut self_finder, DebruijnIndex::INNERMOST)
+            .is_break()
+            || method_bound
+                .return_type
+                .visit_with(&mut self_finder, DebruijnIndex::INNERMOST)
+                .is_break()
+        {
+            return Ok(());
+        }
+
+        match &method_bound.receiver {
+            None => return Ok(()),
+            Some(receiver) if *receiver == self_ty_at(interner, DebruijnIndex::ONE) => {}
+            Some(_) => {
+                match receiver_is_dispatchable_goal(db, trait_id, &trait_datum.binders, method) {
+                    Some(goal) => receiver_goals.push(goal),
+                    None => return Ok(()),
+                }
+            }
+        }
+    }
+
+    let conditions: Vec<Goal<I>> = supertrait_goals.chain(receiver_goals).collect();
+    builder.push_clause(DomainGoal::ObjectSafe(trait_id), conditions);
+    Ok(())
+}
+
+/// Returns `Self` as seen from `depth` binders below the trait's binders.
+fn self_ty_at<I: Interner>(interner: &I, depth: DebruijnIndex) -> Ty<I> {
+    TyKind::BoundVar(BoundVar::new(depth, 0)).intern(interner)
+}
+
+/// Checks whether `wc` is `Self: Sized`, where `depth` is the number of
+/// binders between the trait's binders and the contents of `wc`.
+fn requires_self_sized<I: Interner>(
+    interner: &I,
+    sized_trait_id: Option<TraitId<I>>,
+    wc: &QuantifiedWhereClause<I>,
+    depth: DebruijnIndex,
+) -> bool {
+    match wc.skip_binders() {
+        WhereClause::Implemented(trait_ref) => {
+            Some(trait_ref.trait_id) == sized_trait_id
+                && trait_ref.self_type_parameter(interner) == self_ty_at(interner, depth)
+        }
+        _ => false,
+    }
+}
+
+/// Checks whether the where clause `wc` of a trait mentions `Self` other
+/// than as the self type of a trait or projection, like `T: PartialEq<Self>`
+/// or `T: Iterator<Item = Self>`. A trait object could not satisfy those.
+fn where_clause_references_self<I: Interner>(interner: &I, wc: &QuantifiedWhereClause<I>) -> bool {
+    let substitution = match wc.skip_binders() {
+        WhereClause::Implemented(trait_ref) => &trait_ref.substitution,
+        WhereClause::AliasEq(AliasEq {
+            alias: AliasTy::Projection(projection),
+            ..
+        }) => &projection.substitution,
+        _ => return false,
+    };
+    let no_assoc_tys = FxHashSet::default();
+    let mut self_finder = SelfFinder {
+        interner,
+        self_depth: DebruijnIndex::ONE,
+        allowed_assoc_tys: &no_assoc_tys,
+    };
+    (&substitution.as_slice(interner)[1..])
+        .visit_with(&mut self_finder, DebruijnIndex::INNERMOST)
+        .is_break()
+}
+
+/// Returns `trait_id` and all of its transitive supertraits.
+fn supertraits_and_self<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_id: TraitId<I>,
+) -> Result<FxHashSet<TraitId<I>>, LookupError<I>> {
+    let interner = db.interner();
+    let mut seen = FxHashSet::default();
+    let mut stack = vec![trait_id];
+    while let Some(trait_id) = stack.pop() {
+        if !seen.insert(trait_id) {
+            continue;
+        }
+        let trait_datum = db.try_trait_datum(trait_id)?;
+        for wc in &trait_datum.binders.skip_binders().where_clauses {
+            if let WhereClause::Implemented(trait_ref) = wc.skip_binders() {
+                if trait_ref.self_type_parameter(interner)
+                    == self_ty_at(interner, DebruijnIndex::ONE)
+                {
+                    stack.push(trait_ref.trait_id);
+                }
+            }
+        }
+    }
+    Ok(seen)
+}
+
+/// Looks for `Self` in a value that is `self_depth` binders below the
+/// trait's binders, except within projections of `allowed_assoc_tys` on
+/// `Self` (like `<Self as Iterator>::Item`), which trait objects specify.
+struct SelfFinder<'a, I: Interner> {
+    interner: &'a I,
+    self_depth: DebruijnIndex,
+    allowed_assoc_tys: &'a FxHashSet<AssocTypeId<I>>,
+}
+
+impl<'a, I: Interner> SelfFinder<'a, I> {
+    fn is_self(&self, ty: &Ty<I>, outer_binder: DebruijnIndex) -> bool {
+        match ty.kind(self.interner) {
+            TyKind::BoundVar(bound_var) => {
+                bound_var.index == 0
+                    && bound_var.debruijn.shifted_out_to(outer_binder) == Some(self.self_depth)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a, I: Interner> Visitor<'a, I> for SelfFinder<'a, I> {
+    type BreakTy = ();
+
+    fn as_dyn(&mut self) -> &mut dyn Visitor<'a, I, BreakTy = Self::BreakTy> {
+        self
+    }
+
+    fn visit_ty(&mut self, ty: &Ty<I>, outer_binder: DebruijnIndex) -> ControlFlow<()> {
+        match ty.kind(self.interner) {
+            _ if self.is_self(ty, outer_binder) => ControlFlow::BREAK,
+            TyKind::Alias(AliasTy::Projection(projection))
+                if self
+                    .allowed_assoc_tys
+                    .contains(&projection.associated_ty_id)
+                    && self
+                        .is_self(&projection.self_type_parameter(self.interner), outer_binder) =>
+            {
+                ControlFlow::CONTINUE
+            }
+            _ => ty.super_visit_with(self, outer_binder),
+        }
+    }
+
+    fn interner(&self) -> &'a I {
+        self.interner
+    }
+}
+
+/// Builds the goal checking that the receiver of `method` is dispatchable
+/// (see `add_object_safe_program_clauses`). Returns `None` if this cannot be
+/// expressed because the `Unsize` or `DispatchFromDyn` lang items are missing.
+fn receiver_is_dispatchable_goal<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_id: TraitId<I>,
+    trait_binders: &Binders<TraitDatumBound<I>>,
+    method: &Binders<TraitMethodDatum<I>>,
+) -> Option<Goal<I>> {
+    let interner = db.interner();
+    let unsize_trait_id = db.well_known_trait_id(WellKnownTrait::Unsize)?;
+    let dispatch_from_dyn_trait_id = db.well_known_trait_id(WellKnownTrait::DispatchFromDyn)?;
+
+    // `forall<P0..Pn> forall<M0..Mm>` becomes `forall<P0..Pn, M0..Mm, U>`.
+    let method = trait_binders
+        .map_ref(|_| method.clone())
+        .fuse_binders(interner);
+    let num_trait_params = trait_binders.len(interner);
+    let u_index = method.len(interner);
+    let binders = VariableKinds::from_iter(
+        interner,
+        method
+            .binders
+            .iter(interner)
+            .cloned()
+            .chain(Some(VariableKind::Ty(TyVariableKind::General))),
+    );
+
+    let identity: Vec<GenericArg<I>> = method
+        .binders
+        .iter(interner)
+        .enumerate()
+        .map(|p| p.to_generic_arg(interner))
+        .collect();
+    let self_ty = BoundVar::new(DebruijnIndex::INNERMOST, 0).to_ty(interner);
+    let u_ty = BoundVar::new(DebruijnIndex::INNERMOST, u_index).to_ty(interner);
+    let with_self = |self_ty: &Ty<I>, params: &[GenericArg<I>]| {
+        Substitution::from_iter(
+            interner,
+            Some(self_ty.clone().cast(interner))
+                .into_iter()
+                .chain(params[1..].iter().cloned()),
+        )
+    };
+    let trait_ref_for = |self_ty: &Ty<I>| TraitRef {
+        trait_id,
+        substitution: with_self(self_ty, &identity[..num_trait_params]),
+    };
+
+    let method_datum = method.skip_binders().clone();
+    let receiver = method_datum.receiver.clone().unwrap();
+    // `R[Self := U]`
+    let target_receiver = method
+        .map_ref(|_| receiver.clone())
+        .substitute(interner, &with_self(&u_ty, &identity));
+
+    let hypotheses: Vec<ProgramClause<I>> = vec![
+        DomainGoal::FromEnv(FromEnv::Trait(trait_ref_for(&self_ty))).cast(interner),
+        TraitRef {
+            trait_id: unsize_trait_id,
+            substitution: Substitution::from_iter(
+                interner,
+                [self_ty.clone(), u_ty.clone()].iter().cloned(),
+            ),
+        }
+        .cast(interner),
+        DomainGoal::FromEnv(FromEnv::Trait(trait_ref_for(&u_ty))).cast(interner),
+    ]
+    .into_iter()
+    .chain(
+        method_datum
+            .where_clauses
+            .into_iter()
+            .map(|qwc| qwc.into_from_env_goal(interner).cast(interner)),
+    )
+    .collect();
+
+    let dispatch_goal: Goal<I> = TraitRef {
+        trait_id: dispatch_from_dyn_trait_id,
+        substitution: Substitution::from_iter(
+            interner,
+            [receiver, target_receiver].iter().cloned(),
+        ),
+    }
+    .cast(interner);
+
+    let goal = GoalData::Implies(
+        ProgramClauses::from_iter(interner, hypotheses),
+        dispatch_goal,
+    )
+    .intern(interner);
+    Some(GoalData::Quantified(QuantifierKind::ForAll, Binders::new(binders, goal)).intern(interner))
+}
@@ -24,6 +24,10 @@ impl<I: Interner> ToProgramClauses<I> for ImplDatum<I> {
     /// }
     /// ```
     ///
+    /// Outlives where clauses such as `T: 'a` are not proven as goals;
+    /// instead they become region constraints of the clause whose origin
+    /// is this impl.
+    ///
     /// For a negative impl like `impl... !Clone for ...`, however, we
     /// generate nothing -- this is just a way to *opt out* from the
     /// default auto trait impls, it doesn't have any positive effect
@@ -36,7 +40,17 @@ impl<I: Interner> ToProgramClauses<I> for ImplDatum<I> {
         if self.is_positive() {
             let binders = self.binders.map_ref(|b| (&b.trait_ref, &b.where_clauses));
             builder.push_binders(&binders, |builder, (trait_ref, where_clauses)| {
-                builder.push_clause(trait_ref, where_clauses);
+                let (where_clauses, constraints) = split_outlives_where_clauses(
+                    builder.interner(),
+                    where_clauses,
+                    ConstraintOrigin::ImplWhereClause(self.id),
+                );
+                builder.push_clause_with_priority(
+                    trait_ref,
+                    where_clauses,
+                    constraints,
+                    ClausePriority::High,
+                );
             });
         }
     }
@@ -92,11 +106,15 @@ impl<I: Interner> ToProgramClauses<I> for AssociatedTyValue<I> {
             //
             // 1. require that the where clauses from the impl apply
             let interner = builder.db.interner();
-            let impl_where_clauses = impl_datum
-                .binders
-                .map_ref(|b| &b.where_clauses)
-                .into_iter()
-                .map(|wc| wc.substitute(interner, impl_params));
+            let (impl_where_clauses, constraints) = split_outlives_where_clauses(
+                interner,
+                impl_datum
+                    .binders
+                    .map_ref(|b| &b.where_clauses)
+                    .into_iter()
+                    .map(|wc| wc.substitute(interner, impl_params)),
+                ConstraintOrigin::ImplWhereClause(self.impl_id),
+            );
 
             // 2. any where-clauses from the `type` declaration in the trait: the
             //    parameters must be substituted with those of the impl
@@ -116,12 +134,14 @@ impl<I: Interner> ToProgramClauses<I> for AssociatedTyValue<I> {
             //         Implemented(Iter<'a, T>: 'a).   // (2)
             // }
             // ```
-            builder.push_clause(
+            builder.push_clause_with_priority(
                 Normalize {
                     alias: AliasTy::Projection(projection.clone()),
                     ty: assoc_ty_value.ty,
                 },
-                impl_where_clauses.chain(assoc_ty_where_clauses),
+                impl_where_clauses.into_iter().chain(assoc_ty_where_clauses),
+                constraints,
+                ClausePriority::High,
             );
         });
     }
@@ -213,6 +233,15 @@ impl<I: Interner> ToProgramClauses<I> for OpaqueTyDatum<I> {
 /// }
 /// ```
 ///
+/// Const parameters must have their declared type, so for
+/// `struct Foo<const N: usize> { }` we would generate:
+///
+/// ```notrust
+/// forall<const N: usize> {
+///     WF(Foo<N>) :- ConstHasType(N, usize).
+/// }
+/// ```
+///
 /// # Parameters
 /// - builder -- the clause builder. We assume all the generic types from `Foo` are in scope
 /// - type_name -- in our example above, the name `Foo`
@@ -226,14 +255,70 @@ fn well_formed_program_clauses<'a, I, Wc>(
     Wc: Iterator<Item = &'a QuantifiedWhereClause<I>>,
 {
     let interner = builder.interner();
-    builder.push_clause(
+    let const_types: Vec<Goal<I>> = builder
+        .substitution_in_scope()
+        .iter(interner)
+        .filter_map(|arg| arg.constant(interner))
+        .map(|c| DomainGoal::ConstHasType(c.clone(), c.data(interner).ty.clone()).cast(interner))
+        .collect();
+    let (where_clauses, constraints) = split_outlives_where_clauses(
+        interner,
+        where_clauses.cloned(),
+        ConstraintOrigin::WellFormed(ty.clone()),
+    );
+    builder.push_clause_with_priority(
         WellFormed::Ty(ty),
-        where_clauses
-            .cloned()
-            .map(|qwc| qwc.into_well_formed_goal(interner)),
+        const_types.into_iter().chain(
+            where_clauses
+                .into_iter()
+                .map(|qwc| qwc.into_well_formed_goal(interner).cast(interner)),
+        ),
+        constraints,
+        ClausePriority::High,
     );
 }
 
+/// Separates the outlives requirements from the rest of `where_clauses`.
+/// Outlives where clauses without binders of their own are not proven as
+/// goals; instead they are returned as region constraints tagged with
+/// `origin`, so that region checking can tell which item required them.
+///
+/// The constraints are given an empty environment, so that the clauses
+/// don't depend on the environment they were built for: the solvers
+/// check the constraints of a clause in the environment of the goal it
+/// proves.
+fn split_outlives_where_clauses<I: Interner>(
+    interner: &I,
+    where_clauses: impl IntoIterator<Item = QuantifiedWhereClause<I>>,
+    origin: ConstraintOrigin<I>,
+) -> (
+    Vec<QuantifiedWhereClause<I>>,
+    Vec<InEnvironment<Constraint<I>>>,
+) {
+    let mut goals = vec![];
+    let mut constraints = vec![];
+    for qwc in where_clauses {
+        if !qwc.binders.is_empty(interner) {
+            goals.push(qwc);
+            continue;
+        }
+        let constraint = match qwc.substitute(interner, &[] as &[GenericArg<I>]) {
+            WhereClause::LifetimeOutlives(LifetimeOutlives { a, b }) => {
+                Constraint::LifetimeOutlives(a, b, origin.clone())
+            }
+            WhereClause::TypeOutlives(TypeOutlives { ty, lifetime }) => {
+                Constraint::TypeOutlives(ty, lifetime, origin.clone())
+            }
+            _ => {
+                goals.push(qwc);
+                continue;
+            }
+        };
+        constraints.push(InEnvironment::new(&Environment::new(interner), constraint));
+    }
+    (goals, constraints)
+}
+
 /// Generates the "fully visible" program clauses for an applicative type
 /// with the name `type_name`. For example, given a struct definition:
 ///
@@ -296,15 +381,43 @@ fn implied_bounds_program_clauses<'a, I, Wc>(
     I: Interner,
     Wc: Iterator<Item = &'a QuantifiedWhereClause<I>>,
 {
-    let interner = builder.interner();
-
     for qwc in where_clauses {
         builder.push_binders(&qwc, |builder, wc| {
-            builder.push_clause(wc.into_from_env_goal(interner), Some(ty.clone().from_env()));
+            push_implied_bound(builder, wc, ty.clone().from_env());
         });
     }
 }
 
+/// Pushes the implied bound `FromEnv(WC) :- from_env` for a where clause
+/// `WC` of the item in `from_env`. If `WC` is an outlives requirement, the
+/// clause also carries it as a region constraint whose origin is
+/// `from_env`, so that region checking can tell that it is known to hold in
+/// the environment.
+fn push_implied_bound<I: Interner>(
+    builder: &mut ClauseBuilder<'_, I>,
+    wc: WhereClause<I>,
+    from_env: FromEnv<I>,
+) {
+    let interner = builder.interner();
+    let origin = ConstraintOrigin::ImpliedBound(from_env.clone());
+    let constraint =
+        match &wc {
+            WhereClause::LifetimeOutlives(LifetimeOutlives { a, b }) => {
+                Some(Constraint::LifetimeOutlives(a.clone(), b.clone(), origin))
+            }
+            WhereClause::TypeOutlives(TypeOutlives { ty, lifetime }) => Some(
+                Constraint::TypeOutlives(ty.clone(), lifetime.clone(), origin),
+            ),
+            _ => None,
+        };
+    builder.push_clause_with_priority(
+        wc.into_from_env_goal(interner),
+        Some(from_env),
+        constraint.map(|constraint| InEnvironment::new(&Environment::new(interner), constraint)),
+        ClausePriority::High,
+    );
+}
+
 impl<I: Interner> ToProgramClauses<I> for AdtDatum<I> {
     /// Given the following type definition: `struct Foo<T: Eq> { }`, generate:
     ///
@@ -688,10 +801,7 @@ impl<I: Interner> ToProgramClauses<I> for TraitDatum<I> {
             // ```
             for qwc in &where_clauses {
                 builder.push_binders(qwc, |builder, wc| {
-                    builder.push_clause(
-                        wc.into_from_env_goal(interner),
-                        Some(trait_ref.clone().from_env()),
-                    );
+                    push_implied_bound(builder, wc, trait_ref.clone().from_env());
                 });
             }
 
@@ -136,8 +136,8 @@ impl<I: Interner> CoherenceSolver<'_, I> {
             // Goal was proven with a unique solution, so no impl was found that causes these two
             // to overlap
             Some(Solution::Unique(_)) => true,
-            // Goal was ambiguous, so there *may* be overlap
-            Some(Solution::Ambig(_)) |
+            // Goal was ambiguous or overflowed, so there *may* be overlap
+            Some(Solution::Ambig(_)) | Some(Solution::Overflow(_)) |
             // Goal cannot be proven, so there is some impl that causes overlap
             None => false,
         };
@@ -19,10 +19,14 @@ impl<I: Interner> IsCoinductive<I> for Goal<I> {
         let interner = db.interner();
         match self.data(interner) {
             GoalData::DomainGoal(DomainGoal::Holds(wca)) => match wca {
-                WhereClause::Implemented(tr) => {
-                    db.trait_datum(tr.trait_id).is_auto_trait()
-                        || db.trait_datum(tr.trait_id).is_coinductive_trait()
-                }
+                // If we cannot look the trait up, there are no clauses
+                // to prove the goal with anyway (see `clauses::ClauseError`).
+                WhereClause::Implemented(tr) => match db.try_trait_datum(tr.trait_id) {
+                    Ok(trait_datum) => {
+                        trait_datum.is_auto_trait() || trait_datum.is_coinductive_trait()
+                    }
+                    Err(_) => false,
+                },
                 WhereClause::AliasEq(..) => false,
                 WhereClause::LifetimeOutlives(..) => false,
                 WhereClause::TypeOutlives(..) => false,
@@ -0,0 +1,219 @@
+//! Tracking of the program items that solver results depend upon.
+//!
+//! A [`RustIrDatabase`] that wants solvers to keep their caches across
+//! program edits hands out a [`DependencyRecorder`] (see
+//! [`RustIrDatabase::dependency_recorder`]) and records every item that is
+//! looked up through it. Solvers open a frame on the recorder while they
+//! compute a cached result, and store the [`Dependencies`] collected in that
+//! frame along with the result. When the program changes, the items that
+//! changed are passed to [`Solver::invalidate`], which throws away only the
+//! results that consulted one of them.
+//!
+//! [`RustIrDatabase`]: crate::RustIrDatabase
+//! [`RustIrDatabase::dependency_recorder`]: crate::RustIrDatabase::dependency_recorder
+//! [`Solver::invalidate`]: crate::Solver::invalidate
+
+use crate::rust_ir::{AssociatedTyValueId, WellKnownAdt, WellKnownTrait};
+use chalk_ir::interner::Interner;
+use chalk_ir::{AdtId, AssocTypeId, ClosureId, FnDefId, GeneratorId, ImplId, OpaqueTyId, TraitId};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::{Mutex, PoisonError};
+use std::thread::{self, ThreadId};
+#[cfg(feature = "serde")]
+use {
+    chalk_ir::interner::SerdeInterner,
+    serde::{Deserialize, Serialize},
+};
+
+/// A piece of the program that can be consulted through a `RustIrDatabase`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub enum ItemId<I: Interner> {
+    Trait(TraitId<I>),
+    Adt(AdtId<I>),
+    Impl(ImplId<I>),
+    AssociatedTy(AssocTypeId<I>),
+    AssociatedTyValue(AssociatedTyValueId<I>),
+    OpaqueTy(OpaqueTyId<I>),
+    FnDef(FnDefId<I>),
+    Closure(ClosureId<I>),
+    Generator(GeneratorId<I>),
+    /// The set of impls of the given trait, as opposed to the contents
+    /// of any one of them. This changes when an impl is added or removed.
+    TraitImpls(TraitId<I>),
+    WellKnownTrait(WellKnownTrait),
+    WellKnownAdt(WellKnownAdt),
+    /// The custom program clauses of the program.
+    CustomClauses,
+}
+
+/// A set of items that some result depends upon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub struct Dependencies<I: Interner> {
+    items: FxHashSet<ItemId<I>>,
+}
+
+impl<I: Interner> Dependencies<I> {
+    pub fn new() -> Self {
+        Dependencies {
+            items: FxHashSet::default(),
+        }
+    }
+
+    pub fn insert(&mut self, item: ItemId<I>) {
+        self.items.insert(item);
+    }
+
+    pub fn extend(&mut self, other: &Dependencies<I>) {
+        self.items.extend(other.items.iter().copied());
+    }
+
+    pub fn contains(&self, item: &ItemId<I>) -> bool {
+        self.items.contains(item)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ItemId<I>> {
+        self.items.iter()
+    }
+
+    /// True if any of `changed` is among these dependencies.
+    pub fn affected_by(&self, changed: &Dependencies<I>) -> bool {
+        changed.items.iter().any(|item| self.items.contains(item))
+    }
+}
+
+impl<I: Interner> Default for Dependencies<I> {
+    fn default() -> Self {
+        Dependencies::new()
+    }
+}
+
+impl<I: Interner> std::iter::FromIterator<ItemId<I>> for Dependencies<I> {
+    fn from_iter<T: IntoIterator<Item = ItemId<I>>>(iter: T) -> Self {
+        Dependencies {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Collects the items looked up through a database into stacks of frames,
+/// one per thread.
+///
+/// Solvers call [`start`](Self::start) before computing a result they
+/// intend to cache and [`finish`](Self::finish) afterwards. Frames nest:
+/// whatever a nested frame collected is also added to the frame that
+/// encloses it, since the outer result was computed from the inner one.
+/// Solvers that share a database between threads (see
+/// `ParallelSLGSolver`) each get their own stack, so that the items one
+/// thread looks up are not attributed to the results of another.
+#[derive(Debug)]
+pub struct DependencyRecorder<I: Interner> {
+    frames: Mutex<FxHashMap<ThreadId, Vec<Dependencies<I>>>>,
+}
+
+impl<I: Interner> DependencyRecorder<I> {
+    pub fn new() -> Self {
+        DependencyRecorder {
+            frames: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Runs `op` on the frames of the current thread, and forgets them
+    /// once they are all closed.
+    fn with_frames<R>(&self, op: impl FnOnce(&mut Vec<Dependencies<I>>) -> R) -> R {
+        // Panicking here while unwinding would abort, and the frames are
+        // always left consistent, so ignore any poisoning.
+        let mut frames = self.frames.lock().unwrap_or_else(PoisonError::into_inner);
+        let thread = thread::current().id();
+        let thread_frames = frames.entry(thread).or_default();
+        let result = op(thread_frames);
+        if thread_frames.is_empty() {
+            frames.remove(&thread);
+        }
+        result
+    }
+
+    /// Records that `item` was consulted. Does nothing if no frame is open
+    /// on the current thread.
+    pub fn record(&self, item: ItemId<I>) {
+        self.with_frames(|frames| {
+            if let Some(frame) = frames.last_mut() {
+                frame.insert(item);
+            }
+        })
+    }
+
+    /// Records that everything in `dependencies` was consulted, e.g. because
+    /// a result that depends on them was taken from a cache.
+    pub fn record_all(&self, dependencies: &Dependencies<I>) {
+        self.with_frames(|frames| {
+            if let Some(frame) = frames.last_mut() {
+                frame.extend(dependencies);
+            }
+        })
+    }
+
+    /// Opens a new frame on the current thread.
+    pub fn start(&self) {
+        self.with_frames(|frames| frames.push(Dependencies::new()))
+    }
+
+    /// Closes the innermost frame of the current thread and returns what it
+    /// collected.
+    pub fn finish(&self) -> Dependencies<I> {
+        self.with_frames(|frames| {
+            let dependencies = frames.pop().expect("no dependency frame to finish");
+            if let Some(outer) = frames.last_mut() {
+                outer.extend(&dependencies);
+            }
+            dependencies
+        })
+    }
+
+    /// Returns a guard that, if it is dropped while a panic unwinds,
+    /// closes the frames opened on the current thread since it was
+    /// created, throwing away what they collected. Solvers hold one while
+    /// they solve a goal, so that the results they abandon do not leave
+    /// frames open behind them.
+    pub fn unwind_guard(&self) -> UnwindGuard<'_, I> {
+        UnwindGuard {
+            recorder: self,
+            depth: self.with_frames(|frames| frames.len()),
+        }
+    }
+}
+
+impl<I: Interner> Default for DependencyRecorder<I> {
+    fn default() -> Self {
+        DependencyRecorder::new()
+    }
+}
+
+/// See [`DependencyRecorder::unwind_guard`].
+#[derive(Debug)]
+pub struct UnwindGuard<'a, I: Interner> {
+    recorder: &'a DependencyRecorder<I>,
+    depth: usize,
+}
+
+impl<I: Interner> Drop for UnwindGuard<'_, I> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            let depth = self.depth;
+            self.recorder.with_frames(|frames| frames.truncate(depth));
+        }
+    }
+}
@@ -8,6 +8,7 @@ use std::fmt::{Formatter, Result};
 use crate::rust_ir::*;
 use crate::split::Split;
 use chalk_ir::interner::Interner;
+use chalk_ir::Binders;
 use itertools::Itertools;
 
 use super::{
@@ -88,17 +89,23 @@ impl<I: Interner> RenderAsRust<I> for AdtDatum<I> {
             }
         );
 
+        // well-known
+        if s.db().well_known_adt_id(WellKnownAdt::DynMetadata) == Some(self.id) {
+            writeln!(f, "#[lang(dyn_metadata)]")?;
+        }
+
         // repr
         let repr = s.db().adt_repr(self.id);
 
-        write_flags!(
-            f,
-            repr,
-            AdtRepr {
-                repr_c: "repr(C)",
-                repr_packed: "repr(packed)"
-            }
-        );
+        if repr.repr_c {
+            writeln!(f, "#[repr(C)]")?;
+        }
+        if repr.repr_packed {
+            writeln!(f, "#[repr(packed)]")?;
+        }
+        if let Some(t) = &repr.int {
+            writeln!(f, "#[repr({})]", t.display(s))?;
+        }
 
         // name
         match self.kind {
@@ -184,8 +191,10 @@ impl<I: Interner> RenderAsRust<I> for TraitDatum<I> {
         );
 
         // object safe
-        if s.db().is_object_safe(self.id) {
-            writeln!(f, "#[object_safe]")?;
+        match s.db().is_object_safe(self.id) {
+            Some(true) => writeln!(f, "#[object_safe]")?,
+            Some(false) => {}
+            None => writeln!(f, "#[compute_object_safety]")?,
         }
 
         // well-known
@@ -201,6 +210,12 @@ impl<I: Interner> RenderAsRust<I> for TraitDatum<I> {
                 WellKnownTrait::Unsize => "unsize",
                 WellKnownTrait::Unpin => "unpin",
                 WellKnownTrait::CoerceUnsized => "coerce_unsized",
+                WellKnownTrait::DiscriminantKind => "discriminant_kind",
+                WellKnownTrait::Generator => "generator",
+                WellKnownTrait::DispatchFromDyn => "dispatch_from_dyn",
+                WellKnownTrait::Pointee => "pointee",
+                WellKnownTrait::Tuple => "tuple_trait",
+                WellKnownTrait::FnPtr => "fn_ptr_trait",
             };
             writeln!(f, "#[lang({})]", name)?;
         }
@@ -224,10 +239,18 @@ impl<I: Interner> RenderAsRust<I> for TraitDatum<I> {
         write_joined_non_empty_list!(
             f,
             "\n{}\n",
-            self.associated_ty_ids.iter().map(|assoc_ty_id| {
-                let assoc_ty_data = s.db().associated_ty_data(*assoc_ty_id);
-                format!("{}{}", s.indent(), (*assoc_ty_data).display(s))
-            }),
+            self.associated_ty_ids
+                .iter()
+                .map(|assoc_ty_id| {
+                    let assoc_ty_data = s.db().associated_ty_data(*assoc_ty_id);
+                    format!("{}{}", s.indent(), (*assoc_ty_data).display(s))
+                })
+                .chain(value.associated_consts.iter().enumerate().map(|(idx, ty)| {
+                    format!("{}const CONST_{}: {};", s.indent(), idx, ty.display(s))
+                }),)
+                .chain(value.methods.iter().enumerate().map(|(idx, method)| {
+                    format!("{}{}", s.indent(), display_trait_method(s, idx, method))
+                })),
             "\n"
         )?;
         write!(f, "}}")?;
@@ -332,6 +355,47 @@ impl<I: Interner> RenderAsRust<I> for OpaqueTyDatum<I> {
     }
 }
 
+/// Renders a trait method as `fn method_{idx}<..>(self: R, arg_0: A) -> O
+/// where ..;`. Method and argument names are not stored in the database, so
+/// like fields and variants they are named by their index.
+fn display_trait_method<I: Interner>(
+    s: &InternalWriterState<'_, I>,
+    idx: usize,
+    method: &Binders<TraitMethodDatum<I>>,
+) -> String {
+    let s = &s.add_debrujin_index(None);
+    let value = method.skip_binders();
+    let mut out = format!("fn method_{}", idx);
+    let params = s.binder_var_display(&method.binders).collect::<Vec<_>>();
+    if !params.is_empty() {
+        out += &format!("<{}>", params.join(", "));
+    }
+    let args = value
+        .receiver
+        .iter()
+        .map(|receiver| format!("self: {}", receiver.display(s)))
+        .chain(
+            value
+                .argument_types
+                .iter()
+                .enumerate()
+                .map(|(idx, ty)| format!("arg_{}: {}", idx, ty.display(s))),
+        )
+        .collect::<Vec<_>>();
+    out += &format!("({})", args.join(", "));
+    out += &format!(" -> {}", value.return_type.display(s));
+    if !value.where_clauses.is_empty() {
+        let where_s = &s.add_indent();
+        out += &format!(
+            "\n{}where\n{}",
+            s.indent(),
+            value.where_clauses.display(where_s)
+        );
+    }
+    out += ";";
+    out
+}
+
 impl<I: Interner> RenderAsRust<I> for AssociatedTyDatum<I> {
     fn fmt(&self, s: &InternalWriterState<'_, I>, f: &'_ mut Formatter<'_>) -> Result {
         // In lowering, a completely new empty environment is created for each
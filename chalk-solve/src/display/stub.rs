@@ -8,7 +8,7 @@ use crate::{
         AdtDatumBound, AdtKind, AdtVariantDatum, AssociatedTyDatumBound, FnDefDatumBound,
         OpaqueTyDatumBound, TraitDatumBound,
     },
-    RustIrDatabase,
+    LookupError, RustIrDatabase,
 };
 use chalk_ir::{
     interner::Interner, Binders, CanonicalVarKinds, GeneratorId, Substitution, UnificationDatabase,
@@ -65,6 +65,8 @@ impl<I: Interner, DB: RustIrDatabase<I>> RustIrDatabase<I> for StubWrapper<'_, D
             v.binders.binders.clone(),
             TraitDatumBound {
                 where_clauses: Vec::new(),
+                methods: Vec::new(),
+                associated_consts: Vec::new(),
             },
         );
         Arc::new(v)
@@ -86,7 +88,7 @@ impl<I: Interner, DB: RustIrDatabase<I>> RustIrDatabase<I> for StubWrapper<'_, D
         Arc::new(v)
     }
 
-    fn adt_repr(&self, id: chalk_ir::AdtId<I>) -> crate::rust_ir::AdtRepr {
+    fn adt_repr(&self, id: chalk_ir::AdtId<I>) -> std::sync::Arc<crate::rust_ir::AdtRepr<I>> {
         self.db.adt_repr(id)
     }
 
@@ -176,10 +178,17 @@ impl<I: Interner, DB: RustIrDatabase<I>> RustIrDatabase<I> for StubWrapper<'_, D
         self.db.well_known_trait_id(well_known_trait)
     }
 
+    fn well_known_adt_id(
+        &self,
+        well_known_adt: crate::rust_ir::WellKnownAdt,
+    ) -> Option<chalk_ir::AdtId<I>> {
+        self.db.well_known_adt_id(well_known_adt)
+    }
+
     fn program_clauses_for_env(
         &self,
         environment: &chalk_ir::Environment<I>,
-    ) -> chalk_ir::ProgramClauses<I> {
+    ) -> Result<chalk_ir::ProgramClauses<I>, LookupError<I>> {
         self.db.program_clauses_for_env(environment)
     }
 
@@ -187,7 +196,7 @@ impl<I: Interner, DB: RustIrDatabase<I>> RustIrDatabase<I> for StubWrapper<'_, D
         self.db.interner()
     }
 
-    fn is_object_safe(&self, trait_id: chalk_ir::TraitId<I>) -> bool {
+    fn is_object_safe(&self, trait_id: chalk_ir::TraitId<I>) -> Option<bool> {
         self.db.is_object_safe(trait_id)
     }
 
@@ -0,0 +1,619 @@
+//! Trimming of environments before canonicalization.
+//!
+//! Two goals that differ only in environment clauses which cannot
+//! possibly be used to prove them canonicalize to different keys, and
+//! so miss each other in the solver's caches. Both solvers therefore
+//! drop those clauses from each goal they are about to look up, using
+//! an [`EnvTrimmer`].
+//!
+//! The analysis is deliberately conservative. We compute the set of
+//! traits that proving the goal could ever require -- the traits
+//! mentioned in the goal, and everything reachable from them through
+//! supertraits, associated type bounds, impl where clauses and the
+//! definitions of the types involved. A clause is kept if anything it
+//! implies (directly, or through supertraits and associated type
+//! bounds) is in that set. Clauses that are not about a trait at all
+//! (`FromEnv(Ty)`, outlives requirements, ...) are always kept, and if
+//! the goal contains something we do not know how to reason about, the
+//! environment is left untouched.
+//!
+//! What is reachable from an item only depends on the program, so the
+//! trimmer remembers it, along with the items it consulted to find out,
+//! and only has to look at the goal itself after that.
+
+use crate::dependencies::{Dependencies, DependencyRecorder};
+use crate::rust_ir::WellKnownTrait;
+use crate::RustIrDatabase;
+use chalk_ir::interner::Interner;
+use chalk_ir::visit::{ControlFlow, SuperVisit, Visit, Visitor};
+use chalk_ir::{try_break, *};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Returns `goal` with the environment clauses that cannot affect its
+/// solution removed. See the [module documentation](self) for the
+/// rules used.
+///
+/// This analyzes the program from scratch; solvers keep an
+/// [`EnvTrimmer`] around instead.
+pub fn trim_environment<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    goal: &InEnvironment<Goal<I>>,
+) -> InEnvironment<Goal<I>> {
+    EnvTrimmer::new().trim(db, goal)
+}
+
+/// Returns `goal` with only the environment clauses whose entry in
+/// `kept` is true.
+pub fn keep_clauses<I: Interner>(
+    interner: &I,
+    goal: &InEnvironment<Goal<I>>,
+    kept: &[bool],
+) -> InEnvironment<Goal<I>> {
+    let clauses = goal.environment.clauses.as_slice(interner);
+    assert_eq!(clauses.len(), kept.len());
+    let environment = Environment {
+        clauses: ProgramClauses::from_iter(
+            interner,
+            clauses
+                .iter()
+                .zip(kept)
+                .filter(|(_, kept)| **kept)
+                .map(|(clause, _)| clause.clone()),
+        ),
+    };
+    InEnvironment::new(&environment, goal.goal.clone())
+}
+
+/// Puts the clauses that [`keep_clauses`] dropped back into `environment`,
+/// an environment that was derived from the trimmed one while solving:
+/// environments only ever grow by appending clauses (see
+/// `Environment::add_clauses`), so it starts with the `trimmed_len` kept
+/// clauses, which we replace with the clauses of `original`.
+pub fn restore_clauses<I: Interner>(
+    interner: &I,
+    environment: &Environment<I>,
+    trimmed_len: usize,
+    original: &Environment<I>,
+) -> Environment<I> {
+    match environment.clauses.as_slice(interner).get(trimmed_len..) {
+        Some(added) => original.add_clauses(interner, added.iter().cloned()),
+        None => environment.clone(),
+    }
+}
+
+/// Trims environments (see the [module documentation](self)),
+/// remembering what it learns about the program from one goal to the
+/// next. The items consulted for each of those facts are replayed to the
+/// database's [`DependencyRecorder`] whenever the fact is reused, and
+/// [`invalidate`](Self::invalidate) forgets the facts that a change to
+/// the program affects.
+#[derive(Debug)]
+pub struct EnvTrimmer<I: Interner> {
+    /// For each item, the traits that proving something about it could
+    /// require.
+    reachable: FxHashMap<Item<I>, Memo<I, Reachable<I>>>,
+    /// For each trait, the traits that assuming it implies.
+    implied: FxHashMap<TraitId<I>, Memo<I, FxHashSet<TraitId<I>>>>,
+}
+
+/// The traits reachable from an item, or `None` if we cannot tell.
+type Reachable<I> = Option<FxHashSet<TraitId<I>>>;
+
+/// Something we found out about the program, along with the items we
+/// consulted to find it out.
+#[derive(Debug)]
+struct Memo<I: Interner, T> {
+    value: T,
+    dependencies: Dependencies<I>,
+}
+
+impl<I: Interner> EnvTrimmer<I> {
+    pub fn new() -> Self {
+        EnvTrimmer {
+            reachable: FxHashMap::default(),
+            implied: FxHashMap::default(),
+        }
+    }
+
+    /// Returns `goal` with the environment clauses that cannot affect
+    /// its solution removed.
+    pub fn trim(
+        &mut self,
+        db: &dyn RustIrDatabase<I>,
+        goal: &InEnvironment<Goal<I>>,
+    ) -> InEnvironment<Goal<I>> {
+        match self.relevant_clauses(db, goal) {
+            Some(kept) => keep_clauses(db.interner(), goal, &kept),
+            None => goal.clone(),
+        }
+    }
+
+    /// Determines which of the environment clauses of `goal` can affect
+    /// its solution, for use with [`keep_clauses`]. Returns `None` if
+    /// all of them have to be kept.
+    ///
+    /// The goal must not contain inference variables that are already
+    /// bound, since we cannot see what they are bound to; solvers call
+    /// this on canonicalized goals.
+    pub fn relevant_clauses(
+        &mut self,
+        db: &dyn RustIrDatabase<I>,
+        goal: &InEnvironment<Goal<I>>,
+    ) -> Option<Vec<bool>> {
+        let clauses = goal.environment.clauses.as_slice(db.interner());
+        if clauses.is_empty() {
+            return None;
+        }
+
+        let mut relevance = Relevance {
+            db,
+            trimmer: self,
+            required: FxHashSet::default(),
+            needed: FxHashSet::default(),
+        };
+        if relevance.require(&goal.goal).is_break() {
+            return None;
+        }
+
+        // Using a clause means proving its conditions and normalizing
+        // whatever it mentions, which in turn can make further clauses
+        // relevant.
+        let mut kept = vec![false; clauses.len()];
+        loop {
+            let mut changed = false;
+            for (clause, kept) in clauses.iter().zip(&mut kept) {
+                if *kept || !relevance.is_relevant(clause) {
+                    continue;
+                }
+                *kept = true;
+                changed = true;
+                if relevance.require(clause).is_break() {
+                    return None;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        if kept.iter().all(|&kept| kept) {
+            None
+        } else {
+            Some(kept)
+        }
+    }
+
+    /// Forgets what we learned from any of the `changed` items.
+    pub fn invalidate(&mut self, changed: &Dependencies<I>) {
+        self.reachable
+            .retain(|_, memo| !memo.dependencies.affected_by(changed));
+        self.implied
+            .retain(|_, memo| !memo.dependencies.affected_by(changed));
+    }
+
+    /// Forgets everything we learned about the program.
+    pub fn clear(&mut self) {
+        self.reachable.clear();
+        self.implied.clear();
+    }
+
+    /// Adds the traits reachable from `item` to `traits`. Breaks if
+    /// those cannot be determined.
+    fn add_reachable(
+        &mut self,
+        db: &dyn RustIrDatabase<I>,
+        item: Item<I>,
+        traits: &mut FxHashSet<TraitId<I>>,
+    ) -> ControlFlow<()> {
+        if let Some(result) = self.add_remembered(db, item, traits) {
+            return result;
+        }
+        let recorder = db.dependency_recorder();
+        if let Some(recorder) = recorder {
+            recorder.start();
+        }
+        let reachable = self.explore(db, item);
+        let dependencies = recorder.map_or_else(Dependencies::new, DependencyRecorder::finish);
+        let result = match &reachable {
+            Some(reachable) => {
+                traits.extend(reachable.iter().copied());
+                ControlFlow::CONTINUE
+            }
+            None => ControlFlow::BREAK,
+        };
+        self.reachable.insert(
+            item,
+            Memo {
+                value: reachable,
+                dependencies,
+            },
+        );
+        result
+    }
+
+    /// As `add_reachable`, if we already know what is reachable from
+    /// `item`; returns `None` otherwise.
+    fn add_remembered(
+        &self,
+        db: &dyn RustIrDatabase<I>,
+        item: Item<I>,
+        traits: &mut FxHashSet<TraitId<I>>,
+    ) -> Option<ControlFlow<()>> {
+        let memo = self.reachable.get(&item)?;
+        if let Some(recorder) = db.dependency_recorder() {
+            recorder.record_all(&memo.dependencies);
+        }
+        Some(match &memo.value {
+            Some(reachable) => {
+                traits.extend(reachable.iter().copied());
+                ControlFlow::CONTINUE
+            }
+            None => ControlFlow::BREAK,
+        })
+    }
+
+    /// Computes the traits reachable from `item`, or `None` if we
+    /// reach something we cannot analyze.
+    fn explore(&self, db: &dyn RustIrDatabase<I>, item: Item<I>) -> Option<FxHashSet<TraitId<I>>> {
+        let mut items = ItemCollector::new(db);
+        items.record(item);
+        let mut traits = FxHashSet::default();
+        while let Some(item) = items.pending.pop() {
+            // Everything reachable from an item we explored before is
+            // already known, so there is no need to go through it again.
+            if let Some(result) = self.add_remembered(db, item, &mut traits) {
+                if result.is_break() {
+                    return None;
+                }
+                continue;
+            }
+            if let Item::Trait(trait_id) = item {
+                traits.insert(trait_id);
+            }
+            if items.visit_definition(item).is_break() {
+                return None;
+            }
+        }
+        Some(traits)
+    }
+
+    /// The traits that assuming `trait_id` lets us conclude: itself, its
+    /// supertraits and the bounds on its associated types, transitively.
+    fn implied(
+        &mut self,
+        db: &dyn RustIrDatabase<I>,
+        trait_id: TraitId<I>,
+    ) -> &FxHashSet<TraitId<I>> {
+        let recorder = db.dependency_recorder();
+        if let Some(memo) = self.implied.get(&trait_id) {
+            if let Some(recorder) = recorder {
+                recorder.record_all(&memo.dependencies);
+            }
+        } else {
+            if let Some(recorder) = recorder {
+                recorder.start();
+            }
+            let implied = compute_implied(db, trait_id);
+            let dependencies = recorder.map_or_else(Dependencies::new, DependencyRecorder::finish);
+            self.implied.insert(
+                trait_id,
+                Memo {
+                    value: implied,
+                    dependencies,
+                },
+            );
+        }
+        &self.implied[&trait_id].value
+    }
+}
+
+impl<I: Interner> Default for EnvTrimmer<I> {
+    fn default() -> Self {
+        EnvTrimmer::new()
+    }
+}
+
+fn compute_implied<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    trait_id: TraitId<I>,
+) -> FxHashSet<TraitId<I>> {
+    let interner = db.interner();
+    let mut implied = FxHashSet::default();
+    let mut stack = vec![trait_id];
+    while let Some(trait_id) = stack.pop() {
+        if !implied.insert(trait_id) {
+            continue;
+        }
+        let trait_datum = db.trait_datum(trait_id);
+        let where_clauses = trait_datum
+            .binders
+            .skip_binders()
+            .where_clauses
+            .iter()
+            .cloned();
+        let bounds = trait_datum.associated_ty_ids.iter().flat_map(|&id| {
+            let assoc_ty_datum = db.associated_ty_data(id);
+            assoc_ty_datum.bounds_on_self(interner)
+        });
+        stack.extend(
+            where_clauses
+                .chain(bounds)
+                .filter_map(|wc| where_clause_trait(db, wc.skip_binders())),
+        );
+    }
+    implied
+}
+
+/// The well-known traits whose builtin impls we know to only depend on
+/// one another and on the definitions of the types involved.
+const BUILTIN_TRAITS: &[WellKnownTrait] = &[
+    WellKnownTrait::Sized,
+    WellKnownTrait::Copy,
+    WellKnownTrait::Clone,
+    WellKnownTrait::Drop,
+    WellKnownTrait::FnOnce,
+    WellKnownTrait::FnMut,
+    WellKnownTrait::Fn,
+    WellKnownTrait::Unpin,
+    WellKnownTrait::DiscriminantKind,
+    WellKnownTrait::Pointee,
+    WellKnownTrait::Tuple,
+    WellKnownTrait::FnPtr,
+];
+
+/// The items whose definitions can contribute to a proof.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Item<I: Interner> {
+    Trait(TraitId<I>),
+    Adt(AdtId<I>),
+    FnDef(FnDefId<I>),
+    OpaqueTy(OpaqueTyId<I>),
+}
+
+/// What trimming the environment of one goal has found out so far.
+struct Relevance<'a, I: Interner> {
+    db: &'a dyn RustIrDatabase<I>,
+    trimmer: &'a mut EnvTrimmer<I>,
+    /// The items mentioned in what we have to prove.
+    required: FxHashSet<Item<I>>,
+    /// The traits reachable from those.
+    needed: FxHashSet<TraitId<I>>,
+}
+
+impl<I: Interner> Relevance<'_, I> {
+    /// Records that `value` may have to be proven, along with
+    /// everything that proving it could require. Breaks if `value`
+    /// cannot be analyzed.
+    fn require<T: Visit<I>>(&mut self, value: &T) -> ControlFlow<()> {
+        let mut items = ItemCollector::new(self.db);
+        try_break!(value.visit_with(&mut items, DebruijnIndex::INNERMOST));
+        for item in items.pending {
+            if self.required.insert(item) {
+                try_break!(self.trimmer.add_reachable(self.db, item, &mut self.needed));
+            }
+        }
+        ControlFlow::CONTINUE
+    }
+
+    /// Whether assuming `clause` could help with anything we need.
+    fn is_relevant(&mut self, clause: &ProgramClause<I>) -> bool {
+        let db = self.db;
+        let ProgramClauseData(implication) = clause.data(db.interner());
+        match domain_goal_trait(db, &implication.skip_binders().consequence) {
+            Some(trait_id) => !self.trimmer.implied(db, trait_id).is_disjoint(&self.needed),
+            None => true,
+        }
+    }
+}
+
+/// Collects the items that a value mentions, and those that the
+/// definitions of those items mention in turn.
+struct ItemCollector<'i, I: Interner> {
+    db: &'i dyn RustIrDatabase<I>,
+    seen: FxHashSet<Item<I>>,
+    /// Items in `seen` whose definitions have not been visited yet.
+    pending: Vec<Item<I>>,
+}
+
+impl<'i, I: Interner> ItemCollector<'i, I> {
+    fn new(db: &'i dyn RustIrDatabase<I>) -> Self {
+        ItemCollector {
+            db,
+            seen: FxHashSet::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn visit_definition(&mut self, item: Item<I>) -> ControlFlow<()> {
+        let db = self.db;
+        let interner = db.interner();
+        match item {
+            Item::Trait(trait_id) => {
+                let trait_datum = db.trait_datum(trait_id);
+                match trait_datum.well_known {
+                    // These are solved by builtin rules that look at
+                    // more than the trait's own where clauses.
+                    Some(
+                        WellKnownTrait::Unsize
+                        | WellKnownTrait::CoerceUnsized
+                        | WellKnownTrait::DispatchFromDyn
+                        | WellKnownTrait::Generator,
+                    ) => return ControlFlow::BREAK,
+                    // The builtin rules for the others may consult each
+                    // other (e.g. `Pointee` looks at `Sized`).
+                    Some(_) => {
+                        for &well_known in BUILTIN_TRAITS {
+                            if let Some(trait_id) = db.well_known_trait_id(well_known) {
+                                self.record(Item::Trait(trait_id));
+                            }
+                        }
+                    }
+                    None => {}
+                }
+                try_break!(trait_datum.visit_with(self, DebruijnIndex::INNERMOST));
+                for &assoc_ty_id in &trait_datum.associated_ty_ids {
+                    try_break!(db
+                        .associated_ty_data(assoc_ty_id)
+                        .visit_with(self, DebruijnIndex::INNERMOST));
+                }
+
+                let parameters = trait_datum.binders.identity_substitution(interner);
+                let binders = CanonicalVarKinds::from_iter(
+                    interner,
+                    trait_datum
+                        .binders
+                        .binders
+                        .iter(interner)
+                        .map(|kind| WithKind::new(kind.clone(), UniverseIndex::ROOT)),
+                );
+                for impl_id in db.impls_for_trait(trait_id, parameters.as_slice(interner), &binders)
+                {
+                    let impl_datum = db.impl_datum(impl_id);
+                    try_break!(impl_datum.visit_with(self, DebruijnIndex::INNERMOST));
+                    for &value_id in &impl_datum.associated_ty_value_ids {
+                        try_break!(db
+                            .associated_ty_value(value_id)
+                            .visit_with(self, DebruijnIndex::INNERMOST));
+                    }
+                }
+            }
+            Item::Adt(adt_id) => {
+                try_break!(db
+                    .adt_datum(adt_id)
+                    .visit_with(self, DebruijnIndex::INNERMOST));
+            }
+            Item::FnDef(fn_def_id) => {
+                try_break!(db
+                    .fn_def_datum(fn_def_id)
+                    .visit_with(self, DebruijnIndex::INNERMOST));
+            }
+            Item::OpaqueTy(opaque_ty_id) => {
+                try_break!(db
+                    .opaque_ty_data(opaque_ty_id)
+                    .visit_with(self, DebruijnIndex::INNERMOST));
+                try_break!(db
+                    .hidden_opaque_type(opaque_ty_id)
+                    .visit_with(self, DebruijnIndex::INNERMOST));
+            }
+        }
+        ControlFlow::CONTINUE
+    }
+
+    fn record_alias(&mut self, alias: &AliasTy<I>) {
+        match alias {
+            AliasTy::Projection(_) => {
+                if let Some(trait_id) = alias_trait(self.db, alias) {
+                    self.record(Item::Trait(trait_id));
+                }
+            }
+            AliasTy::Opaque(opaque_ty) => self.record(Item::OpaqueTy(opaque_ty.opaque_ty_id)),
+        }
+    }
+
+    fn record(&mut self, item: Item<I>) {
+        if self.seen.insert(item) {
+            self.pending.push(item);
+        }
+    }
+}
+
+/// The trait an alias belongs to, if it is a projection.
+fn alias_trait<I: Interner>(db: &dyn RustIrDatabase<I>, alias: &AliasTy<I>) -> Option<TraitId<I>> {
+    match alias {
+        AliasTy::Projection(projection) => {
+            Some(db.associated_ty_data(projection.associated_ty_id).trait_id)
+        }
+        AliasTy::Opaque(_) => None,
+    }
+}
+
+fn where_clause_trait<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    where_clause: &WhereClause<I>,
+) -> Option<TraitId<I>> {
+    match where_clause {
+        WhereClause::Implemented(trait_ref) => Some(trait_ref.trait_id),
+        WhereClause::AliasEq(alias_eq) => alias_trait(db, &alias_eq.alias),
+        WhereClause::LifetimeOutlives(_) | WhereClause::TypeOutlives(_) => None,
+    }
+}
+
+/// The trait a domain goal is about, if any.
+fn domain_goal_trait<I: Interner>(
+    db: &dyn RustIrDatabase<I>,
+    domain_goal: &DomainGoal<I>,
+) -> Option<TraitId<I>> {
+    match domain_goal {
+        DomainGoal::Holds(where_clause) => where_clause_trait(db, where_clause),
+        DomainGoal::WellFormed(WellFormed::Trait(trait_ref))
+        | DomainGoal::FromEnv(FromEnv::Trait(trait_ref)) => Some(trait_ref.trait_id),
+        DomainGoal::Normalize(normalize) => alias_trait(db, &normalize.alias),
+        _ => None,
+    }
+}
+
+impl<'i, I: Interner> Visitor<'i, I> for ItemCollector<'i, I>
+where
+    I: 'i,
+{
+    type BreakTy = ();
+
+    fn as_dyn(&mut self) -> &mut dyn Visitor<'i, I, BreakTy = Self::BreakTy> {
+        self
+    }
+
+    fn interner(&self) -> &'i I {
+        self.db.interner()
+    }
+
+    fn visit_ty(&mut self, ty: &Ty<I>, outer_binder: DebruijnIndex) -> ControlFlow<()> {
+        match ty.kind(self.db.interner()) {
+            TyKind::Adt(adt_id, _) => self.record(Item::Adt(*adt_id)),
+            TyKind::FnDef(fn_def_id, _) => self.record(Item::FnDef(*fn_def_id)),
+            TyKind::OpaqueType(opaque_ty_id, _) => self.record(Item::OpaqueTy(*opaque_ty_id)),
+            TyKind::AssociatedType(assoc_ty_id, _) => {
+                let trait_id = self.db.associated_ty_data(*assoc_ty_id).trait_id;
+                self.record(Item::Trait(trait_id));
+            }
+            TyKind::Alias(alias) => self.record_alias(alias),
+            // The builtin impls for these depend on upvars and witness
+            // types, which we do not follow.
+            TyKind::Closure(..) | TyKind::Generator(..) | TyKind::GeneratorWitness(..) => {
+                return ControlFlow::BREAK;
+            }
+            _ => {}
+        }
+        ty.super_visit_with(self, outer_binder)
+    }
+
+    fn visit_where_clause(
+        &mut self,
+        where_clause: &WhereClause<I>,
+        outer_binder: DebruijnIndex,
+    ) -> ControlFlow<()> {
+        match where_clause {
+            WhereClause::Implemented(trait_ref) => self.record(Item::Trait(trait_ref.trait_id)),
+            WhereClause::AliasEq(alias_eq) => self.record_alias(&alias_eq.alias),
+            WhereClause::LifetimeOutlives(_) | WhereClause::TypeOutlives(_) => {}
+        }
+        where_clause.super_visit_with(self.as_dyn(), outer_binder)
+    }
+
+    fn visit_domain_goal(
+        &mut self,
+        domain_goal: &DomainGoal<I>,
+        outer_binder: DebruijnIndex,
+    ) -> ControlFlow<()> {
+        match domain_goal {
+            DomainGoal::WellFormed(WellFormed::Trait(trait_ref))
+            | DomainGoal::FromEnv(FromEnv::Trait(trait_ref)) => {
+                self.record(Item::Trait(trait_ref.trait_id))
+            }
+            DomainGoal::Normalize(normalize) => self.record_alias(&normalize.alias),
+            DomainGoal::Holds(_) | DomainGoal::WellFormed(_) | DomainGoal::FromEnv(_) => {}
+            // Coherence and the like; leave those environments alone.
+            _ => return ControlFlow::BREAK,
+        }
+        domain_goal.super_visit_with(self.as_dyn(), outer_binder)
+    }
+}
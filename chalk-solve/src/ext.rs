@@ -1,4 +1,6 @@
+use crate::env_trim::trim_environment;
 use crate::infer::InferenceTable;
+use crate::RustIrDatabase;
 use chalk_ir::fold::Fold;
 use chalk_ir::interner::{HasInterner, Interner};
 use chalk_ir::*;
@@ -52,6 +54,10 @@ where
 
 pub trait GoalExt<I: Interner> {
     fn into_peeled_goal(self, interner: &I) -> UCanonical<InEnvironment<Goal<I>>>;
+    fn into_trimmed_peeled_goal(
+        self,
+        db: &dyn RustIrDatabase<I>,
+    ) -> UCanonical<InEnvironment<Goal<I>>>;
     fn into_closed_goal(self, interner: &I) -> UCanonical<InEnvironment<Goal<I>>>;
 }
 
@@ -64,35 +70,29 @@ impl<I: Interner> GoalExt<I> for Goal<I> {
     /// REPLs and tests but not much else.
     fn into_peeled_goal(self, interner: &I) -> UCanonical<InEnvironment<Goal<I>>> {
         let mut infer = InferenceTable::new();
-        let peeled_goal = {
-            let mut env_goal = InEnvironment::new(&Environment::new(interner), self);
-            loop {
-                let InEnvironment { environment, goal } = env_goal;
-                match goal.data(interner) {
-                    GoalData::Quantified(QuantifierKind::ForAll, subgoal) => {
-                        let subgoal = infer.instantiate_binders_universally(interner, subgoal);
-                        env_goal = InEnvironment::new(&environment, subgoal);
-                    }
-
-                    GoalData::Quantified(QuantifierKind::Exists, subgoal) => {
-                        let subgoal = infer.instantiate_binders_existentially(interner, subgoal);
-                        env_goal = InEnvironment::new(&environment, subgoal);
-                    }
-
-                    GoalData::Implies(wc, subgoal) => {
-                        let new_environment =
-                            environment.add_clauses(interner, wc.iter(interner).cloned());
-                        env_goal = InEnvironment::new(&new_environment, Goal::clone(subgoal));
-                    }
-
-                    _ => break InEnvironment::new(&environment, goal),
-                }
-            }
-        };
+        let peeled_goal = peel_goal(&mut infer, interner, self);
         let canonical = infer.canonicalize(interner, &peeled_goal).quantified;
         infer.u_canonicalize(interner, &canonical).quantified
     }
 
+    /// As `into_peeled_goal`, but drops the environment clauses that
+    /// cannot affect the goal (see `trim_environment`) before
+    /// canonicalizing, so that goals which differ only in irrelevant
+    /// assumptions share a canonical form. The solvers trim the goals
+    /// they look up themselves; this is for callers that key their own
+    /// caches on canonical goals.
+    fn into_trimmed_peeled_goal(
+        self,
+        db: &dyn RustIrDatabase<I>,
+    ) -> UCanonical<InEnvironment<Goal<I>>> {
+        let interner = db.interner();
+        let mut infer = InferenceTable::new();
+        let peeled_goal = peel_goal(&mut infer, interner, self);
+        let trimmed_goal = trim_environment(db, &peeled_goal);
+        let canonical = infer.canonicalize(interner, &trimmed_goal).quantified;
+        infer.u_canonicalize(interner, &canonical).quantified
+    }
+
     /// Given a goal with no free variables (a "closed" goal), creates
     /// a canonical form suitable for solving. This is a suitable
     /// choice if you don't actually care about the values of any of
@@ -109,3 +109,34 @@ impl<I: Interner> GoalExt<I> for Goal<I> {
         infer.u_canonicalize(interner, &canonical_goal).quantified
     }
 }
+
+/// Converts the outermost quantifiers and implications of `goal` into
+/// free variables and environment clauses; see `into_peeled_goal`.
+fn peel_goal<I: Interner>(
+    infer: &mut InferenceTable<I>,
+    interner: &I,
+    goal: Goal<I>,
+) -> InEnvironment<Goal<I>> {
+    let mut env_goal = InEnvironment::new(&Environment::new(interner), goal);
+    loop {
+        let InEnvironment { environment, goal } = env_goal;
+        match goal.data(interner) {
+            GoalData::Quantified(QuantifierKind::ForAll, subgoal) => {
+                let subgoal = infer.instantiate_binders_universally(interner, subgoal);
+                env_goal = InEnvironment::new(&environment, subgoal);
+            }
+
+            GoalData::Quantified(QuantifierKind::Exists, subgoal) => {
+                let subgoal = infer.instantiate_binders_existentially(interner, subgoal);
+                env_goal = InEnvironment::new(&environment, subgoal);
+            }
+
+            GoalData::Implies(wc, subgoal) => {
+                let new_environment = environment.add_clauses(interner, wc.iter(interner).cloned());
+                env_goal = InEnvironment::new(&new_environment, Goal::clone(subgoal));
+            }
+
+            _ => break InEnvironment::new(&environment, goal),
+        }
+    }
+}
@@ -18,6 +18,33 @@ pub struct InferenceTable<I: Interner> {
     unify: ena::unify::InPlaceUnificationTable<EnaVariable<I>>,
     vars: Vec<EnaVariable<I>>,
     max_universe: UniverseIndex,
+    normalization: NormalizationMode,
+}
+
+/// How unification treats alias types (associated type projections and
+/// `impl Trait` types).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NormalizationMode {
+    /// Relating an alias with any other type produces an `AliasEq`
+    /// subgoal, and inference variables are never bound to types
+    /// containing aliases, so aliases are normalized as soon as they
+    /// are unified with anything.
+    #[default]
+    Eager,
+    /// Aliases are rigid: two projections of the same associated type
+    /// (or two uses of the same `impl Trait`) are first related by
+    /// their parameters, and an inference variable unified with a type
+    /// that contains an alias is bound to it as is. An `AliasEq`
+    /// subgoal is only produced when an alias itself meets a different
+    /// type (or an inference variable), so normalization is deferred
+    /// until it is needed -- and answers may mention aliases that were
+    /// never normalized.
+    ///
+    /// This avoids much of the ambiguity and overflow that eager
+    /// normalization runs into on deeply nested projections, at the
+    /// cost of committing to the parameters of a projection when
+    /// unifying it with another projection of the same item.
+    Lazy,
 }
 
 pub(crate) struct InferenceSnapshot<I: Interner> {
@@ -36,9 +63,21 @@ impl<I: Interner> InferenceTable<I> {
             unify: ena::unify::UnificationTable::new(),
             vars: vec![],
             max_universe: UniverseIndex::root(),
+            normalization: NormalizationMode::default(),
         }
     }
 
+    /// How unification in this table treats alias types.
+    pub fn normalization_mode(&self) -> NormalizationMode {
+        self.normalization
+    }
+
+    /// Sets how unification in this table treats alias types; see
+    /// `NormalizationMode`.
+    pub fn set_normalization_mode(&mut self, mode: NormalizationMode) {
+        self.normalization = mode;
+    }
+
     /// Creates a new inference table, pre-populated with
     /// `num_universes` fresh universes. Instantiates the canonical
     /// value `canonical` within those universes (which must not
@@ -154,6 +154,14 @@ impl<'t, I: Interner> Unifier<'t, I> {
                 a, b
             ),
 
+            // In lazy mode, aliases are rigid: identical aliases are
+            // related without normalizing them.
+            (TyKind::Alias(alias_a), TyKind::Alias(_))
+                if self.table.normalization == NormalizationMode::Lazy =>
+            {
+                self.relate_alias_alias(variance, alias_a, a, b)
+            }
+
             // Unifying an alias type with some other type `U`.
             (_, &TyKind::Alias(ref alias)) => self.relate_alias_ty(variance.invert(), alias, a),
             (&TyKind::Alias(ref alias), _) => self.relate_alias_ty(variance, alias, b),
@@ -455,6 +463,32 @@ impl<'t, I: Interner> Unifier<'t, I> {
         }
     }
 
+    /// Relates two aliases in lazy normalization mode. Two aliases that are
+    /// already the same type are trivially related. Otherwise we must not
+    /// relate their parameters: projections of the same associated type
+    /// with different parameters may still normalize to the same type (and
+    /// parameters that unify are only one way of making them equal), so we
+    /// emit an `AliasEq` goal as in `relate_alias_ty`.
+    #[instrument(level = "debug", skip(self))]
+    fn relate_alias_alias(
+        &mut self,
+        variance: Variance,
+        alias: &AliasTy<I>,
+        a: &Ty<I>,
+        b: &Ty<I>,
+    ) -> Fallible<()> {
+        let interner = self.interner;
+        // Canonicalizing both types at once resolves the inference
+        // variables bound so far, without binding any new ones, and names
+        // the remaining ones consistently.
+        let both = Substitution::from_iter(interner, [a, b].iter().cloned().cloned());
+        let both = self.table.canonicalize(interner, &both).quantified.value;
+        if both.at(interner, 0) == both.at(interner, 1) {
+            return Ok(());
+        }
+        self.relate_alias_ty(variance, alias, b)
+    }
+
     #[instrument(level = "debug", skip(self))]
     fn generalize_ty(&mut self, ty: &Ty<I>, universe_index: UniverseIndex) -> Ty<I> {
         let interner = self.interner;
@@ -635,6 +669,29 @@ impl<'t, I: Interner> Unifier<'t, I> {
                 // generic over, so we just relate directly to it
                 ty.clone()
             }
+            // In lazy mode, aliases are rigid, so we keep them (relating
+            // their parameters to the original ones in
+            // `relate_alias_alias`).
+            TyKind::Alias(AliasTy::Projection(projection))
+                if self.table.normalization == NormalizationMode::Lazy =>
+            {
+                TyKind::Alias(AliasTy::Projection(ProjectionTy {
+                    associated_ty_id: projection.associated_ty_id,
+                    substitution: self
+                        .generalize_substitution(&projection.substitution, universe_index),
+                }))
+                .intern(interner)
+            }
+            TyKind::Alias(AliasTy::Opaque(opaque_ty))
+                if self.table.normalization == NormalizationMode::Lazy =>
+            {
+                TyKind::Alias(AliasTy::Opaque(OpaqueTy {
+                    opaque_ty_id: opaque_ty.opaque_ty_id,
+                    substitution: self
+                        .generalize_substitution(&opaque_ty.substitution, universe_index),
+                }))
+                .intern(interner)
+            }
             TyKind::Alias(_) => {
                 let ena_var = self.table.new_variable(universe_index);
                 ena_var.to_ty(interner)
@@ -956,6 +1013,11 @@ impl<'t, I: Interner> Unifier<'t, I> {
         let a = n_a.as_ref().unwrap_or(a);
         let b = n_b.as_ref().unwrap_or(b);
 
+        let e_a = self.try_evaluate_const(a)?;
+        let e_b = self.try_evaluate_const(b)?;
+        let a = e_a.as_ref().unwrap_or(a);
+        let b = e_b.as_ref().unwrap_or(b);
+
         debug_span!("relate_const_const", ?variance, ?a, ?b);
 
         let ConstData {
@@ -985,13 +1047,15 @@ impl<'t, I: Interner> Unifier<'t, I> {
 
             // Unifying an inference variables with a non-inference variable.
             (&ConstValue::InferenceVar(var), &ConstValue::Concrete(_))
-            | (&ConstValue::InferenceVar(var), &ConstValue::Placeholder(_)) => {
+            | (&ConstValue::InferenceVar(var), &ConstValue::Placeholder(_))
+            | (&ConstValue::InferenceVar(var), &ConstValue::Unevaluated(_)) => {
                 debug!(?var, ty=?b, "unify_var_ty");
                 self.unify_var_const(var, b)
             }
 
             (&ConstValue::Concrete(_), &ConstValue::InferenceVar(var))
-            | (&ConstValue::Placeholder(_), &ConstValue::InferenceVar(var)) => {
+            | (&ConstValue::Placeholder(_), &ConstValue::InferenceVar(var))
+            | (&ConstValue::Unevaluated(_), &ConstValue::InferenceVar(var)) => {
                 debug!(?var, ty=?a, "unify_var_ty");
                 self.unify_var_const(var, a)
             }
@@ -1015,6 +1079,77 @@ impl<'t, I: Interner> Unifier<'t, I> {
                 "unification encountered bound variable: a={:?} b={:?}",
                 a, b
             ),
+
+            // Two occurrences of the same expression are equal if their
+            // arguments are.
+            (ConstValue::Unevaluated(u1), ConstValue::Unevaluated(u2))
+                if u1.interned == u2.interned =>
+            {
+                self.zip_substs(
+                    Variance::Invariant,
+                    None,
+                    u1.substitution.as_slice(interner),
+                    u2.substitution.as_slice(interner),
+                )
+            }
+
+            // We couldn't evaluate the constant (yet), so we can't tell
+            // whether the two are equal.
+            (ConstValue::Unevaluated(_), _) | (_, ConstValue::Unevaluated(_)) => {
+                self.goals.push(InEnvironment::new(
+                    self.environment,
+                    GoalData::CannotProve.intern(interner),
+                ));
+                Ok(())
+            }
+        }
+    }
+
+    /// If `c` is an unevaluated constant, asks the interner to evaluate it,
+    /// after resolving the inference variables among its arguments as far as
+    /// possible. Returns `None` if `c` is not an unevaluated constant or if
+    /// it is too generic to be evaluated yet.
+    fn try_evaluate_const(&mut self, c: &Const<I>) -> Fallible<Option<Const<I>>> {
+        let interner = self.interner;
+        let ConstData { ty, value } = c.data(interner);
+        let unevaluated = match value {
+            ConstValue::Unevaluated(unevaluated) => unevaluated,
+            _ => return Ok(None),
+        };
+
+        let table = &mut self.table;
+        let substitution = Substitution::from_iter(
+            interner,
+            unevaluated.substitution.iter(interner).map(|arg| {
+                match arg.data(interner) {
+                    GenericArgData::Ty(ty) => table
+                        .normalize_ty_shallow(interner, ty)
+                        .map(|ty| ty.cast(interner)),
+                    GenericArgData::Lifetime(lifetime) => table
+                        .normalize_lifetime_shallow(interner, lifetime)
+                        .map(|lifetime| lifetime.cast(interner)),
+                    GenericArgData::Const(constant) => table
+                        .normalize_const_shallow(interner, constant)
+                        .map(|constant| constant.cast(interner)),
+                }
+                .unwrap_or_else(|| arg.clone())
+            }),
+        );
+        let unevaluated = UnevaluatedConst {
+            interned: unevaluated.interned.clone(),
+            substitution,
+        };
+
+        match interner.try_evaluate_const(ty.interned(), &unevaluated) {
+            Ok(interned) => Ok(Some(
+                ConstData {
+                    ty: ty.clone(),
+                    value: ConstValue::Concrete(ConcreteConst { interned }),
+                }
+                .intern(interner),
+            )),
+            Err(ConstEvalError::TooGeneric) => Ok(None),
+            Err(ConstEvalError::Failed) => Err(NoSolution),
         }
     }
 
@@ -1,5 +1,6 @@
 #![deny(rust_2018_idioms)]
 
+use crate::dependencies::{DependencyRecorder, ItemId};
 use crate::display::sanitize_debug_name;
 use crate::rust_ir::*;
 use chalk_ir::interner::Interner;
@@ -11,13 +12,16 @@ use std::sync::Arc;
 pub mod clauses;
 pub mod coherence;
 pub mod coinductive_goal;
+pub mod dependencies;
 pub mod display;
+pub mod env_trim;
 pub mod ext;
 pub mod goal_builder;
 pub mod infer;
 pub mod logging;
 pub mod logging_db;
 pub mod rust_ir;
+pub mod simplified_type;
 pub mod solve;
 pub mod split;
 pub mod wf;
@@ -38,6 +42,21 @@ pub mod wf;
 /// The [`display::sanitize_debug_name`] utility is used in the default
 /// implementations, and might be useful when providing custom implementations.
 ///
+/// # `try_*` methods
+///
+/// Hosts that cannot always produce the data for an item, e.g. because the
+/// code being analyzed is incomplete, can override the `try_*` methods to
+/// report a [`LookupError`] instead. Their default implementations call
+/// the corresponding infallible methods.
+///
+/// The program clauses for a goal are built using the `try_*` methods
+/// only: if one of them fails, the goal is ambiguous, with
+/// [`AmbiguityCause::LookupFailed`] naming the item. The infallible
+/// methods are still called for items whose `try_*` lookup succeeded, so
+/// they must succeed for those. Other uses of the database, such as
+/// coherence and well-formedness checking, only call the infallible
+/// methods.
+///
 /// [`LoggingRustIrDatabase`]: crate::logging_db::LoggingRustIrDatabase
 /// [`display::sanitize_debug_name`]: crate::display::sanitize_debug_name
 /// [`Interner`]: Interner
@@ -65,7 +84,7 @@ pub trait RustIrDatabase<I: Interner>: Debug {
     ) -> Arc<GeneratorWitnessDatum<I>>;
 
     /// Returns the representation for the ADT definition with the given id.
-    fn adt_repr(&self, id: AdtId<I>) -> AdtRepr;
+    fn adt_repr(&self, id: AdtId<I>) -> Arc<AdtRepr<I>>;
 
     /// Returns the datum for the fn definition with the given id.
     fn fn_def_datum(&self, fn_def_id: FnDefId<I>) -> Arc<FnDefDatum<I>>;
@@ -82,6 +101,69 @@ pub trait RustIrDatabase<I: Interner>: Debug {
     /// Returns the "hidden type" corresponding with the opaque type.
     fn hidden_opaque_type(&self, id: OpaqueTyId<I>) -> Ty<I>;
 
+    /// Fallible version of `associated_ty_data`.
+    fn try_associated_ty_data(
+        &self,
+        ty: AssocTypeId<I>,
+    ) -> Result<Arc<AssociatedTyDatum<I>>, LookupError<I>> {
+        Ok(self.associated_ty_data(ty))
+    }
+
+    /// Fallible version of `trait_datum`.
+    fn try_trait_datum(&self, trait_id: TraitId<I>) -> Result<Arc<TraitDatum<I>>, LookupError<I>> {
+        Ok(self.trait_datum(trait_id))
+    }
+
+    /// Fallible version of `adt_datum`.
+    fn try_adt_datum(&self, adt_id: AdtId<I>) -> Result<Arc<AdtDatum<I>>, LookupError<I>> {
+        Ok(self.adt_datum(adt_id))
+    }
+
+    /// Fallible version of `generator_datum`.
+    fn try_generator_datum(
+        &self,
+        generator_id: GeneratorId<I>,
+    ) -> Result<Arc<GeneratorDatum<I>>, LookupError<I>> {
+        Ok(self.generator_datum(generator_id))
+    }
+
+    /// Fallible version of `generator_witness_datum`.
+    fn try_generator_witness_datum(
+        &self,
+        generator_id: GeneratorId<I>,
+    ) -> Result<Arc<GeneratorWitnessDatum<I>>, LookupError<I>> {
+        Ok(self.generator_witness_datum(generator_id))
+    }
+
+    /// Fallible version of `fn_def_datum`.
+    fn try_fn_def_datum(
+        &self,
+        fn_def_id: FnDefId<I>,
+    ) -> Result<Arc<FnDefDatum<I>>, LookupError<I>> {
+        Ok(self.fn_def_datum(fn_def_id))
+    }
+
+    /// Fallible version of `impl_datum`.
+    fn try_impl_datum(&self, impl_id: ImplId<I>) -> Result<Arc<ImplDatum<I>>, LookupError<I>> {
+        Ok(self.impl_datum(impl_id))
+    }
+
+    /// Fallible version of `associated_ty_value`.
+    fn try_associated_ty_value(
+        &self,
+        id: AssociatedTyValueId<I>,
+    ) -> Result<Arc<AssociatedTyValue<I>>, LookupError<I>> {
+        Ok(self.associated_ty_value(id))
+    }
+
+    /// Fallible version of `opaque_ty_data`.
+    fn try_opaque_ty_data(
+        &self,
+        id: OpaqueTyId<I>,
+    ) -> Result<Arc<OpaqueTyDatum<I>>, LookupError<I>> {
+        Ok(self.opaque_ty_data(id))
+    }
+
     /// Returns a list of potentially relevant impls for a given
     /// trait-id; we also supply the type parameters that we are
     /// trying to match (if known: these parameters may contain
@@ -119,14 +201,32 @@ pub trait RustIrDatabase<I: Interner>: Debug {
     /// Returns id of a trait lang item, if found
     fn well_known_trait_id(&self, well_known_trait: WellKnownTrait) -> Option<TraitId<I>>;
 
+    /// Returns id of an ADT lang item, if found. None are found by
+    /// default.
+    #[allow(unused_variables)]
+    fn well_known_adt_id(&self, well_known_adt: WellKnownAdt) -> Option<AdtId<I>> {
+        None
+    }
+
     /// Calculates program clauses from an env. This is intended to call the
     /// `program_clauses_for_env` function and then possibly cache the clauses.
-    fn program_clauses_for_env(&self, environment: &Environment<I>) -> ProgramClauses<I>;
+    /// Fails if an item needed to elaborate the environment cannot be looked
+    /// up (see the `try_*` methods).
+    fn program_clauses_for_env(
+        &self,
+        environment: &Environment<I>,
+    ) -> Result<ProgramClauses<I>, LookupError<I>>;
 
     fn interner(&self) -> &I;
 
-    /// Check if a trait is object safe
-    fn is_object_safe(&self, trait_id: TraitId<I>) -> bool;
+    /// Check if a trait is object safe. `None` asks the solver to compute
+    /// object safety itself from the trait's where clauses, associated items
+    /// and methods (see `TraitDatumBound::methods`), which is only correct if
+    /// the `TraitDatum` describes all of them. Returns `None` by default.
+    #[allow(unused_variables)]
+    fn is_object_safe(&self, trait_id: TraitId<I>) -> Option<bool> {
+        None
+    }
 
     /// Gets the `ClosureKind` for a given closure and substitution.
     fn closure_kind(&self, closure_id: ClosureId<I>, substs: &Substitution<I>) -> ClosureKind;
@@ -163,6 +263,13 @@ pub trait RustIrDatabase<I: Interner>: Debug {
 
     fn unification_database(&self) -> &dyn UnificationDatabase<I>;
 
+    /// Returns the recorder that this database reports the items it is
+    /// asked about to, if it tracks them. Solvers use it to find out which
+    /// items their cached results depend upon (see [`dependencies`]).
+    fn dependency_recorder(&self) -> Option<&DependencyRecorder<I>> {
+        None
+    }
+
     /// Retrieves a trait's original name. No uniqueness guarantees, but must
     /// a valid Rust identifier.
     fn trait_name(&self, trait_id: TraitId<I>) -> String {
@@ -194,11 +301,29 @@ pub trait RustIrDatabase<I: Interner>: Debug {
     }
 }
 
+/// The error returned by the `try_*` methods of a [`RustIrDatabase`]:
+/// the data for `item` could not be produced, e.g. because its
+/// definition has errors.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LookupError<I: Interner> {
+    pub item: ItemId<I>,
+}
+
+impl<I: Interner> LookupError<I> {
+    pub fn new(item: ItemId<I>) -> Self {
+        LookupError { item }
+    }
+}
+
 pub use clauses::program_clauses_for_env;
 
+pub use solve::AmbiguityCause;
+pub use solve::Answers;
 pub use solve::Guidance;
+pub use solve::ProofTree;
 pub use solve::Solution;
 pub use solve::Solver;
+pub use solve::SolverStats;
 pub use solve::SubstitutionResult;
 
 #[macro_use]
@@ -13,8 +13,9 @@ use std::{
 
 use crate::rust_ir::*;
 use crate::{
+    dependencies::DependencyRecorder,
     display::{self, WriterState},
-    RustIrDatabase,
+    LookupError, RustIrDatabase,
 };
 use chalk_ir::{interner::Interner, *};
 
@@ -151,7 +152,7 @@ where
         self.ws.db().borrow().generator_witness_datum(generator_id)
     }
 
-    fn adt_repr(&self, id: AdtId<I>) -> AdtRepr {
+    fn adt_repr(&self, id: AdtId<I>) -> Arc<AdtRepr<I>> {
         self.record(id);
         self.ws.db().adt_repr(id)
     }
@@ -180,6 +181,81 @@ where
         self.ws.db().opaque_ty_data(id)
     }
 
+    // Items that fail to look up are not recorded, as we could not write
+    // them out.
+
+    fn try_associated_ty_data(
+        &self,
+        ty: AssocTypeId<I>,
+    ) -> Result<Arc<AssociatedTyDatum<I>>, LookupError<I>> {
+        let ty_datum = self.ws.db().try_associated_ty_data(ty)?;
+        self.record(ty_datum.trait_id);
+        Ok(ty_datum)
+    }
+
+    fn try_trait_datum(&self, trait_id: TraitId<I>) -> Result<Arc<TraitDatum<I>>, LookupError<I>> {
+        let trait_datum = self.ws.db().try_trait_datum(trait_id)?;
+        self.record(trait_id);
+        Ok(trait_datum)
+    }
+
+    fn try_adt_datum(&self, adt_id: AdtId<I>) -> Result<Arc<AdtDatum<I>>, LookupError<I>> {
+        let adt_datum = self.ws.db().try_adt_datum(adt_id)?;
+        self.record(adt_id);
+        Ok(adt_datum)
+    }
+
+    fn try_generator_datum(
+        &self,
+        generator_id: GeneratorId<I>,
+    ) -> Result<Arc<GeneratorDatum<I>>, LookupError<I>> {
+        let generator_datum = self.ws.db().try_generator_datum(generator_id)?;
+        self.record(generator_id);
+        Ok(generator_datum)
+    }
+
+    fn try_generator_witness_datum(
+        &self,
+        generator_id: GeneratorId<I>,
+    ) -> Result<Arc<GeneratorWitnessDatum<I>>, LookupError<I>> {
+        let witness_datum = self.ws.db().try_generator_witness_datum(generator_id)?;
+        self.record(generator_id);
+        Ok(witness_datum)
+    }
+
+    fn try_fn_def_datum(
+        &self,
+        fn_def_id: FnDefId<I>,
+    ) -> Result<Arc<FnDefDatum<I>>, LookupError<I>> {
+        let fn_def_datum = self.ws.db().try_fn_def_datum(fn_def_id)?;
+        self.record(fn_def_id);
+        Ok(fn_def_datum)
+    }
+
+    fn try_impl_datum(&self, impl_id: ImplId<I>) -> Result<Arc<ImplDatum<I>>, LookupError<I>> {
+        let impl_datum = self.ws.db().try_impl_datum(impl_id)?;
+        self.record(impl_id);
+        Ok(impl_datum)
+    }
+
+    fn try_associated_ty_value(
+        &self,
+        id: AssociatedTyValueId<I>,
+    ) -> Result<Arc<AssociatedTyValue<I>>, LookupError<I>> {
+        let value = self.ws.db().try_associated_ty_value(id)?;
+        self.record(value.impl_id);
+        Ok(value)
+    }
+
+    fn try_opaque_ty_data(
+        &self,
+        id: OpaqueTyId<I>,
+    ) -> Result<Arc<OpaqueTyDatum<I>>, LookupError<I>> {
+        let opaque_ty_datum = self.ws.db().try_opaque_ty_data(id)?;
+        self.record(id);
+        Ok(opaque_ty_datum)
+    }
+
     fn impls_for_trait(
         &self,
         trait_id: TraitId<I>,
@@ -214,10 +290,18 @@ where
         trait_id
     }
 
+    fn well_known_adt_id(&self, well_known_adt: crate::rust_ir::WellKnownAdt) -> Option<AdtId<I>> {
+        let adt_id = self.ws.db().well_known_adt_id(well_known_adt);
+        if let Some(id) = adt_id {
+            self.record(id);
+        }
+        adt_id
+    }
+
     fn program_clauses_for_env(
         &self,
         environment: &chalk_ir::Environment<I>,
-    ) -> chalk_ir::ProgramClauses<I> {
+    ) -> Result<chalk_ir::ProgramClauses<I>, LookupError<I>> {
         self.ws.db().program_clauses_for_env(environment)
     }
 
@@ -241,7 +325,7 @@ where
         self.ws.db().opaque_type_name(opaque_ty_id)
     }
 
-    fn is_object_safe(&self, trait_id: TraitId<I>) -> bool {
+    fn is_object_safe(&self, trait_id: TraitId<I>) -> Option<bool> {
         self.record(trait_id);
         self.ws.db().is_object_safe(trait_id)
     }
@@ -286,6 +370,10 @@ where
     fn unification_database(&self) -> &dyn UnificationDatabase<I> {
         self
     }
+
+    fn dependency_recorder(&self) -> Option<&DependencyRecorder<I>> {
+        self.ws.db().dependency_recorder()
+    }
 }
 
 /// Wraps a [`RustIrDatabase`], and, when dropped, writes out all used
@@ -411,7 +499,7 @@ where
         self.db.borrow().generator_witness_datum(generator_id)
     }
 
-    fn adt_repr(&self, id: AdtId<I>) -> AdtRepr {
+    fn adt_repr(&self, id: AdtId<I>) -> Arc<AdtRepr<I>> {
         self.db.adt_repr(id)
     }
 
@@ -430,6 +518,60 @@ where
         self.db.opaque_ty_data(id)
     }
 
+    fn try_associated_ty_data(
+        &self,
+        ty: AssocTypeId<I>,
+    ) -> Result<Arc<AssociatedTyDatum<I>>, LookupError<I>> {
+        self.db.try_associated_ty_data(ty)
+    }
+
+    fn try_trait_datum(&self, trait_id: TraitId<I>) -> Result<Arc<TraitDatum<I>>, LookupError<I>> {
+        self.db.try_trait_datum(trait_id)
+    }
+
+    fn try_adt_datum(&self, adt_id: AdtId<I>) -> Result<Arc<AdtDatum<I>>, LookupError<I>> {
+        self.db.try_adt_datum(adt_id)
+    }
+
+    fn try_generator_datum(
+        &self,
+        generator_id: GeneratorId<I>,
+    ) -> Result<Arc<GeneratorDatum<I>>, LookupError<I>> {
+        self.db.try_generator_datum(generator_id)
+    }
+
+    fn try_generator_witness_datum(
+        &self,
+        generator_id: GeneratorId<I>,
+    ) -> Result<Arc<GeneratorWitnessDatum<I>>, LookupError<I>> {
+        self.db.try_generator_witness_datum(generator_id)
+    }
+
+    fn try_fn_def_datum(
+        &self,
+        fn_def_id: FnDefId<I>,
+    ) -> Result<Arc<FnDefDatum<I>>, LookupError<I>> {
+        self.db.try_fn_def_datum(fn_def_id)
+    }
+
+    fn try_impl_datum(&self, impl_id: ImplId<I>) -> Result<Arc<ImplDatum<I>>, LookupError<I>> {
+        self.db.try_impl_datum(impl_id)
+    }
+
+    fn try_associated_ty_value(
+        &self,
+        id: AssociatedTyValueId<I>,
+    ) -> Result<Arc<AssociatedTyValue<I>>, LookupError<I>> {
+        self.db.try_associated_ty_value(id)
+    }
+
+    fn try_opaque_ty_data(
+        &self,
+        id: OpaqueTyId<I>,
+    ) -> Result<Arc<OpaqueTyDatum<I>>, LookupError<I>> {
+        self.db.try_opaque_ty_data(id)
+    }
+
     fn hidden_opaque_type(&self, id: OpaqueTyId<I>) -> Ty<I> {
         self.db.hidden_opaque_type(id)
     }
@@ -458,10 +600,14 @@ where
         self.db.well_known_trait_id(well_known_trait)
     }
 
+    fn well_known_adt_id(&self, well_known_adt: crate::rust_ir::WellKnownAdt) -> Option<AdtId<I>> {
+        self.db.well_known_adt_id(well_known_adt)
+    }
+
     fn program_clauses_for_env(
         &self,
         environment: &chalk_ir::Environment<I>,
-    ) -> chalk_ir::ProgramClauses<I> {
+    ) -> Result<chalk_ir::ProgramClauses<I>, LookupError<I>> {
         self.db.program_clauses_for_env(environment)
     }
 
@@ -469,7 +615,7 @@ where
         self.db.interner()
     }
 
-    fn is_object_safe(&self, trait_id: TraitId<I>) -> bool {
+    fn is_object_safe(&self, trait_id: TraitId<I>) -> Option<bool> {
         self.db.is_object_safe(trait_id)
     }
 
@@ -477,6 +623,10 @@ where
         self
     }
 
+    fn dependency_recorder(&self) -> Option<&DependencyRecorder<I>> {
+        self.db.dependency_recorder()
+    }
+
     fn trait_name(&self, trait_id: TraitId<I>) -> String {
         self.db.trait_name(trait_id)
     }
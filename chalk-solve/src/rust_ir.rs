@@ -14,9 +14,19 @@ use chalk_ir::{
     Ty, TyKind, VariableKind, WhereClause, WithKind,
 };
 use std::iter;
+#[cfg(feature = "serde")]
+use {
+    chalk_ir::interner::SerdeInterner,
+    serde::{Deserialize, Serialize},
+};
 
 /// Identifier for an "associated type value" found in some impl.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub struct AssociatedTyValueId<I: Interner>(pub I::DefId);
 
 chalk_ir::id_visit!(AssociatedTyValueId);
@@ -26,6 +36,7 @@ chalk_ir::id_fold!(AssociatedTyValueId);
 pub struct ImplDatum<I: Interner> {
     pub polarity: Polarity,
     pub binders: Binders<ImplDatumBound<I>>,
+    pub id: ImplId<I>,
     pub impl_type: ImplType,
     pub associated_ty_value_ids: Vec<AssociatedTyValueId<I>>,
 }
@@ -115,10 +126,13 @@ pub struct AdtFlags {
 
 chalk_ir::const_visit!(AdtFlags);
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct AdtRepr {
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, HasInterner, Visit)]
+pub struct AdtRepr<I: Interner> {
     pub repr_c: bool,
     pub repr_packed: bool,
+    /// The integer type used for the discriminant of an enum, given by an
+    /// attribute like `#[repr(u16)]`. `None` if no such attribute is present.
+    pub int: Option<Ty<I>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -246,6 +260,7 @@ pub struct TraitDatum<I: Interner> {
 /// A list of the traits that are "well known" to chalk, which means that
 /// the chalk-solve crate has special, hard-coded impls for them.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum WellKnownTrait {
     Sized,
     Copy,
@@ -260,10 +275,45 @@ pub enum WellKnownTrait {
     Unsize,
     Unpin,
     CoerceUnsized,
+    /// The trait `DiscriminantKind`, with a single associated type
+    /// `Discriminant` giving the type of `core::mem::discriminant` values.
+    DiscriminantKind,
+    /// The trait `Generator<R>`, implemented by generators resuming with
+    /// values of type `R`. It has two associated types, in this order:
+    /// `Yield` and `Return`.
+    Generator,
+    /// The trait `DispatchFromDyn<T>`, implemented by pointer types that
+    /// methods can be dispatched through when they point to a trait object.
+    /// Used to decide whether a method receiver is object safe.
+    DispatchFromDyn,
+    /// The trait `Pointee`, implemented by every type. It has a single
+    /// associated type `Metadata`, the metadata of pointers to the type:
+    /// `()` for sized types, `usize` for slices and `str`, and
+    /// `DynMetadata<dyn Trait>` for trait objects.
+    Pointee,
+    /// The marker trait `Tuple`, implemented exactly by tuple types. Used to
+    /// constrain the arguments of `extern "rust-call"` functions.
+    Tuple,
+    /// The trait `FnPtr`, implemented exactly by function pointer types. It
+    /// exposes their signature through two associated types, in this order:
+    /// `Args`, the tuple of argument types, and `Output`, the return type.
+    FnPtr,
 }
 
 chalk_ir::const_visit!(WellKnownTrait);
 
+/// A list of the ADTs that are "well known" to chalk, which means that
+/// the chalk-solve crate refers to them in its hard-coded impls.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum WellKnownAdt {
+    /// The struct `DynMetadata<Dyn>`, the metadata of pointers to the
+    /// trait object type `Dyn`.
+    DynMetadata,
+}
+
+chalk_ir::const_visit!(WellKnownAdt);
+
 impl<I: Interner> TraitDatum<I> {
     pub fn is_auto_trait(&self) -> bool {
         self.flags.auto
@@ -297,6 +347,43 @@ pub struct TraitDatumBound<I: Interner> {
     ///              ^^^^^^^^^^^^^^
     /// ```
     pub where_clauses: Vec<QuantifiedWhereClause<I>>,
+
+    /// The methods defined in the trait, used to determine whether the
+    /// trait is object safe:
+    ///
+    /// ```ignore
+    /// trait Foo<T> { fn foo<U>(self: &Self, u: U) -> T where U: Debug; }
+    ///                ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+    /// ```
+    ///
+    /// The binders of each method are its own generic parameters (`U`).
+    pub methods: Vec<Binders<TraitMethodDatum<I>>>,
+
+    /// The types of the associated consts defined in the trait. Their
+    /// presence makes the trait not object safe:
+    ///
+    /// ```ignore
+    /// trait Foo<T> { const C: T; }
+    ///                ^^^^^^^^^^^
+    /// ```
+    pub associated_consts: Vec<Ty<I>>,
+}
+
+/// The parts of a trait method that matter for object safety.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Fold, Visit, HasInterner)]
+pub struct TraitMethodDatum<I: Interner> {
+    /// The type of the `self` parameter, or `None` if the method has no
+    /// receiver (i.e. it is an associated function).
+    pub receiver: Option<Ty<I>>,
+
+    /// The types of the other parameters of the method.
+    pub argument_types: Vec<Ty<I>>,
+
+    /// The return type of the method.
+    pub return_type: Ty<I>,
+
+    /// Where clauses defined on the method.
+    pub where_clauses: Vec<QuantifiedWhereClause<I>>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -650,15 +737,15 @@ pub struct GeneratorDatum<I: Interner> {
 pub struct GeneratorInputOutputDatum<I: Interner> {
     /// The generator resume type - a value of this type
     /// is supplied by the caller when resuming the generator.
-    /// Currently, this plays no rule in goal resolution.
+    /// This is the `R` in `Generator<R>`.
     pub resume_type: Ty<I>,
     /// The generator yield type - a value of this type
     /// is supplied by the generator during a yield.
-    /// Currently, this plays no role in goal resolution.
+    /// This is the value of `<G as Generator<R>>::Yield`.
     pub yield_type: Ty<I>,
     /// The generator return type - a value of this type
     /// is supplied by the generator when it returns.
-    /// Currently, this plays no role in goal resolution
+    /// This is the value of `<G as Generator<R>>::Return`.
     pub return_type: Ty<I>,
     /// The upvars stored by the generator. These represent
     /// types captured from the generator's environment,
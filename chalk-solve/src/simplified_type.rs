@@ -0,0 +1,195 @@
+//! Indexing of impls by the head of their self type.
+//!
+//! `RustIrDatabase::impls_for_trait` is handed the parameters of the goal,
+//! so that hosts need not return impls that cannot possibly apply. Checking
+//! each impl with `could_match` is linear in the number of impls of the
+//! trait, which hurts for traits like `From` or `Debug` that have thousands
+//! of them. Instead, hosts can build an [`ImplIndex`], which files each
+//! impl under the [`SimplifiedType`] of its self type, and only consider
+//! the impls filed under the simplified self type of the goal, plus those
+//! whose self type does not simplify (e.g. `impl<T> Debug for T`).
+
+use chalk_ir::interner::Interner;
+use chalk_ir::*;
+use rustc_hash::FxHashMap;
+
+/// The head of a type, i.e. its outermost type constructor, ignoring its
+/// parameters. Two types with different simplified types never unify.
+///
+/// Types that may turn out to be anything, such as variables and aliases,
+/// do not have a simplified type (see [`SimplifiedType::from_ty`]).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SimplifiedType<I: Interner> {
+    Adt(AdtId<I>),
+    AssociatedType(AssocTypeId<I>),
+    Scalar(Scalar),
+    /// A tuple of the given arity.
+    Tuple(usize),
+    Array,
+    Slice,
+    Raw(Mutability),
+    Ref(Mutability),
+    OpaqueType(OpaqueTyId<I>),
+    FnDef(FnDefId<I>),
+    Str,
+    Never,
+    Closure(ClosureId<I>),
+    Generator(GeneratorId<I>),
+    GeneratorWitness(GeneratorId<I>),
+    Foreign(ForeignDefId<I>),
+    Dyn,
+    /// A function pointer with the given number of parameters.
+    Function(usize),
+}
+
+impl<I: Interner> SimplifiedType<I> {
+    /// Simplifies `ty` to its head. Returns `None` if `ty` could still
+    /// unify with types of any head: it is a variable, a placeholder, an
+    /// alias or an error. Integer and float variables are included, so
+    /// that the result does not depend on how far inference has gone. A
+    /// function pointer without a return type is malformed, and is treated
+    /// the same way.
+    pub fn from_ty(interner: &I, ty: &Ty<I>) -> Option<Self> {
+        Some(match ty.kind(interner) {
+            TyKind::Adt(id, _) => SimplifiedType::Adt(*id),
+            TyKind::AssociatedType(id, _) => SimplifiedType::AssociatedType(*id),
+            TyKind::Scalar(scalar) => SimplifiedType::Scalar(*scalar),
+            TyKind::Tuple(arity, _) => SimplifiedType::Tuple(*arity),
+            TyKind::Array(..) => SimplifiedType::Array,
+            TyKind::Slice(_) => SimplifiedType::Slice,
+            TyKind::Raw(mutability, _) => SimplifiedType::Raw(*mutability),
+            TyKind::Ref(mutability, ..) => SimplifiedType::Ref(*mutability),
+            TyKind::OpaqueType(id, _) => SimplifiedType::OpaqueType(*id),
+            TyKind::FnDef(id, _) => SimplifiedType::FnDef(*id),
+            TyKind::Str => SimplifiedType::Str,
+            TyKind::Never => SimplifiedType::Never,
+            TyKind::Closure(id, _) => SimplifiedType::Closure(*id),
+            TyKind::Generator(id, _) => SimplifiedType::Generator(*id),
+            TyKind::GeneratorWitness(id, _) => SimplifiedType::GeneratorWitness(*id),
+            TyKind::Foreign(id) => SimplifiedType::Foreign(*id),
+            TyKind::Dyn(_) => SimplifiedType::Dyn,
+            TyKind::Function(fn_ptr) => {
+                // The substitution holds the parameter types followed by
+                // the return type.
+                SimplifiedType::Function(fn_ptr.substitution.0.len(interner).checked_sub(1)?)
+            }
+            TyKind::Placeholder(_)
+            | TyKind::Alias(_)
+            | TyKind::BoundVar(_)
+            | TyKind::InferenceVar(..)
+            | TyKind::Error => return None,
+        })
+    }
+}
+
+/// The impls of a program, filed by trait and by the [`SimplifiedType`] of
+/// their self type.
+///
+/// [`ImplIndex::candidates`] returns the impls in the order they were
+/// inserted, so that hosts can keep answering `impls_for_trait` in the same
+/// order as with a linear scan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImplIndex<I: Interner> {
+    traits: FxHashMap<TraitId<I>, TraitImpls<I>>,
+
+    /// The number of impls inserted so far, used to order the candidates.
+    len: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TraitImpls<I: Interner> {
+    /// The impls whose self type has no simplified type, which apply to
+    /// any self type.
+    blanket: Vec<(usize, ImplId<I>)>,
+    by_self_ty: FxHashMap<SimplifiedType<I>, Vec<(usize, ImplId<I>)>>,
+}
+
+impl<I: Interner> Default for TraitImpls<I> {
+    fn default() -> Self {
+        TraitImpls {
+            blanket: Vec::new(),
+            by_self_ty: FxHashMap::default(),
+        }
+    }
+}
+
+impl<I: Interner> Default for ImplIndex<I> {
+    fn default() -> Self {
+        ImplIndex {
+            traits: FxHashMap::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<I: Interner> ImplIndex<I> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Files `impl_id`, whose header is `trait_ref` (with the impl's
+    /// parameters as bound variables).
+    pub fn insert(&mut self, interner: &I, impl_id: ImplId<I>, trait_ref: &TraitRef<I>) {
+        let entry = (self.len, impl_id);
+        self.len += 1;
+        let trait_impls = self.traits.entry(trait_ref.trait_id).or_default();
+        match SimplifiedType::from_ty(interner, &trait_ref.self_type_parameter(interner)) {
+            Some(self_ty) => trait_impls
+                .by_self_ty
+                .entry(self_ty)
+                .or_default()
+                .push(entry),
+            None => trait_impls.blanket.push(entry),
+        }
+    }
+
+    /// The impls of `trait_id` that may apply to a goal with the given
+    /// `parameters` (the first of which is the self type), in the order
+    /// they were inserted. A placeholder self type only unifies with the
+    /// self types of blanket impls, so only those are returned for it;
+    /// otherwise, these are all the impls of the trait if the self type of
+    /// the goal has no simplified type.
+    ///
+    /// This only looks at the head of the self type, so hosts will usually
+    /// still want to filter the candidates with `could_match`.
+    pub fn candidates(
+        &self,
+        interner: &I,
+        trait_id: TraitId<I>,
+        parameters: &[GenericArg<I>],
+    ) -> Vec<ImplId<I>> {
+        let trait_impls = match self.traits.get(&trait_id) {
+            Some(trait_impls) => trait_impls,
+            None => return Vec::new(),
+        };
+        let self_ty = parameters.first().and_then(|self_ty| self_ty.ty(interner));
+        if let Some(TyKind::Placeholder(_)) = self_ty.map(|self_ty| self_ty.kind(interner)) {
+            return trait_impls
+                .blanket
+                .iter()
+                .map(|&(_, impl_id)| impl_id)
+                .collect();
+        }
+        let self_ty = self_ty.and_then(|self_ty| SimplifiedType::from_ty(interner, self_ty));
+
+        let mut candidates: Vec<_> = match self_ty {
+            Some(self_ty) => {
+                let keyed = trait_impls.by_self_ty.get(&self_ty);
+                trait_impls
+                    .blanket
+                    .iter()
+                    .chain(keyed.into_iter().flatten())
+                    .copied()
+                    .collect()
+            }
+            None => trait_impls
+                .blanket
+                .iter()
+                .chain(trait_impls.by_self_ty.values().flatten())
+                .copied()
+                .collect(),
+        };
+        candidates.sort_unstable_by_key(|&(index, _)| index);
+        candidates.into_iter().map(|(_, impl_id)| impl_id).collect()
+    }
+}
@@ -1,13 +1,37 @@
+use crate::dependencies::Dependencies;
 use crate::RustIrDatabase;
 use chalk_derive::HasInterner;
 use chalk_ir::interner::Interner;
 use chalk_ir::*;
 use std::fmt;
+use std::iter::Peekable;
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "serde")]
+use {
+    chalk_ir::interner::SerdeInterner,
+    serde::{Deserialize, Serialize},
+};
 
+pub mod ambiguity;
+pub mod differential;
+pub mod proof_tree;
+pub mod stats;
 pub mod truncate;
+pub mod unwind;
+
+pub use ambiguity::AmbiguityCause;
+pub use differential::{DifferentialSolver, Disagreement, SolutionDiff, DEFAULT_MAX_DISAGREEMENTS};
+pub use proof_tree::{ProofCandidate, ProofOutcome, ProofTree};
+pub use stats::{RootGoalStats, SolverStats, DEFAULT_MAX_EXPENSIVE_GOALS};
+pub use unwind::SolverPanic;
 
 /// A (possible) solution for a proposed goal.
 #[derive(Clone, Debug, PartialEq, Eq, HasInterner)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum Solution<I: Interner> {
     /// The goal indeed holds, and there is a unique value for all existential
     /// variables. In this case, we also record a set of lifetime constraints
@@ -19,24 +43,95 @@ pub enum Solution<I: Interner> {
     /// constraints, since we have not "committed" to any particular solution
     /// yet.
     Ambig(Guidance<I>),
+
+    /// We could not tell whether the goal holds, because solving it exceeded
+    /// one of the solver's limits.
+    Overflow(Overflow<I>),
+}
+
+/// Describes the limit that a solver hit while solving a goal.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub struct Overflow<I: Interner> {
+    /// The goal (perhaps a subgoal of the one being solved) that exceeded
+    /// the limit.
+    pub goal: Canonical<InEnvironment<Goal<I>>>,
+
+    pub limit: OverflowLimit,
+}
+
+/// The limits a solver places on the goals it solves.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OverflowLimit {
+    /// The number of goals that may be solved within one another (the
+    /// recursive solver's `overflow_depth`).
+    Depth(usize),
+
+    /// The size of goals and answers (the solvers' `max_size`).
+    Size(usize),
+}
+
+impl fmt::Display for OverflowLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverflowLimit::Depth(depth) => write!(f, "depth limit {}", depth),
+            OverflowLimit::Size(size) => write!(f, "size limit {}", size),
+        }
+    }
 }
 
 /// When a goal holds ambiguously (e.g., because there are multiple possible
 /// solutions), we issue a set of *guidance* back to type inference.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
 pub enum Guidance<I: Interner> {
     /// The existential variables *must* have the given values if the goal is
     /// ever to hold, but that alone isn't enough to guarantee the goal will
     /// actually hold.
-    Definite(Canonical<Substitution<I>>),
+    Definite(Canonical<Substitution<I>>, AmbiguityCause<I>),
 
     /// There are multiple plausible values for the existentials, but the ones
     /// here are suggested as the preferred choice heuristically. These should
     /// be used for inference fallback only.
-    Suggested(Canonical<Substitution<I>>),
+    Suggested(Canonical<Substitution<I>>, AmbiguityCause<I>),
 
     /// There's no useful information to feed back to type inference
-    Unknown,
+    Unknown(AmbiguityCause<I>),
+}
+
+// The cause is left out: it explains the ambiguity, but gives type
+// inference nothing to go on.
+impl<I: Interner> PartialEq for Guidance<I> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Guidance::Definite(a, _), Guidance::Definite(b, _))
+            | (Guidance::Suggested(a, _), Guidance::Suggested(b, _)) => a == b,
+            (Guidance::Unknown(_), Guidance::Unknown(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<I: Interner> Eq for Guidance<I> {}
+
+impl<I: Interner> Guidance<I> {
+    /// Why the goal is ambiguous.
+    pub fn cause(&self) -> &AmbiguityCause<I> {
+        match self {
+            Guidance::Definite(_, cause)
+            | Guidance::Suggested(_, cause)
+            | Guidance::Unknown(cause) => cause,
+        }
+    }
 }
 
 impl<I: Interner> Solution<I> {
@@ -47,6 +142,12 @@ impl<I: Interner> Solution<I> {
         }
     }
 
+    pub fn is_overflow(&self) -> bool {
+        matches!(self, Solution::Overflow(..))
+    }
+
+    /// Displays the solution. Region constraints of a unique solution are
+    /// followed by the origin of each constraint, in the same order.
     pub fn display<'a>(&'a self, interner: &'a I) -> SolutionDisplay<'a, I> {
         SolutionDisplay {
             solution: self,
@@ -64,18 +165,39 @@ impl<'a, I: Interner> fmt::Display for SolutionDisplay<'a, I> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         let SolutionDisplay { solution, interner } = self;
         match solution {
-            Solution::Unique(constrained) => write!(f, "Unique; {}", constrained.display(interner)),
-            Solution::Ambig(Guidance::Definite(subst)) => write!(
-                f,
-                "Ambiguous; definite substitution {}",
-                subst.display(interner)
-            ),
-            Solution::Ambig(Guidance::Suggested(subst)) => write!(
+            Solution::Unique(constrained) => {
+                write!(f, "Unique; {}", constrained.display(interner))?;
+                let constraints = constrained.value.constraints.as_slice(interner);
+                if !constraints.is_empty() {
+                    let origins: Vec<_> = constraints.iter().map(|c| c.goal.origin()).collect();
+                    write!(f, "; constraint origins {:?}", origins)?;
+                }
+                Ok(())
+            }
+            Solution::Ambig(guidance) => {
+                match guidance {
+                    Guidance::Definite(subst, _) => write!(
+                        f,
+                        "Ambiguous; definite substitution {}",
+                        subst.display(interner)
+                    )?,
+                    Guidance::Suggested(subst, _) => write!(
+                        f,
+                        "Ambiguous; suggested substitution {}",
+                        subst.display(interner)
+                    )?,
+                    Guidance::Unknown(_) => write!(f, "Ambiguous; no inference guidance")?,
+                }
+                match guidance.cause() {
+                    AmbiguityCause::Other => Ok(()),
+                    cause => write!(f, "; {}", cause),
+                }
+            }
+            Solution::Overflow(overflow) => write!(
                 f,
-                "Ambiguous; suggested substitution {}",
-                subst.display(interner)
+                "Overflow; {} reached by {:?}",
+                overflow.limit, overflow.goal.value.goal
             ),
-            Solution::Ambig(Guidance::Unknown) => write!(f, "Ambiguous; no inference guidance"),
         }
     }
 }
@@ -114,6 +236,45 @@ impl<S: fmt::Display> fmt::Display for SubstitutionResult<S> {
     }
 }
 
+/// The answers to a goal, which a solver finds one at a time as they are
+/// requested (see `Solver::solve_answers`). Dropping it stops the search;
+/// the work done so far stays cached in the solver.
+pub struct Answers<'a, I: Interner> {
+    answers: Peekable<Box<dyn Iterator<Item = Answer<I>> + 'a>>,
+}
+
+/// An answer to a goal, as yielded by `Answers`.
+type Answer<I> = SubstitutionResult<Canonical<ConstrainedSubst<I>>>;
+
+impl<'a, I: Interner> Answers<'a, I> {
+    pub fn new(answers: impl Iterator<Item = Answer<I>> + 'a) -> Self {
+        let answers: Box<dyn Iterator<Item = _> + 'a> = Box::new(answers);
+        Answers {
+            answers: answers.peekable(),
+        }
+    }
+
+    /// Whether there is another answer. This does the work of finding it,
+    /// if we haven't already.
+    pub fn has_next(&mut self) -> bool {
+        self.answers.peek().is_some()
+    }
+}
+
+impl<I: Interner> Iterator for Answers<'_, I> {
+    type Item = Answer<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.answers.next()
+    }
+}
+
+impl<I: Interner> fmt::Debug for Answers<'_, I> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "Answers")
+    }
+}
+
 /// Finds the solution to "goals", or trait queries -- i.e., figures
 /// out what sets of types implement which traits. Also, between
 /// queries, this struct stores the cached state from previous solver
@@ -175,10 +336,47 @@ where
         should_continue: &dyn std::ops::Fn() -> bool,
     ) -> Option<Solution<I>>;
 
+    /// Like `solve`, but if a `RustIrDatabase` callback panics (e.g.
+    /// because the host cancelled a salsa query), returns the panic as a
+    /// [`SolverPanic`] for `goal` instead of unwinding further. The
+    /// solver rolls back the work it had in progress, so it can still be
+    /// used afterwards (see [`unwind`]).
+    fn try_solve(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Result<Option<Solution<I>>, SolverPanic<I>> {
+        panic::catch_unwind(AssertUnwindSafe(|| self.solve(program, goal))).map_err(|payload| {
+            SolverPanic {
+                goal: goal.clone(),
+                payload,
+            }
+        })
+    }
+
+    /// Attempts to solve the given goal, which must be in canonical
+    /// form. Returns an iterator over its answers, which finds each of
+    /// them only once it is requested. This will do only as much work
+    /// towards `goal` as it has to (and that work is cached for future
+    /// attempts).
+    ///
+    /// # Parameters
+    ///
+    /// - `program` -- defines the program clauses in scope.
+    ///   - **Important:** You must supply the same set of program clauses
+    ///     each time you invoke `solve`, as otherwise the cached data may be
+    ///     invalid.
+    /// - `goal` the goal to solve
+    fn solve_answers<'a>(
+        &'a mut self,
+        program: &'a dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Answers<'a, I>;
+
     /// Attempts to solve the given goal, which must be in canonical
     /// form. Provides multiple solutions to function `f`.  This will do
     /// only as much work towards `goal` as it has to (and that work
-    /// is cached for future attempts).
+    /// is cached for future attempts). See also `solve_answers`.
     ///
     /// # Parameters
     ///
@@ -202,7 +400,39 @@ where
         program: &dyn RustIrDatabase<I>,
         goal: &UCanonical<InEnvironment<Goal<I>>>,
         f: &mut dyn FnMut(SubstitutionResult<Canonical<ConstrainedSubst<I>>>, bool) -> bool,
-    ) -> bool;
+    ) -> bool {
+        let mut answers = self.solve_answers(program, goal);
+        while let Some(answer) = answers.next() {
+            if !f(answer, answers.has_next()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Attempts to solve the given goal, which must be in canonical
+    /// form, and additionally records a [`ProofTree`] describing which
+    /// program clauses were tried for each subgoal and how each of them
+    /// turned out.
+    ///
+    /// This is intended for debugging and diagnostics. The explanation
+    /// is computed from scratch: it neither consults nor updates the
+    /// solver's cached state, so it is considerably slower than `solve`.
+    ///
+    /// # Parameters
+    ///
+    /// - `program` -- defines the program clauses in scope.
+    /// - `goal` the goal to solve
+    ///
+    /// # Returns
+    ///
+    /// The same solution `solve` would return, along with the proof tree
+    /// rooted at `goal`.
+    fn solve_explained(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> (Option<Solution<I>>, ProofTree<I>);
 
     /// A convenience method for when one doesn't need the actual solution,
     /// only whether or not one exists.
@@ -216,4 +446,35 @@ where
             None => false,
         }
     }
+
+    /// Discards the cached results that depend on any of the `changed`
+    /// items, so that the solver can keep being used after the program
+    /// changes. Dependencies are only known for results computed with a
+    /// database that provides a [`DependencyRecorder`].
+    ///
+    /// Returns `false` if the solver cannot invalidate its results
+    /// selectively, in which case it must be replaced by a fresh solver.
+    ///
+    /// [`DependencyRecorder`]: crate::dependencies::DependencyRecorder
+    fn invalidate(&mut self, _changed: &Dependencies<I>) -> bool {
+        false
+    }
+
+    /// Discards all the results the solver cached between goals, to free
+    /// the memory they hold. Later goals are solved from scratch. Solvers
+    /// that keep nothing between goals have nothing to do.
+    fn clear(&mut self) {}
+
+    /// The statistics collected over all the goals solved so far (see
+    /// [`SolverStats`]). Solvers that don't collect any return empty
+    /// statistics.
+    fn stats(&self) -> SolverStats<I> {
+        SolverStats::new()
+    }
+
+    /// Returns the disagreements between solvers found since the last call
+    /// (see [`DifferentialSolver`]). Other solvers never find any.
+    fn take_disagreements(&mut self) -> Vec<Disagreement<I>> {
+        vec![]
+    }
 }
@@ -0,0 +1,183 @@
+//! Why a goal turned out to be ambiguous, so that "type annotations
+//! needed" diagnostics can point at the culprit.
+
+use crate::dependencies::ItemId;
+use crate::infer::InferenceTable;
+use crate::RustIrDatabase;
+use chalk_ir::interner::Interner;
+use chalk_ir::*;
+use std::fmt;
+#[cfg(feature = "serde")]
+use {
+    chalk_ir::interner::SerdeInterner,
+    serde::{Deserialize, Serialize},
+};
+
+/// The reason a solver gave for an ambiguous solution (see [`Guidance`]).
+///
+/// [`Guidance`]: super::Guidance
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(bound = "I: SerdeInterner")
+)]
+pub enum AmbiguityCause<I: Interner> {
+    /// Several impls could apply to the goal, or to a subgoal it depends
+    /// on.
+    MultipleImpls(Vec<ImplId<I>>),
+
+    /// The self type of the goal (or of a subgoal) is an inference
+    /// variable that we know nothing about.
+    UnresolvedSelfType,
+
+    /// The goal (or a subgoal) did not contain enough type information to
+    /// enumerate the clauses that might prove it.
+    Floundered,
+
+    /// A subgoal exceeded the solver's limits.
+    Overflow,
+
+    /// The database could not produce the data for an item that the
+    /// program clauses for the goal (or a subgoal) are built from (see
+    /// `RustIrDatabase::try_trait_datum` and friends).
+    LookupFailed(ItemId<I>),
+
+    /// None of the above: e.g. several where clauses from the environment
+    /// apply, or we could not relate two unknown types.
+    Other,
+}
+
+impl<I: Interner> AmbiguityCause<I> {
+    /// Combines the causes of two ambiguous solutions for the same goal,
+    /// keeping the more specific one. The impls of two `MultipleImpls`
+    /// causes are joined.
+    pub fn merge(self, other: AmbiguityCause<I>) -> AmbiguityCause<I> {
+        use self::AmbiguityCause::*;
+
+        match (self, other) {
+            (MultipleImpls(mut impls), MultipleImpls(other_impls)) => {
+                for impl_id in other_impls {
+                    if !impls.contains(&impl_id) {
+                        impls.push(impl_id);
+                    }
+                }
+                MultipleImpls(impls)
+            }
+            (a, b) => {
+                if a.rank() >= b.rank() {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+
+    /// How specific a cause is; an overflow or floundering explains an
+    /// ambiguity better than anything we can tell from the goal itself,
+    /// and a failed lookup means that we could not even try.
+    fn rank(&self) -> u8 {
+        match self {
+            AmbiguityCause::Other => 0,
+            AmbiguityCause::MultipleImpls(_) => 1,
+            AmbiguityCause::UnresolvedSelfType => 2,
+            AmbiguityCause::Floundered => 3,
+            AmbiguityCause::Overflow => 4,
+            AmbiguityCause::LookupFailed(_) => 5,
+        }
+    }
+
+    /// Whether `goal` is a trait goal whose self type we know nothing
+    /// about. Unlike `of_goal`, this does not look at any impls.
+    pub fn of_self_type(
+        interner: &I,
+        goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
+    ) -> Option<AmbiguityCause<I>> {
+        match &goal.canonical.value.goal {
+            DomainGoal::Holds(WhereClause::Implemented(trait_ref)) => {
+                // The variables of a canonical goal are those we know
+                // nothing about.
+                match trait_ref.self_type_parameter(interner).kind(interner) {
+                    TyKind::BoundVar(_) => Some(AmbiguityCause::UnresolvedSelfType),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Tells why `goal` is ambiguous from the goal alone, if it is a trait
+    /// goal: because its self type is unknown, or because it matches the
+    /// headers of several impls.
+    pub fn of_goal(
+        db: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<DomainGoal<I>>>,
+    ) -> Option<AmbiguityCause<I>> {
+        let interner = db.interner();
+        let trait_ref = match &goal.canonical.value.goal {
+            DomainGoal::Holds(WhereClause::Implemented(trait_ref)) => trait_ref,
+            _ => return None,
+        };
+        if let Some(cause) = AmbiguityCause::of_self_type(interner, goal) {
+            return Some(cause);
+        }
+
+        let impls = db.impls_for_trait(
+            trait_ref.trait_id,
+            trait_ref.substitution.as_slice(interner),
+            &goal.canonical.binders,
+        );
+        let (mut infer, subst, environment) =
+            InferenceTable::from_canonical(interner, goal.universes, &goal.canonical);
+        let environment = environment.environment;
+        let trait_ref = subst.apply(trait_ref, interner);
+        let impls: Vec<_> = impls
+            .into_iter()
+            .filter(|&impl_id| {
+                let impl_datum = match db.try_impl_datum(impl_id) {
+                    Ok(impl_datum) => impl_datum,
+                    Err(_) => return false,
+                };
+                if !impl_datum.is_positive() {
+                    return false;
+                }
+                let snapshot = infer.snapshot();
+                let impl_trait_ref = infer.instantiate_binders_existentially(
+                    interner,
+                    &impl_datum.binders.map_ref(|bound| bound.trait_ref.clone()),
+                );
+                let applies = infer
+                    .relate(
+                        interner,
+                        db.unification_database(),
+                        &environment,
+                        Variance::Invariant,
+                        &trait_ref,
+                        &impl_trait_ref,
+                    )
+                    .is_ok();
+                infer.rollback_to(snapshot);
+                applies
+            })
+            .collect();
+        if impls.len() > 1 {
+            Some(AmbiguityCause::MultipleImpls(impls))
+        } else {
+            None
+        }
+    }
+}
+
+impl<I: Interner> fmt::Display for AmbiguityCause<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AmbiguityCause::MultipleImpls(impls) => write!(f, "multiple impls apply: {:?}", impls),
+            AmbiguityCause::UnresolvedSelfType => write!(f, "self type is unknown"),
+            AmbiguityCause::Floundered => write!(f, "floundered"),
+            AmbiguityCause::Overflow => write!(f, "overflow"),
+            AmbiguityCause::LookupFailed(item) => write!(f, "could not look up {:?}", item),
+            AmbiguityCause::Other => Ok(()),
+        }
+    }
+}
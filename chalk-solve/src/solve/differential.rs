@@ -0,0 +1,322 @@
+//! Runs two solvers side by side and records where their solutions
+//! differ, to catch bugs in either of them.
+
+use super::{Answers, Guidance, ProofTree, Solution, Solver, SolverStats};
+use crate::dependencies::Dependencies;
+use crate::RustIrDatabase;
+use chalk_ir::interner::Interner;
+use chalk_ir::*;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A solver that solves every goal with two solvers, answering with the
+/// solution of the `primary` one. Whenever the `secondary` solver comes to
+/// a different solution, the difference is recorded as a [`Disagreement`],
+/// to be retrieved with [`Solver::take_disagreements`]. Only the most
+/// recent `max_disagreements` are kept until then, so that a long run
+/// that never takes them does not grow without bound.
+pub struct DifferentialSolver<I: Interner> {
+    primary: Box<dyn Solver<I> + Send>,
+    secondary: Box<dyn Solver<I> + Send>,
+    disagreements: VecDeque<Disagreement<I>>,
+    max_disagreements: usize,
+}
+
+/// The number of disagreements a [`DifferentialSolver`] keeps by default.
+pub const DEFAULT_MAX_DISAGREEMENTS: usize = 100;
+
+impl<I: Interner> DifferentialSolver<I> {
+    pub fn new(primary: Box<dyn Solver<I> + Send>, secondary: Box<dyn Solver<I> + Send>) -> Self {
+        DifferentialSolver {
+            primary,
+            secondary,
+            disagreements: VecDeque::new(),
+            max_disagreements: DEFAULT_MAX_DISAGREEMENTS,
+        }
+    }
+
+    /// Keeps the `max` most recent disagreements, rather than
+    /// `DEFAULT_MAX_DISAGREEMENTS`.
+    pub fn with_max_disagreements(mut self, max: usize) -> Self {
+        self.max_disagreements = max;
+        self
+    }
+}
+
+impl<I: Interner> fmt::Debug for DifferentialSolver<I> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "DifferentialSolver({:?}, {:?})",
+            self.primary, self.secondary
+        )
+    }
+}
+
+/// A goal for which the two solvers of a [`DifferentialSolver`] came to
+/// different solutions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disagreement<I: Interner> {
+    pub goal: UCanonical<InEnvironment<Goal<I>>>,
+    pub primary: Option<Solution<I>>,
+    pub secondary: Option<Solution<I>>,
+
+    /// How the two solutions differ; never empty.
+    pub diffs: Vec<SolutionDiff<I>>,
+}
+
+/// One way in which two solutions for the same goal differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolutionDiff<I: Interner> {
+    /// Only one of the solvers found the goal to be provable.
+    Provable { primary: bool, secondary: bool },
+
+    /// Only one of the solutions is unique.
+    Unique { primary: bool, secondary: bool },
+
+    /// Only one of the solvers overflowed.
+    Overflow { primary: bool, secondary: bool },
+
+    /// Both solutions are ambiguous, but give different kinds of guidance.
+    Guidance {
+        primary: Guidance<I>,
+        secondary: Guidance<I>,
+    },
+
+    /// The solutions give different values to the goal's `index`th
+    /// variable.
+    Substitution {
+        index: usize,
+        primary: Option<GenericArg<I>>,
+        secondary: Option<GenericArg<I>>,
+    },
+
+    /// Both solutions are unique, but come with different region
+    /// constraints; these are the constraints only one of them has.
+    Constraints {
+        primary: Vec<InEnvironment<Constraint<I>>>,
+        secondary: Vec<InEnvironment<Constraint<I>>>,
+    },
+}
+
+/// Compares the solutions two solvers found for the same goal.
+pub fn diff_solutions<I: Interner>(
+    interner: &I,
+    primary: &Option<Solution<I>>,
+    secondary: &Option<Solution<I>>,
+) -> Vec<SolutionDiff<I>> {
+    let (primary, secondary) = match (primary, secondary) {
+        (Some(primary), Some(secondary)) => (primary, secondary),
+        (None, None) => return vec![],
+        _ => {
+            return vec![SolutionDiff::Provable {
+                primary: primary.is_some(),
+                secondary: secondary.is_some(),
+            }]
+        }
+    };
+
+    let mut diffs = vec![];
+    match (primary, secondary) {
+        (Solution::Unique(_), Solution::Unique(_))
+        | (Solution::Overflow(_), Solution::Overflow(_)) => {}
+        (Solution::Ambig(primary), Solution::Ambig(secondary)) => {
+            if std::mem::discriminant(primary) != std::mem::discriminant(secondary) {
+                diffs.push(SolutionDiff::Guidance {
+                    primary: primary.clone(),
+                    secondary: secondary.clone(),
+                });
+            }
+        }
+        (Solution::Overflow(_), _) | (_, Solution::Overflow(_)) => {
+            diffs.push(SolutionDiff::Overflow {
+                primary: primary.is_overflow(),
+                secondary: secondary.is_overflow(),
+            })
+        }
+        _ => diffs.push(SolutionDiff::Unique {
+            primary: primary.is_unique(),
+            secondary: secondary.is_unique(),
+        }),
+    }
+
+    if let (Some(primary), Some(secondary)) = (substitution(primary), substitution(secondary)) {
+        let primary = primary.as_slice(interner);
+        let secondary = secondary.as_slice(interner);
+        for index in 0..primary.len().max(secondary.len()) {
+            let (primary, secondary) = (primary.get(index), secondary.get(index));
+            if primary != secondary {
+                diffs.push(SolutionDiff::Substitution {
+                    index,
+                    primary: primary.cloned(),
+                    secondary: secondary.cloned(),
+                });
+            }
+        }
+    }
+
+    if let (Solution::Unique(primary), Solution::Unique(secondary)) = (primary, secondary) {
+        let primary = primary.value.constraints.as_slice(interner);
+        let secondary = secondary.value.constraints.as_slice(interner);
+        let only_in = |constraints: &[InEnvironment<Constraint<I>>],
+                       others: &[InEnvironment<Constraint<I>>]| {
+            constraints
+                .iter()
+                .filter(|constraint| !others.contains(constraint))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let (primary_only, secondary_only) =
+            (only_in(primary, secondary), only_in(secondary, primary));
+        if !primary_only.is_empty() || !secondary_only.is_empty() {
+            diffs.push(SolutionDiff::Constraints {
+                primary: primary_only,
+                secondary: secondary_only,
+            });
+        }
+    }
+
+    diffs
+}
+
+/// The substitution a solution gives for the goal's variables, if any.
+fn substitution<I: Interner>(solution: &Solution<I>) -> Option<&Substitution<I>> {
+    match solution {
+        Solution::Unique(constrained) => Some(&constrained.value.subst),
+        Solution::Ambig(Guidance::Definite(subst, _))
+        | Solution::Ambig(Guidance::Suggested(subst, _)) => Some(&subst.value),
+        Solution::Ambig(Guidance::Unknown(_)) | Solution::Overflow(_) => None,
+    }
+}
+
+impl<I: Interner> Disagreement<I> {
+    pub fn display<'a>(&'a self, interner: &'a I) -> DisagreementDisplay<'a, I> {
+        DisagreementDisplay {
+            disagreement: self,
+            interner,
+        }
+    }
+}
+
+pub struct DisagreementDisplay<'a, I: Interner> {
+    disagreement: &'a Disagreement<I>,
+    interner: &'a I,
+}
+
+impl<'a, I: Interner> fmt::Display for DisagreementDisplay<'a, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let DisagreementDisplay {
+            disagreement,
+            interner,
+        } = self;
+        let solution = |solution: &Option<Solution<I>>| match solution {
+            Some(solution) => solution.display(interner).to_string(),
+            None => "No possible solution".to_string(),
+        };
+        writeln!(
+            f,
+            "solvers disagree on {:?}",
+            disagreement.goal.canonical.value
+        )?;
+        writeln!(f, "  primary:   {}", solution(&disagreement.primary))?;
+        writeln!(f, "  secondary: {}", solution(&disagreement.secondary))?;
+        for diff in &disagreement.diffs {
+            match diff {
+                SolutionDiff::Provable { primary, secondary } => {
+                    writeln!(f, "  provable: {} vs {}", primary, secondary)?
+                }
+                SolutionDiff::Unique { primary, secondary } => {
+                    writeln!(f, "  unique: {} vs {}", primary, secondary)?
+                }
+                SolutionDiff::Overflow { primary, secondary } => {
+                    writeln!(f, "  overflow: {} vs {}", primary, secondary)?
+                }
+                SolutionDiff::Guidance { primary, secondary } => {
+                    writeln!(f, "  guidance: {:?} vs {:?}", primary, secondary)?
+                }
+                SolutionDiff::Substitution {
+                    index,
+                    primary,
+                    secondary,
+                } => writeln!(f, "  ?{}: {:?} vs {:?}", index, primary, secondary)?,
+                SolutionDiff::Constraints { primary, secondary } => {
+                    writeln!(f, "  constraints: {:?} vs {:?}", primary, secondary)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<I: Interner> Solver<I> for DifferentialSolver<I> {
+    fn solve(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Option<Solution<I>> {
+        let primary = self.primary.solve(program, goal);
+        let secondary = self.secondary.solve(program, goal);
+        let diffs = diff_solutions(program.interner(), &primary, &secondary);
+        if !diffs.is_empty() && self.max_disagreements > 0 {
+            if self.disagreements.len() == self.max_disagreements {
+                self.disagreements.pop_front();
+            }
+            self.disagreements.push_back(Disagreement {
+                goal: goal.clone(),
+                primary: primary.clone(),
+                secondary,
+                diffs,
+            });
+        }
+        primary
+    }
+
+    /// Only uses the primary solver, since a solution cut short by
+    /// `should_continue` is not comparable with a complete one.
+    fn solve_limited(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+        should_continue: &dyn std::ops::Fn() -> bool,
+    ) -> Option<Solution<I>> {
+        self.primary.solve_limited(program, goal, should_continue)
+    }
+
+    /// Only uses the primary solver.
+    fn solve_answers<'a>(
+        &'a mut self,
+        program: &'a dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> Answers<'a, I> {
+        self.primary.solve_answers(program, goal)
+    }
+
+    /// Only uses the primary solver.
+    fn solve_explained(
+        &mut self,
+        program: &dyn RustIrDatabase<I>,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) -> (Option<Solution<I>>, ProofTree<I>) {
+        self.primary.solve_explained(program, goal)
+    }
+
+    fn clear(&mut self) {
+        self.primary.clear();
+        self.secondary.clear();
+    }
+
+    fn invalidate(&mut self, changed: &Dependencies<I>) -> bool {
+        // Both solvers must be asked, even if the first one fails.
+        let primary = self.primary.invalidate(changed);
+        let secondary = self.secondary.invalidate(changed);
+        primary && secondary
+    }
+
+    fn stats(&self) -> SolverStats<I> {
+        self.primary.stats()
+    }
+
+    fn take_disagreements(&mut self) -> Vec<Disagreement<I>> {
+        self.disagreements.drain(..).collect()
+    }
+}
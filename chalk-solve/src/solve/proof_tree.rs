@@ -0,0 +1,185 @@
+//! Proof trees, which record how a solver arrived at a solution.
+//!
+//! A proof tree is produced by [`Solver::solve_explained`]. Each node
+//! corresponds to a (canonical) goal that the solver tried to prove, and
+//! lists the *candidates* it considered: for domain goals, each candidate
+//! is a program clause whose conclusion unified with the goal; for other
+//! goals (e.g. `forall`, `if` or conjunctions), there is a single candidate
+//! without a clause that represents the simplification of the goal into
+//! its subgoals.
+//!
+//! [`Solver::solve_explained`]: crate::Solver::solve_explained
+
+use chalk_ir::interner::Interner;
+use chalk_ir::{Goal, InEnvironment, ProgramClause, UCanonical};
+use std::fmt;
+
+/// How an attempt to prove a goal (or to apply a candidate) turned out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ProofOutcome {
+    /// The goal holds, with a unique solution.
+    Proven,
+
+    /// The goal may hold, but the solver could not settle on a unique
+    /// solution.
+    Ambiguous,
+
+    /// The goal cannot be proven.
+    Failed,
+
+    /// The goal did not contain enough type information to enumerate
+    /// the clauses that might prove it.
+    Floundered,
+
+    /// The solver gave up on the goal because it exceeded one of the
+    /// solver's limits.
+    Overflowed,
+
+    /// The goal was already being solved further up in the tree; its
+    /// explanation can be found there.
+    Cycle,
+
+    /// The solver stopped before it finished exploring this goal, e.g.
+    /// because an earlier candidate already produced a sufficient answer.
+    Incomplete,
+}
+
+impl fmt::Display for ProofOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProofOutcome::Proven => "proven",
+            ProofOutcome::Ambiguous => "ambiguous",
+            ProofOutcome::Failed => "failed",
+            ProofOutcome::Floundered => "floundered",
+            ProofOutcome::Overflowed => "overflowed",
+            ProofOutcome::Cycle => "cycle",
+            ProofOutcome::Incomplete => "incomplete",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A goal that the solver tried to prove, together with every candidate
+/// that it considered for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofTree<I: Interner> {
+    /// The goal, in the canonical form used by the solver's cache.
+    pub goal: UCanonical<InEnvironment<Goal<I>>>,
+
+    /// The overall outcome for `goal`.
+    pub outcome: ProofOutcome,
+
+    /// The candidates the solver considered, in the order they were tried.
+    pub candidates: Vec<ProofCandidate<I>>,
+}
+
+/// One way in which the solver tried to prove a goal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofCandidate<I: Interner> {
+    /// The program clause that was applied, or `None` if the goal was
+    /// simplified into its subgoals directly.
+    pub clause: Option<ProgramClause<I>>,
+
+    /// The outcome of this candidate on its own.
+    pub outcome: ProofOutcome,
+
+    /// The subgoals that applying this candidate required.
+    pub subgoals: Vec<ProofTree<I>>,
+}
+
+impl<I: Interner> ProofTree<I> {
+    /// Creates a proof tree for `goal` with no candidates.
+    pub fn leaf(goal: UCanonical<InEnvironment<Goal<I>>>, outcome: ProofOutcome) -> Self {
+        ProofTree {
+            goal,
+            outcome,
+            candidates: vec![],
+        }
+    }
+
+    /// Returns the goals at the bottom of the tree that did not succeed,
+    /// i.e. those whose outcome is neither `Proven` nor `Cycle` and which
+    /// have no failing subgoals themselves. These are usually the goals
+    /// worth reporting to a user.
+    pub fn failed_leaves(&self) -> Vec<&ProofTree<I>> {
+        let mut leaves = vec![];
+        self.push_failed_leaves(&mut leaves);
+        leaves
+    }
+
+    fn push_failed_leaves<'a>(&'a self, leaves: &mut Vec<&'a ProofTree<I>>) {
+        match self.outcome {
+            ProofOutcome::Proven | ProofOutcome::Cycle => return,
+            _ => {}
+        }
+        let len = leaves.len();
+        for candidate in &self.candidates {
+            for subgoal in &candidate.subgoals {
+                subgoal.push_failed_leaves(leaves);
+            }
+        }
+        if leaves.len() == len {
+            leaves.push(self);
+        }
+    }
+
+    pub fn display<'a>(&'a self, interner: &'a I) -> ProofTreeDisplay<'a, I> {
+        ProofTreeDisplay {
+            tree: self,
+            interner,
+        }
+    }
+}
+
+pub struct ProofTreeDisplay<'a, I: Interner> {
+    tree: &'a ProofTree<I>,
+    interner: &'a I,
+}
+
+impl<'a, I: Interner> ProofTreeDisplay<'a, I> {
+    fn fmt_tree(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        tree: &ProofTree<I>,
+        indent: usize,
+    ) -> fmt::Result {
+        let goal = &tree.goal.canonical.value.goal;
+        writeln!(
+            f,
+            "{:indent$}{:?} -- {}",
+            "",
+            goal,
+            tree.outcome,
+            indent = indent
+        )?;
+        for candidate in &tree.candidates {
+            match &candidate.clause {
+                Some(clause) => writeln!(
+                    f,
+                    "{:indent$}clause {:?} -- {}",
+                    "",
+                    clause.data(self.interner),
+                    candidate.outcome,
+                    indent = indent + 2
+                )?,
+                None => writeln!(
+                    f,
+                    "{:indent$}simplified -- {}",
+                    "",
+                    candidate.outcome,
+                    indent = indent + 2
+                )?,
+            }
+            for subgoal in &candidate.subgoals {
+                self.fmt_tree(f, subgoal, indent + 4)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, I: Interner> fmt::Display for ProofTreeDisplay<'a, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, self.tree, 0)
+    }
+}
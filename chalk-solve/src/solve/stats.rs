@@ -0,0 +1,234 @@
+//! Statistics that solvers collect while solving goals, to find out which
+//! goals are expensive to solve and why.
+
+use chalk_ir::interner::Interner;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Counters that a solver accumulates over all the goals it solves, along
+/// with the root goals that took the longest. See [`Solver::stats`].
+///
+/// [`Solver::stats`]: crate::Solver::stats
+#[derive(Clone, Debug)]
+pub struct SolverStats<I: Interner> {
+    /// The number of tables (for the SLG solver) or search graph nodes (for
+    /// the recursive solver) that were created.
+    pub tables: usize,
+
+    /// The number of answers produced for goals and their subgoals.
+    pub answers: usize,
+
+    /// The number of times a subgoal was found in an existing table or
+    /// in the cache.
+    pub cache_hits: usize,
+
+    /// The number of times a subgoal had to be solved anew.
+    pub cache_misses: usize,
+
+    /// The number of goals and answers that were given up on because they
+    /// exceeded the solver's limits, such as the maximum size of goals.
+    pub overflows: usize,
+
+    /// The number of tables that were dropped to keep the solver within
+    /// its memory budget, or because the solver was cleared.
+    pub evictions: usize,
+
+    /// The number of tables the solver held when the statistics were
+    /// taken.
+    pub live_tables: usize,
+
+    /// A rough estimate of the memory those tables held, in bytes, if the
+    /// solver keeps track of it.
+    pub table_bytes: usize,
+
+    /// The number of root goals solved.
+    pub root_goals: usize,
+
+    /// The total time spent solving root goals.
+    pub total_time: Duration,
+
+    /// The `max_expensive_goals` root goals that took the longest to
+    /// solve, slowest first. Only these are kept, so that the statistics
+    /// do not grow with the number of goals solved.
+    pub expensive_goals: Vec<RootGoalStats<I>>,
+
+    /// The number of root goals kept in `expensive_goals`. This is a
+    /// setting rather than a counter; it is not changed by `merge`.
+    pub max_expensive_goals: usize,
+}
+
+/// The number of root goals whose statistics are kept by default; see
+/// [`SolverStats::expensive_goals`].
+pub const DEFAULT_MAX_EXPENSIVE_GOALS: usize = 10;
+
+/// What it took to solve a single root goal.
+#[derive(Clone, Debug)]
+pub struct RootGoalStats<I: Interner> {
+    pub goal: UCanonical<InEnvironment<Goal<I>>>,
+
+    /// The time spent solving the goal.
+    pub duration: Duration,
+
+    /// The number of tables or search graph nodes created for the goal.
+    pub tables: usize,
+
+    /// The number of answers produced for the goal and its subgoals.
+    pub answers: usize,
+}
+
+/// Marks the point where the solving of a root goal started (see
+/// [`SolverStats::start_root_goal`]).
+#[derive(Copy, Clone, Debug)]
+pub struct RootGoalStart {
+    instant: Instant,
+    tables: usize,
+    answers: usize,
+}
+
+impl<I: Interner> SolverStats<I> {
+    pub fn new() -> Self {
+        SolverStats::with_max_expensive_goals(DEFAULT_MAX_EXPENSIVE_GOALS)
+    }
+
+    /// Creates statistics that keep the `max_expensive_goals` root goals
+    /// that took the longest to solve.
+    pub fn with_max_expensive_goals(max_expensive_goals: usize) -> Self {
+        SolverStats {
+            tables: 0,
+            answers: 0,
+            cache_hits: 0,
+            cache_misses: 0,
+            overflows: 0,
+            evictions: 0,
+            live_tables: 0,
+            table_bytes: 0,
+            root_goals: 0,
+            total_time: Duration::ZERO,
+            expensive_goals: vec![],
+            max_expensive_goals,
+        }
+    }
+
+    /// Called before solving a root goal; pass the result to
+    /// `finish_root_goal` once it is solved.
+    pub fn start_root_goal(&self) -> RootGoalStart {
+        RootGoalStart {
+            instant: Instant::now(),
+            tables: self.tables,
+            answers: self.answers,
+        }
+    }
+
+    /// Records the time spent solving `goal` since `start`, along with the
+    /// tables and answers created in the meantime if it is among the most
+    /// expensive goals so far.
+    pub fn finish_root_goal(
+        &mut self,
+        start: RootGoalStart,
+        goal: &UCanonical<InEnvironment<Goal<I>>>,
+    ) {
+        let duration = start.instant.elapsed();
+        self.root_goals += 1;
+        self.total_time += duration;
+        if self.expensive_goal_index(duration) < self.max_expensive_goals {
+            self.record_expensive_goal(RootGoalStats {
+                goal: goal.clone(),
+                duration,
+                tables: self.tables - start.tables,
+                answers: self.answers - start.answers,
+            });
+        }
+    }
+
+    /// Where a goal that took `duration` goes in `expensive_goals`.
+    fn expensive_goal_index(&self, duration: Duration) -> usize {
+        self.expensive_goals
+            .partition_point(|root_goal| root_goal.duration >= duration)
+    }
+
+    fn record_expensive_goal(&mut self, root_goal: RootGoalStats<I>) {
+        let index = self.expensive_goal_index(root_goal.duration);
+        if index < self.max_expensive_goals {
+            self.expensive_goals.insert(index, root_goal);
+            self.expensive_goals.truncate(self.max_expensive_goals);
+        }
+    }
+
+    /// Adds the statistics collected by another solver to ours.
+    pub fn merge(&mut self, other: SolverStats<I>) {
+        self.tables += other.tables;
+        self.answers += other.answers;
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+        self.overflows += other.overflows;
+        self.evictions += other.evictions;
+        self.live_tables += other.live_tables;
+        self.table_bytes += other.table_bytes;
+        self.root_goals += other.root_goals;
+        self.total_time += other.total_time;
+        for root_goal in other.expensive_goals {
+            self.record_expensive_goal(root_goal);
+        }
+    }
+
+    /// The `n` root goals that took the longest to solve, slowest first.
+    /// At most `max_expensive_goals` are kept.
+    pub fn most_expensive(&self, n: usize) -> &[RootGoalStats<I>] {
+        &self.expensive_goals[..n.min(self.expensive_goals.len())]
+    }
+
+    /// Displays the counters, followed by the `n` most expensive root
+    /// goals (at most `max_expensive_goals`).
+    pub fn display(&self, n: usize) -> SolverStatsDisplay<'_, I> {
+        SolverStatsDisplay { stats: self, n }
+    }
+}
+
+impl<I: Interner> Default for SolverStats<I> {
+    fn default() -> Self {
+        SolverStats::new()
+    }
+}
+
+pub struct SolverStatsDisplay<'a, I: Interner> {
+    stats: &'a SolverStats<I>,
+    n: usize,
+}
+
+impl<'a, I: Interner> fmt::Display for SolverStatsDisplay<'a, I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let SolverStatsDisplay { stats, n } = self;
+        writeln!(f, "tables:       {}", stats.tables)?;
+        writeln!(f, "answers:      {}", stats.answers)?;
+        writeln!(f, "cache hits:   {}", stats.cache_hits)?;
+        writeln!(f, "cache misses: {}", stats.cache_misses)?;
+        writeln!(f, "overflows:    {}", stats.overflows)?;
+        writeln!(f, "evictions:    {}", stats.evictions)?;
+        writeln!(
+            f,
+            "live tables:  {} (~{} bytes)",
+            stats.live_tables, stats.table_bytes
+        )?;
+        writeln!(
+            f,
+            "root goals:   {} in {:?}",
+            stats.root_goals, stats.total_time
+        )?;
+        let most_expensive = stats.most_expensive(*n);
+        if !most_expensive.is_empty() {
+            writeln!(f, "most expensive goals:")?;
+            for root_goal in most_expensive {
+                writeln!(
+                    f,
+                    "  {:?} ({} tables, {} answers): {:?}",
+                    root_goal.duration,
+                    root_goal.tables,
+                    root_goal.answers,
+                    root_goal.goal.canonical.value
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! Recovering from panics that unwind out of a [`RustIrDatabase`] while a
+//! goal is being solved.
+//!
+//! Hosts built on salsa cancel queries by panicking out of the database
+//! callbacks. The solvers are unwind-safe: as such a panic unwinds through
+//! them, they roll back the tables or search-graph nodes they had in
+//! progress, so they can go on to solve other goals afterwards.
+//! [`Solver::try_solve`] catches the panic and returns it as a
+//! [`SolverPanic`], which can be resumed with
+//! [`SolverPanic::resume_unwind`] once the host is done with it.
+//!
+//! [`RustIrDatabase`]: crate::RustIrDatabase
+//! [`Solver::try_solve`]: super::Solver::try_solve
+
+use chalk_ir::interner::Interner;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+use std::any::Any;
+use std::fmt;
+use std::panic;
+
+/// A panic that unwound out of a solver, along with the goal it was
+/// solving.
+pub struct SolverPanic<I: Interner> {
+    /// The goal whose solving was interrupted.
+    pub goal: UCanonical<InEnvironment<Goal<I>>>,
+
+    /// What the panic was raised with, e.g. salsa's `Cancelled`.
+    pub payload: Box<dyn Any + Send>,
+}
+
+impl<I: Interner> SolverPanic<I> {
+    /// The panic message, if the panic was raised with one.
+    pub fn message(&self) -> Option<&str> {
+        if let Some(message) = self.payload.downcast_ref::<&'static str>() {
+            Some(message)
+        } else {
+            self.payload.downcast_ref::<String>().map(String::as_str)
+        }
+    }
+
+    /// Continues unwinding with the original payload, e.g. so that salsa
+    /// can catch its cancellation further up.
+    pub fn resume_unwind(self) -> ! {
+        panic::resume_unwind(self.payload)
+    }
+}
+
+impl<I: Interner> fmt::Debug for SolverPanic<I> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("SolverPanic")
+            .field("goal", &self.goal)
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+impl<I: Interner> fmt::Display for SolverPanic<I> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "panicked while solving `{:?}`", self.goal)?;
+        if let Some(message) = self.message() {
+            write!(fmt, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I: Interner> std::error::Error for SolverPanic<I> {}
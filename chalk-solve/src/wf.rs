@@ -7,7 +7,7 @@ use chalk_ir::{
     cast::*,
     fold::shift::Shift,
     interner::Interner,
-    visit::{ControlFlow, Visit, Visitor},
+    visit::{ControlFlow, SuperVisit, Visit, Visitor},
     *,
 };
 use tracing::debug;
@@ -50,6 +50,7 @@ pub struct WfSolver<'a, I: Interner> {
 
 struct InputTypeCollector<'i, I: Interner> {
     types: Vec<Ty<I>>,
+    consts: Vec<Const<I>>,
     interner: &'i I,
 }
 
@@ -57,6 +58,7 @@ impl<'i, I: Interner> InputTypeCollector<'i, I> {
     fn new(interner: &'i I) -> Self {
         Self {
             types: Vec::new(),
+            consts: Vec::new(),
             interner,
         }
     }
@@ -66,6 +68,15 @@ impl<'i, I: Interner> InputTypeCollector<'i, I> {
         value.visit_with(&mut collector, DebruijnIndex::INNERMOST);
         collector.types
     }
+
+    /// Collects the unevaluated constants (e.g. `{ N + 1 }`) appearing in
+    /// `value`. These are the only constants whose well-formedness is not
+    /// trivial.
+    fn consts_in(interner: &'i I, value: impl Visit<I>) -> Vec<Const<I>> {
+        let mut collector = Self::new(interner);
+        value.visit_with(&mut collector, DebruijnIndex::INNERMOST);
+        collector.consts
+    }
 }
 
 impl<'i, I: Interner> Visitor<'i, I> for InputTypeCollector<'i, I> {
@@ -95,6 +106,15 @@ impl<'i, I: Interner> Visitor<'i, I> for InputTypeCollector<'i, I> {
         }
     }
 
+    fn visit_const(&mut self, constant: &Const<I>, outer_binder: DebruijnIndex) -> ControlFlow<()> {
+        let interner = self.interner();
+        if let ConstValue::Unevaluated(_) = constant.data(interner).value {
+            self.consts
+                .push(constant.shifted_out_to(interner, outer_binder).unwrap());
+        }
+        constant.super_visit_with(self, outer_binder)
+    }
+
     fn visit_ty(&mut self, ty: &Ty<I>, outer_binder: DebruijnIndex) -> ControlFlow<()> {
         let interner = self.interner();
 
@@ -285,9 +305,18 @@ where
                                     (&fields, &where_clauses),
                                 );
 
+                                // WellFormed({ N + 1 }), for each unevaluated constant there
+                                let consts = InputTypeCollector::consts_in(
+                                    gb.interner(),
+                                    (&fields, &where_clauses),
+                                )
+                                .into_iter()
+                                .map(WellFormed::Const);
+
                                 types
                                     .into_iter()
                                     .map(|ty| ty.well_formed().cast(interner))
+                                    .chain(consts.casted(interner))
                                     .chain(sized_constraint_goal.into_iter())
                             })
                             .collect();
@@ -419,13 +448,25 @@ where
                     &impl_datum,
                 )
             }
+            WellKnownTrait::DispatchFromDyn => {
+                WfWellKnownConstraints::dispatch_from_dyn_impl_constraint(
+                    &mut *solver,
+                    self.db,
+                    &impl_datum,
+                )
+            }
             WellKnownTrait::Clone | WellKnownTrait::Unpin => true,
             // You can't add a manual implementation for the following traits:
             WellKnownTrait::Fn
             | WellKnownTrait::FnOnce
             | WellKnownTrait::FnMut
             | WellKnownTrait::Unsize
-            | WellKnownTrait::Sized => false,
+            | WellKnownTrait::Sized
+            | WellKnownTrait::DiscriminantKind
+            | WellKnownTrait::Generator
+            | WellKnownTrait::Pointee
+            | WellKnownTrait::Tuple
+            | WellKnownTrait::FnPtr => false,
         };
 
         if is_legal {
@@ -1052,4 +1093,134 @@ impl WfWellKnownConstraints {
             _ => false,
         }
     }
+
+    /// Verify constraints of a DispatchFromDyn impl.
+    /// Impls for references and raw pointers are builtin, so the only impls
+    /// that can be written are struct conversions of structures with the same
+    /// definition, `S<P0...Pn>` -> `S<Q0...Qn>`. Such an impl is well-formed if
+    /// 1) `S` is neither `#[repr(C)]` nor `#[repr(packed)]`
+    /// 2) all fields of `S` but one are zero-sized. As chalk has no notion of
+    ///    layout, we only consider `PhantomData` fields and `()` to be zero-sized.
+    /// 3) the remaining field changes its type (from T to U), and
+    ///    T: DispatchFromDyn<U>
+    ///
+    ///    For example, the following impl is well-formed:
+    ///    ```rust,ignore
+    ///    struct Ptr<T> { ptr: *const T, phantom: PhantomData<T> }
+    ///
+    ///    impl<T, U> DispatchFromDyn<Ptr<U>> for Ptr<T> where T: Unsize<U> {}
+    ///    ```
+    ///    since `phantom` is phantom data and `*const T: DispatchFromDyn<*const U>`
+    ///    holds whenever `T: Unsize<U>`.
+    fn dispatch_from_dyn_impl_constraint<I: Interner>(
+        solver: &mut dyn Solver<I>,
+        db: &dyn RustIrDatabase<I>,
+        impl_datum: &ImplDatum<I>,
+    ) -> bool {
+        let interner = db.interner();
+        let mut gb = GoalBuilder::new(db);
+
+        let (binders, impl_datum) = impl_datum.binders.as_ref().into();
+
+        let trait_ref: &TraitRef<I> = &impl_datum.trait_ref;
+
+        let source = trait_ref.self_type_parameter(interner);
+        let target = trait_ref
+            .substitution
+            .at(interner, 1)
+            .assert_ty_ref(interner)
+            .clone();
+
+        let mut place_in_environment = |goal| -> Goal<I> {
+            gb.forall(
+                &Binders::new(
+                    binders.clone(),
+                    (goal, trait_ref, &impl_datum.where_clauses),
+                ),
+                (),
+                |gb, _, (goal, trait_ref, where_clauses), ()| {
+                    let interner = gb.interner();
+                    gb.implies(
+                        impl_wf_environment(interner, where_clauses, trait_ref),
+                        |_| goal,
+                    )
+                },
+            )
+        };
+
+        let (source_id, subst_a, target_id, subst_b) =
+            match (source.kind(interner), target.kind(interner)) {
+                (TyKind::Adt(source_id, subst_a), TyKind::Adt(target_id, subst_b)) => {
+                    (source_id, subst_a, target_id, subst_b)
+                }
+                _ => return false,
+            };
+
+        let adt_datum = db.adt_datum(*source_id);
+        if source_id != target_id || adt_datum.kind != AdtKind::Struct {
+            return false;
+        }
+
+        let adt_repr = db.adt_repr(*source_id);
+        if adt_repr.repr_c || adt_repr.repr_packed {
+            return false;
+        }
+
+        let fields = adt_datum
+            .binders
+            .map_ref(|bound| &bound.variants.last().unwrap().fields);
+
+        let (source_fields, target_fields) = (
+            fields.substitute(interner, subst_a),
+            fields.substitute(interner, subst_b),
+        );
+
+        // Zero-sized fields are ignored, every other field must be coerced.
+        let coerced_fields: Vec<usize> = (0..source_fields.len())
+            .filter(|&i| match source_fields[i].kind(interner) {
+                TyKind::Adt(adt_id, _) => !db.adt_datum(*adt_id).flags.phantom_data,
+                TyKind::Tuple(0, _) => false,
+                _ => true,
+            })
+            .collect();
+
+        let field_id = match coerced_fields[..] {
+            [field_id] => field_id,
+            _ => return false,
+        };
+
+        let eq_goal: Goal<I> = EqGoal {
+            a: source_fields[field_id].clone().cast(interner),
+            b: target_fields[field_id].clone().cast(interner),
+        }
+        .cast(interner);
+
+        // ImplEnv -> Source.fields[i] = Target.fields[i]
+        let eq_goal = place_in_environment(eq_goal);
+
+        // The field must actually change its type
+        if solver.has_unique_solution(db, &eq_goal.into_closed_goal(interner)) {
+            return false;
+        }
+
+        // Source.fields[i]: DispatchFromDyn<TargetFields[i]>
+        let dispatch_from_dyn_goal: Goal<I> = TraitRef {
+            trait_id: trait_ref.trait_id,
+            substitution: Substitution::from_iter(
+                interner,
+                [
+                    source_fields[field_id].clone(),
+                    target_fields[field_id].clone(),
+                ]
+                .iter()
+                .cloned(),
+            ),
+        }
+        .cast(interner);
+
+        // ImplEnv -> Source.fields[i]: DispatchFromDyn<TargetFields[i]>
+        let dispatch_from_dyn_goal = place_in_environment(dispatch_from_dyn_goal);
+
+        solver.has_unique_solution(db, &dispatch_from_dyn_goal.into_closed_goal(interner))
+    }
 }
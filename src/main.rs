@@ -11,6 +11,7 @@ use chalk_integration::lowering::*;
 use chalk_integration::query::LoweringDatabase;
 use chalk_integration::SolverChoice;
 use chalk_solve::ext::*;
+use chalk_solve::infer::NormalizationMode;
 use chalk_solve::logging;
 use chalk_solve::RustIrDatabase;
 use docopt::Docopt;
@@ -26,13 +27,25 @@ Usage:
   chalk (-h | --help)
 
 Options:
-  --help              Show this screen.
-  --program=PATH      Specifies the path to the `.chalk` file containing traits/impls.
-  --goal=GOAL         Specifies a goal to evaluate (may be given more than once).
-  --overflow-depth=N  Specifies the overflow depth [default: 10].
-  --multiple          Output multiple answers instead of ambiguous solution.
+  --help                Show this screen.
+  --program=PATH        Specifies the path to the `.chalk` file containing traits/impls.
+  --goal=GOAL           Specifies a goal to evaluate (may be given more than once).
+  --overflow-depth=N    Specifies the overflow depth [default: 10].
+  --multiple            Output multiple answers instead of ambiguous solution.
+  --explain             Output a proof tree explaining each solution.
+  --stats               Output solver statistics after solving the goals.
+  --differential        Solve each goal with both solvers and report where they disagree.
+  --recursive-overflow-depth=N
+                        Specifies the overflow depth of the recursive solver used by --differential [default: 100].
+  --recursive-max-size=N
+                        Specifies the maximum goal size of the recursive solver used by --differential [default: 30].
+  --lazy-normalization  Keep associated types rigid, normalizing them only when needed.
 ";
 
+/// The number of most expensive goals that the solver keeps statistics
+/// for, all of which are shown by `--stats` and `stats`.
+const EXPENSIVE_GOALS: usize = 10;
+
 /// This struct represents the various command line options available.
 #[derive(Debug, Deserialize)]
 struct Args {
@@ -40,6 +53,12 @@ struct Args {
     flag_goal: Vec<String>,
     flag_overflow_depth: usize,
     flag_multiple: bool,
+    flag_explain: bool,
+    flag_stats: bool,
+    flag_differential: bool,
+    flag_recursive_overflow_depth: usize,
+    flag_recursive_max_size: usize,
+    flag_lazy_normalization: bool,
 }
 
 /// A loaded and parsed program.
@@ -58,41 +77,40 @@ impl LoadedProgram {
         Ok(LoadedProgram { text, db })
     }
 
+    /// Print the statistics collected by the solver so far.
+    fn stats(&self) {
+        print!("{}", self.db.solver_stats().display(EXPENSIVE_GOALS));
+    }
+
     /// Parse a goal and attempt to solve it, using the specified solver.
     fn goal(
         &self,
         mut rl: Option<&mut rustyline::Editor<()>>,
         text: &str,
         multiple_answers: bool,
+        explain: bool,
     ) -> Result<()> {
         let program = self.db.checked_program()?;
         let goal = lower_goal(&*chalk_parse::parse_goal(text)?, &*program)?;
         let peeled_goal = goal.into_peeled_goal(self.db.interner());
-        if multiple_answers {
-            if self.db.solve_multiple(&peeled_goal, &mut |v, has_next| {
-                println!("{}\n", v.as_ref().map(|v| v.display(&ChalkIr)));
-                if has_next {
-                    if let Some(ref mut rl) = rl {
-                        loop {
-                            if let Ok(next) = rl.readline("Show next answer (y/n): ") {
-                                if "y" == next {
-                                    return true;
-                                } else if "n" == next {
-                                    return false;
-                                } else {
-                                    println!("Unknown response. Try again.");
-                                }
-                            } else {
-                                return false;
-                            }
-                        }
-                    } else {
-                        true
+        if explain {
+            let (solution, proof_tree) = self.db.solve_explained(&peeled_goal);
+            match solution {
+                Some(v) => println!("{}\n", v.display(&ChalkIr)),
+                None => println!("No possible solution.\n"),
+            }
+            println!("{}", proof_tree.display(&ChalkIr));
+        } else if multiple_answers {
+            let complete = self.db.with_answers(&peeled_goal, |answers| {
+                while let Some(v) = answers.next() {
+                    println!("{}\n", v.as_ref().map(|v| v.display(&ChalkIr)));
+                    if answers.has_next() && !show_next_answer(&mut rl) {
+                        return false;
                     }
-                } else {
-                    true
                 }
-            }) {
+                true
+            });
+            if complete {
                 println!("No more solutions");
             }
         } else {
@@ -100,6 +118,9 @@ impl LoadedProgram {
                 Some(v) => println!("{}\n", v.display(&ChalkIr)),
                 None => println!("No possible solution.\n"),
             }
+            for disagreement in self.db.take_disagreements() {
+                println!("{}", disagreement.display(&ChalkIr));
+            }
         }
         Ok(())
     }
@@ -147,11 +168,14 @@ fn run() -> Result<()> {
         // and exit.
         prog.db.with_program(|_| -> Result<()> {
             for g in &args.flag_goal {
-                if let Err(e) = prog.goal(None, g, args.flag_multiple) {
+                if let Err(e) = prog.goal(None, g, args.flag_multiple, args.flag_explain) {
                     eprintln!("error: {}", e);
                     exit(1);
                 }
             }
+            if args.flag_stats {
+                prog.stats();
+            }
             Ok(())
         })?;
 
@@ -189,6 +213,23 @@ where
     Ok(())
 }
 
+/// Asks the user whether to show the next answer to a goal. Without a
+/// readline editor (i.e., when not interactive), all answers are shown.
+fn show_next_answer(rl: &mut Option<&mut rustyline::Editor<()>>) -> bool {
+    let rl = match rl {
+        Some(rl) => rl,
+        None => return true,
+    };
+    loop {
+        match rl.readline("Show next answer (y/n): ") {
+            Ok(next) if next == "y" => return true,
+            Ok(next) if next == "n" => return false,
+            Ok(_) => println!("Unknown response. Try again."),
+            Err(_) => return false,
+        }
+    }
+}
+
 /// Process a single command. `args` is a struct containing the command-line
 /// arguments, and `prog` is a parsed `.chalk` file.
 // TODO: Could we pass in an Options struct or something? The Args struct
@@ -240,10 +281,18 @@ fn process(
                 // TODO: Write a line of documentation here.
                 "lowered" => println!("{:#?}", prog.db.environment()),
 
+                // Print out the solver statistics.
+                "stats" => prog.stats(),
+
+                _ if command.starts_with("explain ") => {
+                    let goal = &command["explain ".len()..];
+                    prog.goal(Some(rl), goal, false, true)?
+                }
+
                 // Assume this is a goal.
                 // TODO: Print out "type 'help' to see available commands" if it
                 // fails to parse?
-                _ => prog.goal(Some(rl), command, args.flag_multiple)?,
+                _ => prog.goal(Some(rl), command, args.flag_multiple, args.flag_explain)?,
             }
             Ok(())
         })?
@@ -271,6 +320,8 @@ fn help() {
     println!("  print         print the current program");
     println!("  lowered       print the lowered program");
     println!("  <goal>        attempt to solve <goal>");
+    println!("  explain <goal> attempt to solve <goal> and print a proof tree");
+    println!("  stats         print solver statistics and the most expensive goals");
     println!("  debug <level> set debug level to <level>");
 }
 
@@ -288,9 +339,26 @@ fn read_program(rl: &mut rustyline::Editor<()>) -> Result<String> {
 
 impl Args {
     fn solver_choice(&self) -> SolverChoice {
-        SolverChoice::SLG {
-            max_size: self.flag_overflow_depth,
-            expected_answers: None,
+        let normalization = if self.flag_lazy_normalization {
+            NormalizationMode::Lazy
+        } else {
+            NormalizationMode::Eager
+        };
+        if self.flag_differential {
+            SolverChoice::Differential {
+                slg_max_size: self.flag_overflow_depth,
+                recursive_max_size: self.flag_recursive_max_size,
+                overflow_depth: self.flag_recursive_overflow_depth,
+                normalization,
+                max_expensive_goals: EXPENSIVE_GOALS,
+            }
+        } else {
+            SolverChoice::SLG {
+                max_size: self.flag_overflow_depth,
+                expected_answers: None,
+                normalization,
+                max_expensive_goals: EXPENSIVE_GOALS,
+            }
         }
     }
 }
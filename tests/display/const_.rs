@@ -18,6 +18,18 @@ fn test_const_generics() {
     );
 }
 
+#[test]
+fn test_typed_const_generics() {
+    // Test we render the types of const generic parameters.
+    reparse_test!(
+        program {
+            struct Bar<const U: usize, const B: bool> { }
+            trait Foo<const C: char> { }
+            impl<const U: usize, const C: char> Foo<C> for Bar<U, U> { }
+        }
+    );
+}
+
 #[test]
 #[ignore]
 fn test_basic_const_values_in_impls() {
@@ -88,6 +88,13 @@ fn test_enum_repr() {
             #[repr(packed)]
             #[repr(C)]
             enum PackedCFoo {}
+
+            #[repr(u16)]
+            enum U16Foo {}
+
+            #[repr(C)]
+            #[repr(i32)]
+            enum CI32Foo {}
         }
     );
 }
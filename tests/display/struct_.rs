@@ -113,3 +113,22 @@ fn test_struct_repr_with_flags() {
         }
     );
 }
+
+#[test]
+fn test_struct_lang() {
+    // Test printing a struct lang item, together with flags and a repr.
+    reparse_test!(
+        program {
+            #[lang(dyn_metadata)]
+            struct DynMetadata<Dyn> {}
+        }
+    );
+    reparse_test!(
+        program {
+            #[upstream]
+            #[lang(dyn_metadata)]
+            #[repr(C)]
+            struct DynMetadata<Dyn> {}
+        }
+    );
+}
@@ -32,6 +32,39 @@ fn test_trait_where_clauses() {
     );
 }
 
+#[test]
+fn test_trait_methods() {
+    // Test printing trait methods, with and without receivers, arguments,
+    // return types, generics and where clauses
+    reparse_test!(
+        program {
+            struct Box<T> {}
+            trait Foo<T> {
+                type Assoc;
+                fn method_0();
+                fn method_1(self: Self);
+                fn method_2<'a>(self: &'a Self);
+                fn method_3<U>(self: Box<Self>) where U: Foo<T>;
+                fn method_4(self: Self, arg_0: T, arg_1: Box<T>) -> T;
+                fn method_5(arg_0: u32) -> Self;
+            }
+        }
+    );
+}
+
+#[test]
+fn test_trait_assoc_consts() {
+    reparse_test!(
+        program {
+            trait Foo<T> {
+                type Assoc;
+                const CONST_0: u32;
+                const CONST_1: T;
+            }
+        }
+    );
+}
+
 #[test]
 fn test_basic_trait_impl() {
     // Test simplest trait implementation
@@ -77,11 +110,37 @@ fn test_trait_flags() {
     }
 }
 
+#[test]
+fn test_compute_object_safety_flag() {
+    // `#[object_safe]` takes precedence, so this cannot be combined with it
+    // in `test_trait_flags`
+    reparse_test!(
+        program {
+            #[auto]
+            #[compute_object_safety]
+            trait Foo {}
+        }
+    );
+}
+
 #[test]
 fn test_wellknown_traits() {
     // Test all possible `#[lang]` attributes on traits.
     let well_knowns = vec![
-        "sized", "copy", "clone", "drop", "fn_once", "fn_mut", "fn", "unsize",
+        "sized",
+        "copy",
+        "clone",
+        "drop",
+        "fn_once",
+        "fn_mut",
+        "fn",
+        "unsize",
+        "discriminant_kind",
+        "generator",
+        "dispatch_from_dyn",
+        "pointee",
+        "tuple_trait",
+        "fn_ptr_trait",
     ];
     for flag in well_knowns {
         reparse_test(&format!(
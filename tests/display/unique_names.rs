@@ -2,7 +2,7 @@ use chalk_integration::{program::Program, query::LoweringDatabase, tls};
 use chalk_ir::{interner::Interner, UnificationDatabase};
 use chalk_solve::{
     display::{write_items, WriterState},
-    RustIrDatabase,
+    LookupError, RustIrDatabase,
 };
 use std::marker::PhantomData;
 
@@ -75,7 +75,7 @@ where
     ) -> std::sync::Arc<chalk_solve::rust_ir::AdtDatum<I>> {
         self.db.adt_datum(adt_id)
     }
-    fn adt_repr(&self, id: chalk_ir::AdtId<I>) -> chalk_solve::rust_ir::AdtRepr {
+    fn adt_repr(&self, id: chalk_ir::AdtId<I>) -> std::sync::Arc<chalk_solve::rust_ir::AdtRepr<I>> {
         self.db.adt_repr(id)
     }
     fn fn_def_datum(
@@ -144,16 +144,22 @@ where
     ) -> Option<chalk_ir::TraitId<I>> {
         self.db.well_known_trait_id(well_known_trait)
     }
+    fn well_known_adt_id(
+        &self,
+        well_known_adt: chalk_solve::rust_ir::WellKnownAdt,
+    ) -> Option<chalk_ir::AdtId<I>> {
+        self.db.well_known_adt_id(well_known_adt)
+    }
     fn program_clauses_for_env(
         &self,
         environment: &chalk_ir::Environment<I>,
-    ) -> chalk_ir::ProgramClauses<I> {
+    ) -> Result<chalk_ir::ProgramClauses<I>, LookupError<I>> {
         self.db.program_clauses_for_env(environment)
     }
     fn interner(&self) -> &I {
         self.db.interner()
     }
-    fn is_object_safe(&self, trait_id: chalk_ir::TraitId<I>) -> bool {
+    fn is_object_safe(&self, trait_id: chalk_ir::TraitId<I>) -> Option<bool> {
         self.db.is_object_safe(trait_id)
     }
     fn closure_kind(
@@ -2,7 +2,7 @@ use chalk_integration::interner::{ChalkIr, RawId};
 use chalk_integration::SolverChoice;
 use chalk_ir::*;
 use chalk_solve::rust_ir::*;
-use chalk_solve::RustIrDatabase;
+use chalk_solve::{LookupError, RustIrDatabase};
 use std::sync::Arc;
 
 // FIXME: some of these are probably redundant, so we should figure out which panic in the same place in `chalk-engine`
@@ -71,6 +71,8 @@ impl RustIrDatabase<ChalkIr> for MockDatabase {
                 VariableKinds::empty(&ChalkIr),
                 TraitDatumBound {
                     where_clauses: vec![],
+                    methods: vec![],
+                    associated_consts: vec![],
                 },
             ),
             flags: TraitFlags {
@@ -113,6 +115,7 @@ impl RustIrDatabase<ChalkIr> for MockDatabase {
         Arc::new(ImplDatum {
             polarity: Polarity::Positive,
             binders,
+            id,
             impl_type: ImplType::Local,
             associated_ty_value_ids: vec![],
         })
@@ -153,7 +156,7 @@ impl RustIrDatabase<ChalkIr> for MockDatabase {
         })
     }
 
-    fn adt_repr(&self, id: AdtId<ChalkIr>) -> AdtRepr {
+    fn adt_repr(&self, id: AdtId<ChalkIr>) -> Arc<AdtRepr<ChalkIr>> {
         unimplemented!()
     }
 
@@ -202,12 +205,12 @@ impl RustIrDatabase<ChalkIr> for MockDatabase {
     fn program_clauses_for_env(
         &self,
         environment: &Environment<ChalkIr>,
-    ) -> ProgramClauses<ChalkIr> {
+    ) -> Result<ProgramClauses<ChalkIr>, LookupError<ChalkIr>> {
         if let PanickingMethod::ProgramClausesForEnv = self.panicking_method {
             panic!("program_clauses_for_env panic")
         }
 
-        ProgramClauses::empty(&ChalkIr)
+        Ok(ProgramClauses::empty(&ChalkIr))
     }
 
     fn interner(&self) -> &ChalkIr {
@@ -218,10 +221,6 @@ impl RustIrDatabase<ChalkIr> for MockDatabase {
         &ChalkIr
     }
 
-    fn is_object_safe(&self, trait_id: TraitId<ChalkIr>) -> bool {
-        unimplemented!()
-    }
-
     fn closure_inputs_and_output(
         &self,
         closure_id: ClosureId<ChalkIr>,
@@ -416,3 +415,83 @@ fn interner() {
     db.panicking_method = PanickingMethod::NoPanic;
     assert!(solver.solve(&db, &peeled_goal).is_some());
 }
+
+/// `Foo: Bar, Foo: Bar`, whose subgoals are only looked into once the
+/// solver is in the middle of solving the conjunction.
+fn prepare_conjunction_goal() -> UCanonical<InEnvironment<Goal<ChalkIr>>> {
+    let mut goal = prepare_goal();
+    let subgoal = goal.canonical.value.goal.clone();
+    goal.canonical.value.goal =
+        GoalData::All(Goals::from_iter(&ChalkIr, vec![subgoal.clone(), subgoal])).intern(&ChalkIr);
+    goal
+}
+
+/// Solves `goal` with a database that panics in `panicking_method`, and
+/// checks that the solver still finds the solution once it stops panicking.
+fn solve_after_panic(
+    solver_choice: SolverChoice,
+    panicking_method: PanickingMethod,
+    goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
+) {
+    use std::panic;
+
+    let mut solver = solver_choice.into_solver();
+    let mut db = MockDatabase { panicking_method };
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        solver.solve(&db, goal);
+    }));
+    assert!(result.is_err());
+
+    db.panicking_method = PanickingMethod::NoPanic;
+    assert!(solver.solve(&db, goal).unwrap().is_unique());
+}
+
+#[test]
+fn recursive_solver_panics() {
+    let peeled_goal = prepare_goal();
+    for panicking_method in [
+        PanickingMethod::CustomClauses,
+        PanickingMethod::TraitDatum,
+        PanickingMethod::ImplDatum,
+        PanickingMethod::ImplsForTrait,
+        PanickingMethod::ProgramClausesForEnv,
+        PanickingMethod::Interner,
+    ] {
+        solve_after_panic(
+            SolverChoice::recursive_default(),
+            panicking_method,
+            &peeled_goal,
+        );
+    }
+}
+
+#[test]
+fn subgoal_panics() {
+    let goal = prepare_conjunction_goal();
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        solve_after_panic(solver_choice, PanickingMethod::ImplsForTrait, &goal);
+    }
+}
+
+#[test]
+fn try_solve_returns_panic() {
+    let goal = prepare_conjunction_goal();
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        let mut solver = solver_choice.into_solver();
+        let mut db = MockDatabase {
+            panicking_method: PanickingMethod::ImplsForTrait,
+        };
+        let error = solver.try_solve(&db, &goal).unwrap_err();
+        assert_eq!(error.goal, goal);
+        assert_eq!(error.message(), Some("impls_for_trait panic"));
+
+        db.panicking_method = PanickingMethod::NoPanic;
+        assert!(solver.try_solve(&db, &goal).unwrap().unwrap().is_unique());
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(feature = "bench", feature(test))]
+
 #[macro_use]
 mod test_util;
 #[macro_use]
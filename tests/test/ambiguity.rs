@@ -0,0 +1,85 @@
+//! Tests for the causes recorded in the `Guidance` of ambiguous solutions.
+
+use super::*;
+use chalk_solve::AmbiguityCause;
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    trait Convert<T> { }
+    impl Convert<Foo> for Foo { }
+    impl Convert<Bar> for Foo { }
+    impl Convert<Foo> for Bar { }
+";
+
+/// Solves a goal that is expected to be ambiguous with every solver,
+/// returning the cause each of them gave along with the displayed solution.
+fn solve_ambiguous(goal_text: &str) -> Vec<(AmbiguityCause<ChalkIr>, String)> {
+    with_each_solver(PROGRAM, goal_text, |db, goal| match db.solve(goal) {
+        Some(Solution::Ambig(guidance)) => {
+            let display = Solution::Ambig(guidance.clone())
+                .display(db.interner())
+                .to_string();
+            (guidance.cause().clone(), display)
+        }
+        solution => panic!("expected an ambiguous solution, got {:?}", solution),
+    })
+}
+
+#[test]
+fn multiple_impls() {
+    for (cause, display) in solve_ambiguous("exists<T> { Foo: Convert<T> }") {
+        match cause {
+            AmbiguityCause::MultipleImpls(impls) => assert_eq!(impls.len(), 2),
+            cause => panic!("expected multiple impls, got {:?}", cause),
+        }
+        assert!(display.contains("; multiple impls apply: ["), "{}", display);
+    }
+}
+
+#[test]
+fn unresolved_self_type() {
+    for (cause, display) in solve_ambiguous("exists<T> { T: Convert<Foo> }") {
+        assert_eq!(cause, AmbiguityCause::UnresolvedSelfType);
+        assert!(display.ends_with("; self type is unknown"), "{}", display);
+    }
+}
+
+#[test]
+fn multiple_impls_of_subgoal() {
+    for (cause, _) in solve_ambiguous("exists<T> { Foo: Convert<T>, Bar: Convert<Foo> }") {
+        match cause {
+            AmbiguityCause::MultipleImpls(impls) => assert_eq!(impls.len(), 2),
+            cause => panic!("expected multiple impls, got {:?}", cause),
+        }
+    }
+}
+
+#[test]
+fn merge_causes() {
+    let impls = |ids: &[u32]| -> AmbiguityCause<ChalkIr> {
+        AmbiguityCause::MultipleImpls(
+            ids.iter()
+                .map(|&id| chalk_ir::ImplId(chalk_integration::interner::RawId { index: id }))
+                .collect(),
+        )
+    };
+    assert_eq!(impls(&[0, 1]).merge(impls(&[1, 2])), impls(&[0, 1, 2]));
+    assert_eq!(AmbiguityCause::Other.merge(impls(&[0, 1])), impls(&[0, 1]));
+    assert_eq!(
+        impls(&[0, 1]).merge(AmbiguityCause::Floundered),
+        AmbiguityCause::Floundered
+    );
+    assert_eq!(
+        AmbiguityCause::<ChalkIr>::Overflow.merge(AmbiguityCause::UnresolvedSelfType),
+        AmbiguityCause::Overflow
+    );
+}
+
+#[test]
+fn floundered() {
+    for (cause, display) in solve_ambiguous("exists<T> { not { T: Convert<Foo> } }") {
+        assert_eq!(cause, AmbiguityCause::Floundered);
+        assert!(display.ends_with("; floundered"), "{}", display);
+    }
+}
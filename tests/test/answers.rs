@@ -0,0 +1,100 @@
+//! Tests for iterating over the answers to a goal with
+//! `Solver::solve_answers`.
+
+use super::*;
+use chalk_engine::solve::ParallelSLGSolver;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+use chalk_solve::{Answers, Solver};
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    trait Clone { }
+    trait Convert<T> { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+    impl Convert<Foo> for Foo { }
+    impl Convert<Bar> for Foo { }
+";
+
+/// The first `count` answers to `goal`, displayed.
+fn first_answers(
+    db: &ChalkDatabase,
+    goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>,
+    count: usize,
+) -> Vec<String> {
+    db.with_program(|_| db.with_answers(goal, |answers| display_answers(answers, count)))
+}
+
+fn display_answers(answers: &mut Answers<'_, ChalkIr>, count: usize) -> Vec<String> {
+    answers
+        .take(count)
+        .map(|answer| format!("{}", answer.as_ref().map(|v| v.display(&ChalkIr))))
+        .collect()
+}
+
+#[test]
+fn answers_are_found_lazily() {
+    // There are infinitely many answers, so we could not get any of them if
+    // they were all found up front.
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg(10, None));
+    let goal = peeled_goal(&db, "exists<T> { T: Clone }");
+    let first = first_answers(&db, &goal, 3);
+    assert_eq!(first.len(), 3);
+    assert!(first[0].contains("?0 := Foo"), "{:?}", first);
+    assert!(first[1].contains("?0 := Vec<Foo>"), "{:?}", first);
+    assert!(first[2].contains("?0 := Vec<Vec<Foo>>"), "{:?}", first);
+
+    // Asking again starts over, reusing the answers found so far.
+    let again = first_answers(&db, &goal, 4);
+    assert_eq!(again[..3], first[..]);
+    assert!(again[3].contains("?0 := Vec<Vec<Vec<Foo>>>"), "{:?}", again);
+}
+
+#[test]
+fn has_next() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg(10, None));
+    let goal = peeled_goal(&db, "exists<T> { Foo: Convert<T> }");
+    db.with_answers(&goal, |answers| {
+        assert!(answers.has_next());
+        assert!(answers.next().is_some());
+        assert!(answers.has_next());
+        assert!(answers.next().is_some());
+        assert!(!answers.has_next());
+        assert!(answers.next().is_none());
+    });
+}
+
+#[test]
+fn recursive_solver_yields_its_solution() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::recursive_default());
+
+    let goal = peeled_goal(&db, "exists<T> { Vec<T>: Clone, T = Foo }");
+    let answers = first_answers(&db, &goal, 2);
+    assert_eq!(answers.len(), 1);
+    assert!(answers[0].contains("?0 := Foo"), "{:?}", answers);
+
+    // A goal without inference guidance has no substitution to give.
+    let goal = peeled_goal(&db, "exists<T> { Foo: Convert<T> }");
+    let answers = first_answers(&db, &goal, 2);
+    assert_eq!(answers, vec!["Floundered".to_string()]);
+
+    let goal = peeled_goal(&db, "Bar: Clone");
+    let answers = first_answers(&db, &goal, 2);
+    assert!(answers.is_empty());
+}
+
+#[test]
+fn parallel_solver_publishes_tables_once_answers_are_dropped() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goal = peeled_goal(&db, "exists<T> { Foo: Convert<T> }");
+    let mut solver = ParallelSLGSolver::new(10, None);
+    let shared = solver.clone();
+    db.with_program(|program| {
+        let answers = display_answers(&mut solver.solve_answers(program, &goal), 3);
+        assert_eq!(answers.len(), 2);
+    });
+    assert!(shared.shared_tables() > 0);
+    assert_eq!(shared.stats().root_goals, 1);
+}
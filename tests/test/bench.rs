@@ -1,12 +1,10 @@
 //! Benchmarking tests.
 
+extern crate test;
 use self::test::Bencher;
 
-use chalk_ir;
-use chalk_solve::ext::*;
-use chalk_solve::SolverChoice;
-
-use super::{assert_result, parse_and_lower_goal, parse_and_lower_program_with_env};
+use super::*;
+use chalk_engine::solve::ParallelSLGSolver;
 
 fn run_bench(
     program_text: &str,
@@ -15,20 +13,17 @@ fn run_bench(
     bencher: &mut Bencher,
     expected: &str,
 ) {
-    let (program, env) = parse_and_lower_program_with_env(program_text, solver_choice).unwrap();
-    chalk_ir::tls::set_current_program(&program, || {
-        let goal = parse_and_lower_goal(&program, goal_text).unwrap();
-        let peeled_goal = goal.into_peeled_goal();
+    let db = ChalkDatabase::with(program_text, solver_choice);
+    let peeled_goal = peeled_goal(&db, goal_text);
 
-        // Execute once to get an expected result.
-        let result = solver_choice.solve_root_goal(&env, &peeled_goal);
+    // Execute once to get an expected result.
+    let result = solver_choice.into_solver().solve(&db, &peeled_goal);
 
-        // Check expectation.
-        assert_result(&result, expected);
+    // Check expectation.
+    assert_result(result, expected, db.interner());
 
-        // Then do it many times to measure time.
-        bencher.iter(|| solver_choice.solve_root_goal(&env, &peeled_goal));
-    });
+    // Then do it many times to measure time.
+    bencher.iter(|| solver_choice.into_solver().solve(&db, &peeled_goal));
 }
 
 const CYCLEY: &str = "
@@ -37,12 +32,10 @@ trait Clone { }
 trait Copy where Self: Clone { }
 trait Sized { }
 
-struct i32 { }
 impl Copy for i32 { }
 impl Clone for i32 { }
 impl Sized for i32 { }
 
-struct u32 { }
 impl Copy for u32 { }
 impl Clone for u32 { }
 impl Sized for u32 { }
@@ -97,9 +90,60 @@ forall<T> {
 fn cycley_slg(b: &mut Bencher) {
     run_bench(
         CYCLEY,
-        SolverChoice::SLG { max_size: 20 },
+        SolverChoice::slg(20, None),
         CYCLEY_GOAL,
         b,
         "Unique",
     );
 }
+
+/// A program with many types whose goals share few tables, so that they
+/// can be solved independently.
+fn independent_goals() -> (String, Vec<String>) {
+    let mut program = String::from(
+        "
+        trait Clone { }
+        struct Vec<T> { }
+        struct Box<T> { }
+        struct Rc<T> { }
+        impl<T> Clone for Vec<T> where T: Clone { }
+        impl<T> Clone for Box<T> where T: Clone { }
+        impl<T> Clone for Rc<T> where T: Clone { }
+        ",
+    );
+    let mut goals = vec![];
+    for i in 0..64 {
+        program.push_str(&format!("struct S{i} {{ }} impl Clone for S{i} {{ }}\n"));
+        goals.push(format!("Vec<Box<Rc<Vec<Box<Rc<S{i}>>>>>>: Clone"));
+    }
+    (program, goals)
+}
+
+fn run_parallel_bench(threads: usize, bencher: &mut Bencher) {
+    let (program_text, goal_texts) = independent_goals();
+    let db = ChalkDatabase::with(&program_text, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals: Vec<_> = goal_texts
+        .iter()
+        .map(|goal_text| peeled_goal(&db, goal_text))
+        .collect();
+
+    // Each iteration starts without any shared tables, so that every goal
+    // is actually solved.
+    bencher.iter(|| {
+        let solutions = ParallelSLGSolver::new(10, None).solve_all(&*program, &goals, threads);
+        for solution in solutions {
+            assert_result(solution, "Unique", db.interner());
+        }
+    });
+}
+
+#[bench]
+fn independent_goals_1_thread(b: &mut Bencher) {
+    run_parallel_bench(1, b);
+}
+
+#[bench]
+fn independent_goals_4_threads(b: &mut Bencher) {
+    run_parallel_bench(4, b);
+}
@@ -149,3 +149,236 @@ fn placeholders_eq() {
         }
     }
 }
+
+#[test]
+fn unevaluated_consts() {
+    test! {
+        program {
+            struct S<const N> {}
+
+            trait Trait {}
+
+            impl Trait for S<3> {}
+        }
+
+        goal {
+            S<{ 1 + 2 }>: Trait
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            S<{ 2 * 2 - 1 }>: Trait
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            S<{ 2 * (2 - 1) }>: Trait
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<const N> {
+                S<{ N + 1 }>: Trait
+            }
+        } yields {
+            "Ambiguous"
+        }
+
+        goal {
+            exists<const N> {
+                S<{ N + 1 }> = S<3>
+            }
+        } yields {
+            "Ambiguous"
+        }
+
+        goal {
+            exists<const N> {
+                N = 2, S<{ N + 1 }>: Trait
+            }
+        } yields {
+            "Unique; substitution [?0 := 2]"
+        }
+
+        goal {
+            exists<const N> {
+                N = 1, S<{ N + 1 }>: Trait
+            }
+        } yields {
+            "No possible solution"
+        }
+    }
+}
+
+#[test]
+fn unevaluated_consts_in_arrays() {
+    test! {
+        program {
+            trait Trait {}
+
+            impl<T, const N> Trait for [T; { N + 1 }] {}
+        }
+
+        goal {
+            forall<T, const N> {
+                [T; { N + 1 }]: Trait
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T, const N> {
+                [T; { N + 2 }]: Trait
+            }
+        } yields {
+            "Ambiguous"
+        }
+
+        goal {
+            forall<T, const N> {
+                exists<const M> {
+                    [T; { M * 2 }] = [T; { N * 2 }]
+                }
+            }
+        } yields {
+            "Unique; substitution [?0 := !1_1]"
+        }
+    }
+}
+
+#[test]
+fn unevaluated_const_overflow() {
+    test! {
+        program {
+            struct S<const N> {}
+        }
+
+        goal {
+            exists<const N> {
+                S<{ 0 - 1 }> = S<N>
+            }
+        } yields {
+            "No possible solution"
+        }
+    }
+}
+
+#[test]
+fn typed_const_params() {
+    test! {
+        program {
+            struct S<const N: usize> {}
+            struct B<const F: bool> {}
+        }
+
+        goal {
+            forall<const N: usize> {
+                WellFormed(S<N>)
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<const F: bool> {
+                WellFormed(S<F>)
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<const F: bool> {
+                ConstHasType(F, bool)
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<const F: bool> {
+                ConstHasType(F, usize)
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            ConstHasType(3, u32)
+        } yields {
+            "Unique"
+        }
+
+        // Literals passed to a const parameter have its type.
+        goal {
+            WellFormed(S<3>)
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            exists<const N: usize> {
+                ConstHasType(N, usize)
+            }
+        } yields {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn const_well_formed() {
+    test! {
+        program {
+            struct S<const N> {}
+        }
+
+        goal {
+            WellFormed(3)
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<const N> {
+                WellFormed(N)
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            WellFormed({ 1 + 2 })
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            WellFormed({ 0 - 1 })
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<const N> {
+                WellFormed({ N + 1 })
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<const N> {
+                if (WellFormed({ N + 1 })) {
+                    WellFormed({ N + 1 })
+                }
+            }
+        } yields {
+            "Unique"
+        }
+    }
+}
@@ -164,9 +164,9 @@ fn overflow() {
         goal {
             S<Z>: Q
         } yields[SolverChoice::slg(10, None)] {
-            "Ambiguous; no inference guidance"
+            "Overflow; size limit 10 reached"
         } yields[SolverChoice::recursive_default()] {
-            "Ambiguous; no inference guidance"
+            "Overflow; size limit 30 reached"
         }
     }
 }
@@ -0,0 +1,174 @@
+//! Tests for comparing the solvers with `SolverChoice::Differential`.
+
+use super::*;
+use chalk_solve::solve::differential::diff_solutions;
+use chalk_solve::solve::{DifferentialSolver, SolutionDiff};
+use chalk_solve::Solver;
+
+#[test]
+fn agreeing_solvers() {
+    let db = ChalkDatabase::with(
+        "
+        struct Foo { }
+        struct Vec<T> { }
+        trait Clone { }
+        impl Clone for Foo { }
+        impl<T> Clone for Vec<T> where T: Clone { }
+        ",
+        SolverChoice::differential_default(),
+    );
+    assert!(solve(&db, "Vec<Foo>: Clone").unwrap().is_unique());
+    assert!(solve(&db, "exists<T> { Vec<T>: Clone }").is_some());
+    assert_eq!(solve(&db, "Vec<u32>: Clone"), None);
+    assert_eq!(db.take_disagreements(), vec![]);
+}
+
+#[test]
+fn disagreement_on_uniqueness() {
+    // See `misc::not_really_ambig`.
+    let db = ChalkDatabase::with(
+        "
+        struct Vec<T> { }
+        trait A { }
+        trait B { }
+        impl<T> A for Vec<T> where T: A, T: B { }
+        impl A for u32 { }
+        impl B for u32 { }
+        impl A for i32 { }
+        impl B for i8 { }
+        ",
+        SolverChoice::differential_default(),
+    );
+    let solution = solve(&db, "exists<T> { Vec<T>: A }");
+
+    let disagreements = db.take_disagreements();
+    assert_eq!(disagreements.len(), 1);
+    assert_eq!(disagreements[0].primary, solution);
+    assert_eq!(
+        disagreements[0].diffs,
+        vec![SolutionDiff::Unique {
+            primary: true,
+            secondary: false
+        }]
+    );
+
+    // Disagreements are only reported once.
+    assert_eq!(db.take_disagreements(), vec![]);
+}
+
+#[test]
+fn disagreement_on_provability() {
+    // See `coinduction::coinductive_unsound1`.
+    let db = ChalkDatabase::with(
+        "
+        trait C1orC2 { }
+
+        #[coinductive]
+        trait C1 { }
+
+        #[coinductive]
+        trait C2 { }
+
+        #[coinductive]
+        trait C3 { }
+
+        forall<T> {
+            T: C1 if T: C2, T: C3
+        }
+
+        forall<T> {
+            T: C2 if T: C1
+        }
+
+        forall<T> {
+            T: C1orC2 if T: C1
+        }
+
+        forall<T> {
+            T: C1orC2 if T: C2
+        }
+        ",
+        SolverChoice::differential_default(),
+    );
+    assert_eq!(solve(&db, "forall<X> { X: C1orC2 }"), None);
+
+    let disagreements = db.take_disagreements();
+    assert_eq!(disagreements.len(), 1);
+    assert_eq!(
+        disagreements[0].diffs,
+        vec![SolutionDiff::Provable {
+            primary: false,
+            secondary: true
+        }]
+    );
+}
+
+#[test]
+fn diff_substitutions() {
+    let db = ChalkDatabase::with("", SolverChoice::slg_default());
+    let u32_solution = solve(&db, "exists<T> { T = u32 }");
+    let i32_solution = solve(&db, "exists<T> { T = i32 }");
+
+    let diffs = diff_solutions(db.interner(), &u32_solution, &i32_solution);
+    assert_eq!(diffs.len(), 1);
+    match &diffs[0] {
+        SolutionDiff::Substitution {
+            index,
+            primary,
+            secondary,
+        } => {
+            assert_eq!(*index, 0);
+            assert_ne!(primary, secondary);
+        }
+        diff => panic!("unexpected diff: {:?}", diff),
+    }
+    assert_eq!(
+        diff_solutions(db.interner(), &u32_solution, &u32_solution),
+        vec![]
+    );
+}
+
+#[test]
+fn only_the_most_recent_disagreements_are_kept() {
+    // See `disagreement_on_uniqueness`.
+    let db = ChalkDatabase::with(
+        "
+        struct Vec<T> { }
+        struct Box<T> { }
+        struct Rc<T> { }
+        trait A { }
+        trait B { }
+        impl<T> A for Vec<T> where T: A, T: B { }
+        impl<T> A for Box<T> where T: A, T: B { }
+        impl<T> A for Rc<T> where T: A, T: B { }
+        impl A for u32 { }
+        impl B for u32 { }
+        impl A for i32 { }
+        impl B for i8 { }
+        ",
+        SolverChoice::slg_default(),
+    );
+    let program = db.program_ir().unwrap();
+    let goals: Vec<_> = [
+        "exists<T> { Vec<T>: A }",
+        "exists<T> { Box<T>: A }",
+        "exists<T> { Rc<T>: A }",
+    ]
+    .iter()
+    .map(|goal_text| peeled_goal(&db, goal_text))
+    .collect();
+
+    let mut solver = DifferentialSolver::new(
+        SolverChoice::slg(10, None).into_solver(),
+        SolverChoice::recursive(30, 100).into_solver(),
+    )
+    .with_max_disagreements(2);
+    for goal in &goals {
+        solver.solve(&*program, goal);
+    }
+
+    let disagreements = solver.take_disagreements();
+    assert_eq!(disagreements.len(), 2);
+    assert_eq!(disagreements[0].goal, goals[1]);
+    assert_eq!(disagreements[1].goal, goals[2]);
+}
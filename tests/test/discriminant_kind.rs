@@ -0,0 +1,221 @@
+//! Tests targeting the DiscriminantKind trait
+
+use super::*;
+
+#[test]
+fn discriminant_kind_impl() {
+    test! {
+        program {
+            #[lang(discriminant_kind)]
+            trait DiscriminantKind {
+                type Discriminant;
+            }
+
+            enum A { Variant }
+            struct B { }
+            trait Foo { }
+        }
+
+        goal {
+            A: DiscriminantKind
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            B: DiscriminantKind
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            (u8, &'static str): DiscriminantKind
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                T: DiscriminantKind
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<'a> {
+                dyn Foo + 'a: DiscriminantKind
+            }
+        } yields {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn discriminant_kind_assoc() {
+    test! {
+        program {
+            #[lang(discriminant_kind)]
+            trait DiscriminantKind {
+                type Discriminant;
+            }
+
+            enum A { Variant }
+
+            #[repr(u16)]
+            enum B { Variant }
+
+            #[repr(C)]
+            #[repr(i64)]
+            enum C { Variant }
+
+            struct D { }
+        }
+
+        goal {
+            <A as DiscriminantKind>::Discriminant = isize
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <B as DiscriminantKind>::Discriminant = u16
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <B as DiscriminantKind>::Discriminant = u8
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            <C as DiscriminantKind>::Discriminant = i64
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <D as DiscriminantKind>::Discriminant = u8
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <[u32] as DiscriminantKind>::Discriminant = u8
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            exists<T> {
+                Normalize(<B as DiscriminantKind>::Discriminant -> T)
+            }
+        } yields {
+            "Unique; substitution [?0 := Uint(U16)]"
+        }
+
+        goal {
+            forall<T> {
+                <T as DiscriminantKind>::Discriminant = u8
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<T> {
+                <T as DiscriminantKind>::Discriminant = u8
+            }
+        } yields {
+            "Ambiguous; no inference guidance"
+        }
+    }
+}
+
+#[test]
+fn discriminant_kind_dyn_opaque_generator() {
+    test! {
+        program {
+            #[lang(discriminant_kind)]
+            trait DiscriminantKind {
+                type Discriminant;
+            }
+
+            struct Ty { }
+            trait Foo { }
+            impl Foo for Ty { }
+
+            opaque type T: Foo = Ty;
+
+            generator empty_gen<>[resume = (), yield = ()] {
+                upvars []
+                witnesses []
+            }
+        }
+
+        goal {
+            forall<'a> {
+                <dyn Foo + 'a as DiscriminantKind>::Discriminant = u8
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <T as DiscriminantKind>::Discriminant = u8
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            <empty_gen as DiscriminantKind>::Discriminant = u8
+        } yields {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn discriminant_kind_placeholder() {
+    test! {
+        program {
+            #[lang(discriminant_kind)]
+            trait DiscriminantKind {
+                type Discriminant;
+            }
+        }
+
+        goal {
+            forall<T> {
+                exists<U> {
+                    <T as DiscriminantKind>::Discriminant = U
+                }
+            }
+        } yields {
+            "Unique; substitution [?0 := (DiscriminantKind::Discriminant)<!1_0>]"
+        }
+    }
+}
+
+#[test]
+fn no_discriminant_kind_impls() {
+    lowering_error! {
+        program {
+            #[lang(discriminant_kind)]
+            trait DiscriminantKind {
+                type Discriminant;
+            }
+
+            struct A { }
+
+            impl DiscriminantKind for A {
+                type Discriminant = u8;
+            }
+        } error_msg {
+            "trait impl for `DiscriminantKind` does not meet well-formedness requirements"
+        }
+    }
+}
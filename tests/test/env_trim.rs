@@ -0,0 +1,121 @@
+//! Tests for dropping irrelevant environment clauses, with
+//! `GoalExt::into_trimmed_peeled_goal` and in the solvers themselves.
+
+use super::*;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Box<T> { }
+    trait Base { }
+    trait Derived where Self: Base { }
+    trait Bar { }
+    trait Unrelated { }
+    trait Convert { type Output: Base; }
+    impl<T> Base for Box<T> where T: Bar { }
+";
+
+const UNIQUE: &str = "Unique; substitution [], lifetime constraints []";
+
+fn trimmed_goal(db: &ChalkDatabase, goal_text: &str) -> UCanonical<InEnvironment<Goal<ChalkIr>>> {
+    db.with_program(|program| {
+        lower_goal(&chalk_parse::parse_goal(goal_text).unwrap(), program)
+            .unwrap()
+            .into_trimmed_peeled_goal(program)
+    })
+}
+
+fn clause_count(goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>) -> usize {
+    goal.canonical.value.environment.clauses.len(&ChalkIr)
+}
+
+fn display_solution(db: &ChalkDatabase, goal: &UCanonical<InEnvironment<Goal<ChalkIr>>>) -> String {
+    db.with_program(|_| format!("{}", db.solve(goal).unwrap().display(&ChalkIr)))
+}
+
+#[test]
+fn irrelevant_clauses_share_canonical_form() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let with_extra = trimmed_goal(&db, "forall<T> { if (T: Bar; T: Unrelated) { T: Bar } }");
+    let without = trimmed_goal(&db, "forall<T> { if (T: Bar) { T: Bar } }");
+    assert_eq!(clause_count(&with_extra), 1);
+    assert_eq!(with_extra, without);
+    assert_eq!(display_solution(&db, &with_extra), UNIQUE);
+}
+
+#[test]
+fn supertrait_clauses_are_kept() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goal = trimmed_goal(
+        &db,
+        "forall<T> { if (T: Derived; T: Unrelated) { T: Base } }",
+    );
+    assert_eq!(clause_count(&goal), 1);
+    assert_eq!(display_solution(&db, &goal), UNIQUE);
+}
+
+#[test]
+fn associated_type_bound_clauses_are_kept() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goal = trimmed_goal(
+        &db,
+        "forall<T> { if (T: Convert; T: Unrelated) { <T as Convert>::Output: Base } }",
+    );
+    assert_eq!(clause_count(&goal), 1);
+    assert_eq!(display_solution(&db, &goal), UNIQUE);
+}
+
+#[test]
+fn impl_where_clauses_are_kept() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goal = trimmed_goal(
+        &db,
+        "forall<T> { if (T: Bar; T: Unrelated) { Box<T>: Base } }",
+    );
+    assert_eq!(clause_count(&goal), 1);
+    assert_eq!(display_solution(&db, &goal), UNIQUE);
+}
+
+#[test]
+fn clauses_about_types_are_kept() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goal = trimmed_goal(
+        &db,
+        "forall<T> { if (FromEnv(Box<T>); T: Unrelated) { T: Bar } }",
+    );
+    assert_eq!(clause_count(&goal), 1);
+}
+
+#[test]
+fn coherence_goals_are_left_alone() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let goal = trimmed_goal(&db, "forall<T> { if (T: Unrelated) { IsLocal(Foo) } }");
+    assert_eq!(clause_count(&goal), 1);
+}
+
+#[test]
+fn solvers_trim_subgoal_environments() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let db = ChalkDatabase::with(PROGRAM, solver_choice);
+        let with_extra = peeled_goal(
+            &db,
+            "forall<T> { if (T: Bar; T: Unrelated) { Box<T>: Base } }",
+        );
+        assert_eq!(clause_count(&with_extra), 2);
+        assert_eq!(display_solution(&db, &with_extra), UNIQUE);
+        let stats = db.solver_stats();
+
+        // The `T: Bar` subgoal was solved without `T: Unrelated` in its
+        // environment, so this goal finds it in the cache; at most the
+        // root goal itself needs a new table.
+        let without = peeled_goal(&db, "forall<T> { if (T: Bar) { Box<T>: Base } }");
+        assert_eq!(display_solution(&db, &without), UNIQUE);
+        let stats_again = db.solver_stats();
+        assert!(stats_again.cache_hits > stats.cache_hits);
+        assert!(stats_again.tables <= stats.tables + 1);
+    }
+}
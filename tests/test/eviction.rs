@@ -0,0 +1,179 @@
+//! Tests for keeping the tables of an `SLGSolver` or a `ParallelSLGSolver`
+//! within a `TableBudget`.
+
+use super::*;
+use chalk_engine::solve::{ParallelSLGSolver, SLGSolver, TableBudget};
+use chalk_integration::program::Program;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+use chalk_solve::Solver;
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    struct Box<T> { }
+    trait Clone { }
+    trait Debug { }
+    impl Clone for Foo { }
+    impl Debug for Foo { }
+    impl Debug for Bar { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+    impl<T> Debug for Vec<T> where T: Debug { }
+    impl<T> Clone for Box<T> where T: Clone { }
+    impl<T> Debug for Box<T> where T: Debug { }
+";
+
+const GOALS: &[&str] = &[
+    "Vec<Foo>: Clone",
+    "Box<Vec<Foo>>: Clone",
+    "Box<Vec<Bar>>: Clone",
+    "Vec<Box<Vec<Bar>>>: Debug",
+    "exists<T> { Vec<T>: Debug }",
+    "forall<T> { if (T: Clone) { Vec<Box<T>>: Clone } }",
+    "Vec<Foo>: Clone",
+    "Box<Vec<Bar>>: Debug",
+];
+
+fn peeled_goals(db: &ChalkDatabase) -> Vec<UCanonical<InEnvironment<Goal<ChalkIr>>>> {
+    GOALS
+        .iter()
+        .map(|goal_text| peeled_goal(db, goal_text))
+        .collect()
+}
+
+fn solutions(
+    solver: &mut SLGSolver<ChalkIr>,
+    program: &Program,
+    goals: &[UCanonical<InEnvironment<Goal<ChalkIr>>>],
+    check: impl Fn(&SLGSolver<ChalkIr>),
+) -> Vec<Option<Solution<ChalkIr>>> {
+    goals
+        .iter()
+        .map(|goal| {
+            let solution = solver.solve(program, goal);
+            check(solver);
+            solution
+        })
+        .collect()
+}
+
+#[test]
+fn table_count_budget() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals = peeled_goals(&db);
+    let expected = solutions(&mut SLGSolver::new(10, None), &program, &goals, |_| {});
+
+    let budget = TableBudget {
+        max_tables: Some(3),
+        max_bytes: None,
+    };
+    let mut solver = SLGSolver::with_budget(10, None, budget);
+    let actual = solutions(&mut solver, &program, &goals, |solver| {
+        assert!(solver.table_count() <= 3, "{} tables", solver.table_count());
+    });
+    assert_eq!(actual, expected);
+
+    let stats = solver.stats();
+    assert!(stats.evictions > 0);
+    assert_eq!(stats.live_tables, solver.table_count());
+    assert_eq!(stats.table_bytes, solver.approximate_size());
+}
+
+#[test]
+fn memory_budget() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals = peeled_goals(&db);
+    let mut unbounded = SLGSolver::new(10, None);
+    let expected = solutions(&mut unbounded, &program, &goals, |_| {});
+    let max_bytes = unbounded.approximate_size() / 2;
+    assert!(max_bytes > 0);
+
+    let budget = TableBudget {
+        max_tables: None,
+        max_bytes: Some(max_bytes),
+    };
+    let mut solver = SLGSolver::with_budget(10, None, budget);
+    let actual = solutions(&mut solver, &program, &goals, |solver| {
+        assert!(solver.approximate_size() <= max_bytes);
+    });
+    assert_eq!(actual, expected);
+    assert!(solver.stats().evictions > 0);
+}
+
+#[test]
+fn parallel_solver_budget() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals = peeled_goals(&db);
+    let expected = solutions(&mut SLGSolver::new(10, None), &program, &goals, |_| {});
+
+    let budget = TableBudget {
+        max_tables: Some(3),
+        max_bytes: None,
+    };
+    let mut solver = ParallelSLGSolver::with_budget(10, None, budget);
+    let actual: Vec<_> = goals
+        .iter()
+        .map(|goal| {
+            let solution = solver.solve(&*program, goal);
+            assert!(
+                solver.shared_tables() <= 3,
+                "{} tables",
+                solver.shared_tables()
+            );
+            solution
+        })
+        .collect();
+    assert_eq!(actual, expected);
+
+    let stats = solver.stats();
+    assert!(stats.evictions > 0);
+    assert_eq!(stats.live_tables, solver.shared_tables());
+    assert_eq!(stats.table_bytes, solver.approximate_size());
+}
+
+#[test]
+fn least_recently_used_tables_are_evicted() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals = peeled_goals(&db);
+    let mut solver = SLGSolver::new(10, None);
+    solver.solve(&*program, &goals[0]);
+    solver.solve(&*program, &goals[1]);
+    let tables = solver.table_count();
+
+    // Using the tables of the first goal again keeps them around.
+    solver.solve(&*program, &goals[0]);
+    solver.set_budget(TableBudget {
+        max_tables: Some(tables - 1),
+        max_bytes: None,
+    });
+    solver.gc();
+    assert!(solver.table_count() < tables);
+
+    let stats_before = solver.stats();
+    solver.solve(&*program, &goals[0]);
+    let stats_after = solver.stats();
+    assert_eq!(stats_after.tables, stats_before.tables);
+
+    // The tables of the second goal are gone.
+    solver.solve(&*program, &goals[1]);
+    assert!(solver.stats().tables > stats_after.tables);
+}
+
+#[test]
+fn clear() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals = peeled_goals(&db);
+    let mut solver = SLGSolver::new(10, None);
+    let expected = solutions(&mut solver, &program, &goals, |_| {});
+    assert!(solver.table_count() > 0);
+
+    solver.clear();
+    assert_eq!(solver.table_count(), 0);
+    assert_eq!(solver.approximate_size(), 0);
+    assert_eq!(solutions(&mut solver, &program, &goals, |_| {}), expected);
+}
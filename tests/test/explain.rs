@@ -0,0 +1,100 @@
+//! Tests for proof trees produced by `Solver::solve_explained`.
+
+use super::*;
+use chalk_solve::solve::{ProofOutcome, ProofTree};
+
+fn explain(program_text: &str, goal_text: &str, f: impl Fn(&ProofTree<ChalkIr>)) {
+    with_each_solver(program_text, goal_text, |db, goal| {
+        let (solution, proof_tree) = db.solve_explained(goal);
+        println!("{}", proof_tree.display(&ChalkIr));
+        assert_eq!(solution, db.solve(goal));
+        assert_eq!(&proof_tree.goal, goal);
+        f(&proof_tree);
+    });
+}
+
+fn goal_string(tree: &ProofTree<ChalkIr>) -> String {
+    format!("{:?}", tree.goal.canonical.value.goal)
+}
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    trait Clone { }
+    trait Debug { }
+    impl Clone for Foo { }
+    impl<T> Debug for T where T: Clone { }
+";
+
+#[test]
+fn explain_proven() {
+    explain(PROGRAM, "Foo: Debug", |tree| {
+        assert_eq!(tree.outcome, ProofOutcome::Proven);
+        assert!(tree.failed_leaves().is_empty());
+
+        let proven: Vec<_> = tree
+            .candidates
+            .iter()
+            .filter(|c| c.outcome == ProofOutcome::Proven)
+            .collect();
+        assert_eq!(proven.len(), 1);
+        assert!(proven[0].clause.is_some());
+    });
+}
+
+#[test]
+fn explain_failure_points_at_missing_impl() {
+    explain(PROGRAM, "Bar: Debug", |tree| {
+        assert_eq!(tree.outcome, ProofOutcome::Failed);
+        assert!(tree
+            .candidates
+            .iter()
+            .all(|c| c.outcome == ProofOutcome::Failed));
+
+        let leaves: Vec<_> = tree.failed_leaves().into_iter().map(goal_string).collect();
+        assert!(
+            leaves.contains(&"FromEnv(Bar: Clone)".to_string()),
+            "unexpected failed leaves: {:?}",
+            leaves
+        );
+    });
+}
+
+#[test]
+fn explain_ambiguous() {
+    explain(
+        "
+        struct Foo { }
+        struct Bar { }
+        trait Debug { }
+        impl Debug for Foo { }
+        impl Debug for Bar { }
+        ",
+        "exists<T> { T: Debug }",
+        |tree| {
+            assert_eq!(tree.outcome, ProofOutcome::Ambiguous);
+            let proven = tree
+                .candidates
+                .iter()
+                .filter(|c| c.outcome == ProofOutcome::Proven)
+                .count();
+            assert_eq!(proven, 2);
+        },
+    );
+}
+
+#[test]
+fn explain_floundered() {
+    explain(
+        "
+        #[non_enumerable]
+        trait Foo { }
+        struct Bar { }
+        impl Foo for Bar { }
+        ",
+        "exists<T> { T: Foo }",
+        |tree| {
+            assert_eq!(tree.outcome, ProofOutcome::Floundered);
+        },
+    );
+}
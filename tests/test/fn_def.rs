@@ -225,3 +225,139 @@ fn fn_def_implied_bounds_from_env() {
         }
     }
 }
+
+#[test]
+fn fn_ptr_trait() {
+    test! {
+        program {
+            #[lang(fn_ptr_trait)]
+            trait FnPtr {
+                type Args;
+                type Output;
+            }
+
+            struct S { }
+            fn foo();
+        }
+
+        goal {
+            fn(u8) -> S: FnPtr
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            for<'a> fn(&'a u8): FnPtr
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            foo: FnPtr
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            S: FnPtr
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<T> { T: FnPtr }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<T> { T: FnPtr }
+        } yields_first[SolverChoice::slg(3, None)] {
+            "Floundered"
+        }
+    }
+}
+
+#[test]
+fn fn_ptr_signature() {
+    test! {
+        program {
+            #[lang(fn_ptr_trait)]
+            trait FnPtr {
+                type Args;
+                type Output;
+            }
+
+            struct S { }
+        }
+
+        goal {
+            Normalize(<fn(u8, S) -> S as FnPtr>::Args -> (u8, S))
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            Normalize(<fn(u8, S) -> S as FnPtr>::Output -> S)
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            Normalize(<fn(u8) as FnPtr>::Output -> ())
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            exists<T> {
+                Normalize(<fn(u8, S) -> S as FnPtr>::Args -> T)
+            }
+        } yields {
+            "Unique; substitution [?0 := 2<Uint(U8), S>]"
+        }
+
+        goal {
+            forall<'a> {
+                Normalize(<for<'b> fn(&'b u8) -> &'b u8 as FnPtr>::Output -> &'a u8)
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            exists<T> {
+                Normalize(<unsafe fn(S) -> u8 as FnPtr>::Output -> T)
+            }
+        } yields {
+            "Unique; substitution [?0 := Uint(U8)]"
+        }
+
+        goal {
+            exists<T> {
+                Normalize(<S as FnPtr>::Output -> T)
+            }
+        } yields {
+            "No possible solution"
+        }
+    }
+}
+
+#[test]
+fn no_fn_ptr_trait_impls() {
+    lowering_error! {
+        program {
+            #[lang(fn_ptr_trait)]
+            trait FnPtr {
+                type Args;
+                type Output;
+            }
+
+            struct A { }
+
+            impl FnPtr for A { }
+        } error_msg {
+            "trait impl for `FnPtr` does not meet well-formedness requirements"
+        }
+    }
+}
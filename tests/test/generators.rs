@@ -87,3 +87,228 @@ fn generator_test() {
         }
     }
 }
+
+#[test]
+fn generator_trait() {
+    test! {
+        program {
+            #[lang(generator)]
+            trait Generator<R> {
+                type Yield;
+                type Return;
+            }
+
+            struct StructOne {}
+            struct StructTwo {}
+
+            generator gen<T>[resume = StructOne, yield = T] -> StructTwo {
+                upvars []
+                witnesses []
+            }
+
+            generator unit_gen<>[resume = (), yield = u8] {
+                upvars []
+                witnesses []
+            }
+        }
+
+        goal {
+            forall<T> {
+                gen<T>: Generator<StructOne>
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                gen<T>: Generator<StructTwo>
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<R> {
+                unit_gen: Generator<R>
+            }
+        } yields {
+            "Unique; substitution [?0 := 0]"
+        }
+
+        goal {
+            StructOne: Generator<()>
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<G> {
+                G: Generator<()>
+            }
+        } yields {
+            "Ambiguous; no inference guidance"
+        }
+    }
+}
+
+#[test]
+fn generator_trait_normalize() {
+    test! {
+        program {
+            #[lang(generator)]
+            trait Generator<R> {
+                type Yield;
+                type Return;
+            }
+
+            struct StructOne {}
+            struct StructTwo {}
+
+            generator gen<T>[resume = StructOne, yield = T] -> StructTwo {
+                upvars []
+                witnesses []
+            }
+        }
+
+        goal {
+            forall<T> {
+                Normalize(<gen<T> as Generator<StructOne>>::Yield -> T)
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                Normalize(<gen<T> as Generator<StructOne>>::Return -> StructTwo)
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            exists<U> {
+                Normalize(<gen<u32> as Generator<StructOne>>::Yield -> U)
+            }
+        } yields {
+            "Unique; substitution [?0 := Uint(U32)]"
+        }
+
+        goal {
+            forall<T> {
+                <gen<T> as Generator<StructOne>>::Yield = T
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                <gen<T> as Generator<StructOne>>::Return = StructOne
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<T> {
+                gen<T>: Generator<StructOne, Return = StructTwo>
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                gen<T>: Generator<StructOne, Yield = StructTwo>
+            }
+        } yields {
+            "No possible solution"
+        }
+    }
+}
+
+#[test]
+fn generator_trait_normalize_through_projection() {
+    test! {
+        program {
+            #[lang(generator)]
+            trait Generator<R> {
+                type Yield;
+                type Return;
+            }
+
+            trait Producer {
+                type Gen;
+            }
+
+            struct Foo {}
+            struct Bar {}
+
+            generator foo_gen<>[resume = (), yield = Foo] -> Bar {
+                upvars []
+                witnesses []
+            }
+
+            impl Producer for Foo {
+                type Gen = foo_gen;
+            }
+        }
+
+        // `Normalize` does not normalize the self type itself, so this flounders
+        goal {
+            exists<U> {
+                Normalize(<<Foo as Producer>::Gen as Generator<()>>::Yield -> U)
+            }
+        } yields {
+            "Ambiguous; no inference guidance"
+        }
+
+        goal {
+            <<Foo as Producer>::Gen as Generator<()>>::Yield = Foo
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <<Foo as Producer>::Gen as Generator<()>>::Return = Bar
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <<Foo as Producer>::Gen as Generator<()>>::Return = Foo
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            <Foo as Producer>::Gen: Generator<(), Return = Bar>
+        } yields {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn no_generator_impls() {
+    lowering_error! {
+        program {
+            #[lang(generator)]
+            trait Generator<R> {
+                type Yield;
+                type Return;
+            }
+
+            struct Foo {}
+
+            impl Generator<()> for Foo {
+                type Yield = ();
+                type Return = ();
+            }
+        } error_msg {
+            "trait impl for `Generator` does not meet well-formedness requirements"
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Tests for the index of impls by simplified self type used by
+//! `Program::impls_for_trait`.
+
+use super::*;
+use chalk_integration::interner::{ChalkFnAbi, Identifier};
+use chalk_integration::program::Program;
+use chalk_ir::cast::Cast;
+use chalk_ir::{
+    FnPointer, FnSig, FnSubst, GenericArg, ImplId, PlaceholderIndex, Safety, Scalar, Substitution,
+    TyKind, UintTy, UniverseIndex,
+};
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    trait Show { }
+    impl Show for Foo { }
+    impl<T> Show for Vec<T> { }
+    impl<T> Show for T where T: Copy { }
+    impl Show for u32 { }
+    impl Show for (Foo, Bar) { }
+    trait Copy { }
+";
+
+/// The candidates for `<self_ty as Show>`, as the self types of the impls.
+fn candidates(program: &Program, self_ty: TyKind<ChalkIr>) -> Vec<String> {
+    let trait_id = program.trait_ids[&Identifier::from("Show")];
+    let parameters: [GenericArg<ChalkIr>; 1] = [self_ty.intern(&ChalkIr).cast(&ChalkIr)];
+    program
+        .impl_index
+        .candidates(&ChalkIr, trait_id, &parameters)
+        .into_iter()
+        .map(|impl_id: ImplId<ChalkIr>| {
+            let trait_ref = &program.impl_data[&impl_id].binders.skip_binders().trait_ref;
+            format!("{:?}", trait_ref.self_type_parameter(&ChalkIr))
+        })
+        .collect()
+}
+
+fn adt(program: &Program, name: &str) -> TyKind<ChalkIr> {
+    let id = program.adt_ids[&Identifier::from(name)];
+    TyKind::Adt(id, Substitution::empty(&ChalkIr))
+}
+
+#[test]
+fn candidates_are_filtered_by_head() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    db.with_program(|program| {
+        assert_eq!(candidates(program, adt(program, "Foo")), ["Foo", "^0.0"]);
+        assert_eq!(candidates(program, adt(program, "Bar")), ["^0.0"]);
+        assert_eq!(
+            candidates(program, TyKind::Scalar(Scalar::Uint(UintTy::U32))),
+            ["^0.0", "Uint(U32)"]
+        );
+        assert_eq!(
+            candidates(program, TyKind::Scalar(Scalar::Uint(UintTy::U8))),
+            ["^0.0"]
+        );
+    });
+}
+
+#[test]
+fn variable_self_type_gets_all_impls() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    db.with_program(|program| {
+        assert_eq!(
+            candidates(program, TyKind::Error).len(),
+            program.impl_data.len()
+        );
+    });
+}
+
+#[test]
+fn placeholder_self_type_gets_blanket_impls() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    db.with_program(|program| {
+        let placeholder = PlaceholderIndex {
+            ui: UniverseIndex::ROOT.next(),
+            idx: 0,
+        };
+        assert_eq!(
+            candidates(program, TyKind::Placeholder(placeholder)),
+            ["^0.0"]
+        );
+    });
+}
+
+#[test]
+fn function_pointer_without_return_type_gets_all_impls() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    db.with_program(|program| {
+        let fn_ptr = FnPointer {
+            num_binders: 0,
+            sig: FnSig {
+                abi: ChalkFnAbi::Rust,
+                safety: Safety::Safe,
+                variadic: false,
+            },
+            substitution: FnSubst(Substitution::empty(&ChalkIr)),
+        };
+        assert_eq!(
+            candidates(program, TyKind::Function(fn_ptr)).len(),
+            program.impl_data.len()
+        );
+    });
+}
+
+#[test]
+fn solving_with_index() {
+    test! {
+        program {
+            struct Foo { }
+            struct Bar { }
+            struct Vec<T> { }
+            trait Show<U> { }
+            trait Copy { }
+            impl Show<Foo> for Foo { }
+            impl<T> Show<Foo> for Vec<T> where T: Show<Foo> { }
+            impl<T> Show<Bar> for T where T: Copy { }
+            impl Copy for u32 { }
+            impl Show<Foo> for (Foo, Bar) { }
+        }
+
+        goal {
+            Vec<Foo>: Show<Foo>
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            Vec<Bar>: Show<Foo>
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            u32: Show<Bar>
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            Foo: Show<Bar>
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            (Foo, Bar): Show<Foo>
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            (Bar, Foo): Show<Foo>
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<T> { T: Show<Foo> }
+        } yields {
+            "Ambiguous; no inference guidance"
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Tests that the solver's cache stays correct across program edits.
+
+use super::*;
+use std::sync::Arc;
+
+fn assert_solution(db: &ChalkDatabase, goal_text: &str, expected: &str) {
+    assert_result(solve(db, goal_text), expected, db.interner());
+}
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    trait Clone { }
+    trait Debug { }
+    impl Clone for Foo { }
+    impl<T> Debug for T where T: Clone { }
+";
+
+#[test]
+fn added_impl_invalidates_dependent_results() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let mut db = ChalkDatabase::with(PROGRAM, solver_choice);
+        assert_solution(&db, "Foo: Debug", "Unique");
+        assert_solution(&db, "Bar: Debug", "No possible solution");
+        let solver = db.solver();
+
+        db.set_program_text(Arc::new(format!("{} impl Clone for Bar {{ }}", PROGRAM)));
+        assert!(db.solver() == solver, "solver was not reused");
+        assert_solution(&db, "Foo: Debug", "Unique");
+        assert_solution(&db, "Bar: Debug", "Unique");
+    }
+}
+
+#[test]
+fn removed_impl_invalidates_dependent_results() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let mut db = ChalkDatabase::with(
+            &format!("{} impl Clone for Bar {{ }}", PROGRAM),
+            solver_choice,
+        );
+        assert_solution(&db, "Bar: Debug", "Unique");
+
+        db.set_program_text(Arc::new(PROGRAM.to_string()));
+        assert_solution(&db, "Foo: Debug", "Unique");
+        assert_solution(&db, "Bar: Debug", "No possible solution");
+    }
+}
+
+#[test]
+fn changed_where_clause_invalidates_dependent_results() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let mut db = ChalkDatabase::with(PROGRAM, solver_choice);
+        assert_solution(&db, "Foo: Debug", "Unique");
+
+        db.set_program_text(Arc::new(
+            PROGRAM
+                .replace("where T: Clone", "where T: Copy")
+                .replace("trait Debug { }", "trait Debug { } trait Copy { }"),
+        ));
+        assert_solution(&db, "Foo: Debug", "No possible solution");
+    }
+}
+
+#[test]
+fn added_where_clause_keeps_environment_clauses() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let program = "
+            struct Box<T> { }
+            trait Base { }
+            trait Bar { }
+            impl<T> Base for Box<T> { }
+        ";
+        let goal = "forall<T> { if (T: Bar) { Box<T>: Base } }";
+        let mut db = ChalkDatabase::with(program, solver_choice);
+        assert_solution(&db, goal, "Unique");
+
+        // `T: Bar` used to be irrelevant to the goal, but is needed now.
+        db.set_program_text(Arc::new(program.replace(
+            "impl<T> Base for Box<T> { }",
+            "impl<T> Base for Box<T> where T: Bar { }",
+        )));
+        assert_solution(&db, goal, "Unique");
+        assert_solution(&db, "forall<T> { Box<T>: Base }", "No possible solution");
+    }
+}
@@ -0,0 +1,133 @@
+//! Tests for solving with `NormalizationMode::Lazy`, where alias types stay
+//! rigid during unification.
+
+use super::*;
+use chalk_solve::infer::NormalizationMode;
+
+fn slg_lazy() -> SolverChoice {
+    SolverChoice::slg_default().with_normalization_mode(NormalizationMode::Lazy)
+}
+
+fn recursive_lazy() -> SolverChoice {
+    SolverChoice::recursive_default().with_normalization_mode(NormalizationMode::Lazy)
+}
+
+#[test]
+fn projections_are_not_related_by_their_parameters() {
+    test! {
+        program {
+            struct Vec<T> { }
+            trait Iterator { type Item; }
+            impl<T> Iterator for Vec<T> { type Item = T; }
+        }
+
+        // `U = Vec<<T as Iterator>::Item>` works as well as `U = T`, so
+        // relating the parameters of the projections is not enough.
+        goal {
+            forall<T> {
+                if (T: Iterator) {
+                    exists<U> {
+                        <T as Iterator>::Item = <U as Iterator>::Item
+                    }
+                }
+            }
+        } yields[SolverChoice::slg_default()] {
+            "Ambiguous"
+        } yields[slg_lazy()] {
+            "Ambiguous"
+        } yields[recursive_lazy()] {
+            "Ambiguous"
+        }
+
+        // Projections that are already the same type are equal, however.
+        goal {
+            forall<T> {
+                if (T: Iterator) {
+                    exists<U> {
+                        U = T,
+                        <T as Iterator>::Item = <U as Iterator>::Item
+                    }
+                }
+            }
+        } yields[slg_lazy()] {
+            "Unique; substitution [?0 := !1_0]"
+        } yields[recursive_lazy()] {
+            "Unique; substitution [?0 := !1_0]"
+        }
+    }
+}
+
+#[test]
+fn variables_are_bound_to_unnormalized_projections() {
+    test! {
+        program {
+            struct Vec<T> { }
+            trait Iterator { type Item; }
+        }
+
+        goal {
+            forall<T> {
+                if (T: Iterator; <T as Iterator>::Item: Iterator) {
+                    exists<U> {
+                        U = Vec<<<T as Iterator>::Item as Iterator>::Item>
+                    }
+                }
+            }
+        } yields[SolverChoice::slg_default()] {
+            "Unique; substitution [?0 := Vec<(Iterator::Item)<(Iterator::Item)<!1_0>>>]"
+        } yields[slg_lazy()] {
+            "Unique; substitution [?0 := Vec<<<!1_0 as Iterator>::Item as Iterator>::Item>]"
+        } yields[recursive_lazy()] {
+            "Unique; substitution [?0 := Vec<<<!1_0 as Iterator>::Item as Iterator>::Item>]"
+        }
+    }
+}
+
+#[test]
+fn projections_are_normalized_when_needed() {
+    test! {
+        program {
+            struct Foo { }
+            struct Bar { }
+            struct Vec<T> { }
+            trait Iterator { type Item; }
+            impl Iterator for Vec<Foo> { type Item = Foo; }
+        }
+
+        goal {
+            <Vec<Foo> as Iterator>::Item = Foo
+        } yields[slg_lazy()] {
+            "Unique"
+        } yields[recursive_lazy()] {
+            "Unique"
+        }
+
+        goal {
+            <Vec<Foo> as Iterator>::Item = Bar
+        } yields[slg_lazy()] {
+            "No possible solution"
+        } yields[recursive_lazy()] {
+            "No possible solution"
+        }
+
+        goal {
+            exists<U> {
+                U = Vec<<Vec<Foo> as Iterator>::Item>
+            }
+        } yields[slg_lazy()] {
+            "Unique; substitution [?0 := Vec<<Vec<Foo> as Iterator>::Item>]"
+        } yields[recursive_lazy()] {
+            "Unique; substitution [?0 := Vec<<Vec<Foo> as Iterator>::Item>]"
+        }
+
+        goal {
+            exists<U> {
+                U = Vec<<Vec<Foo> as Iterator>::Item>, U = Vec<Bar>
+            }
+        } yields[slg_lazy()] {
+            "No possible solution"
+        } yields[recursive_lazy()] {
+            "No possible solution"
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Tests for various concrete lifetimes
 
 use super::*;
+use chalk_solve::SubstitutionResult;
 
 #[test]
 fn static_lowering() {
@@ -181,3 +182,87 @@ fn empty_impls() {
         }
     }
 }
+
+#[test]
+fn constraint_origins() {
+    test! {
+        program {
+            trait Foo<'a> {}
+            struct Bar {}
+            struct Ref<'a, T> where T: 'a {}
+            impl<'a> Foo<'a> for Bar where 'a: 'static {}
+        }
+
+        goal {
+            exists<'a> { Bar: Foo<'a> }
+        } yields {
+            "Unique; for<?U0> { substitution [?0 := '^0.0], lifetime constraints [InEnvironment { environment: Env([]), goal: '^0.0: 'static }] }; constraint origins [where clause on ImplId(#3)]"
+        }
+
+        goal {
+            forall<'a> { if ('a: 'static) { Bar: Foo<'a> } }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([for<> '!1_0: 'static]), goal: '!1_0: 'static }]; constraint origins [where clause on ImplId(#3)]"
+        }
+
+        goal {
+            forall<'a, T> { WellFormed(Ref<'a, T>) }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: !1_1: '!1_0 }]; constraint origins [well-formedness of Ref<'!1_0, !1_1>]"
+        }
+
+        goal {
+            forall<'a, 'b> { 'a: 'b }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_1 }]; constraint origins [builtin]"
+        }
+    }
+}
+
+/// An outlives goal that the environment implies can be proven both by the
+/// builtin rule and by the implied bound; the answers say which is which.
+#[test]
+fn implied_bound_constraint_origins() {
+    let db = ChalkDatabase::with(
+        "
+        struct Ref<'a, T> where T: 'a {}
+        trait Foo<'a> where Self: 'a {}
+        ",
+        SolverChoice::slg_default(),
+    );
+    let origins = |goal_text: &str| -> Vec<String> {
+        let goal = peeled_goal(&db, goal_text);
+        db.with_program(|_| {
+            db.with_answers(&goal, |answers| {
+                answers
+                    .map(|answer| match answer {
+                        SubstitutionResult::Definite(answer) => {
+                            let origins: Vec<_> = answer
+                                .value
+                                .constraints
+                                .iter(&ChalkIr)
+                                .map(|constraint| constraint.goal.origin())
+                                .collect();
+                            format!("{:?}", origins)
+                        }
+                        _ => panic!("unexpected answer"),
+                    })
+                    .collect()
+            })
+        })
+    };
+
+    let mut from_type = origins("forall<'a, T> { if (FromEnv(Ref<'a, T>)) { T: 'a } }");
+    from_type.sort();
+    assert_eq!(
+        from_type,
+        ["[builtin]", "[implied by FromEnv(Ref<'!1_0, !1_1>)]"]
+    );
+
+    let mut from_trait = origins("forall<'a, T> { if (FromEnv(T: Foo<'a>)) { T: 'a } }");
+    from_trait.sort();
+    assert_eq!(
+        from_trait,
+        ["[builtin]", "[implied by FromEnv(!1_1: Foo<'!1_0>)]"]
+    );
+}
@@ -0,0 +1,135 @@
+//! Tests for goals that need items the database fails to look up (see the
+//! `try_*` methods of `RustIrDatabase`).
+
+use super::*;
+use chalk_integration::interner::Identifier;
+use chalk_integration::program::Program;
+use chalk_solve::dependencies::ItemId;
+use chalk_solve::AmbiguityCause;
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    struct Wrapper { foo: Foo }
+    trait Broken { }
+    trait Uses { }
+    trait Fine { }
+    trait Base { }
+    trait Derived where Self: Base { }
+    #[auto] trait Send { }
+    impl Broken for Foo { }
+    impl<T> Uses for T where T: Broken { }
+    impl Fine for Bar { }
+";
+
+fn trait_item(program: &Program, name: &str) -> ItemId<ChalkIr> {
+    ItemId::Trait(program.trait_ids[&Identifier::from(name)])
+}
+
+/// The (only) impl of the trait named `trait_name`.
+fn impl_item(program: &Program, trait_name: &str) -> ItemId<ChalkIr> {
+    let trait_id = program.trait_ids[&Identifier::from(trait_name)];
+    let (&impl_id, _) = program
+        .impl_data
+        .iter()
+        .find(|(_, impl_datum)| impl_datum.trait_id() == trait_id)
+        .unwrap();
+    ItemId::Impl(impl_id)
+}
+
+fn adt_item(program: &Program, name: &str) -> ItemId<ChalkIr> {
+    ItemId::Adt(program.adt_ids[&Identifier::from(name)])
+}
+
+/// Solves `goal_text` with every solver, after breaking the item returned by
+/// `item`, and returns their solutions.
+fn solve_with_broken_item(
+    item: impl Fn(&Program) -> ItemId<ChalkIr>,
+    goal_text: &str,
+) -> Vec<(ItemId<ChalkIr>, Option<Solution<ChalkIr>>)> {
+    with_each_solver(PROGRAM, goal_text, |db, goal| {
+        let item = item(&db.program_ir().unwrap());
+        db.break_item(item);
+        (item, db.solve(goal))
+    })
+}
+
+fn assert_lookup_failed(item: ItemId<ChalkIr>, solution: Option<Solution<ChalkIr>>) {
+    match solution {
+        Some(Solution::Ambig(guidance)) => {
+            assert_eq!(guidance.cause(), &AmbiguityCause::LookupFailed(item));
+            let display = Solution::Ambig(guidance.clone())
+                .display(&ChalkIr)
+                .to_string();
+            assert!(display.contains("could not look up"), "{}", display);
+        }
+        solution => panic!("expected an ambiguous solution, got {:?}", solution),
+    }
+}
+
+#[test]
+fn broken_trait() {
+    for (item, solution) in
+        solve_with_broken_item(|program| trait_item(program, "Broken"), "Foo: Broken")
+    {
+        assert_lookup_failed(item, solution);
+    }
+}
+
+#[test]
+fn broken_trait_of_subgoal() {
+    for (item, solution) in
+        solve_with_broken_item(|program| trait_item(program, "Broken"), "Foo: Uses")
+    {
+        assert_lookup_failed(item, solution);
+    }
+}
+
+#[test]
+fn broken_impl() {
+    for (item, solution) in
+        solve_with_broken_item(|program| impl_item(program, "Broken"), "Foo: Broken")
+    {
+        assert_lookup_failed(item, solution);
+    }
+}
+
+#[test]
+fn broken_trait_in_environment() {
+    // The environment implies `T: Base` through `Derived`, which can't be
+    // looked up.
+    for (item, solution) in solve_with_broken_item(
+        |program| trait_item(program, "Derived"),
+        "forall<T> { if (T: Derived) { T: Base } }",
+    ) {
+        assert_lookup_failed(item, solution);
+    }
+}
+
+#[test]
+fn broken_adt_in_auto_trait_goal() {
+    for (item, solution) in
+        solve_with_broken_item(|program| adt_item(program, "Wrapper"), "Wrapper: Send")
+    {
+        assert_lookup_failed(item, solution);
+    }
+}
+
+#[test]
+fn broken_adt_in_well_formed_goal() {
+    for (item, solution) in solve_with_broken_item(
+        |program| adt_item(program, "Wrapper"),
+        "WellFormed(Wrapper)",
+    ) {
+        assert_lookup_failed(item, solution);
+    }
+}
+
+#[test]
+fn unrelated_goals_are_solved() {
+    for (_, solution) in
+        solve_with_broken_item(|program| trait_item(program, "Broken"), "Bar: Fine")
+    {
+        assert_result(solution, "Unique", &ChalkIr);
+    }
+}
@@ -6,7 +6,7 @@ use chalk_integration::interner::ChalkIr;
 use chalk_integration::lowering::lower_goal;
 use chalk_integration::query::LoweringDatabase;
 use chalk_integration::SolverChoice;
-use chalk_ir::Constraints;
+use chalk_ir::{Constraints, Goal, InEnvironment, UCanonical};
 use chalk_solve::ext::*;
 use chalk_solve::logging::with_tracing_logs;
 use chalk_solve::RustIrDatabase;
@@ -321,6 +321,47 @@ fn solve_goal(program_text: &str, goals: Vec<(&str, SolverChoice, TestGoal)>, co
     })
 }
 
+/// Parses `goal_text` and lowers it against the program of `db`, ready to
+/// be solved.
+fn peeled_goal(db: &ChalkDatabase, goal_text: &str) -> UCanonical<InEnvironment<Goal<ChalkIr>>> {
+    db.with_program(|program| {
+        lower_goal(&chalk_parse::parse_goal(goal_text).unwrap(), program)
+            .unwrap()
+            .into_peeled_goal(db.interner())
+    })
+}
+
+/// Solves `goal_text` with the solver chosen for `db`.
+fn solve(db: &ChalkDatabase, goal_text: &str) -> Option<Solution<ChalkIr>> {
+    let goal = peeled_goal(db, goal_text);
+    db.with_program(|_| db.solve(&goal))
+}
+
+/// Calls `op` with a database for `program_text` and the peeled `goal_text`,
+/// once with the SLG solver and once with the recursive one, and returns
+/// what it returned each time.
+fn with_each_solver<R>(
+    program_text: &str,
+    goal_text: &str,
+    mut op: impl FnMut(&ChalkDatabase, &UCanonical<InEnvironment<Goal<ChalkIr>>>) -> R,
+) -> Vec<R> {
+    let solver_choices = [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ];
+    solver_choices
+        .iter()
+        .map(|&solver_choice| {
+            println!("using solver: {:?}", solver_choice);
+            let db = ChalkDatabase::with(program_text, solver_choice);
+            let goal = peeled_goal(&db, goal_text);
+            db.with_program(|_| op(&db, &goal))
+        })
+        .collect()
+}
+
+mod ambiguity;
+mod answers;
 mod arrays;
 mod auto_traits;
 mod closures;
@@ -328,23 +369,38 @@ mod coherence_goals;
 mod coinduction;
 mod constants;
 mod cycle;
+mod differential;
+mod discriminant_kind;
+mod env_trim;
+mod eviction;
 mod existential_types;
+mod explain;
 mod fn_def;
 mod foreign_types;
 mod generators;
+mod impl_index;
 mod implied_bounds;
 mod impls;
+mod incremental;
+mod lazy_normalization;
 mod lifetimes;
+mod lookup_errors;
 mod misc;
 mod negation;
 mod never;
 mod numerics;
 mod object_safe;
 mod opaque_types;
+mod overflow;
+mod parallel;
+mod pointee;
 mod projection;
 mod refs;
 mod scalars;
+#[cfg(feature = "serde")]
+mod serialize;
 mod slices;
+mod stats;
 mod string;
 mod subtype;
 mod tuples;
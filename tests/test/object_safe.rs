@@ -13,3 +13,285 @@ fn object_safe_flag() {
         goal { not { ObjectSafe(Bar) } } yields { "Unique" }
     }
 }
+
+#[test]
+fn object_safe_computed() {
+    test! {
+        program {
+            #[lang(sized)]
+            trait Sized {}
+
+            #[compute_object_safety]
+            trait NoMethods {}
+            #[compute_object_safety]
+            trait ByValue { fn by_value(self: Self); }
+            #[compute_object_safety]
+            trait AssocFn { fn assoc_fn(); }
+            #[compute_object_safety]
+            trait SizedAssocFn { fn assoc_fn() where Self: Sized; }
+            #[compute_object_safety]
+            trait SizedTrait where Self: Sized {}
+            #[compute_object_safety]
+            trait Generic { fn generic<T>(self: Self); }
+            #[compute_object_safety]
+            trait SizedGeneric { fn generic<T>(self: Self) where Self: Sized; }
+            #[compute_object_safety]
+            trait LifetimeGeneric { fn generic<'a>(self: Self); }
+            #[compute_object_safety]
+            trait WithParams<T, U> { fn by_value(self: Self); }
+        }
+
+        goal { ObjectSafe(NoMethods) } yields { "Unique" }
+        goal { ObjectSafe(ByValue) } yields { "Unique" }
+        goal { not { ObjectSafe(AssocFn) } } yields { "Unique" }
+        goal { ObjectSafe(SizedAssocFn) } yields { "Unique" }
+        goal { not { ObjectSafe(SizedTrait) } } yields { "Unique" }
+        goal { not { ObjectSafe(Generic) } } yields { "Unique" }
+        goal { ObjectSafe(SizedGeneric) } yields { "Unique" }
+        goal { ObjectSafe(LifetimeGeneric) } yields { "Unique" }
+        goal { ObjectSafe(WithParams) } yields { "Unique" }
+    }
+}
+
+#[test]
+fn object_safe_supertraits() {
+    test! {
+        program {
+            #[compute_object_safety]
+            trait Safe { fn safe(self: Self); }
+            #[compute_object_safety]
+            trait Unsafe { fn unsafe_fn(); }
+            #[compute_object_safety]
+            trait SafeSub where Self: Safe {}
+            #[compute_object_safety]
+            trait UnsafeSub where Self: Unsafe {}
+            #[compute_object_safety]
+            trait UnsafeSubSub where Self: UnsafeSub {}
+        }
+
+        goal { ObjectSafe(SafeSub) } yields { "Unique" }
+        goal { not { ObjectSafe(UnsafeSub) } } yields { "Unique" }
+        goal { not { ObjectSafe(UnsafeSubSub) } } yields { "Unique" }
+    }
+}
+
+#[test]
+fn object_safe_signatures() {
+    test! {
+        program {
+            #[lang(sized)]
+            trait Sized {}
+
+            struct Vec<T> {}
+
+            #[compute_object_safety]
+            trait Args { fn args(self: Self, a: u32, b: Vec<u8>) -> bool; }
+            #[compute_object_safety]
+            trait SelfArg { fn self_arg(self: Self, other: Self); }
+            #[compute_object_safety]
+            trait SelfInArg { fn self_in_arg(self: Self, other: Vec<Self>); }
+            #[compute_object_safety]
+            trait SelfReturn { fn self_return(self: Self) -> Self; }
+            #[compute_object_safety]
+            trait SizedSelfReturn { fn self_return(self: Self) -> Self where Self: Sized; }
+            #[compute_object_safety]
+            trait Iterator {
+                type Item;
+                fn next(self: Self) -> Vec<<Self as Iterator>::Item>;
+            }
+            #[compute_object_safety]
+            trait SubIterator where Self: Iterator {
+                fn first(self: Self) -> <Self as Iterator>::Item;
+            }
+            #[compute_object_safety]
+            trait Other { type Item; }
+            #[compute_object_safety]
+            trait OtherProjection {
+                fn other(self: Self) -> <Self as Other>::Item;
+            }
+        }
+
+        goal { ObjectSafe(Args) } yields { "Unique" }
+        goal { not { ObjectSafe(SelfArg) } } yields { "Unique" }
+        goal { not { ObjectSafe(SelfInArg) } } yields { "Unique" }
+        goal { not { ObjectSafe(SelfReturn) } } yields { "Unique" }
+        goal { ObjectSafe(SizedSelfReturn) } yields { "Unique" }
+        goal { ObjectSafe(Iterator) } yields { "Unique" }
+        goal { ObjectSafe(SubIterator) } yields { "Unique" }
+        goal { not { ObjectSafe(OtherProjection) } } yields { "Unique" }
+    }
+}
+
+#[test]
+fn object_safe_associated_items() {
+    test! {
+        program {
+            #[compute_object_safety]
+            trait AssocTy { type Assoc; }
+            #[compute_object_safety]
+            trait AssocConst { const C: u32; }
+            #[compute_object_safety]
+            trait GenericAssocTy { type Assoc<T>; }
+            #[compute_object_safety]
+            trait LifetimeAssocTy { type Assoc<'a>; }
+        }
+
+        goal { ObjectSafe(AssocTy) } yields { "Unique" }
+        goal { not { ObjectSafe(AssocConst) } } yields { "Unique" }
+        goal { not { ObjectSafe(GenericAssocTy) } } yields { "Unique" }
+        goal { not { ObjectSafe(LifetimeAssocTy) } } yields { "Unique" }
+    }
+}
+
+#[test]
+fn object_safe_where_clauses() {
+    test! {
+        program {
+            #[compute_object_safety]
+            #[lang(sized)]
+            trait Sized {}
+
+            #[compute_object_safety]
+            trait PartialEq<T> {}
+            #[compute_object_safety]
+            trait Iterator { type Item; }
+
+            #[compute_object_safety]
+            trait Eq where Self: PartialEq<Self> {}
+            #[compute_object_safety]
+            trait EqU32 where Self: PartialEq<u32> {}
+            #[compute_object_safety]
+            trait Param<T> where T: PartialEq<Self> {}
+            #[compute_object_safety]
+            trait ParamSelf<T> where Self: PartialEq<T> {}
+            #[compute_object_safety]
+            trait ItemIsSelf<T> where T: Iterator<Item = Self> {}
+            #[compute_object_safety]
+            trait SizedSuper where Self: Sized {}
+            #[compute_object_safety]
+            trait SubOfSized where Self: SizedSuper {}
+        }
+
+        goal { not { ObjectSafe(Eq) } } yields { "Unique" }
+        goal { ObjectSafe(EqU32) } yields { "Unique" }
+        goal { not { ObjectSafe(Param) } } yields { "Unique" }
+        goal { ObjectSafe(ParamSelf) } yields { "Unique" }
+        goal { ObjectSafe(ItemIsSelf) } yields { "Unique" }
+        goal { not { ObjectSafe(SubOfSized) } } yields { "Unique" }
+        goal { not { ObjectSafe(Sized) } } yields { "Unique" }
+    }
+}
+
+#[test]
+fn object_safe_receivers() {
+    test! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            struct Box<T> { ptr: *mut T }
+            struct Rc<T> { ptr: *mut T }
+            struct Vec<T> { ptr: *mut T }
+
+            impl<T, U> DispatchFromDyn<Box<U>> for Box<T> where T: Unsize<U> {}
+            impl<T, U> DispatchFromDyn<Rc<U>> for Rc<T> where T: Unsize<U> {}
+
+            #[compute_object_safety]
+            trait Ref { fn by_ref<'a>(self: &'a Self); }
+            #[compute_object_safety]
+            trait RefMut { fn by_ref_mut<'a>(self: &'a mut Self); }
+            #[compute_object_safety]
+            trait ConstPtr { fn by_ptr(self: *const Self); }
+            #[compute_object_safety]
+            trait Boxed { fn boxed(self: Box<Self>); }
+            #[compute_object_safety]
+            trait Mixed {
+                fn by_ref<'a>(self: &'a Self);
+                fn boxed(self: Box<Self>);
+                fn rc(self: Rc<Self>);
+            }
+            #[compute_object_safety]
+            trait InVec { fn in_vec(self: Vec<Self>); }
+            #[compute_object_safety]
+            trait RefRef { fn ref_ref<'a, 'b>(self: &'a &'b Self); }
+            #[compute_object_safety]
+            trait Generic<T> { fn boxed(self: Box<Self>); }
+        }
+
+        goal { ObjectSafe(Ref) } yields { "Unique" }
+        goal { ObjectSafe(RefMut) } yields { "Unique" }
+        goal { ObjectSafe(ConstPtr) } yields { "Unique" }
+        goal { ObjectSafe(Boxed) } yields { "Unique" }
+        goal { ObjectSafe(Mixed) } yields { "Unique" }
+        goal { ObjectSafe(Generic) } yields { "Unique" }
+        goal { not { ObjectSafe(InVec) } } yields { "Unique" }
+        goal { not { ObjectSafe(RefRef) } } yields { "Unique" }
+    }
+}
+
+#[test]
+fn object_safe_receivers_without_lang_items() {
+    test! {
+        program {
+            #[compute_object_safety]
+            trait Ref { fn by_ref<'a>(self: &'a Self); }
+        }
+
+        goal { not { ObjectSafe(Ref) } } yields { "Unique" }
+    }
+}
+
+#[test]
+fn dispatch_from_dyn_builtin() {
+    test! {
+        program {
+            #[lang(sized)]
+            trait Sized {}
+
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            #[compute_object_safety]
+            trait Foo { fn foo<'a>(self: &'a Self); }
+            struct S {}
+            impl Foo for S {}
+        }
+
+        goal {
+            forall<'a> { &'a S: DispatchFromDyn<&'a (dyn Foo + 'a)> }
+        } yields {
+            "Unique"
+        }
+        goal {
+            forall<'a> { &'a mut S: DispatchFromDyn<&'a mut (dyn Foo + 'a)> }
+        } yields {
+            "Unique"
+        }
+        goal {
+            forall<'a> { *const S: DispatchFromDyn<*const (dyn Foo + 'a)> }
+        } yields {
+            "Unique"
+        }
+        goal {
+            forall<'a> { not { &'a mut S: DispatchFromDyn<&'a (dyn Foo + 'a)> } }
+        } yields {
+            "Unique"
+        }
+        goal {
+            forall<'a> { not { *mut S: DispatchFromDyn<*const (dyn Foo + 'a)> } }
+        } yields {
+            "Unique"
+        }
+        goal {
+            forall<'a> { not { &'a [u32; 3]: DispatchFromDyn<&'a (dyn Foo + 'a)> } }
+        } yields {
+            "Unique"
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Tests for the `Overflow` solutions reported when solving a goal exceeds
+//! the solver's limits.
+
+use super::*;
+use chalk_recursive::RecursiveSolver;
+use chalk_solve::solve::{OverflowLimit, ProofOutcome};
+use chalk_solve::{AmbiguityCause, Guidance, Solver};
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Vec<T> { }
+    trait Clone { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+/// Solves a goal that is expected to overflow, returning the limit that was
+/// hit along with the goal that hit it.
+fn solve_overflow(solver_choice: SolverChoice, goal_text: &str) -> (OverflowLimit, String) {
+    let db = ChalkDatabase::with(PROGRAM, solver_choice);
+    let solution = solve(&db, goal_text);
+    db.with_program(|_| match solution {
+        Some(Solution::Overflow(overflow)) => {
+            (overflow.limit, format!("{:?}", overflow.goal.value.goal))
+        }
+        solution => panic!("expected an overflow, got {:?}", solution),
+    })
+}
+
+#[test]
+fn depth_overflow() {
+    // This used to panic, rather than give up on the goal.
+    let (limit, goal) = solve_overflow(
+        SolverChoice::recursive(30, 6),
+        "Vec<Vec<Vec<Vec<Vec<Vec<Vec<Foo>>>>>>>: Clone",
+    );
+    assert_eq!(limit, OverflowLimit::Depth(6));
+    assert!(goal.contains("Vec<Vec<Foo>>: Clone"), "{}", goal);
+}
+
+#[test]
+fn size_overflow() {
+    for solver_choice in [SolverChoice::slg(3, None), SolverChoice::recursive(3, 100)] {
+        println!("using solver: {:?}", solver_choice);
+        let (limit, goal) = solve_overflow(solver_choice, "Vec<Vec<Vec<Vec<Foo>>>>: Clone");
+        assert_eq!(limit, OverflowLimit::Size(3));
+        assert!(goal.contains("Vec<Vec<Vec<Foo>>>>: Clone"), "{}", goal);
+    }
+}
+
+#[test]
+fn no_overflow_within_limits() {
+    for solver_choice in [SolverChoice::slg(3, None), SolverChoice::recursive(3, 6)] {
+        let db = ChalkDatabase::with(PROGRAM, solver_choice);
+        db.with_program(|program| {
+            let goal = lower_goal(
+                &chalk_parse::parse_goal("Vec<Foo>: Clone").unwrap(),
+                program,
+            )
+            .unwrap();
+            let solution = db.solve(&goal.into_peeled_goal(db.interner()));
+            assert!(solution.unwrap().is_unique());
+        });
+    }
+}
+
+#[test]
+fn overflow_in_proof_tree() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::recursive(30, 6));
+    db.with_program(|program| {
+        let goal = lower_goal(
+            &chalk_parse::parse_goal("Vec<Vec<Vec<Vec<Vec<Vec<Vec<Foo>>>>>>>: Clone").unwrap(),
+            program,
+        )
+        .unwrap();
+        let (solution, proof_tree) = db.solve_explained(&goal.into_peeled_goal(db.interner()));
+        assert!(solution.unwrap().is_overflow());
+        assert_eq!(proof_tree.outcome, ProofOutcome::Overflowed);
+        assert!(proof_tree
+            .failed_leaves()
+            .iter()
+            .any(|leaf| leaf.outcome == ProofOutcome::Overflowed));
+    });
+}
+
+#[test]
+fn deep_goal_within_limits() {
+    // Solving each level of a goal used to take several native stack
+    // frames, so a chain of adaptors like this one overflowed the test
+    // thread's stack long before the solver's own overflow depth.
+    const DEPTH: usize = 200;
+    let mut program_text =
+        String::from("struct Empty { } trait Adaptor0 { } impl Adaptor0 for Empty { }");
+    for i in 1..=DEPTH {
+        program_text += &format!(
+            "trait Adaptor{} {{ }} impl<I> Adaptor{} for I where I: Adaptor{} {{ }}",
+            i,
+            i,
+            i - 1
+        );
+    }
+    let db = ChalkDatabase::with(&program_text, SolverChoice::default());
+    // Checking a program this size takes a while, and is not what we are
+    // testing here.
+    let program = db.program_ir().unwrap();
+    let goal = lower_goal(
+        &chalk_parse::parse_goal(&format!("Empty: Adaptor{}", DEPTH)).unwrap(),
+        &program,
+    )
+    .unwrap();
+    let mut solver = RecursiveSolver::new(2 * DEPTH, 30, true);
+    let solution = solver.solve(&*program, &goal.into_peeled_goal(&ChalkIr));
+    assert!(solution.unwrap().is_unique());
+}
+
+#[test]
+fn depth_overflow_is_not_cached() {
+    // The goals on the stack when the first goal overflowed would have been
+    // solved if they had been the root goal, so their overflows must not be
+    // reused for the second goal.
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::default());
+    let program = db.program_ir().unwrap();
+    let mut solver = RecursiveSolver::new(6, 30, true);
+    for (goal_text, expected) in [
+        ("Vec<Vec<Vec<Vec<Vec<Vec<Vec<Vec<Foo>>>>>>>>: Clone", false),
+        ("Vec<Vec<Vec<Foo>>>: Clone", true),
+    ] {
+        let solution = solver
+            .solve(&*program, &peeled_goal(&db, goal_text))
+            .unwrap();
+        assert_eq!(solution.is_unique(), expected, "{:?}", solution);
+    }
+}
+
+#[test]
+fn depth_overflow_keeps_guidance() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::recursive(30, 6));
+    db.with_program(|program| {
+        let goal = lower_goal(
+            &chalk_parse::parse_goal(
+                "exists<T, U> { T = Vec<U>, Vec<Vec<Vec<Vec<Vec<Vec<Vec<Foo>>>>>>>: Clone }",
+            )
+            .unwrap(),
+            program,
+        )
+        .unwrap();
+        match db.solve(&goal.into_peeled_goal(db.interner())) {
+            Some(Solution::Ambig(guidance @ Guidance::Definite(..))) => {
+                assert_eq!(guidance.cause(), &AmbiguityCause::Overflow);
+            }
+            solution => panic!("expected definite guidance, got {:?}", solution),
+        }
+    });
+}
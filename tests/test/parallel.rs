@@ -0,0 +1,143 @@
+//! Tests for solving goals on several threads with `ParallelSLGSolver`.
+
+use super::*;
+use chalk_engine::solve::{ParallelSLGSolver, SLGSolver};
+use chalk_integration::program::Program;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+use chalk_solve::Solver;
+use salsa::ParallelDatabase;
+use std::sync::Arc;
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar { }
+    struct Vec<T> { }
+    struct Box<T> { }
+    trait Clone { }
+    trait Debug { }
+    trait Send { }
+    impl Clone for Foo { }
+    impl Debug for Foo { }
+    impl Debug for Bar { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+    impl<T> Debug for Vec<T> where T: Debug { }
+    impl<T> Clone for Box<T> where T: Clone { }
+    impl<T> Debug for Box<T> where T: Debug { }
+    impl<T> Send for Vec<T> where T: Send { }
+    impl<T> Send for Box<T> where T: Send { }
+    impl Send for Foo { }
+";
+
+const GOALS: &[&str] = &[
+    "Foo: Clone",
+    "Bar: Clone",
+    "Vec<Foo>: Clone",
+    "Vec<Bar>: Clone",
+    "Box<Vec<Foo>>: Clone",
+    "Box<Vec<Bar>>: Debug",
+    "Vec<Box<Vec<Foo>>>: Send",
+    "Vec<Box<Vec<Bar>>>: Send",
+    "exists<T> { Vec<T>: Debug }",
+    "exists<T> { Box<T>: Clone }",
+    "forall<T> { if (T: Clone) { Vec<Box<T>>: Clone } }",
+    "forall<T> { Vec<T>: Send }",
+];
+
+fn peeled_goals(db: &ChalkDatabase) -> Vec<UCanonical<InEnvironment<Goal<ChalkIr>>>> {
+    GOALS
+        .iter()
+        .map(|goal_text| peeled_goal(db, goal_text))
+        .collect()
+}
+
+fn sequential_solutions(
+    program: &Program,
+    goals: &[UCanonical<InEnvironment<Goal<ChalkIr>>>],
+) -> Vec<Option<Solution<ChalkIr>>> {
+    let mut solver = SLGSolver::new(10, None);
+    goals
+        .iter()
+        .map(|goal| solver.solve(program, goal))
+        .collect()
+}
+
+#[test]
+fn parallel_solutions_match_sequential() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let program = db.program_ir().unwrap();
+    let goals = peeled_goals(&db);
+    let expected = sequential_solutions(&program, &goals);
+
+    for threads in 1..=4 {
+        let solver = ParallelSLGSolver::new(10, None);
+        assert_eq!(solver.solve_all(&*program, &goals, threads), expected);
+
+        // A second round starts from the tables completed by the first.
+        assert!(solver.shared_tables() > 0);
+        assert_eq!(solver.solve_all(&*program, &goals, threads), expected);
+    }
+}
+
+#[test]
+fn shared_tables_are_invalidated() {
+    let mut db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let goals = peeled_goals(&db);
+    let previous = db.program_ir().unwrap();
+
+    // Solve through the database, so that the dependencies of the tables
+    // are recorded.
+    let mut solver = ParallelSLGSolver::new(10, None);
+    for goal in &goals {
+        solver.solve(&db, goal);
+    }
+
+    db.set_program_text(Arc::new(PROGRAM.replace("impl Debug for Bar { }", "")));
+    let program = db.program_ir().unwrap();
+    assert!(solver.invalidate(&program.changed_items(&previous)));
+
+    let solutions: Vec<_> = goals.iter().map(|goal| solver.solve(&db, goal)).collect();
+    assert_eq!(solutions, sequential_solutions(&program, &goals));
+}
+
+#[test]
+fn snapshots_share_the_parallel_solver() {
+    let mut db = ChalkDatabase::with(PROGRAM, SolverChoice::parallel_default());
+    let goals = peeled_goals(&db);
+    let expected = sequential_solutions(&db.program_ir().unwrap(), &goals);
+
+    // Each thread solves every goal through a snapshot of its own; they all
+    // share one solver and record their dependencies in one recorder.
+    let solve_on_threads = |db: &ChalkDatabase, goals: &[_]| {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let snapshot = db.snapshot();
+                    scope.spawn(move || {
+                        goals
+                            .iter()
+                            .map(|goal| snapshot.solve(goal))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        })
+    };
+    for solutions in solve_on_threads(&db, &goals) {
+        assert_eq!(solutions, expected);
+    }
+
+    // The tables recorded on the other threads must be invalidated when the
+    // program changes.
+    db.set_program_text(Arc::new(PROGRAM.replace("impl Debug for Bar { }", "")));
+    let previous = expected;
+    let goals = peeled_goals(&db);
+    let expected = sequential_solutions(&db.program_ir().unwrap(), &goals);
+    assert_ne!(expected, previous);
+    for solutions in solve_on_threads(&db, &goals) {
+        assert_eq!(solutions, expected);
+    }
+}
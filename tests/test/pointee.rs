@@ -0,0 +1,223 @@
+//! Tests targeting the Pointee trait
+
+use super::*;
+
+#[test]
+fn pointee_impl() {
+    test! {
+        program {
+            #[lang(pointee)]
+            trait Pointee {
+                type Metadata;
+            }
+
+            struct S { }
+            trait Foo { }
+        }
+
+        goal {
+            S: Pointee
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            [u8]: Pointee
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                T: Pointee
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<'a> {
+                dyn Foo + 'a: Pointee
+            }
+        } yields {
+            "Unique"
+        }
+    }
+}
+
+#[test]
+fn pointee_metadata() {
+    test! {
+        program {
+            #[lang(sized)]
+            trait Sized { }
+
+            #[lang(pointee)]
+            trait Pointee {
+                type Metadata;
+            }
+
+            #[lang(dyn_metadata)]
+            struct DynMetadata<Dyn> { }
+
+            struct S { }
+            enum E { Variant }
+            trait Foo { }
+        }
+
+        goal {
+            <u32 as Pointee>::Metadata = ()
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <S as Pointee>::Metadata = ()
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <E as Pointee>::Metadata = ()
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<'a> {
+                <&'a str as Pointee>::Metadata = ()
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <[u8] as Pointee>::Metadata = usize
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <str as Pointee>::Metadata = usize
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<'a> {
+                <dyn Foo + 'a as Pointee>::Metadata = DynMetadata<dyn Foo + 'a>
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                if (T: Sized) {
+                    <T as Pointee>::Metadata = ()
+                }
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> {
+                Normalize(<T as Pointee>::Metadata -> ())
+            }
+        } yields {
+            "No possible solution"
+        }
+    }
+}
+
+#[test]
+fn pointee_metadata_struct_tail() {
+    test! {
+        program {
+            #[lang(pointee)]
+            trait Pointee {
+                type Metadata;
+            }
+
+            #[lang(dyn_metadata)]
+            struct DynMetadata<Dyn> { }
+
+            struct Slice<T> {
+                len: usize,
+                data: [T],
+            }
+            struct Nested<T> {
+                tag: u8,
+                inner: Slice<T>,
+            }
+            struct DynTail<'a> {
+                data: dyn Foo + 'a,
+            }
+            struct Sized<T> {
+                data: [T; 3],
+            }
+            trait Foo { }
+        }
+
+        goal {
+            <Slice<u8> as Pointee>::Metadata = usize
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <Nested<u8> as Pointee>::Metadata = usize
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<'a> {
+                <DynTail<'a> as Pointee>::Metadata = DynMetadata<dyn Foo + 'a>
+            }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <Sized<u8> as Pointee>::Metadata = ()
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            <(u8, str) as Pointee>::Metadata = usize
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            exists<T> {
+                Normalize(<Slice<u8> as Pointee>::Metadata -> T)
+            }
+        } yields {
+            "Unique; substitution [?0 := Uint(Usize)]"
+        }
+    }
+}
+
+#[test]
+fn no_pointee_impls() {
+    lowering_error! {
+        program {
+            #[lang(pointee)]
+            trait Pointee {
+                type Metadata;
+            }
+
+            struct A { }
+
+            impl Pointee for A {
+                type Metadata = ();
+            }
+        } error_msg {
+            "trait impl for `Pointee` does not meet well-formedness requirements"
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Tests for serializing goals, solutions and solver caches.
+
+use super::*;
+use chalk_ir::{Goal, InEnvironment, UCanonical};
+use chalk_recursive::RecursiveSolver;
+use chalk_solve::Solver;
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Bar<T> { }
+    trait Clone { }
+    trait Debug { }
+    impl Clone for Foo { }
+    impl<T> Debug for Bar<T> where T: Clone { }
+";
+
+/// `PROGRAM` without the impl of `Clone`, with the same ids for everything
+/// else.
+const PROGRAM_WITHOUT_IMPL: &str = "
+    struct Foo { }
+    struct Bar<T> { }
+    trait Clone { }
+    trait Debug { }
+    impl<T> Debug for Bar<T> where T: Clone { }
+";
+
+#[test]
+fn goals_and_solutions_round_trip() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::recursive_default());
+    for goal_text in &[
+        "Bar<Foo>: Debug",
+        "exists<T> { Bar<T>: Debug }",
+        "forall<'a, T> { if (T: Clone) { Bar<&'a T>: Debug } }",
+    ] {
+        let goal = peeled_goal(&db, goal_text);
+        let json = serde_json::to_string(&goal).unwrap();
+        let deserialized: UCanonical<InEnvironment<Goal<ChalkIr>>> =
+            serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, goal);
+
+        let solution = db.solve(&goal);
+        let json = serde_json::to_string(&solution).unwrap();
+        let deserialized: Option<Solution<ChalkIr>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, solution);
+    }
+}
+
+#[test]
+fn recursive_solver_cache_round_trip() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::recursive_default());
+    let goal = peeled_goal(&db, "Bar<Foo>: Debug");
+    let mut solver = RecursiveSolver::new(100, 30, true);
+    let solution = db.with_program(|_| solver.solve(&db, &goal));
+    assert_result(solution.clone(), "Unique", db.interner());
+
+    let mut json = vec![];
+    solver
+        .save_cache(&mut serde_json::Serializer::new(&mut json))
+        .unwrap();
+
+    // The reloaded solver answers from its cache, without consulting the
+    // program, which no longer proves the goal.
+    let mut reloaded = RecursiveSolver::new(100, 30, true);
+    reloaded
+        .load_cache(&mut serde_json::Deserializer::from_slice(&json))
+        .unwrap();
+    let db = ChalkDatabase::with(PROGRAM_WITHOUT_IMPL, SolverChoice::recursive_default());
+    assert_eq!(db.with_program(|_| reloaded.solve(&db, &goal)), solution);
+    assert_eq!(
+        db.with_program(|_| RecursiveSolver::new(100, 30, true).solve(&db, &goal)),
+        None
+    );
+}
@@ -0,0 +1,113 @@
+//! Tests for the statistics collected by the solvers.
+
+use super::*;
+use chalk_solve::solve::DEFAULT_MAX_EXPENSIVE_GOALS;
+
+const PROGRAM: &str = "
+    struct Foo { }
+    struct Vec<T> { }
+    trait Clone { }
+    impl Clone for Foo { }
+    impl<T> Clone for Vec<T> where T: Clone { }
+";
+
+#[test]
+fn stats_count_tables_answers_and_cache_hits() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::recursive_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let db = ChalkDatabase::with(PROGRAM, solver_choice);
+        assert!(solve(&db, "Vec<Vec<Foo>>: Clone").unwrap().is_unique());
+        let stats = db.solver_stats();
+        assert!(stats.tables > 0);
+        assert!(stats.answers > 0);
+        assert_eq!(stats.cache_misses, stats.tables);
+        assert_eq!(stats.overflows, 0);
+        assert_eq!(stats.root_goals, 1);
+        assert_eq!(stats.expensive_goals[0].tables, stats.tables);
+
+        // Solving the goal again reuses what we found the first time.
+        solve(&db, "Vec<Vec<Foo>>: Clone");
+        let stats_again = db.solver_stats();
+        assert!(stats_again.cache_hits > stats.cache_hits);
+        assert_eq!(stats_again.tables, stats.tables);
+        assert_eq!(stats_again.root_goals, 2);
+        assert!(stats_again
+            .expensive_goals
+            .iter()
+            .any(|root_goal| root_goal.tables == 0));
+    }
+}
+
+#[test]
+fn stats_count_overflows() {
+    for solver_choice in [SolverChoice::slg(3, None), SolverChoice::recursive(3, 100)] {
+        println!("using solver: {:?}", solver_choice);
+        let db = ChalkDatabase::with(PROGRAM, solver_choice);
+        solve(&db, "Vec<Vec<Vec<Vec<Foo>>>>: Clone");
+        assert!(db.solver_stats().overflows > 0);
+    }
+}
+
+#[test]
+fn most_expensive_goals() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    solve(&db, "Foo: Clone");
+    solve(&db, "Vec<Vec<Vec<Foo>>>: Clone");
+    solve(&db, "Vec<Foo>: Clone");
+    let stats = db.solver_stats();
+
+    let most_expensive = stats.most_expensive(2);
+    assert_eq!(most_expensive.len(), 2);
+    assert!(most_expensive[0].duration >= most_expensive[1].duration);
+    assert!(stats.total_time >= most_expensive[0].duration + most_expensive[1].duration);
+    assert_eq!(stats.most_expensive(10).len(), 3);
+
+    let report = db.with_program(|_| stats.display(2).to_string());
+    assert!(report.contains("most expensive goals:"));
+    assert_eq!(report.lines().count(), 8 + 1 + 2);
+}
+
+#[test]
+fn only_the_most_expensive_goals_are_kept() {
+    let db = ChalkDatabase::with(PROGRAM, SolverChoice::slg_default());
+    let goals = ["Foo: Clone", "Vec<Foo>: Clone", "Vec<Vec<Foo>>: Clone"];
+    for goal in goals.iter().cycle().take(DEFAULT_MAX_EXPENSIVE_GOALS + 5) {
+        solve(&db, goal);
+    }
+    let stats = db.solver_stats();
+    assert_eq!(stats.root_goals, DEFAULT_MAX_EXPENSIVE_GOALS + 5);
+    assert_eq!(stats.expensive_goals.len(), DEFAULT_MAX_EXPENSIVE_GOALS);
+    assert!(stats
+        .expensive_goals
+        .windows(2)
+        .all(|pair| pair[0].duration >= pair[1].duration));
+    let most_expensive = stats.most_expensive(3);
+    assert_eq!(most_expensive.len(), 3);
+    assert_eq!(
+        most_expensive[0].duration,
+        stats.expensive_goals[0].duration
+    );
+}
+
+#[test]
+fn max_expensive_goals_is_a_solver_setting() {
+    for solver_choice in [
+        SolverChoice::slg_default(),
+        SolverChoice::parallel_default(),
+        SolverChoice::recursive_default(),
+        SolverChoice::differential_default(),
+    ] {
+        println!("using solver: {:?}", solver_choice);
+        let db = ChalkDatabase::with(PROGRAM, solver_choice.with_max_expensive_goals(2));
+        for goal in ["Foo: Clone", "Vec<Foo>: Clone", "Vec<Vec<Foo>>: Clone"] {
+            solve(&db, goal);
+        }
+        let stats = db.solver_stats();
+        assert_eq!(stats.root_goals, 3);
+        assert_eq!(stats.max_expensive_goals, 2);
+        assert_eq!(stats.expensive_goals.len(), 2);
+    }
+}
@@ -80,10 +80,10 @@ fn fn_lifetime_variance_args() {
         goal {
             for<'a, 'b> fn(&'a u32, &'b u32) = for<'a> fn(&'a u32, &'a u32)
         } yields[SolverChoice::recursive_default()] {
-            "Unique; for<?U1,?U2,?U2> { substitution [], lifetime constraints [\
-                InEnvironment { environment: Env([]), goal: '!1_0: '^0.0 }, \
-                InEnvironment { environment: Env([]), goal: '!1_1: '^0.0 }, \
-                InEnvironment { environment: Env([]), goal: '!2_0: '^0.1 }, \
+            "Unique; for<?U2,?U1,?U2> { substitution [], lifetime constraints [\
+                InEnvironment { environment: Env([]), goal: '!1_0: '^0.1 }, \
+                InEnvironment { environment: Env([]), goal: '!1_1: '^0.1 }, \
+                InEnvironment { environment: Env([]), goal: '!2_0: '^0.0 }, \
                 InEnvironment { environment: Env([]), goal: '!2_0: '^0.2 }\
             ] }"
         } yields[SolverChoice::slg_default()] {
@@ -237,3 +237,73 @@ fn tuples_are_clone() {
         }
     }
 }
+
+#[test]
+fn tuple_trait() {
+    test! {
+        program {
+            #[lang(tuple_trait)]
+            trait Tuple { }
+
+            struct S { }
+        }
+
+        goal {
+            (): Tuple
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            (u8, S): Tuple
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            forall<T> { (T,): Tuple }
+        } yields {
+            "Unique"
+        }
+
+        goal {
+            S: Tuple
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            [(u8,); 1]: Tuple
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<T> { T: Tuple }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            exists<T> { T: Tuple }
+        } yields_first[SolverChoice::slg(3, None)] {
+            "Floundered"
+        }
+    }
+}
+
+#[test]
+fn no_tuple_trait_impls() {
+    lowering_error! {
+        program {
+            #[lang(tuple_trait)]
+            trait Tuple { }
+
+            struct A { }
+
+            impl Tuple for A { }
+        } error_msg {
+            "trait impl for `Tuple` does not meet well-formedness requirements"
+        }
+    }
+}
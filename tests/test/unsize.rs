@@ -142,6 +142,204 @@ fn dyn_to_dyn_unsizing() {
     }
 }
 
+#[test]
+fn dyn_upcasting() {
+    test! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[object_safe]
+            trait Super {}
+            #[object_safe]
+            trait Sub where Self: Super {}
+            #[object_safe]
+            trait SubSub where Self: Sub {}
+            #[object_safe]
+            trait Unrelated {}
+
+            #[object_safe]
+            trait GenericSuper<T> {}
+            #[object_safe]
+            trait GenericSub<T> where Self: GenericSuper<T> {}
+
+            #[auto]
+            #[object_safe]
+            trait Auto1 {}
+
+            #[auto]
+            #[object_safe]
+            trait Auto2 {}
+        }
+
+        goal {
+            forall<'a> {
+                dyn Sub + 'a: Unsize<dyn Super + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        // Upcasting goes through several levels of supertraits
+        goal {
+            forall<'a> {
+                dyn SubSub + 'a: Unsize<dyn Super + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        // The source lifetime must outlive the target lifetime
+        goal {
+            forall<'a> {
+                forall<'b> {
+                    dyn Sub + 'a: Unsize<dyn Super + 'b>
+                }
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!2_0 }]"
+        }
+
+        // Downcasting is not allowed
+        goal {
+            forall<'a> {
+                dyn Super + 'a: Unsize<dyn Sub + 'a>
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<'a> {
+                dyn Sub + 'a: Unsize<dyn Unrelated + 'a>
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        // Auto traits may be dropped, but not added
+        goal {
+            forall<'a> {
+                dyn Sub + Auto1 + Auto2 + 'a: Unsize<dyn Super + Auto1 + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        goal {
+            forall<'a> {
+                dyn Sub + Auto1 + 'a: Unsize<dyn Super + Auto1 + Auto2 + 'a>
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        // Generic parameters are carried over to the supertrait
+        goal {
+            forall<'a> {
+                dyn GenericSub<u8> + 'a: Unsize<dyn GenericSuper<u8> + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        goal {
+            forall<'a> {
+                dyn GenericSub<u8> + 'a: Unsize<dyn GenericSuper<u16> + 'a>
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<'a> {
+                exists<T> {
+                    dyn GenericSub<u8> + 'a: Unsize<dyn GenericSuper<T> + 'a>
+                }
+            }
+        } yields {
+            "Unique; substitution [?0 := Uint(U8)], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+    }
+}
+
+#[test]
+fn dyn_upcasting_assoc_bindings() {
+    test! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[object_safe]
+            trait Super {
+                type Item;
+            }
+            #[object_safe]
+            trait Sub where Self: Super {
+                type Other;
+            }
+            #[object_safe]
+            trait SubFixed where Self: Super<Item = u8> {}
+            #[object_safe]
+            trait SubGeneric<T> where Self: Super<Item = T> {}
+            #[object_safe]
+            trait SubSubGeneric<T> where Self: SubGeneric<T> {}
+        }
+
+        // Bindings of the source principal are dropped
+        goal {
+            forall<'a> {
+                dyn Sub<Other = u16> + 'a: Unsize<dyn Super + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        // Bindings implied by the supertrait where clauses are carried over
+        goal {
+            forall<'a> {
+                dyn SubFixed + 'a: Unsize<dyn Super<Item = u8> + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        goal {
+            forall<'a> {
+                dyn SubFixed + 'a: Unsize<dyn Super<Item = u16> + 'a>
+            }
+        } yields {
+            "No possible solution"
+        }
+
+        goal {
+            forall<'a> {
+                exists<T> {
+                    dyn SubGeneric<u32> + 'a: Unsize<dyn Super<Item = T> + 'a>
+                }
+            }
+        } yields {
+            "Unique; substitution [?0 := Uint(U32)], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        goal {
+            forall<'a> {
+                dyn SubSubGeneric<u32> + 'a: Unsize<dyn Super<Item = u32> + 'a>
+            }
+        } yields {
+            "Unique; substitution [], lifetime constraints [InEnvironment { environment: Env([]), goal: '!1_0: '!1_0 }]"
+        }
+
+        goal {
+            forall<'a> {
+                dyn SubSubGeneric<u32> + 'a: Unsize<dyn Super<Item = u8> + 'a>
+            }
+        } yields {
+            "No possible solution"
+        }
+    }
+}
+
 #[test]
 fn ty_to_dyn_unsizing() {
     test! {
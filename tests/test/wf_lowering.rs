@@ -1438,3 +1438,191 @@ fn coerce_unsized_struct() {
         }
     }
 }
+
+#[test]
+fn dispatch_from_dyn() {
+    lowering_success! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            #[phantom_data]
+            struct PhantomData<T> {}
+
+            struct Box<T> {
+                ptr: *mut T,
+            }
+
+            struct Ptr<T> {
+                ptr: *const T,
+                phantom: PhantomData<T>,
+                unit: (),
+            }
+
+            struct Wrapper<T> {
+                inner: T,
+            }
+
+            impl<T, U> DispatchFromDyn<Box<U>> for Box<T> where T: Unsize<U> {}
+            impl<T, U> DispatchFromDyn<Ptr<U>> for Ptr<T> where T: Unsize<U> {}
+            impl<T, U> DispatchFromDyn<Wrapper<U>> for Wrapper<T> where T: DispatchFromDyn<U> {}
+        }
+    }
+
+    // Impls for builtin pointer types are not allowed
+    lowering_error! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            impl<'a, T, U> DispatchFromDyn<&'a U> for &'a T where T: Unsize<U> {}
+        } error_msg {
+            "trait impl for `DispatchFromDyn` does not meet well-formedness requirements"
+        }
+    }
+
+    // T: Unsize<U> is not in the environment
+    lowering_error! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            struct Box<T> {
+                ptr: *mut T,
+            }
+
+            impl<T, U> DispatchFromDyn<Box<U>> for Box<T> {}
+        } error_msg {
+            "trait impl for `DispatchFromDyn` does not meet well-formedness requirements"
+        }
+    }
+
+    // Extra fields that are not zero-sized
+    lowering_error! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            struct Box<T> {
+                ptr: *mut T,
+                len: usize,
+            }
+
+            impl<T, U> DispatchFromDyn<Box<U>> for Box<T> where T: Unsize<U> {}
+        } error_msg {
+            "trait impl for `DispatchFromDyn` does not meet well-formedness requirements"
+        }
+    }
+
+    // No field is coerced
+    lowering_error! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            struct Box<T> {
+                ptr: *mut T,
+            }
+
+            impl<T> DispatchFromDyn<Box<T>> for Box<T> where T: Unsize<T> {}
+        } error_msg {
+            "trait impl for `DispatchFromDyn` does not meet well-formedness requirements"
+        }
+    }
+
+    // Different structs
+    lowering_error! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            struct Box<T> {
+                ptr: *mut T,
+            }
+
+            struct Rc<T> {
+                ptr: *mut T,
+            }
+
+            impl<T, U> DispatchFromDyn<Rc<U>> for Box<T> where T: Unsize<U> {}
+        } error_msg {
+            "trait impl for `DispatchFromDyn` does not meet well-formedness requirements"
+        }
+    }
+
+    // repr(C) structs
+    lowering_error! {
+        program {
+            #[lang(unsize)]
+            trait Unsize<T> {}
+
+            #[lang(dispatch_from_dyn)]
+            trait DispatchFromDyn<T> {}
+
+            #[repr(C)]
+            struct Box<T> {
+                ptr: *mut T,
+            }
+
+            impl<T, U> DispatchFromDyn<Box<U>> for Box<T> where T: Unsize<U> {}
+        } error_msg {
+            "trait impl for `DispatchFromDyn` does not meet well-formedness requirements"
+        }
+    }
+}
+
+#[test]
+fn mismatched_const_types() {
+    lowering_success! {
+        program {
+            struct S<const N: usize> { }
+            struct T<const M: usize> {
+                s: S<M>,
+            }
+            struct U {
+                s: S<3>,
+                t: S<{ 1 + 2 }>,
+            }
+        }
+    }
+
+    lowering_error! {
+        program {
+            struct S<const N: usize> { }
+            struct T<const B: bool> {
+                s: S<B>,
+            }
+        } error_msg {
+            "type declaration `T` does not meet well-formedness requirements"
+        }
+    }
+
+    lowering_error! {
+        program {
+            struct S<const N> { }
+            struct T {
+                s: S<{ 0 - 1 }>,
+            }
+        } error_msg {
+            "type declaration `T` does not meet well-formedness requirements"
+        }
+    }
+}